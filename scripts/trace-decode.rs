@@ -0,0 +1,346 @@
+#!/usr/bin/env -S cargo +nightly -Zscript
+---cargo
+[package]
+edition = "2024"
+
+[dependencies]
+argh = { version = "0.1.13" }
+miette = { version = "7.2.0", features = ["fancy"] }
+serde_json = { version = "1.0" }
+thiserror = { version = "2.0.12" }
+
+---
+//! Converts a scheduler trace dumped by `ariel_os::thread::trace` into the Chrome trace event
+//! JSON format, which can be opened in Perfetto (https://ui.perfetto.dev) or `chrome://tracing`.
+//!
+//! The input is either a binary dump, as written by `trace::dump()` or to a file on native, or a
+//! log containing the `ariel-trace:` lines printed by `trace::log_dump()`.
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use miette::Diagnostic;
+use serde_json::{Value, json};
+
+const MAGIC: &[u8; 4] = b"ATRC";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 20;
+const RECORD_SIZE: usize = 16;
+const LOG_PREFIX: &str = "ariel-trace: ";
+const NO_THREAD: u8 = 0xFF;
+
+/// Names of the thread states, in the order of their encoding.
+///
+/// New states are appended, the codes of existing states never change.
+const STATES: &[&str] = &[
+    "Invalid",
+    "Running",
+    "Parked",
+    "LockBlocked",
+    "FlagBlocked",
+    "ChannelRxBlocked",
+    "ChannelTxBlocked",
+    "WaitQueueBlocked",
+    "QueueRxBlocked",
+    "QueueTxBlocked",
+    "SemaphoreBlocked",
+    "RwLockReadBlocked",
+    "RwLockWriteBlocked",
+    "CondvarBlocked",
+    "JoinBlocked",
+    "Finished",
+    "WorkQueueIdle",
+    "EventGroupBlocked",
+];
+
+/// Process IDs of the timeline tracks.
+const PID_CORES: u32 = 0;
+const PID_THREADS: u32 = 1;
+const PID_INTERRUPTS: u32 = 2;
+
+#[derive(Debug, thiserror::Error, Diagnostic)]
+enum Error {
+    #[error("I/O error : {0}")]
+    Io(#[from] io::Error),
+    #[error("JSON error : {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("no trace found in the input")]
+    NoTrace,
+    #[error("unsupported trace format version {0}")]
+    Version(u8),
+    #[error("unsupported record size {0}")]
+    RecordSize(u8),
+    #[error("trace data ends with a truncated record")]
+    Truncated,
+    #[error("malformed `ariel-trace:` line: {0}")]
+    LogLine(String),
+}
+
+#[derive(argh::FromArgs)]
+/// Converts an Ariel OS scheduler trace into Chrome trace event JSON.
+struct Args {
+    /// binary trace dump, or log containing `ariel-trace:` lines
+    #[argh(positional)]
+    input: PathBuf,
+    /// output file, defaults to the input file with a `.json` extension
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+/// A decoded trace record.
+struct Record {
+    /// Timestamp, in microseconds.
+    ts: f64,
+    kind: u8,
+    core: u8,
+    a: u8,
+    b: u8,
+    value: u32,
+}
+
+/// Splits the input into the chunks written by `trace::dump()`.
+fn chunks(input: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    if input.starts_with(MAGIC) {
+        let (header, records) = input.split_at(HEADER_SIZE.min(input.len()));
+        if records.len() % RECORD_SIZE != 0 {
+            return Err(Error::Truncated);
+        }
+        let mut chunks = vec![header.to_vec()];
+        chunks.extend(records.chunks(RECORD_SIZE).map(<[u8]>::to_vec));
+        return Ok(chunks);
+    }
+
+    // Log lines look like `... ariel-trace: [65, 84, 82, 67, ...]`.
+    String::from_utf8_lossy(input)
+        .lines()
+        .filter_map(|line| line.split_once(LOG_PREFIX).map(|(_, data)| data))
+        .map(|data| {
+            data.trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(',')
+                .map(|byte| byte.trim().parse::<u8>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| Error::LogLine(data.to_string()))
+        })
+        .collect()
+}
+
+/// Decodes the records of all dumps contained in the chunks.
+///
+/// Returns the records and the number of events that were overwritten before being dumped.
+fn decode(chunks: &[Vec<u8>]) -> Result<(Vec<Record>, u64), Error> {
+    let mut tick_hz = None;
+    let mut dropped = 0;
+    let mut records = Vec::new();
+
+    for chunk in chunks {
+        if chunk.len() == HEADER_SIZE && chunk.starts_with(MAGIC) {
+            if chunk[4] != VERSION {
+                return Err(Error::Version(chunk[4]));
+            }
+            if usize::from(chunk[5]) != RECORD_SIZE {
+                return Err(Error::RecordSize(chunk[5]));
+            }
+            dropped += u64::from(u32::from_le_bytes(chunk[8..12].try_into().unwrap()));
+            tick_hz = Some(u64::from_le_bytes(chunk[12..20].try_into().unwrap()));
+            continue;
+        }
+        let Some(tick_hz) = tick_hz else {
+            // Records without a preceding header can't be interpreted.
+            continue;
+        };
+        if chunk.len() != RECORD_SIZE {
+            return Err(Error::Truncated);
+        }
+        let ticks = u64::from_le_bytes(chunk[..8].try_into().unwrap());
+        records.push(Record {
+            ts: ticks as f64 * 1_000_000.0 / tick_hz as f64,
+            kind: chunk[8],
+            core: chunk[9],
+            a: chunk[10],
+            b: chunk[11],
+            value: u32::from_le_bytes(chunk[12..16].try_into().unwrap()),
+        });
+    }
+
+    if tick_hz.is_none() {
+        return Err(Error::NoTrace);
+    }
+    Ok((records, dropped))
+}
+
+fn state_name(code: u32) -> String {
+    STATES
+        .get(code as usize)
+        .map_or_else(|| format!("state {code}"), |name| name.to_string())
+}
+
+/// Builds timeline spans out of events that each start a new span on their track.
+#[derive(Default)]
+struct Spans {
+    /// Open span per track, as `(start, name)`.
+    open: HashMap<(u32, u32), (f64, String)>,
+    events: Vec<Value>,
+}
+
+impl Spans {
+    /// Ends the open span of the track at `ts`, and starts a new one if `name` is given.
+    fn switch(&mut self, pid: u32, tid: u32, ts: f64, name: Option<String>) {
+        if let Some((start, name)) = self.open.remove(&(pid, tid)) {
+            self.events.push(json!({
+                "name": name,
+                "ph": "X",
+                "pid": pid,
+                "tid": tid,
+                "ts": start,
+                "dur": ts - start,
+            }));
+        }
+        if let Some(name) = name {
+            self.open.insert((pid, tid), (ts, name));
+        }
+    }
+
+    /// Ends all open spans at `ts`.
+    fn finish(mut self, ts: f64) -> Vec<Value> {
+        let open: Vec<_> = self.open.keys().copied().collect();
+        for (pid, tid) in open {
+            self.switch(pid, tid, ts, None);
+        }
+        self.events
+    }
+}
+
+fn metadata(name: &str, pid: u32, tid: Option<u32>, value: &str) -> Value {
+    let mut event = json!({
+        "name": name,
+        "ph": "M",
+        "pid": pid,
+        "args": { "name": value },
+    });
+    if let Some(tid) = tid {
+        event["tid"] = json!(tid);
+    }
+    event
+}
+
+fn to_chrome_trace(records: &[Record], dropped: u64) -> Value {
+    let mut spans = Spans::default();
+    let mut events = vec![
+        metadata("process_name", PID_CORES, None, "Cores"),
+        metadata("process_name", PID_THREADS, None, "Threads"),
+        metadata("process_name", PID_INTERRUPTS, None, "Interrupts"),
+    ];
+    let mut cores = Vec::new();
+    let mut threads = Vec::new();
+
+    for record in records {
+        let core = u32::from(record.core);
+        if !cores.contains(&core) {
+            cores.push(core);
+            events.push(metadata(
+                "thread_name",
+                PID_CORES,
+                Some(core),
+                &format!("core {core}"),
+            ));
+            events.push(metadata(
+                "thread_name",
+                PID_INTERRUPTS,
+                Some(core),
+                &format!("core {core}"),
+            ));
+        }
+        let thread = u32::from(record.a);
+        if record.kind != 4
+            && record.kind != 5
+            && record.a != NO_THREAD
+            && !threads.contains(&thread)
+        {
+            threads.push(thread);
+            events.push(metadata(
+                "thread_name",
+                PID_THREADS,
+                Some(thread),
+                &format!("thread {thread}"),
+            ));
+        }
+
+        match record.kind {
+            // Context switch
+            0 => spans.switch(
+                PID_CORES,
+                core,
+                record.ts,
+                Some(format!("thread {}", record.b)),
+            ),
+            // State change
+            1 => {
+                let state = (record.value != 0).then(|| state_name(record.value));
+                spans.switch(PID_THREADS, thread, record.ts, state);
+            }
+            // Flags set
+            2 => events.push(json!({
+                "name": "flags set",
+                "ph": "i",
+                "s": "t",
+                "pid": PID_THREADS,
+                "tid": thread,
+                "ts": record.ts,
+                "args": { "flags": format!("{:#06x}", record.value) },
+            })),
+            // Timer wakeup
+            3 => events.push(json!({
+                "name": "timer wakeup",
+                "ph": "i",
+                "s": "t",
+                "pid": PID_THREADS,
+                "tid": thread,
+                "ts": record.ts,
+            })),
+            // ISR enter and exit
+            4 | 5 => {
+                let phase = if record.kind == 4 { "B" } else { "E" };
+                events.push(json!({
+                    "name": format!("IRQ {}", record.value),
+                    "ph": phase,
+                    "pid": PID_INTERRUPTS,
+                    "tid": core,
+                    "ts": record.ts,
+                }));
+            }
+            kind => eprintln!("skipping record of unknown kind {kind}"),
+        }
+    }
+
+    let end = records.last().map_or(0.0, |record| record.ts);
+    events.extend(spans.finish(end));
+
+    json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ns",
+        "otherData": { "dropped_events": dropped },
+    })
+}
+
+fn main() -> miette::Result<()> {
+    let args: Args = argh::from_env();
+
+    let input = fs::read(&args.input).map_err(Error::from)?;
+    let chunks = chunks(&input)?;
+    let (records, dropped) = decode(&chunks)?;
+    if dropped > 0 {
+        eprintln!("warning: {dropped} events were overwritten before being dumped");
+    }
+
+    let trace = to_chrome_trace(&records, dropped);
+    let output = args
+        .output
+        .unwrap_or_else(|| args.input.with_extension("json"));
+    fs::write(&output, serde_json::to_string(&trace).map_err(Error::from)?).map_err(Error::from)?;
+    println!(
+        "Converted {} events into {}",
+        records.len(),
+        output.to_string_lossy()
+    );
+    Ok(())
+}
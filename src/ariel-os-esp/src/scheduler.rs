@@ -114,6 +114,7 @@ impl SchedulerImplementation for ArielScheduler {
             unsafe { core::mem::transmute::<extern "C" fn(*mut c_void), extern "Rust" fn()>(task) };
 
         // SAFETY: Upholding `create_raw()` invariants: We know what we are doing.
+        // Dropping the handle detaches the thread.
         let thread_id = unsafe {
             create_raw(
                 task,
@@ -122,7 +123,8 @@ impl SchedulerImplementation for ArielScheduler {
                 priority as u8,
                 core_affinity,
             )
-        };
+        }
+        .thread_id();
 
         ariel_os_log::debug!(
             "task_create() name={} thread_id={:?} priority={} stack size={}",
@@ -9,6 +9,8 @@
 /// - `affinity`: (*optional*) an `ariel_os::thread::CoreAffinity` instance allowing to pin the
 ///   thread to specific cores.
 /// - `priority`: (*optional*) the thread's priority.
+/// - `name`: (*optional*) the thread's name, used for introspection; defaults to the name of the
+///   function.
 /// - `no_wait`: (*optional*) don't wait for system initialization to be finished
 ///   before starting the thread.
 ///
@@ -74,6 +76,11 @@ pub fn thread(args: TokenStream, item: TokenStream) -> TokenStream {
     let fn_name = thread_function.sig.ident.clone();
     let trampoline_function_name = format_ident!("__{fn_name}_trampoline");
 
+    let name = attrs
+        .name
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote! { stringify!(#fn_name) });
+
     let Parameters {
         stack_size,
         priority,
@@ -90,7 +97,7 @@ pub fn thread(args: TokenStream, item: TokenStream) -> TokenStream {
             #fn_name()
         }
 
-        #thread_crate::autostart_thread!(#trampoline_function_name, stacksize = #stack_size, priority = #priority, affinity = #affinity);
+        #thread_crate::autostart_thread!(#trampoline_function_name, stacksize = #stack_size, priority = #priority, affinity = #affinity, name = #name);
     };
 
     TokenStream::from(expanded)
@@ -139,6 +146,7 @@ mod thread {
         pub stack_size: Option<syn::Expr>,
         pub priority: Option<syn::Expr>,
         pub affinity: Option<syn::Expr>,
+        pub name: Option<syn::Expr>,
         pub no_wait: bool,
     }
 
@@ -169,6 +177,11 @@ mod thread {
                 return Ok(());
             }
 
+            if meta.path.is_ident("name") {
+                self.name = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+
             if meta.path.is_ident("no_wait") {
                 self.no_wait = true;
                 return Ok(());
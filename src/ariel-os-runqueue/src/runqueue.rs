@@ -183,6 +183,12 @@ impl<const N_QUEUES: usize, const N_THREADS: usize> RunQueue<{ N_QUEUES }, { N_T
         self.queues.is_empty(rq.0)
     }
 
+    /// Checks if a runqueue contains more than one thread.
+    pub fn has_multiple(&self, rq: RunqueueId) -> bool {
+        debug_assert!((rq.0 as usize) < N_QUEUES);
+        self.queues.has_multiple(rq.0)
+    }
+
     /// Returns an iterator over the [`RunQueue`], starting after thread `start` in runqueue `rq`.
     ///
     /// The `start` is not included in the iterator.
@@ -360,6 +366,13 @@ mod clist {
             }
         }
 
+        pub fn has_multiple(&self, rq: u8) -> bool {
+            match self.peek_head(rq) {
+                Some(head) => head != self.tail[rq as usize],
+                None => false,
+            }
+        }
+
         pub fn peek_next(&self, curr: u8) -> u8 {
             self.next_idxs[curr as usize]
         }
@@ -445,6 +458,19 @@ mod clist {
             assert!(clist.is_empty(0));
         }
 
+        #[test]
+        fn test_clist_has_multiple() {
+            let mut clist: CList<8, 32> = CList::new();
+            assert!(!clist.has_multiple(0));
+            clist.push(0, 0);
+            assert!(!clist.has_multiple(0));
+            clist.push(1, 0);
+            assert!(clist.has_multiple(0));
+            assert!(!clist.has_multiple(1));
+            clist.pop_head(0);
+            assert!(!clist.has_multiple(0));
+        }
+
         #[test]
         fn test_clist_advance() {
             let mut clist: CList<8, 32> = CList::new();
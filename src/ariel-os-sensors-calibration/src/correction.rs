@@ -0,0 +1,256 @@
+use ariel_os_sensors::sensor::{Sample, SampleMetadata};
+
+use crate::CalibrationError;
+
+/// Correction applied to the samples of a reading channel.
+///
+/// Corrections are polynomials of degree 3 at most, evaluated on the sample values as returned
+/// by the sensor driver, i.e., using the [scaling](ariel_os_sensors::sensor::ReadingChannel::scaling())
+/// of the reading channel: `c0 + c1·x + c2·x² + c3·x³`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Correction {
+    coefficients: [f32; 4],
+}
+
+impl Correction {
+    /// Correction leaving samples unchanged.
+    pub const IDENTITY: Self = Self::linear(0.0, 1.0);
+
+    /// Creates a linear correction: `offset + gain·x`.
+    #[must_use]
+    pub const fn linear(offset: f32, gain: f32) -> Self {
+        Self::polynomial([offset, gain, 0.0, 0.0])
+    }
+
+    /// Creates a polynomial correction from its coefficients, in increasing degree order.
+    #[must_use]
+    pub const fn polynomial(coefficients: [f32; 4]) -> Self {
+        Self { coefficients }
+    }
+
+    /// Creates the linear correction mapping the measured values of the two points to their
+    /// reference values.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CalibrationError::IdenticalMeasurements`] if the two points have the same
+    /// measured value.
+    pub fn two_point(
+        first: CalibrationPoint,
+        second: CalibrationPoint,
+    ) -> Result<Self, CalibrationError> {
+        if first.measured == second.measured {
+            return Err(CalibrationError::IdenticalMeasurements);
+        }
+
+        // Compute in `f64` to avoid losing precision on the differences.
+        let (m1, m2) = (f64::from(first.measured), f64::from(second.measured));
+        let (r1, r2) = (f64::from(first.reference), f64::from(second.reference));
+
+        let gain = (r2 - r1) / (m2 - m1);
+        let offset = r1 - gain * m1;
+
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "coefficients are stored as `f32`"
+        )]
+        Ok(Self::linear(offset as f32, gain as f32))
+    }
+
+    /// Returns the coefficients of the correction, in increasing degree order.
+    #[must_use]
+    pub const fn coefficients(&self) -> [f32; 4] {
+        self.coefficients
+    }
+
+    /// Returns the corrected value of `value`, saturating if it does not fit.
+    #[must_use]
+    pub fn apply(&self, value: i32) -> i32 {
+        let [c0, c1, c2, c3] = self.coefficients.map(f64::from);
+
+        // Evaluate in `f64`, which represents all sample values exactly.
+        let x = f64::from(value);
+        let y = c0 + x * (c1 + x * (c2 + x * c3));
+
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "float to integer casts saturate"
+        )]
+        let y = add_half(y) as i32;
+        y
+    }
+
+    /// Returns the gain of the correction at `value`, i.e., its derivative.
+    fn gain(&self, value: i32) -> f64 {
+        let [_, c1, c2, c3] = self.coefficients.map(f64::from);
+        let x = f64::from(value);
+        c1 + x * (2.0 * c2 + x * 3.0 * c3)
+    }
+
+    /// Returns the corrected `sample`.
+    ///
+    /// The deviation and bias of a [`SampleMetadata::SymmetricalError`] are scaled by the gain of
+    /// the correction at the sample value, so that they apply to the corrected value; other
+    /// metadata is left unchanged.
+    pub(crate) fn apply_to_sample(&self, sample: Sample) -> Sample {
+        let Ok(value) = sample.value() else {
+            return sample;
+        };
+
+        let metadata = match sample.metadata() {
+            SampleMetadata::SymmetricalError {
+                deviation,
+                bias,
+                scaling,
+            } => scale_error(deviation, bias, scaling, self.gain(value)),
+            metadata => metadata,
+        };
+
+        Sample::new(self.apply(value), metadata)
+    }
+}
+
+/// Returns `x` moved by one half away from zero, so that truncating the result rounds `x` half
+/// away from zero; `f64::round()` is not available in `core`.
+fn add_half(x: f64) -> f64 {
+    if x >= 0.0 { x + 0.5 } else { x - 0.5 }
+}
+
+/// Returns the [`SampleMetadata::SymmetricalError`] of a sample whose value got multiplied by
+/// `gain`.
+///
+/// The bias is multiplied by `gain`, so its sign flips with a negative gain, while the deviation
+/// is multiplied by its absolute value.
+/// The deviation is rounded up, and a coarser scaling is used if either does not fit otherwise.
+fn scale_error(deviation: u8, bias: i8, scaling: i8, gain: f64) -> SampleMetadata {
+    let mut deviation = f64::from(deviation) * gain.abs();
+    let mut bias = f64::from(bias) * gain;
+    let mut scaling = scaling;
+
+    while (deviation > f64::from(u8::MAX) || bias.abs() > f64::from(i8::MAX)) && scaling < i8::MAX {
+        deviation /= 10.0;
+        bias /= 10.0;
+        scaling += 1;
+    }
+
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "float to integer casts saturate"
+    )]
+    let (rounded_deviation, bias) = (deviation as u8, add_half(bias) as i8);
+    let deviation = if f64::from(rounded_deviation) < deviation {
+        rounded_deviation.saturating_add(1)
+    } else {
+        rounded_deviation
+    };
+
+    SampleMetadata::SymmetricalError {
+        deviation,
+        bias,
+        scaling,
+    }
+}
+
+impl Default for Correction {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Point of a two-point calibration, see [`Correction::two_point()`].
+///
+/// Values use the scaling of the reading channel being calibrated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CalibrationPoint {
+    /// Uncorrected value measured by the sensor driver instance.
+    pub measured: i32,
+    /// Actual value of the measured physical quantity.
+    pub reference: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear() {
+        let correction = Correction::linear(-12.0, 1.5);
+        assert_eq!(correction.apply(100), 138);
+        assert_eq!(correction.apply(-100), -162);
+        assert_eq!(Correction::IDENTITY.apply(-2150), -2150);
+        assert_eq!(Correction::linear(0.0, 0.25).apply(-2), -1);
+        // Not representable exactly as `f32`.
+        assert_eq!(Correction::IDENTITY.apply(16_777_217), 16_777_217);
+    }
+
+    #[test]
+    fn polynomial() {
+        let correction = Correction::polynomial([1.0, 2.0, -0.5, 0.25]);
+        // 1 + 2·4 - 0.5·16 + 0.25·64
+        assert_eq!(correction.apply(4), 17);
+        assert_eq!(correction.apply(i32::MAX), i32::MAX);
+    }
+
+    #[test]
+    fn error_is_scaled() {
+        let error = |deviation, bias, scaling| SampleMetadata::SymmetricalError {
+            deviation,
+            bias,
+            scaling,
+        };
+
+        let sample =
+            Correction::linear(-12.0, 1.5).apply_to_sample(Sample::new(100, error(25, -20, -2)));
+        assert_eq!(sample.value(), Ok(138));
+        assert_eq!(sample.metadata(), error(38, -30, -2));
+
+        // The scaling gets coarser if the deviation does not fit otherwise.
+        let sample =
+            Correction::linear(0.0, -2.0).apply_to_sample(Sample::new(100, error(200, -25, -2)));
+        assert_eq!(sample.metadata(), error(40, 5, -1));
+
+        // Or if the bias does not fit otherwise.
+        let sample =
+            Correction::linear(0.0, 2.0).apply_to_sample(Sample::new(100, error(10, 100, -2)));
+        assert_eq!(sample.metadata(), error(2, 20, -1));
+
+        // The gain of polynomial corrections depends on the value: 2·x for x².
+        let correction = Correction::polynomial([0.0, 0.0, 1.0, 0.0]);
+        let sample = correction.apply_to_sample(Sample::new(10, error(5, 0, 0)));
+        assert_eq!(sample.value(), Ok(100));
+        assert_eq!(sample.metadata(), error(100, 0, 0));
+
+        let sample = correction.apply_to_sample(Sample::new(10, SampleMetadata::UnknownAccuracy));
+        assert_eq!(sample.metadata(), SampleMetadata::UnknownAccuracy);
+    }
+
+    #[test]
+    fn two_point() {
+        let correction = Correction::two_point(
+            CalibrationPoint {
+                measured: 50,
+                reference: 0,
+            },
+            CalibrationPoint {
+                measured: 9_850,
+                reference: 10_000,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(correction.apply(50), 0);
+        assert_eq!(correction.apply(9_850), 10_000);
+        assert_eq!(correction.apply(2_500), 2_500);
+
+        let point = CalibrationPoint {
+            measured: 50,
+            reference: 0,
+        };
+        assert_eq!(
+            Correction::two_point(point, point),
+            Err(CalibrationError::IdenticalMeasurements)
+        );
+    }
+}
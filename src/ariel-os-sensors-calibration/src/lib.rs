@@ -0,0 +1,623 @@
+//! Provides a calibration layer for sensor driver instances.
+//!
+//! [`CalibratedSensor`] wraps a sensor driver instance, and applies a per-channel [`Correction`]
+//! to the samples of its readings.
+//! As it implements [`Sensor`] itself, it is registered in place of the wrapped sensor driver
+//! instance:
+//!
+//! ```ignore
+//! use ariel_os::sensors::calibration::{CalibratedSensor, CalibrationPoint};
+//!
+//! pub static CALIBRATED_SENSOR: CalibratedSensor = CalibratedSensor::new(&SENSOR);
+//! #[ariel_os::reexports::linkme::distributed_slice(ariel_os::sensors::SENSOR_REFS)]
+//! #[linkme(crate = ariel_os::reexports::linkme)]
+//! static CALIBRATED_SENSOR_REF: &'static dyn ariel_os::sensors::Sensor = &CALIBRATED_SENSOR;
+//!
+//! #[ariel_os::task(autostart)]
+//! async fn calibrated_sensor_runner() {
+//!     // The wrapped sensor driver instance must be initialized and run as usual.
+//!     CALIBRATED_SENSOR.run().await
+//! }
+//! ```
+//!
+//! # Two-point calibration
+//!
+//! A linear correction can be computed from two measurements of known reference values, for
+//! instance, for a temperature channel (with a scaling of -2):
+//!
+//! ```ignore
+//! // With the sensor device in an ice bath.
+//! let measured = CALIBRATED_SENSOR.measure_uncorrected(0).await?;
+//! let first = CalibrationPoint { measured, reference: 0 };
+//!
+//! // With the sensor device in boiling water.
+//! let measured = CALIBRATED_SENSOR.measure_uncorrected(0).await?;
+//! let second = CalibrationPoint { measured, reference: 100_00 };
+//!
+//! CALIBRATED_SENSOR.calibrate_two_point(0, first, second)?;
+//! ```
+//!
+//! # Persistence
+//!
+//! With the `storage` feature, corrections can be stored with [`CalibratedSensor::store()`], and
+//! loaded back, e.g., on startup, with [`CalibratedSensor::load()`].
+//! They are stored per sensor driver instance label, which must therefore be unique.
+//!
+//! # Triggers
+//!
+//! Triggers are not supported by [`CalibratedSensor`], as they would be evaluated on uncorrected
+//! sample values by the wrapped sensor driver instance.
+
+#![cfg_attr(not(test), no_std)]
+#![deny(missing_docs)]
+
+mod correction;
+
+use core::cell::Cell;
+
+use ariel_os_sensors::{
+    Category, Reading as _, Sensor,
+    sensor::{
+        Mode, ReadingChannels, ReadingError, ReadingResult, ReadingWaiter, Samples, SetModeError,
+        Setting, SettingError, SettingInfo, State, TriggerMeasurementError,
+    },
+    signal::Signal as ReadingSignal,
+};
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use portable_atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "storage")]
+use ariel_os_storage::Storage;
+#[cfg(feature = "storage")]
+use embedded_storage_async::nor_flash::NorFlash;
+
+pub use correction::{CalibrationPoint, Correction};
+
+/// Maximum number of reading channels that can be corrected, per sensor driver instance.
+///
+/// Samples of further reading channels are returned uncorrected.
+///
+/// Can be configured with the `CONFIG_SENSORS_CALIBRATION_MAX_CHANNELS` environment variable,
+/// defaults to 3.
+pub const MAX_CHANNELS: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_SENSORS_CALIBRATION_MAX_CHANNELS",
+    3,
+    "maximum number of reading channels corrected per calibrated sensor driver instance"
+);
+
+/// Sensor driver instance wrapping another one, correcting the samples of its readings.
+///
+/// Corrections are initially unset, leaving samples unchanged.
+pub struct CalibratedSensor {
+    sensor: &'static dyn Sensor,
+    corrections: Mutex<CriticalSectionRawMutex, Cell<[Option<Correction>; MAX_CHANNELS]>>,
+    measuring: AtomicBool,
+    signaling: Signal<CriticalSectionRawMutex, ()>,
+    reading: ReadingSignal<ReadingResult<Samples>>,
+}
+
+impl CalibratedSensor {
+    /// Creates a calibration layer wrapping `sensor`.
+    ///
+    /// `sensor` should not be registered itself, as it would otherwise be listed twice.
+    #[must_use]
+    pub const fn new(sensor: &'static dyn Sensor) -> Self {
+        Self {
+            sensor,
+            corrections: Mutex::new(Cell::new([None; MAX_CHANNELS])),
+            measuring: AtomicBool::new(false),
+            signaling: Signal::new(),
+            reading: ReadingSignal::new(),
+        }
+    }
+
+    /// Returns the wrapped sensor driver instance.
+    #[must_use]
+    pub fn inner(&self) -> &'static dyn Sensor {
+        self.sensor
+    }
+
+    /// Listens for measurement requests generated by
+    /// [`CalibratedSensor::trigger_measurement()`], and responds to them with corrected
+    /// readings.
+    /// This should be called before [`CalibratedSensor::wait_for_reading()`], as that method will
+    /// otherwise not be able to respond to measurement requests from
+    /// [`CalibratedSensor::trigger_measurement()`].
+    pub async fn run(&'static self) -> ! {
+        loop {
+            self.signaling.wait().await;
+
+            let reading = self.sensor.wait_for_reading().await;
+            self.reading
+                .signal(reading.map(|samples| self.correct(samples)));
+        }
+    }
+
+    /// Returns the correction of the reading channel at index `channel`, if any.
+    #[must_use]
+    pub fn correction(&self, channel: usize) -> Option<Correction> {
+        self.corrections
+            .lock(|corrections| corrections.get().get(channel).copied().flatten())
+    }
+
+    /// Sets the correction of the reading channel at index `channel`, or removes it if `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CalibrationError::InvalidChannel`] if there is no such reading channel, or if
+    /// `channel` is not lower than [`MAX_CHANNELS`].
+    pub fn set_correction(
+        &self,
+        channel: usize,
+        correction: Option<Correction>,
+    ) -> Result<(), CalibrationError> {
+        if channel >= self.channel_count() {
+            return Err(CalibrationError::InvalidChannel);
+        }
+
+        self.corrections.lock(|corrections| {
+            let mut updated = corrections.get();
+            let slot = updated
+                .get_mut(channel)
+                .ok_or(CalibrationError::InvalidChannel)?;
+            *slot = correction;
+            corrections.set(updated);
+            Ok(())
+        })
+    }
+
+    /// Sets the correction of the reading channel at index `channel` from a two-point
+    /// calibration, see [`Correction::two_point()`], and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CalibrationError::InvalidChannel`] if there is no such reading channel, or
+    /// [`CalibrationError::IdenticalMeasurements`] if the two points have the same measured
+    /// value.
+    pub fn calibrate_two_point(
+        &self,
+        channel: usize,
+        first: CalibrationPoint,
+        second: CalibrationPoint,
+    ) -> Result<Correction, CalibrationError> {
+        let correction = Correction::two_point(first, second)?;
+        self.set_correction(channel, Some(correction))?;
+        Ok(correction)
+    }
+
+    /// Measures the reading channel at index `channel` with the wrapped sensor driver instance,
+    /// and returns its uncorrected sample value, e.g., to build a [`CalibrationPoint`].
+    ///
+    /// This must not be called concurrently with other measurements of this sensor driver
+    /// instance.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`CalibrationError::InvalidChannel`] if there is no such reading channel.
+    /// - Returns [`CalibrationError::Reading`] if the measurement failed.
+    /// - Returns [`CalibrationError::SampleUnavailable`] if the sample is not available.
+    pub async fn measure_uncorrected(&self, channel: usize) -> Result<i32, CalibrationError> {
+        if channel >= self.channel_count() {
+            return Err(CalibrationError::InvalidChannel);
+        }
+
+        self.sensor
+            .trigger_measurement()
+            .map_err(|_| CalibrationError::Reading)?;
+        let samples = self
+            .sensor
+            .wait_for_reading()
+            .await
+            .map_err(|_| CalibrationError::Reading)?;
+
+        let (_, sample) = samples
+            .samples()
+            .nth(channel)
+            .ok_or(CalibrationError::InvalidChannel)?;
+        sample
+            .value()
+            .map_err(|_| CalibrationError::SampleUnavailable)
+    }
+
+    /// Returns the number of reading channels that can be corrected.
+    fn channel_count(&self) -> usize {
+        self.sensor
+            .reading_channels()
+            .iter()
+            .count()
+            .min(MAX_CHANNELS)
+    }
+
+    /// Returns `samples` with the corrections applied.
+    fn correct(&'static self, samples: Samples) -> Samples {
+        let corrections = self.corrections.lock(Cell::get);
+
+        samples.map(self, |index, sample| {
+            match corrections.get(index).copied().flatten() {
+                Some(correction) => correction.apply_to_sample(sample),
+                None => sample,
+            }
+        })
+    }
+}
+
+#[cfg(feature = "storage")]
+impl CalibratedSensor {
+    /// Stores the corrections of this sensor driver instance, replacing previously stored ones.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`CalibrationError::InvalidLabel`] if the sensor driver instance has no label,
+    ///   or if it is too long.
+    /// - Returns [`CalibrationError::Storage`] if storing failed.
+    pub async fn store(&self) -> Result<(), CalibrationError> {
+        self.store_in(&mut *ariel_os_storage::lock().await).await
+    }
+
+    /// Loads the stored corrections of this sensor driver instance, and returns whether any
+    /// was found.
+    ///
+    /// Corrections of reading channels without stored ones are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`CalibrationError::InvalidLabel`] if the sensor driver instance has no label,
+    ///   or if it is too long.
+    /// - Returns [`CalibrationError::Storage`] if loading failed.
+    pub async fn load(&self) -> Result<bool, CalibrationError> {
+        self.load_from(&mut *ariel_os_storage::lock().await).await
+    }
+
+    /// Stores the corrections of this sensor driver instance in `storage`.
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::store()`].
+    async fn store_in<F: NorFlash>(
+        &self,
+        storage: &mut Storage<F>,
+    ) -> Result<(), CalibrationError> {
+        for channel in 0..self.channel_count() {
+            let key = self.storage_key(channel)?;
+            let coefficients = self.correction(channel).map(|c| c.coefficients());
+
+            storage
+                .insert(&key, coefficients)
+                .await
+                .map_err(|_| CalibrationError::Storage)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the corrections of this sensor driver instance stored in `storage`.
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::load()`].
+    async fn load_from<F: NorFlash>(
+        &self,
+        storage: &mut Storage<F>,
+    ) -> Result<bool, CalibrationError> {
+        let mut found = false;
+
+        for channel in 0..self.channel_count() {
+            let key = self.storage_key(channel)?;
+
+            let stored = storage
+                .get::<Option<[f32; 4]>>(&key)
+                .await
+                .map_err(|_| CalibrationError::Storage)?;
+            if let Some(coefficients) = stored {
+                self.set_correction(channel, coefficients.map(Correction::polynomial))?;
+                found = true;
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Returns the storage key of the correction of the reading channel at index `channel`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CalibrationError::InvalidLabel`] if the sensor driver instance has no label,
+    /// or if the key does not fit.
+    fn storage_key(
+        &self,
+        channel: usize,
+    ) -> Result<heapless::String<{ ariel_os_storage::MAX_KEY_LEN }>, CalibrationError> {
+        use core::fmt::Write as _;
+
+        let label = self.sensor.label().ok_or(CalibrationError::InvalidLabel)?;
+
+        let mut key = heapless::String::new();
+        write!(key, "calib:{label}:{channel}").map_err(|_| CalibrationError::InvalidLabel)?;
+        Ok(key)
+    }
+}
+
+impl Sensor for CalibratedSensor {
+    fn trigger_measurement(&self) -> Result<(), TriggerMeasurementError> {
+        self.reading.clear();
+
+        self.sensor.trigger_measurement()?;
+        self.measuring.store(true, Ordering::Release);
+
+        self.signaling.signal(());
+
+        Ok(())
+    }
+
+    fn wait_for_reading(&'static self) -> ReadingWaiter {
+        if self.measuring.swap(false, Ordering::AcqRel) {
+            return ReadingWaiter::new(self.reading.wait());
+        }
+
+        match self.sensor.state() {
+            State::Enabled | State::Measuring => ReadingWaiter::new_err(ReadingError::NotMeasuring),
+            State::Uninitialized | State::Disabled | State::Sleeping => {
+                ReadingWaiter::new_err(ReadingError::NonEnabled)
+            }
+        }
+    }
+
+    fn reading_channels(&self) -> ReadingChannels {
+        self.sensor.reading_channels()
+    }
+
+    fn set_mode(&self, mode: Mode) -> Result<State, SetModeError> {
+        self.sensor.set_mode(mode)
+    }
+
+    fn state(&self) -> State {
+        match self.sensor.state() {
+            // The corrected reading is not available yet.
+            State::Enabled if self.measuring.load(Ordering::Acquire) => State::Measuring,
+            state => state,
+        }
+    }
+
+    fn categories(&self) -> &'static [Category] {
+        self.sensor.categories()
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        self.sensor.label()
+    }
+
+    fn display_name(&self) -> Option<&'static str> {
+        self.sensor.display_name()
+    }
+
+    fn part_number(&self) -> Option<&'static str> {
+        self.sensor.part_number()
+    }
+
+    fn version(&self) -> u8 {
+        self.sensor.version()
+    }
+
+    fn settings(&self) -> &'static [SettingInfo] {
+        self.sensor.settings()
+    }
+
+    fn setting(&self, setting: Setting) -> Result<u32, SettingError> {
+        self.sensor.setting(setting)
+    }
+
+    fn set_setting(&self, setting: Setting, value: u32) -> Result<(), SettingError> {
+        self.sensor.set_setting(setting, value)
+    }
+}
+
+/// Errors returned by the calibration layer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CalibrationError {
+    /// The reading channel does not exist, or cannot be corrected.
+    InvalidChannel,
+    /// The calibration points have the same measured value.
+    IdenticalMeasurements,
+    /// The sensor driver instance has no label, or it is too long for a storage key.
+    InvalidLabel,
+    /// The measurement failed.
+    Reading,
+    /// The measured sample is not available.
+    SampleUnavailable,
+    /// Storing or loading the corrections failed.
+    Storage,
+}
+
+impl core::fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidChannel => write!(f, "invalid reading channel"),
+            Self::IdenticalMeasurements => {
+                write!(f, "calibration points have identical measurements")
+            }
+            Self::InvalidLabel => write!(f, "missing or too long sensor label"),
+            Self::Reading => write!(f, "measurement failed"),
+            Self::SampleUnavailable => write!(f, "sample not available"),
+            Self::Storage => write!(f, "storage error"),
+        }
+    }
+}
+
+impl core::error::Error for CalibrationError {}
+
+#[cfg(test)]
+mod tests {
+    use ariel_os_sensors::{
+        Label, MeasurementUnit,
+        sensor::{ReadingChannel, Sample, SampleMetadata},
+    };
+    use ariel_os_sensors_utils::TestSensor;
+
+    use super::*;
+
+    static SENSOR: TestSensor = TestSensor::new(
+        &[
+            ReadingChannel::new(Label::Temperature, -2, MeasurementUnit::Celsius),
+            ReadingChannel::new(
+                Label::RelativeHumidity,
+                0,
+                MeasurementUnit::PercentageRelativeHumidity,
+            ),
+        ],
+        &[
+            Sample::new(2250, SampleMetadata::UnknownAccuracy),
+            Sample::new(0, SampleMetadata::ChannelTemporarilyUnavailable),
+        ],
+    )
+    .with_label("indoor")
+    .with_categories(&[Category::RelativeHumidityTemperature]);
+
+    #[test]
+    fn readings_are_corrected() {
+        static CALIBRATED: CalibratedSensor = CalibratedSensor::new(&SENSOR);
+
+        assert_eq!(CALIBRATED.label(), Some("indoor"));
+
+        embassy_futures::block_on(async {
+            assert!(matches!(
+                CALIBRATED.wait_for_reading().await,
+                Err(ReadingError::NotMeasuring)
+            ));
+
+            embassy_futures::select::select(CALIBRATED.run(), async {
+                let measured = CALIBRATED.measure_uncorrected(0).await.unwrap();
+                assert_eq!(measured, 2250);
+                assert_eq!(
+                    CALIBRATED.measure_uncorrected(1).await,
+                    Err(CalibrationError::SampleUnavailable)
+                );
+
+                let first = CalibrationPoint {
+                    measured,
+                    reference: 2200,
+                };
+                let second = CalibrationPoint {
+                    measured: 3250,
+                    reference: 3300,
+                };
+                CALIBRATED.calibrate_two_point(0, first, second).unwrap();
+                CALIBRATED
+                    .set_correction(1, Some(Correction::linear(5.0, 1.0)))
+                    .unwrap();
+                assert_eq!(
+                    CALIBRATED.set_correction(2, Some(Correction::IDENTITY)),
+                    Err(CalibrationError::InvalidChannel)
+                );
+
+                CALIBRATED.trigger_measurement().unwrap();
+                let reading = CALIBRATED.wait_for_reading().await.unwrap();
+
+                let samples = reading.samples().collect::<Vec<_>>();
+                let [(t_channel, t_sample), (_, rh_sample)] = samples.as_slice() else {
+                    unreachable!()
+                };
+                assert_eq!(t_channel.label(), Label::Temperature);
+                assert_eq!(t_sample.value(), Ok(2200));
+                assert_eq!(t_sample.metadata(), SampleMetadata::UnknownAccuracy);
+                // Unavailable samples are left unchanged.
+                assert_eq!(
+                    rh_sample.metadata(),
+                    SampleMetadata::ChannelTemporarilyUnavailable
+                );
+            })
+            .await;
+        });
+    }
+
+    /// NOR flash emulated in RAM.
+    #[cfg(feature = "storage")]
+    struct RamFlash([u8; RamFlash::CAPACITY]);
+
+    #[cfg(feature = "storage")]
+    impl RamFlash {
+        const CAPACITY: usize = 4 * <Self as NorFlash>::ERASE_SIZE;
+    }
+
+    #[cfg(feature = "storage")]
+    #[derive(Debug)]
+    struct RamFlashError;
+
+    #[cfg(feature = "storage")]
+    impl embedded_storage_async::nor_flash::NorFlashError for RamFlashError {
+        fn kind(&self) -> embedded_storage_async::nor_flash::NorFlashErrorKind {
+            embedded_storage_async::nor_flash::NorFlashErrorKind::OutOfBounds
+        }
+    }
+
+    #[cfg(feature = "storage")]
+    impl embedded_storage_async::nor_flash::ErrorType for RamFlash {
+        type Error = RamFlashError;
+    }
+
+    #[cfg(feature = "storage")]
+    impl embedded_storage_async::nor_flash::ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 1;
+
+        async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let start = offset as usize;
+            let stored = self
+                .0
+                .get(start..start + bytes.len())
+                .ok_or(RamFlashError)?;
+            bytes.copy_from_slice(stored);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            Self::CAPACITY
+        }
+    }
+
+    #[cfg(feature = "storage")]
+    impl NorFlash for RamFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 256;
+
+        async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.0
+                .get_mut(from as usize..to as usize)
+                .ok_or(RamFlashError)?
+                .fill(0xff);
+            Ok(())
+        }
+
+        async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let start = offset as usize;
+            self.0
+                .get_mut(start..start + bytes.len())
+                .ok_or(RamFlashError)?
+                .copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn corrections_are_stored() {
+        static CALIBRATED: CalibratedSensor = CalibratedSensor::new(&SENSOR);
+        static RESTORED: CalibratedSensor = CalibratedSensor::new(&SENSOR);
+
+        let correction = Correction::polynomial([1.0, 2.0, 0.5, 0.25]);
+        let mut storage = Storage::new(RamFlash([0xff; RamFlash::CAPACITY]), 0..1024);
+
+        embassy_futures::block_on(async {
+            assert_eq!(RESTORED.load_from(&mut storage).await, Ok(false));
+
+            CALIBRATED.set_correction(1, Some(correction)).unwrap();
+            CALIBRATED.store_in(&mut storage).await.unwrap();
+
+            assert_eq!(RESTORED.load_from(&mut storage).await, Ok(true));
+            assert_eq!(RESTORED.correction(0), None);
+            assert_eq!(RESTORED.correction(1), Some(correction));
+        });
+    }
+}
@@ -0,0 +1,582 @@
+use ariel_os_sensors::{
+    Sensor,
+    sensor::{Mode, State},
+};
+use ariel_os_sensors_senml::Encoder;
+use coap_handler::Handler;
+use coap_message::{
+    Code as _, MessageOption as _, MinimalWritableMessage, MutableWritableMessage,
+    OptionNumber as _, ReadableMessage,
+};
+use coap_message_utils::Error;
+use coap_numbers::{code, option};
+use embassy_time::Instant;
+
+// Content formats, see the CoAP Content-Formats IANA registry.
+pub(crate) const TEXT_PLAIN: u16 = 0;
+pub(crate) const SENML_JSON: u16 = 110;
+pub(crate) const SENML_CBOR: u16 = 112;
+
+/// Value of the Observe option registering as an observer, see RFC 7641.
+const OBSERVE_REGISTER: u16 = 0;
+
+/// Maximum size of the SenML packs served.
+const PAYLOAD_SIZE: usize = 512;
+
+/// CoAP handler exposing the registered sensor driver instances, see the
+/// [crate-level documentation](crate).
+///
+/// Resources are located below `/sensors`.
+/// Other resources can be served along with them by using this handler as the fallback of
+/// other ones, e.g., with
+/// [`HandlerBuilder::at()`](https://docs.rs/coap-handler-implementations/latest/coap_handler_implementations/trait.HandlerBuilder.html#method.at).
+#[derive(Debug, Default)]
+pub struct SensorsHandler {
+    _private: (),
+}
+
+impl SensorsHandler {
+    /// Creates a new [`SensorsHandler`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { _private: () }
+    }
+}
+
+/// Request data extracted by [`SensorsHandler`].
+#[derive(Debug, Clone)]
+pub struct RequestData(Request);
+
+impl RequestData {
+    /// Returns the index of the sensor driver instance the request registers as an observer of,
+    /// if any, see [Observe](crate#observe).
+    ///
+    /// The registration only succeeds if the response is successful (2.05 Content).
+    #[must_use]
+    pub fn observed_sensor(&self) -> Option<usize> {
+        match self.0 {
+            Request::Reading {
+                index,
+                format: Some(_),
+                observe: true,
+            } => Some(index),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Request {
+    /// GET on `/sensors/<index>`; the format is `None` if no accepted format is supported.
+    Reading {
+        index: usize,
+        format: Option<Format>,
+        /// Whether this registers as an observer.
+        observe: bool,
+    },
+    /// GET on `/sensors/<index>/mode`.
+    State { index: usize },
+    /// PUT on `/sensors/<index>/mode`.
+    SetMode { index: usize, mode: Mode },
+    /// PUT with a payload in an unsupported format.
+    UnsupportedContentFormat,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Format {
+    Cbor,
+    Json,
+}
+
+/// Path of a request, updated with each Uri-Path option.
+#[derive(Debug, Copy, Clone)]
+enum Path {
+    Root,
+    Sensors,
+    Reading(usize),
+    Mode(usize),
+    NotFound,
+}
+
+impl Path {
+    fn push(self, segment: &[u8]) -> Self {
+        match (self, segment) {
+            (Self::Root, b"sensors") => Self::Sensors,
+            (Self::Sensors, index) => core::str::from_utf8(index)
+                .ok()
+                .and_then(|index| index.parse().ok())
+                .filter(|index| crate::sensor(*index).is_some())
+                .map_or(Self::NotFound, Self::Reading),
+            (Self::Reading(index), b"mode") => Self::Mode(index),
+            _ => Self::NotFound,
+        }
+    }
+}
+
+impl Handler for SensorsHandler {
+    type RequestData = RequestData;
+    type ExtractRequestError = Error;
+    type BuildResponseError<M: MinimalWritableMessage> = M::UnionError;
+
+    fn extract_request_data<M: ReadableMessage>(
+        &mut self,
+        request: &M,
+    ) -> Result<Self::RequestData, Self::ExtractRequestError> {
+        let mut path = Path::Root;
+        let mut accept = None;
+        let mut content_format = None;
+        let mut observe = None;
+
+        for opt in request.options() {
+            match opt.number() {
+                option::URI_PATH => path = path.push(opt.value()),
+                option::ACCEPT => accept = Some(uint(opt.value())),
+                option::CONTENT_FORMAT => content_format = Some(uint(opt.value())),
+                option::OBSERVE => observe = Some(uint(opt.value())),
+                // Critical options have odd numbers.
+                number if number & 1 == 1 => return Err(Error::bad_option(number)),
+                _ => {}
+            }
+        }
+
+        let code: u8 = request.code().into();
+        let request = match (path, code) {
+            (Path::Reading(index), code::GET) => Request::Reading {
+                index,
+                format: match accept {
+                    None | Some(Some(SENML_CBOR)) => Some(Format::Cbor),
+                    Some(Some(SENML_JSON)) => Some(Format::Json),
+                    Some(_) => None,
+                },
+                // Registrations are served as regular GET requests if Observe is not supported.
+                observe: observe == Some(Some(OBSERVE_REGISTER)) && crate::observe::is_enabled(),
+            },
+            (Path::Mode(index), code::GET) => Request::State { index },
+            (Path::Mode(index), code::PUT) => match content_format {
+                None | Some(Some(TEXT_PLAIN)) => Request::SetMode {
+                    index,
+                    mode: parse_mode(request.payload()).ok_or_else(Error::bad_request)?,
+                },
+                Some(_) => Request::UnsupportedContentFormat,
+            },
+            (Path::Reading(_) | Path::Mode(_), _) => return Err(Error::method_not_allowed()),
+            (Path::Root | Path::Sensors | Path::NotFound, _) => return Err(Error::not_found()),
+        };
+
+        Ok(RequestData(request))
+    }
+
+    fn estimate_length(&mut self, _request: &Self::RequestData) -> usize {
+        PAYLOAD_SIZE + 16
+    }
+
+    fn build_response<M: MutableWritableMessage>(
+        &mut self,
+        response: &mut M,
+        request: Self::RequestData,
+    ) -> Result<(), Self::BuildResponseError<M>> {
+        match request.0 {
+            Request::Reading { format: None, .. } => set_code(response, code::NOT_ACCEPTABLE),
+            Request::Reading {
+                index,
+                format: Some(format),
+                observe,
+            } => build_reading(response, index, format, observe),
+            Request::State { index } => {
+                let state = crate::sensor(index).map_or(State::Uninitialized, Sensor::state);
+                set_code(response, code::CONTENT)?;
+                add_uint_option(response, option::CONTENT_FORMAT, TEXT_PLAIN.into())?;
+                response
+                    .set_payload(state_name(state).as_bytes())
+                    .map_err(Into::into)
+            }
+            Request::SetMode { index, mode } => {
+                match crate::sensor(index).map(|sensor| sensor.set_mode(mode)) {
+                    Some(Ok(_)) => set_code(response, code::CHANGED),
+                    // The sensor driver is not initialized yet.
+                    _ => set_code(response, code::SERVICE_UNAVAILABLE),
+                }
+            }
+            Request::UnsupportedContentFormat => {
+                set_code(response, code::UNSUPPORTED_CONTENT_FORMAT)
+            }
+        }
+    }
+}
+
+/// Builds the response to a GET request for the latest reading, which is also a notification if
+/// `observe` is set.
+///
+/// # Errors
+///
+/// Returns an error if the response cannot be written.
+fn build_reading<M: MutableWritableMessage>(
+    response: &mut M,
+    index: usize,
+    format: Format,
+    observe: bool,
+) -> Result<(), M::UnionError> {
+    let min_period = crate::min_period();
+
+    let Some(crate::Reading {
+        timestamp,
+        samples,
+        sequence,
+    }) = crate::reading(index)
+    else {
+        set_code(response, code::SERVICE_UNAVAILABLE)?;
+        // Omitted if `run()` is not running, as no reading is to be expected.
+        if min_period != embassy_time::Duration::MAX {
+            add_uint_option(response, option::MAX_AGE, max_age(min_period))?;
+        }
+        return Ok(());
+    };
+
+    let now = Instant::now();
+    // SenML relative times are negative for the past.
+    let age = i64::try_from(now.saturating_duration_since(timestamp).as_millis())
+        .map_or(i64::MIN, |age| -age);
+    let encoder = Encoder::new().with_base_time(age);
+
+    let mut buf = [0; PAYLOAD_SIZE];
+    let (content_format, len) = match format {
+        Format::Cbor => (SENML_CBOR, encoder.encode_cbor(&samples, &mut buf)),
+        Format::Json => (SENML_JSON, encoder.encode_json(&samples, &mut buf)),
+    };
+    let Some(payload) = len.ok().and_then(|len| buf.get(..len)) else {
+        ariel_os_log::warn!("sensors-coap: reading does not fit into the payload");
+        return set_code(response, code::INTERNAL_SERVER_ERROR);
+    };
+
+    let next_reading = timestamp
+        .checked_add(min_period)
+        .map_or(min_period, |next| next.saturating_duration_since(now));
+
+    set_code(response, code::CONTENT)?;
+    if observe {
+        add_uint_option(response, option::OBSERVE, sequence)?;
+    }
+    add_uint_option(response, option::CONTENT_FORMAT, content_format.into())?;
+    add_uint_option(response, option::MAX_AGE, max_age(next_reading))?;
+    response.set_payload(payload).map_err(Into::into)
+}
+
+/// Returns the Max-Age option value, in seconds, of `duration`.
+fn max_age(duration: embassy_time::Duration) -> u32 {
+    u32::try_from(duration.as_millis().div_ceil(1000)).unwrap_or(u32::MAX)
+}
+
+/// Sets the response code.
+///
+/// # Errors
+///
+/// Returns an error if the response cannot be written.
+fn set_code<M: MinimalWritableMessage>(response: &mut M, code: u8) -> Result<(), M::UnionError> {
+    response.set_code(M::Code::new(code)?);
+    Ok(())
+}
+
+/// Adds an unsigned integer option to the response.
+///
+/// # Errors
+///
+/// Returns an error if the response cannot be written.
+fn add_uint_option<M: MinimalWritableMessage>(
+    response: &mut M,
+    number: u16,
+    value: u32,
+) -> Result<(), M::UnionError> {
+    // Option values are encoded without leading zero bytes.
+    let bytes = value.to_be_bytes();
+    let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    let number = M::OptionNumber::new(number)?;
+    response
+        .add_option(number, bytes.get(leading_zeros..).unwrap_or_default())
+        .map_err(Into::into)
+}
+
+/// Parses an unsigned integer option value, returns `None` if it does not fit.
+fn uint(value: &[u8]) -> Option<u16> {
+    if value.len() > 2 {
+        return None;
+    }
+    Some(
+        value
+            .iter()
+            .fold(0, |acc, byte| (acc << 8) | u16::from(*byte)),
+    )
+}
+
+fn parse_mode(payload: &[u8]) -> Option<Mode> {
+    match payload {
+        b"enabled" => Some(Mode::Enabled),
+        b"disabled" => Some(Mode::Disabled),
+        b"sleeping" => Some(Mode::Sleeping),
+        _ => None,
+    }
+}
+
+fn state_name(state: State) -> &'static str {
+    match state {
+        State::Uninitialized => "uninitialized",
+        State::Disabled => "disabled",
+        State::Enabled => "enabled",
+        State::Measuring => "measuring",
+        State::Sleeping => "sleeping",
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ariel_os_sensors::{
+        Category, Label, MeasurementUnit,
+        sensor::{ReadingChannel, Sample, SampleMetadata},
+    };
+    use ariel_os_sensors_utils::TestSensor;
+    use coap_message::error::RenderableOnMinimal as _;
+    use coap_message_implementations::heap::HeapMessage;
+
+    use super::*;
+
+    /// Temperature sensor driver, registered as the only sensor driver instance.
+    static SENSOR: TestSensor = TestSensor::new(
+        &[ReadingChannel::new(
+            Label::Temperature,
+            -1,
+            MeasurementUnit::Celsius,
+        )],
+        &[Sample::new(215, SampleMetadata::UnknownAccuracy)],
+    )
+    .with_label("indoor")
+    .with_categories(&[Category::Temperature]);
+
+    #[linkme::distributed_slice(ariel_os_sensors_registry::SENSOR_REFS)]
+    static SENSOR_REF: &'static dyn Sensor = &SENSOR;
+
+    /// Stores a reading of [`SENSOR`], as `run()` would.
+    pub(crate) fn store_reading() {
+        critical_section::with(|cs| {
+            crate::MIN_PERIOD
+                .borrow(cs)
+                .set(embassy_time::Duration::from_secs(10));
+        });
+        crate::store_reading(0, SENSOR.samples());
+    }
+
+    /// Returns a request with the given code, path, and options, which must be sorted by number.
+    fn request(code: u8, path: &[&str], options: &[(u16, &[u8])], payload: &[u8]) -> HeapMessage {
+        let mut request = HeapMessage::new();
+        request.set_code(code);
+        for (number, value) in options
+            .iter()
+            .filter(|(number, _)| *number < option::URI_PATH)
+        {
+            request.add_option(*number, value).unwrap();
+        }
+        for segment in path {
+            request
+                .add_option(option::URI_PATH, segment.as_bytes())
+                .unwrap();
+        }
+        for (number, value) in options
+            .iter()
+            .filter(|(number, _)| *number > option::URI_PATH)
+        {
+            request.add_option(*number, value).unwrap();
+        }
+        request.set_payload(payload).unwrap();
+        request
+    }
+
+    /// Returns the response of [`SensorsHandler`] to `request`.
+    fn handle(request: &HeapMessage) -> HeapMessage {
+        let mut handler = SensorsHandler::new();
+        let mut response = HeapMessage::new();
+        match handler.extract_request_data(request) {
+            Ok(data) => handler.build_response(&mut response, data).unwrap(),
+            Err(err) => err.render(&mut response).unwrap(),
+        }
+        response
+    }
+
+    /// Returns the value of the unsigned integer option `number` of `response`.
+    fn uint_option(response: &HeapMessage, number: u16) -> Option<u16> {
+        response
+            .options()
+            .find(|opt| opt.number() == number)
+            .and_then(|opt| uint(opt.value()))
+    }
+
+    #[test]
+    fn get_reading_as_senml_cbor() {
+        store_reading();
+
+        let response = handle(&request(code::GET, &["sensors", "0"], &[], &[]));
+        assert_eq!(response.code(), code::CONTENT);
+        assert_eq!(
+            uint_option(&response, option::CONTENT_FORMAT),
+            Some(SENML_CBOR)
+        );
+        assert!(uint_option(&response, option::MAX_AGE).is_some_and(|max_age| max_age <= 10));
+        // A CBOR array with a single record.
+        assert_eq!(response.payload().first(), Some(&0x81));
+    }
+
+    #[test]
+    fn get_reading_as_senml_json() {
+        store_reading();
+
+        let accept = SENML_JSON.to_be_bytes();
+        let response = handle(&request(
+            code::GET,
+            &["sensors", "0"],
+            &[(option::ACCEPT, &accept)],
+            &[],
+        ));
+        assert_eq!(response.code(), code::CONTENT);
+        assert_eq!(
+            uint_option(&response, option::CONTENT_FORMAT),
+            Some(SENML_JSON)
+        );
+        let payload = core::str::from_utf8(response.payload()).unwrap();
+        assert!(payload.starts_with(r#"[{"bn":"indoor:""#));
+        assert!(payload.ends_with(r#""n":"temperature","u":"Cel","v":21.5}]"#));
+    }
+
+    #[test]
+    fn get_reading_with_unsupported_accept() {
+        store_reading();
+
+        let accept = TEXT_PLAIN.to_be_bytes();
+        let response = handle(&request(
+            code::GET,
+            &["sensors", "0"],
+            &[(option::ACCEPT, &accept)],
+            &[],
+        ));
+        assert_eq!(response.code(), code::NOT_ACCEPTABLE);
+    }
+
+    // Observe is enabled globally, so this is tested in a single test.
+    #[test]
+    fn observe() {
+        store_reading();
+        let register = request(code::GET, &["sensors", "0"], &[(option::OBSERVE, &[])], &[]);
+        let deregister = request(
+            code::GET,
+            &["sensors", "0"],
+            &[(option::OBSERVE, &[1])],
+            &[],
+        );
+
+        // Registrations are served as regular GET requests until notifications are taken.
+        let response = handle(&register);
+        assert_eq!(response.code(), code::CONTENT);
+        assert_eq!(uint_option(&response, option::OBSERVE), None);
+
+        let _notifications = crate::notifications();
+
+        let mut handler = SensorsHandler::new();
+        let data = handler.extract_request_data(&register).unwrap();
+        assert_eq!(data.observed_sensor(), Some(0));
+        let mut response = HeapMessage::new();
+        handler.build_response(&mut response, data.clone()).unwrap();
+        assert_eq!(response.code(), code::CONTENT);
+        let registered = uint_option(&response, option::OBSERVE).unwrap();
+
+        // Notifications are built from the request data of the registration.
+        store_reading();
+        let mut notification = HeapMessage::new();
+        handler.build_response(&mut notification, data).unwrap();
+        assert_eq!(notification.code(), code::CONTENT);
+        assert!(uint_option(&notification, option::OBSERVE).is_some_and(|seq| seq != registered));
+
+        let data = handler.extract_request_data(&deregister).unwrap();
+        assert_eq!(data.observed_sensor(), None);
+        let response = handle(&deregister);
+        assert_eq!(response.code(), code::CONTENT);
+        assert_eq!(uint_option(&response, option::OBSERVE), None);
+    }
+
+    #[test]
+    fn unknown_resources_are_not_found() {
+        for path in [
+            &["sensors"][..],
+            &["sensors", "1"],
+            &["sensors", "0", "other"],
+            &["other"],
+        ] {
+            let response = handle(&request(code::GET, path, &[], &[]));
+            assert_eq!(response.code(), code::NOT_FOUND);
+        }
+    }
+
+    #[test]
+    fn unsupported_critical_options_are_rejected() {
+        let response = handle(&request(
+            code::GET,
+            &["sensors", "0"],
+            &[(option::IF_MATCH, &[])],
+            &[],
+        ));
+        assert_eq!(response.code(), code::BAD_OPTION);
+    }
+
+    #[test]
+    fn get_state() {
+        let response = handle(&request(code::GET, &["sensors", "0", "mode"], &[], &[]));
+        assert_eq!(response.code(), code::CONTENT);
+        assert_eq!(response.payload(), b"enabled");
+    }
+
+    #[test]
+    fn put_mode() {
+        // Other tests expect the sensor driver instance to stay enabled.
+        let response = handle(&request(
+            code::PUT,
+            &["sensors", "0", "mode"],
+            &[],
+            b"enabled",
+        ));
+        assert_eq!(response.code(), code::CHANGED);
+        assert_eq!(SENSOR.last_mode(), Some(Mode::Enabled));
+        assert_eq!(parse_mode(b"sleeping"), Some(Mode::Sleeping));
+
+        let response = handle(&request(
+            code::PUT,
+            &["sensors", "0", "mode"],
+            &[],
+            b"measuring",
+        ));
+        assert_eq!(response.code(), code::BAD_REQUEST);
+
+        let content_format = SENML_JSON.to_be_bytes();
+        let response = handle(&request(
+            code::PUT,
+            &["sensors", "0", "mode"],
+            &[(option::CONTENT_FORMAT, &content_format)],
+            b"enabled",
+        ));
+        assert_eq!(response.code(), code::UNSUPPORTED_CONTENT_FORMAT);
+
+        let response = handle(&request(code::POST, &["sensors", "0", "mode"], &[], &[]));
+        assert_eq!(response.code(), code::METHOD_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn uint_options_are_parsed() {
+        assert_eq!(uint(&[]), Some(0));
+        assert_eq!(uint(&[110]), Some(SENML_JSON));
+        assert_eq!(uint(&[0x01, 0x00]), Some(256));
+        assert_eq!(uint(&[0x01, 0x00, 0x00]), None);
+    }
+
+    #[test]
+    fn max_age_is_rounded_up() {
+        assert_eq!(max_age(embassy_time::Duration::from_millis(0)), 0);
+        assert_eq!(max_age(embassy_time::Duration::from_millis(1)), 1);
+        assert_eq!(max_age(embassy_time::Duration::from_millis(10_000)), 10);
+        assert_eq!(max_age(embassy_time::Duration::MAX), u32::MAX);
+    }
+}
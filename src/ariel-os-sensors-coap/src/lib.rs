@@ -0,0 +1,224 @@
+//! Exposes registered sensor driver instances as CoAP resources.
+//!
+//! [`SensorsHandler`] is a [`coap_handler::Handler`] serving the sensor driver instances of the
+//! [registry](ariel_os_sensors_registry::REGISTRY), and the [`run()`] task refreshes the readings
+//! it serves:
+//!
+//! ```ignore
+//! #[ariel_os::task(autostart)]
+//! async fn coap_run() {
+//!     use coap_handler_implementations::{HandlerBuilder as _, SimpleRendered};
+//!
+//!     // Requests to other paths than `/hello` are handled by `SensorsHandler`.
+//!     let handler = SensorsHandler::new().at(&["hello"], SimpleRendered("Hello from Ariel OS"));
+//!     ariel_os::coap::coap_run(handler).await;
+//! }
+//!
+//! #[ariel_os::task(autostart)]
+//! async fn sensors() {
+//!     ariel_os::sensors::coap::run(Duration::from_secs(10)).await
+//! }
+//! ```
+//!
+//! # Resources
+//!
+//! Each sensor driver instance is identified by its index in the registry, and exposes the
+//! following resources:
+//!
+//! - `/sensors/<index>`: on GET, returns the latest reading of the sensor driver instance as a
+//!   [SenML](ariel_os_sensors_senml) pack, in CBOR (`application/senml+cbor`, the default) or
+//!   JSON (`application/senml+json`) depending on the Accept option.
+//!   It is listed in `/.well-known/core` with the `core.s` interface, and a resource type per
+//!   [`Category`](ariel_os_sensors::Category) of the sensor driver instance, e.g.,
+//!   `ariel.sensor.temperature`.
+//! - `/sensors/<index>/mode`: on GET, returns the [state](ariel_os_sensors::sensor::State) of the sensor
+//!   driver instance as plain text, e.g., `enabled`; on PUT, sets its
+//!   [mode](ariel_os_sensors::sensor::Mode) from a plain text payload: `enabled`, `disabled` or
+//!   `sleeping`.
+//!   It is listed in `/.well-known/core` with the `core.p` interface.
+//!
+//! Only the first [`MAX_SENSORS`] registered sensor driver instances are exposed.
+//!
+//! # Minimum period
+//!
+//! Handling requests is synchronous, so readings cannot be obtained when a request is received.
+//! Instead, [`run()`] measures the enabled sensor driver instances at the minimum period it is
+//! given, and responses contain the latest reading, with:
+//!
+//! - The base time set to how long ago the reading was obtained.
+//! - The Max-Age option set to when the next reading is expected, allowing clients to poll at the
+//!   minimum period.
+//!
+//! Until a first reading is obtained, GET requests fail with 5.03 Service Unavailable.
+//!
+//! # Observe
+//!
+//! Clients can register as observers of `/sensors/<index>` ([RFC 7641]), to get notified of new
+//! readings.
+//! Notifications are throttled: observers are notified at most once per minimum period, as new
+//! readings are obtained by [`run()`].
+//!
+//! Sending notifications is up to the CoAP transport, which keeps the observer registrations,
+//! and thus Observe is only supported once the transport has taken the [`Notifications`] with
+//! [`notifications()`]:
+//!
+//! - Responses to registrations carry the Observe option, and
+//!   [`RequestData::observed_sensor()`] tells the transport which sensor driver instance the
+//!   registration is for.
+//! - [`Notifications::next()`] tells the transport when to notify the observers of a sensor
+//!   driver instance; notifications are built by passing the [`RequestData`] of the registrations
+//!   to [`SensorsHandler`] again.
+//!
+//! The CoAP server of Ariel OS does not keep observer registrations yet.
+//! Until a transport takes the [`Notifications`], requests to register as an observer are served
+//! as regular GET requests, as allowed by RFC 7641, and resources are not listed as observable.
+//! Clients then fall back to polling, and the Max-Age option of responses tells them when to.
+//!
+//! [RFC 7641]: https://www.rfc-editor.org/rfc/rfc7641
+
+#![cfg_attr(not(test), no_std)]
+#![deny(missing_docs)]
+
+mod handler;
+mod observe;
+mod report;
+
+use core::cell::{Cell, RefCell};
+
+use ariel_os_sensors::{
+    Sensor,
+    sensor::{Samples, State},
+};
+use ariel_os_sensors_registry::REGISTRY;
+use critical_section::Mutex;
+use embassy_time::{Duration, Instant, Ticker};
+
+pub use handler::{RequestData, SensorsHandler};
+pub use observe::{Notifications, notifications};
+pub use report::{Attributes, PathSegment, Reporter, ResourceRecord};
+
+/// Maximum number of sensor driver instances exposed.
+///
+/// Can be configured with the `CONFIG_SENSORS_COAP_MAX_SENSORS` environment variable, defaults
+/// to 8.
+pub const MAX_SENSORS: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_SENSORS_COAP_MAX_SENSORS",
+    8,
+    "maximum number of sensor driver instances exposed over CoAP"
+);
+
+/// Reading of a sensor driver instance.
+#[derive(Copy, Clone)]
+struct Reading {
+    /// When the reading was obtained.
+    timestamp: Instant,
+    samples: Samples,
+    /// Observe sequence number of the reading, incremented with every new reading.
+    sequence: u32,
+}
+
+/// Latest reading of each exposed sensor driver instance.
+static READINGS: Mutex<RefCell<[Option<Reading>; MAX_SENSORS]>> =
+    Mutex::new(RefCell::new([None; MAX_SENSORS]));
+
+/// Minimum period between readings, as configured with [`run()`].
+static MIN_PERIOD: Mutex<Cell<Duration>> = Mutex::new(Cell::new(Duration::MAX));
+
+/// Refreshes the readings served by [`SensorsHandler`] every `min_period`, forever.
+///
+/// This must be run in a task for GET requests to return readings.
+/// Only enabled sensor driver instances are measured.
+///
+/// # Panics
+///
+/// Panics if `min_period` is zero.
+pub async fn run(min_period: Duration) -> ! {
+    assert!(min_period.as_ticks() > 0, "minimum period must not be zero");
+
+    critical_section::with(|cs| MIN_PERIOD.borrow(cs).set(min_period));
+
+    let mut ticker = Ticker::every(min_period);
+    loop {
+        measure().await;
+        ticker.next().await;
+    }
+}
+
+/// Returns the exposed sensor driver instances, along with their indices.
+fn sensors() -> impl Iterator<Item = (usize, &'static dyn Sensor)> {
+    REGISTRY.sensors().take(MAX_SENSORS).enumerate()
+}
+
+/// Returns the exposed sensor driver instance at `index`.
+fn sensor(index: usize) -> Option<&'static dyn Sensor> {
+    sensors()
+        .find(|(i, _)| *i == index)
+        .map(|(_, sensor)| sensor)
+}
+
+/// Measures the enabled sensor driver instances, and stores their readings.
+async fn measure() {
+    let is_enabled = |sensor: &dyn Sensor| sensor.state() == State::Enabled;
+
+    // Trigger the measurements first, so that they happen concurrently.
+    let mut triggered = [false; MAX_SENSORS];
+    for (index, sensor) in sensors().filter(|(_, sensor)| is_enabled(*sensor)) {
+        match sensor.trigger_measurement() {
+            Ok(()) => {
+                if let Some(triggered) = triggered.get_mut(index) {
+                    *triggered = true;
+                }
+            }
+            Err(_err) => {
+                ariel_os_log::warn!(
+                    "sensors-coap: could not trigger sensor {:?}: {}",
+                    sensor.label(),
+                    _err
+                );
+            }
+        }
+    }
+
+    // Only wait for the readings of the sensor driver instances that were triggered, other ones
+    // could be measured by other tasks.
+    let is_triggered = |index: usize| triggered.get(index).copied().unwrap_or_default();
+    for (index, sensor) in sensors().filter(|(index, _)| is_triggered(*index)) {
+        match sensor.wait_for_reading().await {
+            Ok(samples) => store_reading(index, samples),
+            Err(_err) => {
+                ariel_os_log::warn!(
+                    "sensors-coap: error reading sensor {:?}: {}",
+                    sensor.label(),
+                    _err
+                );
+            }
+        }
+    }
+}
+
+/// Stores a new reading of the sensor driver instance at `index`.
+fn store_reading(index: usize, samples: Samples) {
+    critical_section::with(|cs| {
+        if let Some(slot) = READINGS.borrow_ref_mut(cs).get_mut(index) {
+            let sequence = slot.map_or(0, |reading| {
+                reading.sequence.wrapping_add(1) & observe::SEQUENCE_MASK
+            });
+            *slot = Some(Reading {
+                timestamp: Instant::now(),
+                samples,
+                sequence,
+            });
+        }
+    });
+    observe::NEW_READING.signal(());
+}
+
+/// Returns the latest reading of the sensor driver instance at `index`.
+fn reading(index: usize) -> Option<Reading> {
+    critical_section::with(|cs| READINGS.borrow_ref(cs).get(index).copied().flatten())
+}
+
+/// Returns the minimum period configured with [`run()`].
+fn min_period() -> Duration {
+    critical_section::with(|cs| MIN_PERIOD.borrow(cs).get())
+}
@@ -0,0 +1,143 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Instant};
+
+use crate::MAX_SENSORS;
+
+/// Mask of the Observe option values, which are 24-bit sequence numbers.
+pub(crate) const SEQUENCE_MASK: u32 = 0x00FF_FFFF;
+
+/// Whether [`notifications()`] has been called, and thus whether clients can register as
+/// observers.
+static ENABLED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// Signals [`Notifications`] that a new reading has been stored.
+pub(crate) static NEW_READING: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Returns whether clients can register as observers.
+pub(crate) fn is_enabled() -> bool {
+    critical_section::with(|cs| ENABLED.borrow(cs).get())
+}
+
+/// Returns the notifications to send to observers of the sensor driver instances, and allows
+/// clients to register as observers.
+///
+/// This is meant to be called by a CoAP transport that keeps observer registrations, see
+/// [Observe](crate#observe).
+///
+/// # Panics
+///
+/// Panics if called more than once.
+#[must_use]
+pub fn notifications() -> Notifications {
+    let was_enabled = critical_section::with(|cs| ENABLED.borrow(cs).replace(true));
+    assert!(!was_enabled, "notifications can only be taken once");
+    Notifications::new()
+}
+
+/// Notifications to send to observers of the sensor driver instances, obtained with
+/// [`notifications()`].
+#[derive(Debug)]
+pub struct Notifications {
+    /// Sequence number of the last reading notified for each sensor driver instance, along with
+    /// when it was notified.
+    notified: [Option<(u32, Instant)>; MAX_SENSORS],
+}
+
+/// What [`Notifications`] should do next.
+#[derive(Debug, PartialEq, Eq)]
+enum Next {
+    /// Notify the observers of the sensor driver instance at the given index.
+    Notify(usize),
+    /// Check again at the given instant, when a notification is no longer throttled.
+    WaitUntil(Instant),
+    /// Check again once a new reading has been stored.
+    WaitForReading,
+}
+
+impl Notifications {
+    fn new() -> Self {
+        let mut notified = [None; MAX_SENSORS];
+        // Observers registering from now on get the current readings in the registration response.
+        for (index, notified) in notified.iter_mut().enumerate() {
+            *notified = crate::reading(index).map(|reading| (reading.sequence, reading.timestamp));
+        }
+        Self { notified }
+    }
+
+    /// Waits until the observers of a sensor driver instance need to be notified, and returns its
+    /// index.
+    ///
+    /// Observers are notified of every new reading, but at most once per minimum period given to
+    /// [`run()`](crate::run()).
+    /// Notifications are built by passing the [`RequestData`](crate::RequestData) of the
+    /// registrations of observers of that sensor driver instance to
+    /// [`SensorsHandler`](crate::SensorsHandler) again, see
+    /// [`RequestData::observed_sensor()`](crate::RequestData::observed_sensor()).
+    pub async fn next(&mut self) -> usize {
+        loop {
+            match self.poll(Instant::now(), crate::min_period()) {
+                Next::Notify(index) => return index,
+                Next::WaitUntil(deadline) => {
+                    // Either way, the readings need to be checked again.
+                    let _ = embassy_time::with_deadline(deadline, NEW_READING.wait()).await;
+                }
+                Next::WaitForReading => NEW_READING.wait().await,
+            }
+        }
+    }
+
+    /// Returns what to do next at `now`, marking the returned sensor driver instance as notified.
+    fn poll(&mut self, now: Instant, min_period: Duration) -> Next {
+        let mut next = Next::WaitForReading;
+        for (index, notified) in self.notified.iter_mut().enumerate() {
+            let Some(reading) = crate::reading(index) else {
+                continue;
+            };
+            match *notified {
+                Some((sequence, _)) if sequence == reading.sequence => {}
+                Some((_, at)) if now < at.saturating_add(min_period) => {
+                    let due = at.saturating_add(min_period);
+                    next = match next {
+                        Next::WaitUntil(other) if other < due => Next::WaitUntil(other),
+                        _ => Next::WaitUntil(due),
+                    };
+                }
+                _ => {
+                    *notified = Some((reading.sequence, now));
+                    return Next::Notify(index);
+                }
+            }
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Other tests store readings concurrently, so only new readings are assumed to be stored.
+    #[test]
+    fn notifications_are_throttled() {
+        let min_period = Duration::from_secs(10);
+        crate::handler::tests::store_reading();
+
+        // The reading stored before creating the notifications is considered notified.
+        let mut notifications = Notifications::new();
+        assert!(notifications.notified.first().copied().flatten().is_some());
+
+        // Notifications are throttled from when that reading was obtained.
+        let start = Instant::now() + min_period;
+        crate::handler::tests::store_reading();
+        assert_eq!(notifications.poll(start, min_period), Next::Notify(0));
+        assert_ne!(notifications.poll(start, min_period), Next::Notify(0));
+
+        crate::handler::tests::store_reading();
+        let due = start + min_period;
+        assert_eq!(notifications.poll(start, min_period), Next::WaitUntil(due));
+        assert_eq!(notifications.poll(due, min_period), Next::Notify(0));
+    }
+}
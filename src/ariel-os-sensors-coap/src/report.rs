@@ -0,0 +1,225 @@
+use ariel_os_sensors::{Category, Sensor};
+use coap_handler::{Attribute, Record, Reporting};
+
+use crate::SensorsHandler;
+
+impl Reporting for SensorsHandler {
+    type Record<'res>
+        = ResourceRecord
+    where
+        Self: 'res;
+    type Reporter<'res>
+        = Reporter
+    where
+        Self: 'res;
+
+    fn report(&self) -> Self::Reporter<'_> {
+        Reporter {
+            index: 0,
+            mode: false,
+        }
+    }
+}
+
+/// Iterator over the resources of [`SensorsHandler`], listed in `/.well-known/core`.
+#[derive(Debug)]
+pub struct Reporter {
+    index: usize,
+    /// Whether the next resource is the mode resource of the sensor driver instance.
+    mode: bool,
+}
+
+impl Iterator for Reporter {
+    type Item = ResourceRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sensor = crate::sensor(self.index)?;
+        let record = ResourceRecord {
+            index: self.index,
+            sensor,
+            mode: self.mode,
+        };
+
+        if self.mode {
+            self.index += 1;
+        }
+        self.mode = !self.mode;
+
+        Some(record)
+    }
+}
+
+/// A resource of [`SensorsHandler`], listed in `/.well-known/core`.
+pub struct ResourceRecord {
+    index: usize,
+    sensor: &'static dyn Sensor,
+    /// Whether this is the mode resource of the sensor driver instance.
+    mode: bool,
+}
+
+impl core::fmt::Debug for ResourceRecord {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ResourceRecord")
+            .field("index", &self.index)
+            .field("sensor", &"&dyn Sensor")
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+/// Attributes of a [`ResourceRecord`].
+pub type Attributes = core::iter::Chain<
+    core::iter::Flatten<core::array::IntoIter<Option<Attribute>, 5>>,
+    core::iter::Map<core::slice::Iter<'static, Category>, fn(&Category) -> Attribute>,
+>;
+
+impl Record for ResourceRecord {
+    type PathElement = PathSegment;
+    type PathElements = core::iter::Chain<
+        core::array::IntoIter<PathSegment, 2>,
+        core::option::IntoIter<PathSegment>,
+    >;
+    type Attributes = Attributes;
+
+    fn path(&self) -> Self::PathElements {
+        let mode = self.mode.then(|| PathSegment::from("mode"));
+
+        [PathSegment::from("sensors"), PathSegment::index(self.index)]
+            .into_iter()
+            .chain(mode)
+    }
+
+    fn rel(&self) -> Option<&str> {
+        None
+    }
+
+    fn attributes(&self) -> Self::Attributes {
+        let title = self.sensor.label().or(self.sensor.display_name());
+
+        let (attributes, categories): (_, &'static [Category]) = if self.mode {
+            (
+                [
+                    Some(Attribute::Interface("core.p")),
+                    Some(Attribute::Ct(crate::handler::TEXT_PLAIN)),
+                    title.map(Attribute::Title),
+                    None,
+                    None,
+                ],
+                &[],
+            )
+        } else {
+            (
+                [
+                    Some(Attribute::Interface("core.s")),
+                    Some(Attribute::Ct(crate::handler::SENML_CBOR)),
+                    Some(Attribute::Ct(crate::handler::SENML_JSON)),
+                    title.map(Attribute::Title),
+                    crate::observe::is_enabled().then_some(Attribute::Observable),
+                ],
+                self.sensor.categories(),
+            )
+        };
+
+        let resource_type: fn(&Category) -> Attribute =
+            |category| Attribute::ResourceType(resource_type(*category));
+
+        attributes
+            .into_iter()
+            .flatten()
+            .chain(categories.iter().map(resource_type))
+    }
+}
+
+/// Segment of the path of a [`ResourceRecord`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PathSegment {
+    buf: [u8; 8],
+    len: usize,
+}
+
+impl PathSegment {
+    fn index(index: usize) -> Self {
+        use core::fmt::Write as _;
+
+        let mut segment = Self::default();
+        // NOTE: indices are small, as they are bounded by `MAX_SENSORS`.
+        let _ = write!(segment, "{index}");
+        segment
+    }
+}
+
+impl From<&str> for PathSegment {
+    fn from(s: &str) -> Self {
+        let mut segment = Self::default();
+        let _ = core::fmt::Write::write_str(&mut segment, s);
+        segment
+    }
+}
+
+impl core::fmt::Write for PathSegment {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(core::fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl AsRef<str> for PathSegment {
+    fn as_ref(&self) -> &str {
+        self.buf
+            .get(..self.len)
+            .and_then(|bytes| core::str::from_utf8(bytes).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the resource type of sensor driver instances part of `category`.
+fn resource_type(category: Category) -> &'static str {
+    match category {
+        Category::Accelerometer => "ariel.sensor.accelerometer",
+        Category::AccelerometerTemperature => "ariel.sensor.accelerometer-temperature",
+        Category::AccelerometerGyroscope => "ariel.sensor.accelerometer-gyroscope",
+        Category::AccelerometerGyroscopeTemperature => {
+            "ariel.sensor.accelerometer-gyroscope-temperature"
+        }
+        Category::AccelerometerMagnetometerTemperature => {
+            "ariel.sensor.accelerometer-magnetometer-temperature"
+        }
+        Category::Altimeter => "ariel.sensor.altimeter",
+        Category::Ammeter => "ariel.sensor.ammeter",
+        Category::Co2Gas => "ariel.sensor.co2-gas",
+        Category::Color => "ariel.sensor.color",
+        Category::DewPoint => "ariel.sensor.dew-point",
+        Category::Gnss => "ariel.sensor.gnss",
+        Category::Gyroscope => "ariel.sensor.gyroscope",
+        Category::Inclinometer => "ariel.sensor.inclinometer",
+        Category::RelativeHumidity => "ariel.sensor.relative-humidity",
+        Category::RelativeHumidityTemperature => "ariel.sensor.relative-humidity-temperature",
+        Category::Light => "ariel.sensor.light",
+        Category::Magnetometer => "ariel.sensor.magnetometer",
+        Category::Ph => "ariel.sensor.ph",
+        Category::Pressure => "ariel.sensor.pressure",
+        Category::PressureTemperature => "ariel.sensor.pressure-temperature",
+        Category::PushButton => "ariel.sensor.push-button",
+        Category::Temperature => "ariel.sensor.temperature",
+        Category::Tvoc => "ariel.sensor.tvoc",
+        Category::Voltage => "ariel.sensor.voltage",
+        _ => "ariel.sensor",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_segments() {
+        assert_eq!(PathSegment::from("sensors").as_ref(), "sensors");
+        assert_eq!(PathSegment::index(0).as_ref(), "0");
+        assert_eq!(PathSegment::index(42).as_ref(), "42");
+    }
+}
@@ -0,0 +1,125 @@
+use ariel_os_sensors::{
+    Category, Label, MeasurementUnit, Sensor,
+    sensor::{ReadingChannel, ReadingChannels, Samples},
+};
+use portable_atomic::{AtomicU32, Ordering};
+
+use crate::{Derivation, Fixed, Input, derive_sample};
+
+/// Scaling of the altitude reading channel.
+const SCALING: i8 = -1;
+
+/// Derives the altitude from pressure.
+///
+/// Inputs must have a [`Label::Pressure`] reading channel in [`MeasurementUnit::Pascal`].
+///
+/// The altitude is computed with the barometric formula of the International Standard
+/// Atmosphere, relative to the configured sea-level pressure:
+/// `44330 m · (1 - (p / p0)^(1 / 5.255))`.
+#[derive(Debug)]
+pub struct Altitude {
+    sea_level_pressure: AtomicU32,
+}
+
+impl Altitude {
+    /// Sea-level pressure of the International Standard Atmosphere, in Pa.
+    pub const STANDARD_SEA_LEVEL_PRESSURE: u32 = 101_325;
+
+    /// Creates an altitude derivation, relative to `sea_level_pressure`, in Pa.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sea_level_pressure` is zero.
+    #[must_use]
+    pub const fn new(sea_level_pressure: u32) -> Self {
+        assert!(
+            sea_level_pressure > 0,
+            "sea-level pressure must not be zero"
+        );
+
+        Self {
+            sea_level_pressure: AtomicU32::new(sea_level_pressure),
+        }
+    }
+
+    /// Returns the sea-level pressure, in Pa.
+    #[must_use]
+    pub fn sea_level_pressure(&self) -> u32 {
+        self.sea_level_pressure.load(Ordering::Relaxed)
+    }
+
+    /// Sets the sea-level pressure, in Pa, e.g., from the local QNH.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sea_level_pressure` is zero.
+    pub fn set_sea_level_pressure(&self, sea_level_pressure: u32) {
+        assert!(
+            sea_level_pressure > 0,
+            "sea-level pressure must not be zero"
+        );
+
+        self.sea_level_pressure
+            .store(sea_level_pressure, Ordering::Relaxed);
+    }
+}
+
+impl Default for Altitude {
+    fn default() -> Self {
+        Self::new(Self::STANDARD_SEA_LEVEL_PRESSURE)
+    }
+}
+
+impl<const N: usize> Derivation<N> for Altitude {
+    fn categories(&self) -> &'static [Category] {
+        &[Category::Altimeter]
+    }
+
+    fn reading_channels(&self) -> ReadingChannels {
+        ReadingChannels::from([ReadingChannel::new(
+            Label::Altitude,
+            SCALING,
+            MeasurementUnit::Meter,
+        )])
+    }
+
+    fn display_name(&self) -> Option<&'static str> {
+        Some("barometric altitude")
+    }
+
+    fn derive(&self, sensor: &'static dyn Sensor, inputs: &[Samples; N]) -> Samples {
+        let pressure = Input::find(inputs, Label::Pressure, MeasurementUnit::Pascal);
+        let sea_level_pressure = Fixed::from_sample(
+            i32::try_from(self.sea_level_pressure()).unwrap_or(i32::MAX),
+            0,
+        );
+
+        let altitude = derive_sample([pressure], SCALING, |[p]| altitude(p, sea_level_pressure));
+
+        Samples::from_1(sensor, [altitude])
+    }
+}
+
+/// Returns the altitude, in m, or `None` if the pressure is not positive.
+fn altitude(pressure: Fixed, sea_level_pressure: Fixed) -> Option<Fixed> {
+    const EXPONENT: Fixed = Fixed::from_ratio(1000, 5255);
+
+    let ratio = (pressure / sea_level_pressure).powf(EXPONENT)?;
+    Some(Fixed::from_int(44_330) * (Fixed::ONE - ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn altitudes() {
+        let altitude = |p, p0| altitude(Fixed::from_int(p), Fixed::from_int(p0));
+
+        assert_eq!(altitude(101_325, 101_325).unwrap().to_sample(-1), 0);
+        assert_eq!(altitude(89_875, 101_325).unwrap().to_sample(-1), 10_001);
+        assert_eq!(altitude(95_000, 101_325).unwrap().to_sample(-1), 5_404);
+        assert_eq!(altitude(95_000, 100_000).unwrap().to_sample(-1), 4_306);
+        assert_eq!(altitude(0, 101_325), None);
+    }
+}
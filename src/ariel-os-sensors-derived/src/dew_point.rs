@@ -0,0 +1,88 @@
+use ariel_os_sensors::{
+    Category, Label, MeasurementUnit, Sensor,
+    sensor::{ReadingChannel, ReadingChannels, Samples},
+};
+
+use crate::{Derivation, Fixed, Input, derive_sample};
+
+/// Scaling of the dew point reading channel.
+const SCALING: i8 = -2;
+
+/// Derives the dew point from temperature and relative humidity.
+///
+/// Inputs must have a [`Label::Temperature`] reading channel in [`MeasurementUnit::Celsius`], and
+/// a [`Label::RelativeHumidity`] reading channel in
+/// [`MeasurementUnit::PercentageRelativeHumidity`]; they may be provided by the same input
+/// sensor driver instance.
+///
+/// The dew point is computed with the Magnus formula, using the coefficients of Sonntag (1990),
+/// which are accurate to ±0.35 °C between -45 °C and 60 °C.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DewPoint;
+
+impl<const N: usize> Derivation<N> for DewPoint {
+    fn categories(&self) -> &'static [Category] {
+        &[Category::DewPoint]
+    }
+
+    fn reading_channels(&self) -> ReadingChannels {
+        ReadingChannels::from([ReadingChannel::new(
+            Label::DewPoint,
+            SCALING,
+            MeasurementUnit::Celsius,
+        )])
+    }
+
+    fn display_name(&self) -> Option<&'static str> {
+        Some("dew point")
+    }
+
+    fn derive(&self, sensor: &'static dyn Sensor, inputs: &[Samples; N]) -> Samples {
+        let temperature = Input::find(inputs, Label::Temperature, MeasurementUnit::Celsius);
+        let humidity = Input::find(
+            inputs,
+            Label::RelativeHumidity,
+            MeasurementUnit::PercentageRelativeHumidity,
+        );
+
+        let dew_point = derive_sample([temperature, humidity], SCALING, |[t, rh]| dew_point(t, rh));
+
+        Samples::from_1(sensor, [dew_point])
+    }
+}
+
+/// Returns the dew point, in °C, or `None` if the inputs are out of the domain of the formula.
+fn dew_point(temperature: Fixed, humidity: Fixed) -> Option<Fixed> {
+    const A: Fixed = Fixed::from_ratio(1762, 100);
+    const B: Fixed = Fixed::from_ratio(24_312, 100);
+
+    let b_t = B + temperature;
+    if b_t <= Fixed::ZERO {
+        return None;
+    }
+
+    let gamma = (humidity / Fixed::from_int(100)).ln()? + A * temperature / b_t;
+
+    let a_gamma = A - gamma;
+    if a_gamma <= Fixed::ZERO {
+        return None;
+    }
+
+    Some(B * gamma / a_gamma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dew_points() {
+        let dew_point = |t, rh| dew_point(Fixed::from_sample(t, -1), Fixed::from_int(rh));
+
+        assert_eq!(dew_point(250, 60).unwrap().to_sample(-2), 1669);
+        assert_eq!(dew_point(215, 40).unwrap().to_sample(-2), 732);
+        assert_eq!(dew_point(-100, 80).unwrap().to_sample(-2), -1280);
+        assert_eq!(dew_point(200, 100).unwrap().to_sample(-2), 2000);
+        assert_eq!(dew_point(200, 0), None);
+    }
+}
@@ -0,0 +1,440 @@
+//! Fixed-point arithmetic, to derive samples without floats.
+
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Number of fractional bits of [`Fixed`].
+const FRAC_BITS: u32 = 32;
+
+/// π, in Q32.32.
+const PI: i64 = 13_493_037_705;
+/// ln(2), in Q2.62.
+const LN_2_Q62: u128 = 3_196_577_161_300_663_808;
+/// ln(2), in Q32.32.
+const LN_2: i64 = 2_977_044_472;
+/// log2(e), in Q32.32.
+const LOG2_E: i64 = 6_196_328_019;
+/// 180 / π, in Q32.32.
+const DEGREES_PER_RADIAN: i64 = 246_083_499_208;
+
+/// atan(2^-i), in radians and Q32.32, for the iterations of CORDIC.
+const ATAN_TABLE: [i64; 32] = [
+    3_373_259_426,
+    1_991_351_318,
+    1_052_175_346,
+    534_100_635,
+    268_086_748,
+    134_174_063,
+    67_103_403,
+    33_553_749,
+    16_777_131,
+    8_388_597,
+    4_194_303,
+    2_097_152,
+    1_048_576,
+    524_288,
+    262_144,
+    131_072,
+    65_536,
+    32_768,
+    16_384,
+    8_192,
+    4_096,
+    2_048,
+    1_024,
+    512,
+    256,
+    128,
+    64,
+    32,
+    16,
+    8,
+    4,
+    2,
+];
+
+/// Signed fixed-point number, with 32 integer bits and 32 fractional bits.
+///
+/// Arithmetic operations saturate instead of overflowing, except division by zero, which panics
+/// like for integers.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(i64);
+
+impl Fixed {
+    /// Zero.
+    pub const ZERO: Self = Self(0);
+    /// One.
+    pub const ONE: Self = Self(1 << FRAC_BITS);
+    /// Largest representable value.
+    pub const MAX: Self = Self(i64::MAX);
+    /// Smallest representable value.
+    pub const MIN: Self = Self(i64::MIN);
+
+    /// Returns the fixed-point representation of `value`.
+    #[must_use]
+    pub const fn from_int(value: i32) -> Self {
+        Self((value as i64) << FRAC_BITS)
+    }
+
+    /// Returns the fixed-point representation of `numerator / denominator`, rounded to nearest.
+    ///
+    /// This is intended to define constants, e.g., `Fixed::from_ratio(1762, 100)` for 17.62.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `denominator` is zero.
+    #[must_use]
+    pub const fn from_ratio(numerator: i64, denominator: i64) -> Self {
+        Self::from_i128(div_round(
+            (numerator as i128) << FRAC_BITS,
+            denominator as i128,
+        ))
+    }
+
+    /// Returns the value of a [`Sample`](ariel_os_sensors::sensor::Sample) with the given
+    /// [scaling](ariel_os_sensors::sensor::ReadingChannel::scaling()), i.e.,
+    /// `value·10^scaling`.
+    #[must_use]
+    pub fn from_sample(value: i32, scaling: i8) -> Self {
+        let value = i128::from(value) << FRAC_BITS;
+        let power = pow10(scaling.unsigned_abs());
+
+        if scaling >= 0 {
+            Self::from_i128(value.saturating_mul(power))
+        } else {
+            Self::from_i128(div_round(value, power))
+        }
+    }
+
+    /// Returns the [`Sample`](ariel_os_sensors::sensor::Sample) value representing `self` with
+    /// the given [scaling](ariel_os_sensors::sensor::ReadingChannel::scaling()), rounded to
+    /// nearest and saturating if it does not fit.
+    #[must_use]
+    pub fn to_sample(self, scaling: i8) -> i32 {
+        let value = i128::from(self.0);
+        let power = pow10(scaling.unsigned_abs());
+
+        let scaled = if scaling >= 0 {
+            div_round(value, power << FRAC_BITS)
+        } else {
+            div_round(value.saturating_mul(power), 1 << FRAC_BITS)
+        };
+
+        #[expect(clippy::cast_possible_truncation, reason = "the value is clamped")]
+        let scaled = scaled.clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+        scaled
+    }
+
+    /// Returns the absolute value of `self`, saturating.
+    #[must_use]
+    pub const fn abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+
+    /// Returns the square root of `self`, or `None` if `self` is negative.
+    #[must_use]
+    pub fn sqrt(self) -> Option<Self> {
+        let value = u128::try_from(self.0).ok()?;
+        Some(Self::from_u128((value << FRAC_BITS).isqrt()))
+    }
+
+    /// Returns the base-2 logarithm of `self`, or `None` if `self` is not positive.
+    #[must_use]
+    pub fn log2(self) -> Option<Self> {
+        let value = u64::try_from(self.0).ok().filter(|value| *value > 0)?;
+
+        let msb = value.ilog2();
+        let mut integer = i64::from(msb) - i64::from(FRAC_BITS);
+
+        // Normalize to a mantissa within [1, 2), in Q2.62, then compute its logarithm bit by
+        // bit: squaring the mantissa doubles its logarithm.
+        let mut mantissa = u128::from(value) << (62 - msb);
+        let mut fraction = 0;
+        for bit in (0..FRAC_BITS).rev() {
+            mantissa = (mantissa * mantissa) >> 62;
+            if mantissa >= 2 << 62 {
+                mantissa >>= 1;
+                fraction |= 1 << bit;
+            }
+        }
+
+        integer <<= FRAC_BITS;
+        Some(Self(integer | fraction))
+    }
+
+    /// Returns the natural logarithm of `self`, or `None` if `self` is not positive.
+    #[must_use]
+    pub fn ln(self) -> Option<Self> {
+        self.log2().map(|log2| log2 * Self(LN_2))
+    }
+
+    /// Returns 2 raised to the power of `self`, saturating.
+    #[must_use]
+    pub fn exp2(self) -> Self {
+        let integer = self.0 >> FRAC_BITS;
+        let fraction = u128::from(self.0.cast_unsigned() & ((1 << FRAC_BITS) - 1));
+
+        // 2^fraction = e^(fraction·ln(2)), within [1, 2), computed in Q2.62 with its Taylor
+        // series, which converges quickly as fraction·ln(2) < 0.7.
+        let x = (fraction * LN_2_Q62) >> FRAC_BITS;
+        let mut term: u128 = 1 << 62;
+        let mut sum = term;
+        for k in 1.. {
+            term = ((term * x) >> 62) / k;
+            if term == 0 {
+                break;
+            }
+            sum += term;
+        }
+
+        // Scale back to Q32.32, by 2^integer.
+        let shift = 62 - i64::from(FRAC_BITS) - integer;
+        match u32::try_from(shift) {
+            Ok(shift) if shift >= 128 => Self::ZERO,
+            Ok(0) => Self::from_u128(sum),
+            Ok(shift) => Self::from_u128((sum + (1 << (shift - 1))) >> shift),
+            // The result does not fit.
+            Err(_) => Self::MAX,
+        }
+    }
+
+    /// Returns e raised to the power of `self`, saturating.
+    #[must_use]
+    pub fn exp(self) -> Self {
+        (self * Self(LOG2_E)).exp2()
+    }
+
+    /// Returns `self` raised to the power of `exponent`, or `None` if `self` is not positive.
+    #[must_use]
+    pub fn powf(self, exponent: Self) -> Option<Self> {
+        self.log2().map(|log2| (log2 * exponent).exp2())
+    }
+
+    /// Returns the four-quadrant arctangent of `self` (`y`) and `x`, in radians within
+    /// [-π, π].
+    ///
+    /// Returns zero if both are zero.
+    #[must_use]
+    pub fn atan2(self, x: Self) -> Self {
+        let (mut x, mut y) = (i128::from(x.0), i128::from(self.0));
+        if x == 0 && y == 0 {
+            return Self::ZERO;
+        }
+
+        // Rotate by π to the right half-plane, where CORDIC converges.
+        let mut angle = 0;
+        if x < 0 {
+            angle = if y >= 0 { PI } else { -PI };
+            (x, y) = (-x, -y);
+        }
+
+        // Increase the precision of the shifts below.
+        (x, y) = (x << FRAC_BITS, y << FRAC_BITS);
+
+        // CORDIC in vectoring mode: rotate the vector to the X axis, accumulating the angle.
+        for (i, step) in ATAN_TABLE.iter().enumerate() {
+            let (dx, dy) = (y >> i, x >> i);
+            if y > 0 {
+                (x, y) = (x + dx, y - dy);
+                angle += step;
+            } else {
+                (x, y) = (x - dx, y + dy);
+                angle -= step;
+            }
+        }
+
+        Self(angle)
+    }
+
+    /// Converts `self` from radians to degrees.
+    #[must_use]
+    pub fn to_degrees(self) -> Self {
+        self * Self(DEGREES_PER_RADIAN)
+    }
+
+    const fn from_i128(value: i128) -> Self {
+        if value > i64::MAX as i128 {
+            Self::MAX
+        } else if value < i64::MIN as i128 {
+            Self::MIN
+        } else {
+            #[expect(clippy::cast_possible_truncation, reason = "the value was checked")]
+            Self(value as i64)
+        }
+    }
+
+    fn from_u128(value: u128) -> Self {
+        i64::try_from(value).map_or(Self::MAX, Self)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(self.0.saturating_neg())
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let product = i128::from(self.0) * i128::from(rhs.0);
+        Self::from_i128(div_round(product, 1 << FRAC_BITS))
+    }
+}
+
+impl Div for Fixed {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        assert!(rhs.0 != 0, "division by zero");
+        Self::from_i128(div_round(
+            i128::from(self.0) << FRAC_BITS,
+            i128::from(rhs.0),
+        ))
+    }
+}
+
+/// Divides `numerator` by `denominator`, rounding half away from zero.
+const fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let half = denominator.abs() / 2;
+    if (numerator < 0) == (denominator < 0) {
+        (numerator + half) / denominator
+    } else {
+        (numerator - half) / denominator
+    }
+}
+
+/// Returns 10^`exponent`, saturating.
+fn pow10(exponent: u8) -> i128 {
+    10i128.saturating_pow(exponent.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that `actual` is within `tolerance` of `expected`.
+    fn assert_close(actual: Fixed, expected: Fixed, tolerance: Fixed) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual:?} is not close to {expected:?}"
+        );
+    }
+
+    #[test]
+    fn samples() {
+        let value = Fixed::from_sample(2150, -2);
+        assert_eq!(value, Fixed::from_ratio(43, 2));
+        assert_eq!(value.to_sample(-2), 2150);
+        assert_eq!(value.to_sample(-1), 215);
+        assert_eq!(value.to_sample(0), 22);
+        assert_eq!((-value).to_sample(0), -22);
+        assert_eq!(Fixed::from_sample(1013, 2), Fixed::from_int(101_300));
+        assert_eq!(Fixed::from_int(101_300).to_sample(2), 1013);
+        assert_eq!(Fixed::MAX.to_sample(-3), i32::MAX);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Fixed::from_ratio(3, 2);
+        let b = Fixed::from_int(-4);
+
+        assert_eq!(a + b, Fixed::from_ratio(-5, 2));
+        assert_eq!(a - b, Fixed::from_ratio(11, 2));
+        assert_eq!(a * b, Fixed::from_int(-6));
+        assert_eq!(b / a, Fixed::from_ratio(-8, 3));
+        assert_eq!(Fixed::MAX + Fixed::ONE, Fixed::MAX);
+        assert_eq!(Fixed::MAX * b, Fixed::MIN);
+    }
+
+    #[test]
+    fn logarithms() {
+        let tolerance = Fixed::from_ratio(1, 1_000_000_000);
+
+        assert_eq!(Fixed::ONE.log2(), Some(Fixed::ZERO));
+        assert_eq!(Fixed::from_int(1024).log2(), Some(Fixed::from_int(10)));
+        assert_eq!(Fixed::from_ratio(1, 8).log2(), Some(Fixed::from_int(-3)));
+        assert_close(
+            Fixed::from_int(3).log2().unwrap(),
+            Fixed::from_ratio(1_584_962_500_721, 1_000_000_000_000),
+            tolerance,
+        );
+        assert_close(
+            Fixed::from_ratio(6, 10).ln().unwrap(),
+            Fixed::from_ratio(-510_825_623_766, 1_000_000_000_000),
+            tolerance,
+        );
+        assert_eq!(Fixed::ZERO.log2(), None);
+        assert_eq!(Fixed::from_int(-1).ln(), None);
+    }
+
+    #[test]
+    fn exponentials() {
+        let tolerance = Fixed::from_ratio(1, 1_000_000_000);
+
+        assert_eq!(Fixed::ZERO.exp2(), Fixed::ONE);
+        assert_eq!(Fixed::from_int(10).exp2(), Fixed::from_int(1024));
+        assert_eq!(Fixed::from_int(-2).exp2(), Fixed::from_ratio(1, 4));
+        assert_close(
+            Fixed::from_ratio(1, 2).exp2(),
+            Fixed::from_ratio(1_414_213_562_373, 1_000_000_000_000),
+            tolerance,
+        );
+        assert_close(
+            Fixed::ONE.exp(),
+            Fixed::from_ratio(2_718_281_828_459, 1_000_000_000_000),
+            tolerance,
+        );
+        assert_eq!(Fixed::from_int(40).exp2(), Fixed::MAX);
+        assert_eq!(Fixed::from_int(-40).exp2(), Fixed::ZERO);
+
+        assert_close(
+            Fixed::from_int(8).powf(Fixed::from_ratio(1, 3)).unwrap(),
+            Fixed::from_int(2),
+            tolerance,
+        );
+    }
+
+    #[test]
+    fn square_roots() {
+        assert_eq!(Fixed::from_int(16).sqrt(), Some(Fixed::from_int(4)));
+        assert_eq!(
+            Fixed::from_ratio(1, 4).sqrt(),
+            Some(Fixed::from_ratio(1, 2))
+        );
+        assert_eq!(Fixed::from_int(-1).sqrt(), None);
+    }
+
+    #[test]
+    fn arctangents() {
+        let tolerance = Fixed::from_ratio(1, 100_000);
+        let degrees = |y, x| Fixed::from_int(y).atan2(Fixed::from_int(x)).to_degrees();
+
+        assert_close(degrees(0, 1), Fixed::ZERO, tolerance);
+        assert_close(degrees(1, 1), Fixed::from_int(45), tolerance);
+        assert_close(degrees(1, 0), Fixed::from_int(90), tolerance);
+        assert_close(degrees(1, -1), Fixed::from_int(135), tolerance);
+        assert_close(degrees(-1, -1), Fixed::from_int(-135), tolerance);
+        assert_close(degrees(-1, 0), Fixed::from_int(-90), tolerance);
+        assert_close(degrees(0, -1), Fixed::from_int(180), tolerance);
+        assert_eq!(degrees(0, 0), Fixed::ZERO);
+    }
+}
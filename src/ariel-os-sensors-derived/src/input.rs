@@ -0,0 +1,241 @@
+use ariel_os_sensors::{
+    Label, MeasurementUnit, Reading,
+    sensor::{ReadingChannel, Sample, SampleMetadata, Samples},
+};
+
+use crate::Fixed;
+
+/// Sample of an input reading channel, converted to fixed-point.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Input {
+    /// Value of the sample, in the unit of its reading channel.
+    pub value: Fixed,
+    /// Accuracy of the sample.
+    pub accuracy: Accuracy,
+}
+
+/// Accuracy of an [`Input`], see [`SampleMetadata`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Accuracy {
+    /// Unknown accuracy.
+    #[default]
+    Unknown,
+    /// No measurement error.
+    Exact,
+    /// Measurement error symmetrical around `bias`, see [`SampleMetadata::SymmetricalError`].
+    Symmetrical {
+        /// Deviation around the bias value, in the unit of the reading channel.
+        deviation: Fixed,
+        /// Bias (mean accuracy error), in the unit of the reading channel.
+        bias: Fixed,
+    },
+}
+
+impl Input {
+    /// Returns the input of the given sample of `channel`, or `None` if it is not available.
+    #[must_use]
+    pub fn from_sample(channel: ReadingChannel, sample: Sample) -> Option<Self> {
+        let scaling = channel.scaling();
+
+        let accuracy = match sample.metadata() {
+            SampleMetadata::UnknownAccuracy => Accuracy::Unknown,
+            SampleMetadata::NoMeasurementError => Accuracy::Exact,
+            SampleMetadata::SymmetricalError {
+                deviation,
+                bias,
+                scaling,
+            } => Accuracy::Symmetrical {
+                deviation: Fixed::from_sample(deviation.into(), scaling),
+                bias: Fixed::from_sample(bias.into(), scaling),
+            },
+            SampleMetadata::ChannelTemporarilyUnavailable | SampleMetadata::ChannelDisabled => {
+                return None;
+            }
+        };
+
+        Some(Self {
+            value: Fixed::from_sample(sample.value().ok()?, scaling),
+            accuracy,
+        })
+    }
+
+    /// Returns the input of the first reading channel labeled `label` and measured in `unit`
+    /// among `readings`, or `None` if there is none or if its sample is not available.
+    #[must_use]
+    pub fn find(readings: &[Samples], label: Label, unit: MeasurementUnit) -> Option<Self> {
+        readings
+            .iter()
+            .flat_map(Reading::samples)
+            .find(|(channel, _)| channel.label() == label && channel.unit() == unit)
+            .and_then(|(channel, sample)| Self::from_sample(channel, sample))
+    }
+}
+
+/// Returns the sample computed by `f` from `inputs`, with the given
+/// [scaling](ariel_os_sensors::sensor::ReadingChannel::scaling()).
+///
+/// The accuracy of the inputs is propagated to the sample, by evaluating how the result of `f`
+/// changes with the bias and deviation of each input.
+///
+/// The sample is [temporarily unavailable](SampleMetadata::ChannelTemporarilyUnavailable) if an
+/// input is `None`, or if `f` returns `None`, e.g., if the inputs are out of its domain.
+pub fn derive_sample<const K: usize>(
+    inputs: [Option<Input>; K],
+    scaling: i8,
+    f: impl Fn([Fixed; K]) -> Option<Fixed>,
+) -> Sample {
+    let unavailable = Sample::new(0, SampleMetadata::ChannelTemporarilyUnavailable);
+
+    if inputs.iter().any(Option::is_none) {
+        return unavailable;
+    }
+    let inputs = inputs.map(Option::unwrap_or_default);
+
+    let Some(value) = f(inputs.map(|input| input.value)) else {
+        return unavailable;
+    };
+
+    Sample::new(
+        value.to_sample(scaling),
+        propagate(&inputs, value, scaling, &f),
+    )
+}
+
+/// Returns the accuracy of `value`, computed by `f` from `inputs`.
+fn propagate<const K: usize>(
+    inputs: &[Input; K],
+    value: Fixed,
+    scaling: i8,
+    f: &impl Fn([Fixed; K]) -> Option<Fixed>,
+) -> SampleMetadata {
+    if inputs.iter().all(|input| input.accuracy == Accuracy::Exact) {
+        return SampleMetadata::NoMeasurementError;
+    }
+    if inputs
+        .iter()
+        .any(|input| input.accuracy == Accuracy::Unknown)
+    {
+        return SampleMetadata::UnknownAccuracy;
+    }
+
+    let biased = inputs.map(|input| match input.accuracy {
+        Accuracy::Symmetrical { bias, .. } => input.value + bias,
+        Accuracy::Unknown | Accuracy::Exact => input.value,
+    });
+    let Some(biased_value) = f(biased) else {
+        return SampleMetadata::UnknownAccuracy;
+    };
+
+    // Worst case: the deviations of the result caused by each input add up.
+    let mut deviation = Fixed::ZERO;
+    for (index, input) in inputs.iter().enumerate() {
+        let Accuracy::Symmetrical {
+            deviation: input_deviation,
+            ..
+        } = input.accuracy
+        else {
+            continue;
+        };
+
+        let mut input_effect = Fixed::ZERO;
+        for shift in [input_deviation, -input_deviation] {
+            let mut shifted = biased;
+            if let Some(value) = shifted.get_mut(index) {
+                *value = *value + shift;
+            }
+            let Some(shifted_value) = f(shifted) else {
+                return SampleMetadata::UnknownAccuracy;
+            };
+            input_effect = input_effect.max((shifted_value - biased_value).abs());
+        }
+        deviation = deviation + input_effect;
+    }
+
+    symmetrical_error(deviation, biased_value - value, scaling)
+}
+
+/// Returns the [`SampleMetadata::SymmetricalError`] with the finest scaling, starting from
+/// `scaling`, that fits `deviation` and `bias`.
+fn symmetrical_error(deviation: Fixed, bias: Fixed, scaling: i8) -> SampleMetadata {
+    for scaling in scaling..=i8::MAX {
+        let mut rounded_deviation = deviation.to_sample(scaling);
+        // Do not round a non-zero deviation down to zero.
+        if rounded_deviation == 0 && deviation > Fixed::ZERO {
+            rounded_deviation = 1;
+        }
+
+        if let (Ok(deviation), Ok(bias)) = (
+            u8::try_from(rounded_deviation),
+            i8::try_from(bias.to_sample(scaling)),
+        ) {
+            return SampleMetadata::SymmetricalError {
+                deviation,
+                bias,
+                scaling,
+            };
+        }
+    }
+
+    SampleMetadata::UnknownAccuracy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(value: i32, deviation: u8) -> Option<Input> {
+        let channel = ReadingChannel::new(Label::Temperature, -1, MeasurementUnit::Celsius);
+        let metadata = SampleMetadata::SymmetricalError {
+            deviation,
+            bias: 0,
+            scaling: -1,
+        };
+        Input::from_sample(channel, Sample::new(value, metadata))
+    }
+
+    #[test]
+    fn accuracy_is_propagated() {
+        let sum = |[a, b]: [Fixed; 2]| Some(a + b);
+
+        let sample = derive_sample([input(215, 3), input(100, 5)], -1, sum);
+        assert_eq!(sample.value(), Ok(315));
+        assert_eq!(
+            sample.metadata(),
+            SampleMetadata::SymmetricalError {
+                deviation: 8,
+                bias: 0,
+                scaling: -1,
+            }
+        );
+
+        // Deviations too large for the scaling of the result use a coarser one.
+        let scale = |[a]: [Fixed; 1]| Some(a * Fixed::from_int(100));
+        let sample = derive_sample([input(215, 3)], -1, scale);
+        assert_eq!(sample.value(), Ok(21_500));
+        assert_eq!(
+            sample.metadata(),
+            SampleMetadata::SymmetricalError {
+                deviation: 30,
+                bias: 0,
+                scaling: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn unavailable_inputs() {
+        let identity = |[a]: [Fixed; 1]| Some(a);
+
+        let sample = derive_sample([None], 0, identity);
+        assert_eq!(
+            sample.metadata(),
+            SampleMetadata::ChannelTemporarilyUnavailable
+        );
+
+        let sample = derive_sample([input(215, 3)], 0, |_| None);
+        assert_eq!(
+            sample.metadata(),
+            SampleMetadata::ChannelTemporarilyUnavailable
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Provides virtual sensor drivers, deriving their readings from other sensor driver instances.
+//!
+//! A [`DerivedSensor`] implements [`Sensor`] by measuring its input sensor driver instances,
+//! and computing new reading channels from their readings with a [`Derivation`].
+//! Computations use fixed-point arithmetic, see [`Fixed`], and propagate the accuracy of the
+//! input samples, see [`derive_sample()`].
+//!
+//! The following derivations are provided:
+//!
+//! - [`DewPoint`]: dew point from temperature and relative humidity.
+//! - [`Altitude`]: altitude from pressure.
+//! - [`Tilt`]: pitch and roll from acceleration.
+//!
+//! Derived sensor driver instances are registered like other sensor drivers, e.g., for the dew
+//! point from an AHT20 sensor driver instance:
+//!
+//! ```ignore
+//! use ariel_os::sensors::derived::{DerivedSensor, DewPoint};
+//!
+//! pub static DEW_POINT: DerivedSensor<DewPoint, 1> =
+//!     DerivedSensor::new(Some("dew point"), [&AHT20], DewPoint);
+//! #[ariel_os::reexports::linkme::distributed_slice(ariel_os::sensors::SENSOR_REFS)]
+//! #[linkme(crate = ariel_os::reexports::linkme)]
+//! static DEW_POINT_REF: &'static dyn ariel_os::sensors::Sensor = &DEW_POINT;
+//!
+//! #[ariel_os::task(autostart)]
+//! async fn dew_point_runner() {
+//!     DEW_POINT.run().await
+//! }
+//! ```
+//!
+//! # Measurements
+//!
+//! Measuring a derived sensor driver instance measures all its input sensor driver instances,
+//! which therefore must not be measured concurrently by other tasks, as only one of them would
+//! obtain the reading.
+
+#![cfg_attr(not(test), no_std)]
+#![deny(missing_docs)]
+
+mod altitude;
+mod dew_point;
+mod fixed;
+mod input;
+mod tilt;
+
+use ariel_os_sensors::{
+    Category, Sensor,
+    sensor::{
+        Mode, ReadingChannels, ReadingError, ReadingResult, ReadingWaiter, Samples, SetModeError,
+        State, TriggerMeasurementError,
+    },
+    signal::Signal as ReadingSignal,
+};
+use ariel_os_sensors_utils::AtomicState;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+
+pub use altitude::Altitude;
+pub use dew_point::DewPoint;
+pub use fixed::Fixed;
+pub use input::{Accuracy, Input, derive_sample};
+pub use tilt::Tilt;
+
+/// Computation of the readings of a [`DerivedSensor`] from the readings of its `N` input
+/// sensor driver instances.
+pub trait Derivation<const N: usize>: Send + Sync {
+    /// Returns the categories of the derived sensor driver, see [`Sensor::categories()`].
+    fn categories(&self) -> &'static [Category];
+
+    /// Returns the reading channels of the derived sensor driver, see
+    /// [`Sensor::reading_channels()`].
+    fn reading_channels(&self) -> ReadingChannels;
+
+    /// Returns the display name of the derived sensor driver, see [`Sensor::display_name()`].
+    fn display_name(&self) -> Option<&'static str>;
+
+    /// Returns the derived reading, attributed to `sensor`, from the readings of the input
+    /// sensor driver instances, in the same order.
+    ///
+    /// Its samples must match [`Derivation::reading_channels()`]; samples that cannot be
+    /// derived, e.g., because input reading channels are missing, must be reported as
+    /// [temporarily unavailable](ariel_os_sensors::sensor::SampleMetadata::ChannelTemporarilyUnavailable).
+    fn derive(&self, sensor: &'static dyn Sensor, inputs: &[Samples; N]) -> Samples;
+}
+
+/// Virtual sensor driver, deriving its readings from `N` input sensor driver instances.
+///
+/// It is initially enabled; its mode is independent of the mode of its inputs.
+pub struct DerivedSensor<D, const N: usize> {
+    state: AtomicState,
+    label: Option<&'static str>,
+    inputs: [&'static dyn Sensor; N],
+    derivation: D,
+    signaling: Signal<CriticalSectionRawMutex, ()>,
+    reading: ReadingSignal<ReadingResult<Samples>>,
+}
+
+impl<D: Derivation<N> + 'static, const N: usize> DerivedSensor<D, N> {
+    /// Creates a derived sensor driver, computing its readings from the readings of `inputs`
+    /// with `derivation`.
+    #[must_use]
+    pub const fn new(
+        label: Option<&'static str>,
+        inputs: [&'static dyn Sensor; N],
+        derivation: D,
+    ) -> Self {
+        Self {
+            state: AtomicState::new(State::Enabled),
+            label,
+            inputs,
+            derivation,
+            signaling: Signal::new(),
+            reading: ReadingSignal::new(),
+        }
+    }
+
+    /// Returns the derivation of the driver.
+    #[must_use]
+    pub fn derivation(&self) -> &D {
+        &self.derivation
+    }
+
+    /// Listens for measurement requests generated by
+    /// [`DerivedSensor::trigger_measurement()`], and responds to them.
+    /// This should be called before [`DerivedSensor::wait_for_reading()`], as that method will
+    /// otherwise not be able to respond to measurement requests from
+    /// [`DerivedSensor::trigger_measurement()`].
+    pub async fn run(&'static self) -> ! {
+        loop {
+            self.signaling.wait().await;
+
+            self.reading.signal(self.measure().await);
+        }
+    }
+
+    /// Measures the input sensor driver instances, and derives a reading from their readings.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by an input sensor driver instance.
+    #[expect(clippy::missing_panics_doc, reason = "cannot actually panic")]
+    async fn measure(&'static self) -> ReadingResult<Samples> {
+        // Trigger the measurements first, so that they happen concurrently.
+        for input in self.inputs {
+            // The error is reported when waiting for the reading below.
+            let _ = input.trigger_measurement();
+        }
+
+        // Wait for all readings, even after an error, so that no measurement is left pending.
+        let mut readings = [None; N];
+        let mut result = Ok(());
+        for (input, reading) in self.inputs.iter().zip(&mut readings) {
+            match input.wait_for_reading().await {
+                Ok(samples) => *reading = Some(samples),
+                Err(err) => {
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result?;
+
+        // NOTE(no-panic): all readings were obtained, as no error was returned.
+        let readings = readings.map(|reading| reading.unwrap());
+        Ok(self.derivation.derive(self, &readings))
+    }
+}
+
+impl<D: Derivation<N> + 'static, const N: usize> Sensor for DerivedSensor<D, N> {
+    fn trigger_measurement(&self) -> Result<(), TriggerMeasurementError> {
+        self.reading.clear();
+
+        match self.state.get() {
+            State::Measuring => {}
+            State::Enabled => {
+                self.state.set(State::Measuring);
+            }
+            State::Uninitialized | State::Disabled | State::Sleeping => {
+                return Err(TriggerMeasurementError::NonEnabled);
+            }
+        }
+
+        self.signaling.signal(());
+
+        Ok(())
+    }
+
+    fn wait_for_reading(&'static self) -> ReadingWaiter {
+        match self.state.get() {
+            State::Measuring => {
+                self.state.set(State::Enabled);
+
+                ReadingWaiter::new(self.reading.wait())
+            }
+            State::Enabled => ReadingWaiter::new_err(ReadingError::NotMeasuring),
+            State::Uninitialized | State::Disabled | State::Sleeping => {
+                ReadingWaiter::new_err(ReadingError::NonEnabled)
+            }
+        }
+    }
+
+    fn set_mode(&self, mode: Mode) -> Result<State, SetModeError> {
+        self.state.set_mode(mode)
+    }
+
+    fn state(&self) -> State {
+        self.state.get()
+    }
+
+    fn categories(&self) -> &'static [Category] {
+        self.derivation.categories()
+    }
+
+    fn reading_channels(&self) -> ReadingChannels {
+        self.derivation.reading_channels()
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    fn display_name(&self) -> Option<&'static str> {
+        self.derivation.display_name()
+    }
+
+    fn part_number(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn version(&self) -> u8 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ariel_os_sensors::{
+        Label, MeasurementUnit, Reading as _,
+        sensor::{ReadingChannel, Sample, SampleMetadata},
+    };
+    use ariel_os_sensors_utils::TestSensor;
+
+    use super::*;
+
+    /// Temperature and humidity sensor driver.
+    static SENSOR: TestSensor = TestSensor::new(
+        &[
+            ReadingChannel::new(
+                Label::RelativeHumidity,
+                -1,
+                MeasurementUnit::PercentageRelativeHumidity,
+            ),
+            ReadingChannel::new(Label::Temperature, -1, MeasurementUnit::Celsius),
+        ],
+        &[
+            Sample::new(600, accuracy(20)),
+            Sample::new(250, accuracy(3)),
+        ],
+    )
+    .with_categories(&[Category::RelativeHumidityTemperature]);
+
+    const fn accuracy(deviation: u8) -> SampleMetadata {
+        SampleMetadata::SymmetricalError {
+            deviation,
+            bias: 0,
+            scaling: -1,
+        }
+    }
+
+    #[test]
+    fn fetch_derived_reading() {
+        static DEW_POINT: DerivedSensor<DewPoint, 1> =
+            DerivedSensor::new(Some("dew point"), [&SENSOR], DewPoint);
+
+        assert_eq!(DEW_POINT.categories(), &[Category::DewPoint]);
+
+        embassy_futures::block_on(async {
+            embassy_futures::select::select(DEW_POINT.run(), async {
+                DEW_POINT.trigger_measurement().unwrap();
+                let reading = DEW_POINT.wait_for_reading().await.unwrap();
+
+                let (channel, sample) = reading.sample();
+                assert_eq!(channel.label(), Label::DewPoint);
+                assert_eq!(channel.unit(), MeasurementUnit::Celsius);
+                assert_eq!(sample.value(), Ok(1669));
+                assert!(matches!(
+                    sample.metadata(),
+                    SampleMetadata::SymmetricalError { .. }
+                ));
+            })
+            .await;
+        });
+    }
+}
@@ -0,0 +1,98 @@
+use ariel_os_sensors::{
+    Category, Label, MeasurementUnit, Sensor,
+    sensor::{ReadingChannel, ReadingChannels, Samples},
+};
+
+use crate::{Derivation, Fixed, Input, derive_sample};
+
+/// Scaling of the pitch and roll reading channels.
+const SCALING: i8 = -1;
+
+/// Derives the pitch and roll from acceleration.
+///
+/// Inputs must have [`Label::AccelerationX`], [`Label::AccelerationY`] and
+/// [`Label::AccelerationZ`] reading channels in [`MeasurementUnit::AccelG`].
+///
+/// The device is assumed to be static, so that only gravity is measured; the pitch and roll
+/// are then computed as follows, in degrees:
+///
+/// - Pitch: `atan2(-x, √(y² + z²))`, within [-90°, 90°].
+/// - Roll: `atan2(y, z)`, within [-180°, 180°].
+///
+/// Both are zero when the Z axis points upward.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Tilt;
+
+impl<const N: usize> Derivation<N> for Tilt {
+    fn categories(&self) -> &'static [Category] {
+        &[Category::Inclinometer]
+    }
+
+    fn reading_channels(&self) -> ReadingChannels {
+        ReadingChannels::from([
+            ReadingChannel::new(Label::Pitch, SCALING, MeasurementUnit::Degree),
+            ReadingChannel::new(Label::Roll, SCALING, MeasurementUnit::Degree),
+        ])
+    }
+
+    fn display_name(&self) -> Option<&'static str> {
+        Some("tilt")
+    }
+
+    fn derive(&self, sensor: &'static dyn Sensor, inputs: &[Samples; N]) -> Samples {
+        let axes = [
+            Label::AccelerationX,
+            Label::AccelerationY,
+            Label::AccelerationZ,
+        ]
+        .map(|label| Input::find(inputs, label, MeasurementUnit::AccelG));
+
+        let pitch = derive_sample(axes, SCALING, |[x, y, z]| pitch(x, y, z));
+        let roll = derive_sample(axes, SCALING, |[_, y, z]| roll(y, z));
+
+        Samples::from_2(sensor, [pitch, roll])
+    }
+}
+
+/// Returns the pitch, in degrees, or `None` if no acceleration is measured.
+fn pitch(x: Fixed, y: Fixed, z: Fixed) -> Option<Fixed> {
+    let yz = (y * y + z * z).sqrt()?;
+    if x == Fixed::ZERO && yz == Fixed::ZERO {
+        return None;
+    }
+
+    Some((-x).atan2(yz).to_degrees())
+}
+
+/// Returns the roll, in degrees, or `None` if it is undefined.
+fn roll(y: Fixed, z: Fixed) -> Option<Fixed> {
+    if y == Fixed::ZERO && z == Fixed::ZERO {
+        return None;
+    }
+
+    Some(y.atan2(z).to_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tilts() {
+        let g = |milli_g| Fixed::from_sample(milli_g, -3);
+        let tilt = |x, y, z| {
+            let (x, y, z) = (g(x), g(y), g(z));
+            (
+                pitch(x, y, z).map(|pitch| pitch.to_sample(-1)),
+                roll(y, z).map(|roll| roll.to_sample(-1)),
+            )
+        };
+
+        assert_eq!(tilt(0, 0, 1000), (Some(0), Some(0)));
+        assert_eq!(tilt(500, 0, 866), (Some(-300), Some(0)));
+        assert_eq!(tilt(0, 500, 866), (Some(0), Some(300)));
+        assert_eq!(tilt(0, 0, -1000), (Some(0), Some(1800)));
+        assert_eq!(tilt(-1000, 0, 0), (Some(900), None));
+        assert_eq!(tilt(0, 0, 0), (None, None));
+    }
+}
@@ -0,0 +1,547 @@
+//! Provides a service sampling registered sensor driver instances periodically.
+//!
+//! Instead of every application running its own loop of
+//! [`Sensor::trigger_measurement()`] and [`Sensor::wait_for_reading()`], code [subscribes](subscribe)
+//! to sensor driver instances, selected by label or by [`Category`], with a sampling interval.
+//! The [`run()`] task then schedules the measurements and delivers the resulting
+//! [`TimestampedSamples`] to the channels of the subscribers:
+//!
+//! ```ignore
+//! static TEMPERATURE: SampleChannel<4> = SampleChannel::new();
+//!
+//! #[ariel_os::task(autostart)]
+//! async fn sampling() {
+//!     ariel_os::sensors::sampling::run().await
+//! }
+//!
+//! #[ariel_os::task(autostart)]
+//! async fn main() {
+//!     let _subscription = subscribe(
+//!         Selector::Category(Category::Temperature),
+//!         Duration::from_secs(10),
+//!         &TEMPERATURE,
+//!     )
+//!     .unwrap();
+//!
+//!     loop {
+//!         let samples = TEMPERATURE.receive().await;
+//!         // ...
+//!     }
+//! }
+//! ```
+//!
+//! Subscriptions to the same sensor driver instance share measurements: when several of them are
+//! due at the same time, the sensor driver instance is only measured once, and measurements of
+//! all due sensor driver instances are carried out concurrently.
+//!
+//! The service also handles errors and sensor driver modes centrally:
+//!
+//! - [`ReadingError`]s are logged and the reading is skipped, subscribers only receive
+//!   successful readings.
+//! - Sleeping sensor driver instances are [enabled](Mode::Enabled) when they get their first
+//!   subscription, and put back to [sleep](Mode::Sleeping) once their last subscription is
+//!   dropped.
+//!   Only sensor driver instances enabled by the service are put back to sleep, and only the
+//!   first [`MAX_SENSORS`] registered sensor driver instances are enabled by the service.
+//!   Disabled sensor driver instances are left disabled, and are not sampled.
+
+#![cfg_attr(not(test), no_std)]
+#![deny(missing_docs)]
+
+use core::cell::RefCell;
+
+use ariel_os_sensors::{
+    Category, Sensor,
+    sensor::{Mode, ReadingError, Samples, State},
+};
+use ariel_os_sensors_registry::REGISTRY;
+use critical_section::Mutex;
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    channel::{Channel, SendDynamicSender, TrySendError},
+    signal::Signal,
+};
+use embassy_time::{Duration, Instant};
+
+/// Maximum number of subscriptions that can exist at the same time.
+///
+/// Can be configured with the `CONFIG_SENSORS_SAMPLING_SUBSCRIPTIONS` environment variable,
+/// defaults to 8.
+pub const MAX_SUBSCRIPTIONS: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_SENSORS_SAMPLING_SUBSCRIPTIONS",
+    8,
+    "maximum number of sensor sampling subscriptions"
+);
+
+/// Maximum number of registered sensor driver instances the service enables when subscribed to,
+/// see the [crate-level documentation](crate).
+///
+/// Can be configured with the `CONFIG_SENSORS_SAMPLING_MAX_SENSORS` environment variable,
+/// defaults to 16.
+pub const MAX_SENSORS: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_SENSORS_SAMPLING_MAX_SENSORS",
+    16,
+    "maximum number of sensor driver instances enabled by the sampling service"
+);
+
+/// Channel receiving the [`TimestampedSamples`] of a subscription, holding up to `N` of them.
+pub type SampleChannel<const N: usize> = Channel<CriticalSectionRawMutex, TimestampedSamples, N>;
+
+struct Entry {
+    selector: Selector,
+    interval: Duration,
+    next_due: Instant,
+    /// Whether the subscription is due in the current sampling round.
+    due: bool,
+    sender: SendDynamicSender<'static, TimestampedSamples>,
+}
+
+static SUBSCRIPTIONS: Mutex<RefCell<[Option<Entry>; MAX_SUBSCRIPTIONS]>> =
+    Mutex::new(RefCell::new([const { None }; MAX_SUBSCRIPTIONS]));
+
+/// Whether each registered sensor driver instance was enabled by the service, by registry index.
+static ENABLED_BY_SERVICE: Mutex<RefCell<[bool; MAX_SENSORS]>> =
+    Mutex::new(RefCell::new([false; MAX_SENSORS]));
+
+/// Signals the service that the subscriptions have changed.
+static CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Selects the sensor driver instances a subscription is about.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// Sensor driver instances with this label, see [`Sensor::label()`].
+    Label(&'static str),
+    /// Sensor driver instances part of this category, see [`Sensor::categories()`].
+    Category(Category),
+}
+
+impl Selector {
+    /// Returns whether `sensor` is selected.
+    #[must_use]
+    pub fn matches(&self, sensor: &dyn Sensor) -> bool {
+        match self {
+            Self::Label(label) => sensor.label() == Some(*label),
+            Self::Category(category) => sensor.categories().contains(category),
+        }
+    }
+}
+
+/// A reading delivered to subscribers, along with when it was obtained.
+#[derive(Debug, Copy, Clone)]
+pub struct TimestampedSamples {
+    timestamp: Instant,
+    samples: Samples,
+}
+
+impl TimestampedSamples {
+    /// Returns when the reading was obtained.
+    #[must_use]
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+
+    /// Returns the samples of the reading.
+    #[must_use]
+    pub fn samples(&self) -> &Samples {
+        &self.samples
+    }
+
+    /// Returns the sensor driver instance that produced the reading.
+    #[must_use]
+    pub fn sensor(&self) -> &'static dyn Sensor {
+        use ariel_os_sensors::sensor::SensorAccess as _;
+
+        self.samples.sensor()
+    }
+}
+
+/// Errors returned by [`subscribe()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SubscribeError {
+    /// No registered sensor driver instance matches the selector.
+    NoMatchingSensor,
+    /// [`MAX_SUBSCRIPTIONS`] subscriptions already exist.
+    TooManySubscriptions,
+}
+
+impl core::fmt::Display for SubscribeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoMatchingSensor => write!(f, "no sensor driver instance matches the selector"),
+            Self::TooManySubscriptions => write!(f, "too many sensor sampling subscriptions"),
+        }
+    }
+}
+
+impl core::error::Error for SubscribeError {}
+
+/// Subscribes to the sensor driver instances matching `selector`, sampling them every
+/// `interval`.
+///
+/// The first readings are delivered as soon as possible.
+/// Readings are delivered to `channel` without waiting: if it is full when a reading is
+/// obtained, the reading is dropped for this subscription.
+///
+/// The subscription ends when the returned [`Subscription`] is dropped.
+///
+/// # Errors
+///
+/// - Returns [`SubscribeError::NoMatchingSensor`] if no registered sensor driver instance
+///   matches `selector`.
+/// - Returns [`SubscribeError::TooManySubscriptions`] if [`MAX_SUBSCRIPTIONS`] subscriptions
+///   already exist.
+///
+/// # Panics
+///
+/// Panics if `interval` is zero.
+pub fn subscribe<const N: usize>(
+    selector: Selector,
+    interval: Duration,
+    channel: &'static SampleChannel<N>,
+) -> Result<Subscription, SubscribeError> {
+    assert!(
+        interval.as_ticks() > 0,
+        "sampling interval must not be zero"
+    );
+
+    if !REGISTRY.sensors().any(|sensor| selector.matches(sensor)) {
+        return Err(SubscribeError::NoMatchingSensor);
+    }
+
+    let index = critical_section::with(|cs| {
+        let mut subscriptions = SUBSCRIPTIONS.borrow_ref_mut(cs);
+        let (index, slot) = subscriptions
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| slot.is_none())
+            .ok_or(SubscribeError::TooManySubscriptions)?;
+        *slot = Some(Entry {
+            selector,
+            interval,
+            next_due: Instant::now(),
+            due: false,
+            sender: channel.sender().into(),
+        });
+        Ok(index)
+    })?;
+
+    for (index, sensor) in REGISTRY
+        .sensors()
+        .enumerate()
+        .filter(|(_, sensor)| selector.matches(*sensor))
+    {
+        if sensor.state() != State::Sleeping {
+            continue;
+        }
+        if index >= MAX_SENSORS {
+            ariel_os_log::warn!(
+                "sampling: not enabling sleeping sensor {:?}, increase MAX_SENSORS",
+                sensor.label()
+            );
+            continue;
+        }
+
+        ariel_os_log::debug!("sampling: enabling sleeping sensor {:?}", sensor.label());
+        // The sensor driver is initialized, as it is sleeping.
+        if sensor.set_mode(Mode::Enabled).is_ok() {
+            critical_section::with(|cs| {
+                if let Some(enabled) = ENABLED_BY_SERVICE.borrow_ref_mut(cs).get_mut(index) {
+                    *enabled = true;
+                }
+            });
+        }
+    }
+
+    CHANGED.signal(());
+
+    Ok(Subscription { index })
+}
+
+/// A subscription to sensor driver instances, see [`subscribe()`].
+///
+/// Dropping it ends the subscription.
+#[must_use = "dropping a Subscription ends it"]
+pub struct Subscription {
+    index: usize,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let selector = critical_section::with(|cs| {
+            SUBSCRIPTIONS
+                .borrow_ref_mut(cs)
+                .get_mut(self.index)
+                .and_then(Option::take)
+                .map(|entry| entry.selector)
+        });
+        let Some(selector) = selector else {
+            return;
+        };
+
+        // Put sensors enabled by the service that are not sampled anymore back to sleep.
+        for (index, sensor) in REGISTRY
+            .sensors()
+            .enumerate()
+            .filter(|(_, sensor)| selector.matches(*sensor))
+        {
+            if is_subscribed(sensor) {
+                continue;
+            }
+            let enabled_by_service = critical_section::with(|cs| {
+                ENABLED_BY_SERVICE
+                    .borrow_ref_mut(cs)
+                    .get_mut(index)
+                    .is_some_and(core::mem::take)
+            });
+            if enabled_by_service && sensor.state() == State::Enabled {
+                ariel_os_log::debug!("sampling: putting sensor {:?} to sleep", sensor.label());
+                // The sensor driver is initialized, as it is enabled.
+                let _ = sensor.set_mode(Mode::Sleeping);
+            }
+        }
+
+        CHANGED.signal(());
+    }
+}
+
+/// Returns whether any subscription matches `sensor`.
+fn is_subscribed(sensor: &dyn Sensor) -> bool {
+    critical_section::with(|cs| {
+        SUBSCRIPTIONS
+            .borrow_ref(cs)
+            .iter()
+            .flatten()
+            .any(|entry| entry.selector.matches(sensor))
+    })
+}
+
+/// Returns whether any subscription due in the current round matches `sensor`.
+fn is_due(sensor: &dyn Sensor) -> bool {
+    critical_section::with(|cs| {
+        SUBSCRIPTIONS
+            .borrow_ref(cs)
+            .iter()
+            .flatten()
+            .any(|entry| entry.due && entry.selector.matches(sensor))
+    })
+}
+
+/// Marks the subscriptions due at `now`, and schedules their next sampling.
+///
+/// Returns whether any subscription is due.
+fn start_round(now: Instant) -> bool {
+    critical_section::with(|cs| {
+        let mut any_due = false;
+        for entry in SUBSCRIPTIONS.borrow_ref_mut(cs).iter_mut().flatten() {
+            if entry.next_due <= now {
+                entry.due = true;
+                any_due = true;
+                entry.next_due += entry.interval;
+                // Skip the missed samplings instead of catching up.
+                if entry.next_due <= now {
+                    entry.next_due = now + entry.interval;
+                }
+            }
+        }
+        any_due
+    })
+}
+
+/// Delivers a reading to the due subscriptions matching its sensor.
+fn deliver(samples: TimestampedSamples) {
+    critical_section::with(|cs| {
+        for entry in SUBSCRIPTIONS.borrow_ref(cs).iter().flatten() {
+            if !entry.due || !entry.selector.matches(samples.sensor()) {
+                continue;
+            }
+            if let Err(TrySendError::Full(_)) = entry.sender.try_send(samples) {
+                ariel_os_log::warn!(
+                    "sampling: channel full, dropping reading of sensor {:?}",
+                    samples.sensor().label()
+                );
+            }
+        }
+    });
+}
+
+/// Ends the current round.
+///
+/// Returns the time the next subscription is due, if any.
+fn end_round() -> Option<Instant> {
+    critical_section::with(|cs| {
+        let mut subscriptions = SUBSCRIPTIONS.borrow_ref_mut(cs);
+        subscriptions
+            .iter_mut()
+            .flatten()
+            .for_each(|entry| entry.due = false);
+        subscriptions
+            .iter()
+            .flatten()
+            .map(|entry| entry.next_due)
+            .min()
+    })
+}
+
+/// Runs the sampling service, forever.
+///
+/// This must be run in a task for subscriptions to receive readings.
+pub async fn run() -> ! {
+    loop {
+        let now = Instant::now();
+        if start_round(now) {
+            sample_due().await;
+        }
+
+        match end_round() {
+            Some(next_due) => {
+                // Wakes up early if the subscriptions change.
+                let _ = embassy_time::with_deadline(next_due, CHANGED.wait()).await;
+            }
+            None => CHANGED.wait().await,
+        }
+    }
+}
+
+/// Samples the sensor driver instances of the due subscriptions.
+async fn sample_due() {
+    // Trigger the measurements first, so that they happen concurrently.
+    for sensor in REGISTRY.sensors().filter(|sensor| is_due(*sensor)) {
+        if let Err(_err) = sensor.trigger_measurement() {
+            // The error is reported when waiting for the reading below.
+            ariel_os_log::debug!(
+                "sampling: could not trigger sensor {:?}: {}",
+                sensor.label(),
+                _err
+            );
+        }
+    }
+
+    for sensor in REGISTRY.sensors().filter(|sensor| is_due(*sensor)) {
+        match sensor.wait_for_reading().await {
+            Ok(samples) => deliver(TimestampedSamples {
+                timestamp: Instant::now(),
+                samples,
+            }),
+            Err(ReadingError::NonEnabled) => {
+                ariel_os_log::debug!("sampling: skipping non-enabled sensor {:?}", sensor.label());
+            }
+            Err(_err) => {
+                ariel_os_log::warn!(
+                    "sampling: error reading sensor {:?}: {}",
+                    sensor.label(),
+                    _err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ariel_os_sensors::{
+        Label, MeasurementUnit, Reading as _,
+        sensor::{ReadingChannel, Sample, SampleMetadata},
+    };
+    use ariel_os_sensors_utils::TestSensor;
+
+    use super::*;
+
+    const CHANNELS: &[ReadingChannel] = &[ReadingChannel::new(
+        Label::Temperature,
+        -1,
+        MeasurementUnit::Celsius,
+    )];
+    const SAMPLES: &[Sample] = &[Sample::new(215, SampleMetadata::UnknownAccuracy)];
+
+    static SLEEPING: TestSensor = TestSensor::new(CHANNELS, SAMPLES)
+        .with_label("sleeping")
+        .with_categories(&[Category::Temperature])
+        .with_state(State::Sleeping);
+    static ENABLED: TestSensor = TestSensor::new(CHANNELS, SAMPLES)
+        .with_label("enabled")
+        .with_categories(&[Category::Pressure]);
+    // Separate from `SLEEPING`, as tests run in parallel and both change the sensor state.
+    static PERIODIC: TestSensor = TestSensor::new(CHANNELS, SAMPLES)
+        .with_label("periodic")
+        .with_categories(&[Category::RelativeHumidity])
+        .with_state(State::Sleeping);
+
+    #[linkme::distributed_slice(ariel_os_sensors_registry::SENSOR_REFS)]
+    static SLEEPING_REF: &'static dyn Sensor = &SLEEPING;
+    #[linkme::distributed_slice(ariel_os_sensors_registry::SENSOR_REFS)]
+    static ENABLED_REF: &'static dyn Sensor = &ENABLED;
+    #[linkme::distributed_slice(ariel_os_sensors_registry::SENSOR_REFS)]
+    static PERIODIC_REF: &'static dyn Sensor = &PERIODIC;
+
+    #[test]
+    fn unknown_sensor() {
+        static CHANNEL: SampleChannel<1> = SampleChannel::new();
+
+        let subscription = subscribe(Selector::Label("unknown"), Duration::from_secs(1), &CHANNEL);
+        assert!(matches!(
+            subscription,
+            Err(SubscribeError::NoMatchingSensor)
+        ));
+    }
+
+    #[test]
+    fn periodic_delivery() {
+        static CHANNEL: SampleChannel<4> = SampleChannel::new();
+
+        let interval = Duration::from_millis(20);
+        let subscription = subscribe(Selector::Label("periodic"), interval, &CHANNEL).unwrap();
+        assert_eq!(PERIODIC.state(), State::Enabled);
+
+        embassy_futures::block_on(async {
+            embassy_futures::select::select(run(), async {
+                let first = CHANNEL.receive().await;
+                let second = CHANNEL.receive().await;
+
+                assert_eq!(first.sensor().label(), Some("periodic"));
+                assert_eq!(first.samples().sample().1.value(), Ok(215));
+                assert!(second.timestamp() - first.timestamp() >= interval);
+            })
+            .await;
+        });
+
+        drop(subscription);
+    }
+
+    #[test]
+    fn sleeping_sensor_sleeps_after_last_subscription() {
+        static CHANNEL: SampleChannel<1> = SampleChannel::new();
+
+        let by_label = subscribe(
+            Selector::Label("sleeping"),
+            Duration::from_secs(1),
+            &CHANNEL,
+        )
+        .unwrap();
+        let by_category = subscribe(
+            Selector::Category(Category::Temperature),
+            Duration::from_secs(1),
+            &CHANNEL,
+        )
+        .unwrap();
+        assert_eq!(SLEEPING.state(), State::Enabled);
+
+        drop(by_label);
+        assert_eq!(SLEEPING.state(), State::Enabled);
+
+        drop(by_category);
+        assert_eq!(SLEEPING.state(), State::Sleeping);
+    }
+
+    #[test]
+    fn enabled_sensor_stays_enabled() {
+        static CHANNEL: SampleChannel<1> = SampleChannel::new();
+
+        let subscription =
+            subscribe(Selector::Label("enabled"), Duration::from_secs(1), &CHANNEL).unwrap();
+        assert_eq!(ENABLED.state(), State::Enabled);
+
+        drop(subscription);
+        assert_eq!(ENABLED.state(), State::Enabled);
+    }
+}
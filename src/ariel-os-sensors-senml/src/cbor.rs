@@ -0,0 +1,171 @@
+// These functions only fail when the buffer is too small.
+#![expect(clippy::missing_errors_doc)]
+
+use crate::{
+    Base, EncodeError, Writer,
+    record::{Decimal, Record, Value},
+};
+
+// CBOR major types.
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const DECIMAL_FRACTION_TAG: u64 = 4;
+
+// SenML labels, see Table 4 of RFC 8428.
+const BASE_NAME: i64 = -2;
+const BASE_TIME: i64 = -3;
+const NAME: i64 = 0;
+const UNIT: i64 = 1;
+const VALUE: i64 = 2;
+const BOOLEAN_VALUE: i64 = 4;
+
+pub(crate) fn encode(
+    w: &mut Writer<'_>,
+    base: &Base<'_>,
+    records: impl Iterator<Item = Record>,
+    count: usize,
+) -> Result<(), EncodeError> {
+    head(w, ARRAY, count as u64)?;
+
+    for (i, record) in records.enumerate() {
+        let mut fields = 2 + u64::from(record.unit.is_some());
+        if record.error.is_some() {
+            fields += 2;
+        }
+        if i == 0 {
+            fields += u64::from(base.name.is_some()) + u64::from(base.time.is_some());
+        }
+        head(w, MAP, fields)?;
+
+        if i == 0 {
+            if let Some(name) = base.name {
+                int(w, BASE_NAME)?;
+                text(w, &name)?;
+            }
+            if let Some(time) = base.time {
+                int(w, BASE_TIME)?;
+                number(w, time)?;
+            }
+        }
+
+        int(w, NAME)?;
+        text(w, &[record.name])?;
+
+        if let Some(unit) = record.unit {
+            int(w, UNIT)?;
+            text(w, &[unit])?;
+        }
+
+        match record.value {
+            Value::Number(value) => {
+                int(w, VALUE)?;
+                number(w, value)?;
+            }
+            Value::Bool(value) => {
+                int(w, BOOLEAN_VALUE)?;
+                w.write(&[if value { TRUE } else { FALSE }])?;
+            }
+        }
+
+        if let Some((min, max)) = record.error {
+            text(w, &["errmin"])?;
+            number(w, min)?;
+            text(w, &["errmax"])?;
+            number(w, max)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the head of a data item of type `major`, with argument `value`.
+fn head(w: &mut Writer<'_>, major: u8, value: u64) -> Result<(), EncodeError> {
+    let major = major << 5;
+
+    if let Ok(value) = u8::try_from(value) {
+        if value < 24 {
+            w.write(&[major | value])
+        } else {
+            w.write(&[major | 0x18, value])
+        }
+    } else if let Ok(value) = u16::try_from(value) {
+        w.write(&[major | 0x19])?;
+        w.write(&value.to_be_bytes())
+    } else if let Ok(value) = u32::try_from(value) {
+        w.write(&[major | 0x1a])?;
+        w.write(&value.to_be_bytes())
+    } else {
+        w.write(&[major | 0x1b])?;
+        w.write(&value.to_be_bytes())
+    }
+}
+
+fn int(w: &mut Writer<'_>, value: i64) -> Result<(), EncodeError> {
+    if value < 0 {
+        head(w, NEGATIVE, (-1 - value).unsigned_abs())
+    } else {
+        head(w, UNSIGNED, value.unsigned_abs())
+    }
+}
+
+/// Writes the concatenation of `parts` as a text string.
+fn text(w: &mut Writer<'_>, parts: &[&str]) -> Result<(), EncodeError> {
+    let len = parts.iter().map(|part| part.len()).sum::<usize>();
+    head(w, TEXT, len as u64)?;
+
+    for part in parts {
+        w.write(part.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Writes `value` as an integer if possible, as a decimal fraction otherwise.
+fn number(w: &mut Writer<'_>, value: Decimal) -> Result<(), EncodeError> {
+    let integer = u32::try_from(value.exponent)
+        .ok()
+        .and_then(|exponent| 10i64.checked_pow(exponent))
+        .and_then(|pow| value.mantissa.checked_mul(pow));
+    if let Some(integer) = integer {
+        return int(w, integer);
+    }
+
+    head(w, TAG, DECIMAL_FRACTION_TAG)?;
+    head(w, ARRAY, 2)?;
+    int(w, i64::from(value.exponent))?;
+    int(w, value.mantissa)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        let mut buf = [0; 16];
+        for (value, expected) in [
+            (0, &[0x00][..]),
+            (23, &[0x17]),
+            (24, &[0x18, 0x18]),
+            (-1, &[0x20]),
+            (-500, &[0x39, 0x01, 0xf3]),
+            (1_000_000, &[0x1a, 0x00, 0x0f, 0x42, 0x40]),
+            (
+                i64::MIN,
+                &[0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+        ] {
+            let mut w = Writer::new(&mut buf);
+            int(&mut w, value).unwrap();
+            let len = w.len;
+            assert_eq!(buf.get(..len), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// These functions only fail when the buffer is too small.
+#![expect(clippy::missing_errors_doc)]
+
+use core::fmt::Write as _;
+
+use crate::{
+    Base, EncodeError, Writer,
+    record::{Decimal, Record, Value},
+};
+
+pub(crate) fn encode(
+    w: &mut Writer<'_>,
+    base: &Base<'_>,
+    records: impl Iterator<Item = Record>,
+) -> Result<(), EncodeError> {
+    w.write(b"[")?;
+
+    for (i, record) in records.enumerate() {
+        if i == 0 {
+            w.write(b"{")?;
+            if let Some(name) = base.name {
+                w.write(br#""bn":"#)?;
+                string(w, &name)?;
+                w.write(b",")?;
+            }
+            if let Some(time) = base.time {
+                w.write(br#""bt":"#)?;
+                number(w, time)?;
+                w.write(b",")?;
+            }
+        } else {
+            w.write(b",{")?;
+        }
+
+        w.write(br#""n":"#)?;
+        string(w, &[record.name])?;
+
+        if let Some(unit) = record.unit {
+            w.write(br#","u":"#)?;
+            string(w, &[unit])?;
+        }
+
+        match record.value {
+            Value::Number(value) => {
+                w.write(br#","v":"#)?;
+                number(w, value)?;
+            }
+            Value::Bool(value) => {
+                w.write(br#","vb":"#)?;
+                w.write(if value { b"true" } else { b"false" })?;
+            }
+        }
+
+        if let Some((min, max)) = record.error {
+            w.write(br#","errmin":"#)?;
+            number(w, min)?;
+            w.write(br#","errmax":"#)?;
+            number(w, max)?;
+        }
+
+        w.write(b"}")?;
+    }
+
+    w.write(b"]")
+}
+
+/// Writes the concatenation of `parts` as a JSON string.
+fn string(w: &mut Writer<'_>, parts: &[&str]) -> Result<(), EncodeError> {
+    w.write(b"\"")?;
+
+    for byte in parts.iter().flat_map(|part| part.bytes()) {
+        match byte {
+            b'"' => w.write(b"\\\"")?,
+            b'\\' => w.write(b"\\\\")?,
+            0x00..0x20 => write!(w, "\\u{byte:04x}").map_err(|_| EncodeError::BufferTooSmall)?,
+            _ => w.write(&[byte])?,
+        }
+    }
+
+    w.write(b"\"")
+}
+
+/// Writes `value` as a JSON number, in plain decimal notation.
+fn number(w: &mut Writer<'_>, value: Decimal) -> Result<(), EncodeError> {
+    let Decimal { mantissa, exponent } = value;
+
+    let res = if let Ok(zeros) = usize::try_from(exponent) {
+        // The mantissa is non-zero unless the exponent is zero.
+        write!(w, "{mantissa}{:0<zeros$}", "")
+    } else {
+        let sign = if mantissa < 0 { "-" } else { "" };
+        let abs = mantissa.unsigned_abs();
+        let digits = usize::try_from(exponent.unsigned_abs()).unwrap_or(usize::MAX);
+        // The mantissa has no trailing zeros, so neither does the fractional part.
+        match u32::try_from(digits)
+            .ok()
+            .and_then(|d| 10u64.checked_pow(d))
+        {
+            Some(pow) => write!(w, "{sign}{}.{:0digits$}", abs / pow, abs % pow),
+            // The mantissa is smaller than 10^digits.
+            None => write!(w, "{sign}0.{abs:0digits$}"),
+        }
+    };
+
+    res.map_err(|_| EncodeError::BufferTooSmall)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_string(value: Decimal, buf: &mut [u8]) -> &str {
+        let mut w = Writer::new(buf);
+        number(&mut w, value).unwrap();
+        let len = w.len;
+        core::str::from_utf8(buf.get(..len).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn numbers() {
+        let mut buf = [0; 64];
+        for (value, expected) in [
+            (Decimal::new(0, 0), "0"),
+            (Decimal::new(42, 0), "42"),
+            (Decimal::new(-42, 2), "-4200"),
+            (Decimal::new(2225, -2), "22.25"),
+            (Decimal::new(-5, -2), "-0.05"),
+            (Decimal::new(-45, -1), "-4.5"),
+            (Decimal::new(1, -21), "0.000000000000000000001"),
+            (Decimal::new(i64::MIN + 1, -20), "-0.09223372036854775807"),
+        ] {
+            assert_eq!(to_string(value, &mut buf), expected);
+        }
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let mut buf = [0; 64];
+        let mut w = Writer::new(&mut buf);
+        string(&mut w, &["a\"b\\", "c\n"]).unwrap();
+        let len = w.len;
+        assert_eq!(buf.get(..len), Some(&br#""a\"b\\c\u000a""#[..]));
+    }
+}
@@ -0,0 +1,330 @@
+//! Encodes sensor readings as [SenML](https://www.rfc-editor.org/rfc/rfc8428) packs, in JSON or
+//! CBOR.
+//!
+//! An [`Encoder`] writes the [`Samples`] of a reading into a caller-provided buffer, without
+//! allocating:
+//!
+//! ```ignore
+//! let samples = sensor.wait_for_reading().await?;
+//!
+//! let mut buf = [0u8; 256];
+//! let len = Encoder::new().with_base_time(now_millis).encode_json(&samples, &mut buf)?;
+//! // `&buf[..len]` contains, e.g.:
+//! // [{"bn":"indoor:","bt":1767225600.25,"n":"temperature","u":"Cel","v":22.25}]
+//! ```
+//!
+//! # Mapping of readings to SenML records
+//!
+//! Each [`Sample`](ariel_os_sensors::sensor::Sample) becomes a record:
+//!
+//! - The base name (`bn`) is the [label](Sensor::label()) of the sensor driver instance followed
+//!   by `:`, unless set with [`Encoder::with_base_name()`].
+//!   It is omitted if the sensor driver instance has no label.
+//! - The name (`n`) is derived from the [`Label`](ariel_os_sensors::Label) of the channel, e.g.,
+//!   `temperature` or `acceleration-x`.
+//! - The unit (`u`) is the SenML unit of the [`MeasurementUnit`](ariel_os_sensors::MeasurementUnit)
+//!   of the channel.
+//!   Values in units SenML does not recommend are converted: *g* to `m/s2`, grams to `kg`, and
+//!   percents to ratios (`/`).
+//!   The unit is omitted when SenML has no equivalent (e.g., for degrees per second).
+//! - The value is scaled according to the [scaling](ariel_os_sensors::sensor::ReadingChannel::scaling())
+//!   of the channel, and written as an exact decimal number: a JSON number, or a CBOR integer or
+//!   decimal fraction (tag 4).
+//!   Channels of [booleans](ariel_os_sensors::MeasurementUnit::Bool) use boolean values (`vb`)
+//!   instead.
+//! - The base time (`bt`) is set on the first record when provided with
+//!   [`Encoder::with_base_time()`].
+//!
+//! Samples of unavailable or disabled channels are skipped, as SenML records must have a value;
+//! so are samples of opaque channels.
+//!
+//! SenML does not define fields for the measurement accuracy.
+//! When enabled with [`Encoder::with_accuracy()`], the bounds of the
+//! [measurement error](ariel_os_sensors::sensor::SampleMetadata::SymmetricalError) are added to
+//! records as the `errmin` and `errmax` extension fields, in the unit of the record.
+//! Both fields are unregistered, and thus ignored by SenML recipients that do not know them.
+
+#![no_std]
+#![deny(missing_docs)]
+
+mod cbor;
+mod json;
+mod record;
+
+use ariel_os_sensors::{
+    Reading as _, Sensor,
+    sensor::{Samples, SensorAccess as _},
+};
+
+use record::{Decimal, Record};
+
+/// Encodes readings as SenML packs, see the [crate-level documentation](crate).
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Encoder<'a> {
+    base_name: Option<&'a str>,
+    base_time: Option<i64>,
+    accuracy: bool,
+}
+
+impl<'a> Encoder<'a> {
+    /// Creates a new [`Encoder`], using the label of the sensor driver instance as base name,
+    /// without base time and without accuracy.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            base_name: None,
+            base_time: None,
+            accuracy: false,
+        }
+    }
+
+    /// Uses `base_name` as base name (`bn`) instead of the label of the sensor driver instance.
+    ///
+    /// SenML names are the concatenation of the base name and of the record names, `base_name`
+    /// thus usually ends with a separator such as `:` or `/`.
+    #[must_use]
+    pub const fn with_base_name(mut self, base_name: &'a str) -> Self {
+        self.base_name = Some(base_name);
+        self
+    }
+
+    /// Sets the base time (`bt`) of the pack, in milliseconds.
+    ///
+    /// As specified by SenML, times of at least 2<sup>28</sup> seconds are absolute, since the
+    /// Unix epoch, and smaller times are relative to the time the pack is processed by the
+    /// recipient: e.g., `-2000` means two seconds before.
+    #[must_use]
+    pub const fn with_base_time(mut self, millis: i64) -> Self {
+        self.base_time = Some(millis);
+        self
+    }
+
+    /// Adds the bounds of the measurement error to records, when available.
+    #[must_use]
+    pub const fn with_accuracy(mut self) -> Self {
+        self.accuracy = true;
+        self
+    }
+
+    /// Encodes `samples` as a SenML JSON pack into `buf`, and returns the length of the pack.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::BufferTooSmall`] if the pack does not fit into `buf`.
+    pub fn encode_json(&self, samples: &Samples, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut writer = Writer::new(buf);
+        json::encode(
+            &mut writer,
+            &self.base(samples.sensor()),
+            self.records(samples),
+        )?;
+        Ok(writer.len)
+    }
+
+    /// Encodes `samples` as a SenML CBOR pack into `buf`, and returns the length of the pack.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::BufferTooSmall`] if the pack does not fit into `buf`.
+    pub fn encode_cbor(&self, samples: &Samples, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let mut writer = Writer::new(buf);
+        let count = self.records(samples).count();
+        cbor::encode(
+            &mut writer,
+            &self.base(samples.sensor()),
+            self.records(samples),
+            count,
+        )?;
+        Ok(writer.len)
+    }
+
+    fn base(&self, sensor: &'static dyn Sensor) -> Base<'a> {
+        let name = match self.base_name {
+            Some(base_name) => Some([base_name, ""]),
+            None => sensor.label().map(|label| [label, ":"]),
+        };
+
+        Base {
+            name,
+            time: self.base_time.map(|millis| Decimal::new(millis, -3)),
+        }
+    }
+
+    fn records<'s>(&self, samples: &'s Samples) -> impl Iterator<Item = Record> + 's {
+        let accuracy = self.accuracy;
+        samples
+            .samples()
+            .filter_map(move |(channel, sample)| Record::new(channel, sample, accuracy))
+    }
+}
+
+/// Base fields, set on the first record of a pack.
+pub(crate) struct Base<'a> {
+    /// Base name, as the concatenation of its parts.
+    name: Option<[&'a str; 2]>,
+    time: Option<Decimal>,
+}
+
+/// Errors returned when encoding a SenML pack.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EncodeError {
+    /// The pack does not fit into the provided buffer.
+    BufferTooSmall,
+}
+
+impl core::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BufferTooSmall => write!(f, "buffer is too small for the SenML pack"),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// Writes bytes into a buffer, keeping track of its length.
+pub(crate) struct Writer<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn new(buf: &'b mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Appends `bytes` to the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncodeError::BufferTooSmall`] if `bytes` do not fit into the buffer.
+    pub(crate) fn write(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        let end = self
+            .len
+            .checked_add(bytes.len())
+            .ok_or(EncodeError::BufferTooSmall)?;
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(EncodeError::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl core::fmt::Write for Writer<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ariel_os_sensors::{
+        Category, Label, MeasurementUnit,
+        sensor::{ReadingChannel, Sample, SampleMetadata},
+    };
+    use ariel_os_sensors_utils::TestSensor;
+
+    use super::*;
+
+    static SENSOR: TestSensor = TestSensor::new(
+        &[
+            ReadingChannel::new(Label::Temperature, -2, MeasurementUnit::Celsius),
+            ReadingChannel::new(
+                Label::RelativeHumidity,
+                0,
+                MeasurementUnit::PercentageRelativeHumidity,
+            ),
+        ],
+        &[
+            Sample::new(2250, SampleMetadata::UnknownAccuracy),
+            Sample::new(40, SampleMetadata::UnknownAccuracy),
+        ],
+    )
+    .with_label("indoor")
+    .with_categories(&[Category::RelativeHumidityTemperature]);
+
+    fn samples(humidity: SampleMetadata) -> Samples {
+        let temperature = Sample::new(
+            2250,
+            SampleMetadata::SymmetricalError {
+                deviation: 25,
+                bias: -20,
+                scaling: -2,
+            },
+        );
+        Samples::from_2(&SENSOR, [temperature, Sample::new(40, humidity)])
+    }
+
+    #[test]
+    fn encode_json() {
+        let mut buf = [0; 256];
+        let len = Encoder::new()
+            .with_base_time(1_767_225_600_250)
+            .encode_json(&samples(SampleMetadata::UnknownAccuracy), &mut buf)
+            .unwrap();
+
+        assert_eq!(
+            core::str::from_utf8(buf.get(..len).unwrap()).unwrap(),
+            r#"[{"bn":"indoor:","bt":1767225600.25,"n":"temperature","u":"Cel","v":22.5},{"n":"relative-humidity","u":"%RH","v":40}]"#
+        );
+    }
+
+    #[test]
+    fn encode_json_with_accuracy() {
+        let mut buf = [0; 256];
+        let len = Encoder::new()
+            .with_base_name("urn:dev:test:")
+            .with_accuracy()
+            .encode_json(&samples(SampleMetadata::ChannelDisabled), &mut buf)
+            .unwrap();
+
+        assert_eq!(
+            core::str::from_utf8(buf.get(..len).unwrap()).unwrap(),
+            r#"[{"bn":"urn:dev:test:","n":"temperature","u":"Cel","v":22.5,"errmin":-0.45,"errmax":0.05}]"#
+        );
+    }
+
+    #[test]
+    fn encode_cbor() {
+        let mut buf = [0; 256];
+        let len = Encoder::new()
+            .with_base_time(-2000)
+            .encode_cbor(&samples(SampleMetadata::UnknownAccuracy), &mut buf)
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x82, // array(2)
+            0xa5, // map(5)
+            0x21, 0x67, b'i', b'n', b'd', b'o', b'o', b'r', b':', // bn: "indoor:"
+            0x22, 0x21, // bt: -2
+            0x00, 0x6b, b't', b'e', b'm', b'p', b'e', b'r', b'a', b't', b'u', b'r', b'e', // n
+            0x01, 0x63, b'C', b'e', b'l', // u: "Cel"
+            0x02, 0xc4, 0x82, 0x20, 0x18, 0xe1, // v: 4([-1, 225])
+            0xa3, // map(3)
+            0x00, 0x71, b'r', b'e', b'l', b'a', b't', b'i', b'v', b'e', b'-', b'h', b'u', b'm',
+            b'i', b'd', b'i', b't', b'y', // n
+            0x01, 0x63, b'%', b'R', b'H', // u: "%RH"
+            0x02, 0x18, 0x28, // v: 40
+        ];
+        assert_eq!(buf.get(..len), Some(expected));
+    }
+
+    #[test]
+    fn buffer_too_small() {
+        let samples = samples(SampleMetadata::UnknownAccuracy);
+        let mut buf = [0; 16];
+
+        assert_eq!(
+            Encoder::new().encode_json(&samples, &mut buf),
+            Err(EncodeError::BufferTooSmall)
+        );
+        assert_eq!(
+            Encoder::new().encode_cbor(&samples, &mut buf),
+            Err(EncodeError::BufferTooSmall)
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use ariel_os_sensors::{
+    Label, MeasurementUnit,
+    sensor::{ReadingChannel, Sample, SampleMetadata},
+};
+
+/// Exact decimal number: `mantissa · 10^exponent`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Decimal {
+    pub(crate) mantissa: i64,
+    pub(crate) exponent: i32,
+}
+
+impl Decimal {
+    /// Creates a new [`Decimal`], normalized so that the mantissa has no trailing zeros.
+    pub(crate) fn new(mut mantissa: i64, mut exponent: i32) -> Self {
+        if mantissa == 0 {
+            return Self {
+                mantissa,
+                exponent: 0,
+            };
+        }
+
+        while mantissa % 10 == 0 {
+            mantissa /= 10;
+            exponent += 1;
+        }
+
+        Self { mantissa, exponent }
+    }
+}
+
+/// Value of a SenML record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    /// Numeric value (`v`).
+    Number(Decimal),
+    /// Boolean value (`vb`).
+    Bool(bool),
+}
+
+/// SenML record obtained from a single sample.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Record {
+    pub(crate) name: &'static str,
+    pub(crate) unit: Option<&'static str>,
+    pub(crate) value: Value,
+    /// Lower and upper bounds of the measurement error.
+    pub(crate) error: Option<(Decimal, Decimal)>,
+}
+
+impl Record {
+    /// Returns the record of `sample`, or `None` if it cannot be represented in SenML: opaque
+    /// channels, and channels without a value, because SenML records must have one.
+    pub(crate) fn new(channel: ReadingChannel, sample: Sample, accuracy: bool) -> Option<Self> {
+        let name = name(channel.label())?;
+        let value = sample.value().ok()?;
+
+        if channel.unit() == MeasurementUnit::Bool {
+            return Some(Self {
+                name,
+                unit: None,
+                value: Value::Bool(value != 0),
+                error: None,
+            });
+        }
+
+        let unit = Unit::new(channel.unit(), channel.label());
+        let scaled = |value: i64, scaling: i8| {
+            Decimal::new(value * unit.factor, i32::from(scaling) + unit.shift)
+        };
+
+        let error = match sample.metadata() {
+            SampleMetadata::SymmetricalError {
+                deviation,
+                bias,
+                scaling,
+            } if accuracy => {
+                let (deviation, bias) = (i64::from(deviation), i64::from(bias));
+                Some((
+                    scaled(bias - deviation, scaling),
+                    scaled(bias + deviation, scaling),
+                ))
+            }
+            _ => None,
+        };
+
+        Some(Self {
+            name,
+            unit: unit.symbol,
+            value: Value::Number(scaled(i64::from(value), channel.scaling())),
+            error,
+        })
+    }
+}
+
+/// SenML unit of a [`MeasurementUnit`], along with the conversion of values to it:
+/// `value · factor · 10^shift`.
+struct Unit {
+    symbol: Option<&'static str>,
+    factor: i64,
+    shift: i32,
+}
+
+impl Unit {
+    fn new(unit: MeasurementUnit, label: Label) -> Self {
+        let symbol = match unit {
+            // SenML only registers m/s², using standard gravity.
+            MeasurementUnit::AccelG => {
+                return Self {
+                    symbol: Some("m/s2"),
+                    factor: 980_665,
+                    shift: -5,
+                };
+            }
+            // The gram is not recommended by SenML.
+            MeasurementUnit::Gram => {
+                return Self {
+                    symbol: Some("kg"),
+                    factor: 1,
+                    shift: -3,
+                };
+            }
+            // The percent is not recommended by SenML, which uses ratios instead.
+            MeasurementUnit::Percent => {
+                return Self {
+                    symbol: Some("/"),
+                    factor: 1,
+                    shift: -2,
+                };
+            }
+            MeasurementUnit::DecimalDegree => match label {
+                Label::Latitude => Some("lat"),
+                Label::Longitude => Some("lon"),
+                _ => Some("deg"),
+            },
+            MeasurementUnit::Ampere => Some("A"),
+            MeasurementUnit::Becquerel => Some("Bq"),
+            MeasurementUnit::Candela => Some("cd"),
+            MeasurementUnit::Celsius => Some("Cel"),
+            MeasurementUnit::Coulomb => Some("C"),
+            MeasurementUnit::Decibel => Some("dB"),
+            MeasurementUnit::Degree => Some("deg"),
+            MeasurementUnit::Farad => Some("F"),
+            MeasurementUnit::Gray => Some("Gy"),
+            MeasurementUnit::Henry => Some("H"),
+            MeasurementUnit::Hertz => Some("Hz"),
+            MeasurementUnit::Joule => Some("J"),
+            MeasurementUnit::Katal => Some("kat"),
+            MeasurementUnit::Kelvin => Some("K"),
+            MeasurementUnit::Lumen => Some("lm"),
+            MeasurementUnit::Lux => Some("lx"),
+            MeasurementUnit::Meter => Some("m"),
+            MeasurementUnit::MeterPerSecond => Some("m/s"),
+            MeasurementUnit::Mole => Some("mol"),
+            MeasurementUnit::Newton => Some("N"),
+            MeasurementUnit::Ohm => Some("Ohm"),
+            MeasurementUnit::PartsPerMillion => Some("ppm"),
+            MeasurementUnit::Pascal => Some("Pa"),
+            MeasurementUnit::PercentageRelativeHumidity => Some("%RH"),
+            MeasurementUnit::Radian => Some("rad"),
+            MeasurementUnit::Second => Some("s"),
+            MeasurementUnit::Siemens => Some("S"),
+            MeasurementUnit::Sievert => Some("Sv"),
+            MeasurementUnit::Steradian => Some("sr"),
+            MeasurementUnit::Tesla => Some("T"),
+            MeasurementUnit::Volt => Some("V"),
+            MeasurementUnit::Watt => Some("W"),
+            MeasurementUnit::Weber => Some("Wb"),
+            // No SenML unit exists for these, the unit is then omitted.
+            _ => None,
+        };
+
+        Self {
+            symbol,
+            factor: 1,
+            shift: 0,
+        }
+    }
+}
+
+/// Returns the SenML record name of the channel labeled `label`.
+fn name(label: Label) -> Option<&'static str> {
+    let name = match label {
+        Label::AccelerationX => "acceleration-x",
+        Label::AccelerationY => "acceleration-y",
+        Label::AccelerationZ => "acceleration-z",
+        Label::Altitude => "altitude",
+        Label::AngularVelocityX => "angular-velocity-x",
+        Label::AngularVelocityY => "angular-velocity-y",
+        Label::AngularVelocityZ => "angular-velocity-z",
+        Label::Co2 => "co2",
+        Label::DewPoint => "dew-point",
+        Label::GroundSpeed => "ground-speed",
+        Label::Illuminance => "illuminance",
+        Label::Latitude => "latitude",
+        Label::Longitude => "longitude",
+        Label::Pitch => "pitch",
+        Label::Pressure => "pressure",
+        Label::RelativeHumidity => "relative-humidity",
+        Label::Roll => "roll",
+        Label::Heading => "heading",
+        Label::Temperature => "temperature",
+        Label::VerticalSpeed => "vertical-speed",
+        Label::X => "x",
+        Label::Y => "y",
+        Label::Z => "z",
+        // Opaque channels are intended for the sensor driver only.
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_is_normalized() {
+        assert_eq!(
+            Decimal::new(2500, -2),
+            Decimal {
+                mantissa: 25,
+                exponent: 0
+            }
+        );
+        assert_eq!(
+            Decimal::new(-2250, -2),
+            Decimal {
+                mantissa: -225,
+                exponent: -1
+            }
+        );
+        assert_eq!(
+            Decimal::new(0, -3),
+            Decimal {
+                mantissa: 0,
+                exponent: 0
+            }
+        );
+    }
+
+    #[test]
+    fn unavailable_channels_are_skipped() {
+        let channel = ReadingChannel::new(Label::Temperature, -2, MeasurementUnit::Celsius);
+
+        for metadata in [
+            SampleMetadata::ChannelTemporarilyUnavailable,
+            SampleMetadata::ChannelDisabled,
+        ] {
+            assert_eq!(Record::new(channel, Sample::new(0, metadata), true), None);
+        }
+
+        let channel = ReadingChannel::new(Label::Opaque, 0, MeasurementUnit::Celsius);
+        let sample = Sample::new(0, SampleMetadata::UnknownAccuracy);
+        assert_eq!(Record::new(channel, sample, true), None);
+    }
+
+    #[test]
+    fn accel_g_is_converted() {
+        let channel = ReadingChannel::new(Label::AccelerationZ, -3, MeasurementUnit::AccelG);
+        let sample = Sample::new(
+            1000,
+            SampleMetadata::SymmetricalError {
+                deviation: 20,
+                bias: 0,
+                scaling: -3,
+            },
+        );
+
+        let record = Record::new(channel, sample, true).unwrap();
+        assert_eq!(record.unit, Some("m/s2"));
+        assert_eq!(record.value, Value::Number(Decimal::new(980_665, -5)));
+        assert_eq!(
+            record.error,
+            Some((
+                Decimal::new(-20 * 980_665, -8),
+                Decimal::new(20 * 980_665, -8)
+            ))
+        );
+
+        // Accuracy is only provided when requested.
+        assert_eq!(Record::new(channel, sample, false).unwrap().error, None);
+    }
+}
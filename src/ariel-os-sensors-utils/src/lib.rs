@@ -4,5 +4,9 @@
 #![deny(missing_docs)]
 
 mod atomic_state;
+#[cfg(feature = "test-sensor")]
+mod test_sensor;
 
 pub use atomic_state::AtomicState;
+#[cfg(feature = "test-sensor")]
+pub use test_sensor::TestSensor;
@@ -0,0 +1,154 @@
+use core::cell::Cell;
+
+use ariel_os_sensors::{
+    Category, Sensor,
+    sensor::{
+        Mode, ReadingChannel, ReadingChannels, ReadingError, ReadingResult, ReadingWaiter, Sample,
+        Samples, SetModeError, State, TriggerMeasurementError,
+    },
+    signal::Signal as ReadingSignal,
+};
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+use crate::AtomicState;
+
+/// Configurable sensor driver for tests, returning a reading as soon as it is waited for.
+///
+/// Its reading channels and samples are set at construction, and it must have either one or two
+/// of them.
+/// Readings are only returned while it is enabled, and setting its mode changes its state
+/// accordingly.
+///
+/// ```
+/// # use ariel_os_sensors::{Label, MeasurementUnit};
+/// # use ariel_os_sensors::sensor::{ReadingChannel, Sample, SampleMetadata};
+/// # use ariel_os_sensors_utils::TestSensor;
+/// static SENSOR: TestSensor = TestSensor::new(
+///     &[ReadingChannel::new(Label::Temperature, -1, MeasurementUnit::Celsius)],
+///     &[Sample::new(215, SampleMetadata::UnknownAccuracy)],
+/// )
+/// .with_label("indoor");
+/// ```
+pub struct TestSensor {
+    channels: &'static [ReadingChannel],
+    samples: &'static [Sample],
+    label: Option<&'static str>,
+    categories: &'static [Category],
+    state: AtomicState,
+    last_mode: CriticalSectionMutex<Cell<Option<Mode>>>,
+    reading: ReadingSignal<ReadingResult<Samples>>,
+}
+
+impl TestSensor {
+    /// Creates a new, enabled [`TestSensor`] returning `samples` on `channels`.
+    ///
+    /// It has no label and is part of no category.
+    #[must_use]
+    pub const fn new(channels: &'static [ReadingChannel], samples: &'static [Sample]) -> Self {
+        Self {
+            channels,
+            samples,
+            label: None,
+            categories: &[],
+            state: AtomicState::new(State::Enabled),
+            last_mode: CriticalSectionMutex::new(Cell::new(None)),
+            reading: ReadingSignal::new(),
+        }
+    }
+
+    /// Sets the label of the sensor driver instance.
+    #[must_use]
+    pub const fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Sets the categories of the sensor driver instance.
+    #[must_use]
+    pub const fn with_categories(mut self, categories: &'static [Category]) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    /// Sets the initial state of the sensor driver instance.
+    #[must_use]
+    pub const fn with_state(mut self, state: State) -> Self {
+        self.state = AtomicState::new(state);
+        self
+    }
+
+    /// Returns the last mode set with [`Sensor::set_mode()`], if any.
+    pub fn last_mode(&self) -> Option<Mode> {
+        self.last_mode.lock(Cell::get)
+    }
+
+    /// Returns the samples of the sensor driver instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sensor driver instance does not have one or two samples.
+    pub fn samples(&'static self) -> Samples {
+        match *self.samples {
+            [sample] => Samples::from_1(self, [sample]),
+            [first, second] => Samples::from_2(self, [first, second]),
+            _ => unimplemented!("test sensors have one or two samples"),
+        }
+    }
+}
+
+impl Sensor for TestSensor {
+    fn trigger_measurement(&self) -> Result<(), TriggerMeasurementError> {
+        if self.state.get() == State::Enabled {
+            Ok(())
+        } else {
+            Err(TriggerMeasurementError::NonEnabled)
+        }
+    }
+
+    fn wait_for_reading(&'static self) -> ReadingWaiter {
+        if self.state.get() != State::Enabled {
+            return ReadingWaiter::new_err(ReadingError::NonEnabled);
+        }
+        self.reading.signal(Ok(self.samples()));
+        ReadingWaiter::new(self.reading.wait())
+    }
+
+    fn reading_channels(&self) -> ReadingChannels {
+        match *self.channels {
+            [channel] => ReadingChannels::from([channel]),
+            [first, second] => ReadingChannels::from([first, second]),
+            _ => unimplemented!("test sensors have one or two reading channels"),
+        }
+    }
+
+    fn set_mode(&self, mode: Mode) -> Result<State, SetModeError> {
+        self.last_mode.lock(|last_mode| last_mode.set(Some(mode)));
+        let state = State::from(mode);
+        self.state.set(state);
+        Ok(state)
+    }
+
+    fn state(&self) -> State {
+        self.state.get()
+    }
+
+    fn categories(&self) -> &'static [Category] {
+        self.categories
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    fn display_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn part_number(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn version(&self) -> u8 {
+        0
+    }
+}
@@ -39,16 +39,22 @@ pub enum Category {
     AccelerometerGyroscopeTemperature,
     /// Accelerometer & magnetometer & temperature sensor.
     AccelerometerMagnetometerTemperature,
+    /// Altimeter.
+    Altimeter,
     /// Ammeter (ampere meter).
     Ammeter,
     /// CO₂ gas sensor.
     Co2Gas,
     /// Color sensor.
     Color,
+    /// Dew point sensor.
+    DewPoint,
     /// GNSS (Global Navigation Satellite System) receiver.
     Gnss,
     /// Gyroscope.
     Gyroscope,
+    /// Inclinometer (tilt sensor).
+    Inclinometer,
     /// Relative humidity sensor.
     RelativeHumidity,
     /// Relative humidity & temperature sensor.
@@ -25,6 +25,8 @@ pub enum Label {
     AngularVelocityZ,
     /// CO<sub>2</sub> concentration.
     Co2,
+    /// Dew point temperature.
+    DewPoint,
     /// Ground speed.
     GroundSpeed,
     /// Illuminance.
@@ -37,10 +39,14 @@ pub enum Label {
     Opaque,
     /// Opaque channel marker used by `GnssTimeExt`.
     OpaqueGnssTime,
+    /// Pitch, rotation about the lateral axis.
+    Pitch,
     /// Pressure.
     Pressure,
     /// Relative humidity.
     RelativeHumidity,
+    /// Roll, rotation about the longitudinal axis.
+    Roll,
     /// Heading.
     Heading,
     /// Temperature.
@@ -66,13 +72,16 @@ impl core::fmt::Display for Label {
             Self::AngularVelocityY => write!(f, "Angular velocity Y"),
             Self::AngularVelocityZ => write!(f, "Angular velocity Z"),
             Self::Co2 => write!(f, "CO2 concentration"),
+            Self::DewPoint => write!(f, "Dew point"),
             Self::GroundSpeed => write!(f, "Ground speed"),
             Self::Illuminance => write!(f, "Illuminance"),
             Self::Latitude => write!(f, "Latitude"),
             Self::Longitude => write!(f, "Longitude"),
             Self::Opaque | Self::OpaqueGnssTime => write!(f, "[opaque]"),
+            Self::Pitch => write!(f, "Pitch"),
             Self::Pressure => write!(f, "Pressure"),
             Self::RelativeHumidity => write!(f, "Relative humidity"),
+            Self::Roll => write!(f, "Roll"),
             Self::Heading => write!(f, "Heading"),
             Self::Temperature => write!(f, "Temperature"),
             Self::VerticalSpeed => write!(f, "Vertical speed"),
@@ -45,6 +45,23 @@
 //! [`ReadingChannel`](sensor::ReadingChannel), for each [`Sample`](sample::Sample) returned.
 //! See [`Sample`](sample::Sample) for more details.
 //!
+//! # Event-driven notifications
+//!
+//! Some sensor drivers can additionally notify events, such as a new reading being available or
+//! a threshold being crossed, without having to trigger measurements.
+//! A [`Trigger`](sensor::Trigger) is configured with [`Sensor::set_trigger()`], after which
+//! the resulting [`Event`](sensor::Event)s can be awaited with [`Sensor::wait_for_event()`].
+//! Sensor drivers not supporting a trigger return
+//! [`SetTriggerError::Unsupported`](sensor::SetTriggerError::Unsupported).
+//!
+//! # Configuring sensor drivers
+//!
+//! Sensor drivers may allow to adjust settings of the sensor device, such as its output data
+//! rate, full-scale range or oversampling, through the [`Sensor`] trait as well:
+//! [`Sensor::settings()`] lists the supported [`Setting`](sensor::Setting)s and their supported
+//! values, which can be read with [`Sensor::setting()`] and changed with
+//! [`Sensor::set_setting()`].
+//!
 //! # For implementors
 //!
 //! Sensor drivers must implement the [`Sensor`] trait.
@@ -3,6 +3,8 @@
 mod channels_samples_zip;
 mod reading_channels;
 mod samples;
+mod settings;
+mod trigger;
 
 use core::{
     future::Future,
@@ -19,6 +21,10 @@ pub use crate::Reading;
 pub use crate::sample::{Sample, SampleError, SampleMetadata};
 pub use reading_channels::ReadingChannels;
 pub use samples::{Samples, SensorAccess};
+pub use settings::{Setting, SettingError, SettingInfo};
+pub use trigger::{
+    Event, EventError, EventResult, EventWaiter, SetTriggerError, ThresholdDirection, Trigger,
+};
 
 /// This trait must be implemented by sensor drivers.
 ///
@@ -104,6 +110,82 @@ pub trait Sensor: Send + Sync {
     /// Returns the sensor driver version number.
     #[must_use]
     fn version(&self) -> u8;
+
+    /// Configures the sensor driver to notify [`Event`]s when `trigger` occurs, replacing the
+    /// previously configured trigger.
+    /// Passing `None` stops notifying events.
+    ///
+    /// Events are obtained with [`Sensor::wait_for_event()`].
+    ///
+    /// # For implementors
+    ///
+    /// This method should return quickly; the sensor device may be configured later on.
+    /// The default implementation does not support any trigger.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`SetTriggerError::Unsupported`] if the sensor driver does not support
+    ///   `trigger`.
+    /// - Returns [`SetTriggerError::NonEnabled`] if the sensor driver is not enabled.
+    fn set_trigger(&self, trigger: Option<Trigger>) -> Result<(), SetTriggerError> {
+        match trigger {
+            None => Ok(()),
+            Some(_) => Err(SetTriggerError::Unsupported),
+        }
+    }
+
+    /// Waits for the next event of the trigger configured with [`Sensor::set_trigger()`] and
+    /// returns it asynchronously.
+    ///
+    /// # Note
+    ///
+    /// Only a single task should wait for events of a given sensor driver instance.
+    ///
+    /// # Errors
+    ///
+    /// - Quickly returns [`EventError::NoTrigger`] if no trigger is configured.
+    /// - Returns [`EventError::SensorAccess`] if the sensor device cannot be accessed.
+    fn wait_for_event(&'static self) -> EventWaiter {
+        EventWaiter::new_err(EventError::NoTrigger)
+    }
+
+    /// Returns the settings supported by the sensor driver, along with their supported values.
+    ///
+    /// # For implementors
+    ///
+    /// The default implementation does not support any setting.
+    #[must_use]
+    fn settings(&self) -> &'static [SettingInfo] {
+        &[]
+    }
+
+    /// Returns the current value of `setting`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SettingError::Unsupported`] if the sensor driver does not support `setting`.
+    fn setting(&self, setting: Setting) -> Result<u32, SettingError> {
+        let _ = setting;
+        Err(SettingError::Unsupported)
+    }
+
+    /// Sets `setting` to `value`.
+    ///
+    /// The new value takes effect for the next measurement at the latest.
+    ///
+    /// # For implementors
+    ///
+    /// This method should return quickly; the sensor device may be configured later on.
+    ///
+    /// # Errors
+    ///
+    /// - Returns [`SettingError::Unsupported`] if the sensor driver does not support `setting`.
+    /// - Returns [`SettingError::InvalidValue`] if `value` is not one of the values returned by
+    ///   [`Sensor::settings()`] for `setting`.
+    fn set_setting(&self, setting: Setting, value: u32) -> Result<(), SettingError> {
+        let _ = (setting, value);
+        Err(SettingError::Unsupported)
+    }
 }
 
 /// Future returned by [`Sensor::wait_for_reading()`].
@@ -285,7 +367,7 @@ impl ReadingChannel {
     ///
     /// This constructor is intended for sensor driver implementors only.
     #[must_use]
-    pub fn new(label: Label, scaling: i8, unit: MeasurementUnit) -> Self {
+    pub const fn new(label: Label, scaling: i8, unit: MeasurementUnit) -> Self {
         Self {
             label,
             scaling,
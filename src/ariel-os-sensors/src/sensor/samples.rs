@@ -154,6 +154,60 @@ impl Samples {
             sensor,
         }
     }
+
+    /// Returns new [`Samples`] attributed to `sensor`, where each sample is replaced by the
+    /// result of `f`, which is given the index of the sample and the sample.
+    ///
+    /// # Note
+    ///
+    /// For implementors of sensor drivers wrapping another sensor driver only: `sensor` must
+    /// return the same [`ReadingChannel`]s as the sensor driver that produced these samples.
+    #[must_use]
+    pub fn map(
+        self,
+        sensor: &'static dyn Sensor,
+        mut f: impl FnMut(usize, Sample) -> Sample,
+    ) -> Self {
+        fn map_array<const N: usize>(
+            samples: [Sample; N],
+            f: &mut impl FnMut(usize, Sample) -> Sample,
+        ) -> [Sample; N] {
+            let mut index = 0;
+            samples.map(|sample| {
+                let sample = f(index, sample);
+                index += 1;
+                sample
+            })
+        }
+
+        let samples = match self.samples {
+            InnerSamples::V1(samples) => InnerSamples::V1(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-2")]
+            InnerSamples::V2(samples) => InnerSamples::V2(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-3")]
+            InnerSamples::V3(samples) => InnerSamples::V3(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-4")]
+            InnerSamples::V4(samples) => InnerSamples::V4(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-5")]
+            InnerSamples::V5(samples) => InnerSamples::V5(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-6")]
+            InnerSamples::V6(samples) => InnerSamples::V6(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-7")]
+            InnerSamples::V7(samples) => InnerSamples::V7(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-8")]
+            InnerSamples::V8(samples) => InnerSamples::V8(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-9")]
+            InnerSamples::V9(samples) => InnerSamples::V9(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-10")]
+            InnerSamples::V10(samples) => InnerSamples::V10(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-11")]
+            InnerSamples::V11(samples) => InnerSamples::V11(map_array(samples, &mut f)),
+            #[cfg(feature = "max-sample-min-count-12")]
+            InnerSamples::V12(samples) => InnerSamples::V12(map_array(samples, &mut f)),
+        };
+
+        Self { samples, sensor }
+    }
 }
 
 impl Reading for Samples {
@@ -0,0 +1,97 @@
+/// A configurable setting of a sensor driver, see [`Sensor::set_setting()`].
+///
+/// [`Sensor::set_setting()`]: super::Sensor::set_setting()
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Setting {
+    /// Rate at which the sensor device produces readings when measuring continuously, in
+    /// millihertz.
+    OutputDataRate,
+    /// Full-scale range of the sensor device, i.e., the largest absolute value it can measure.
+    ///
+    /// Uses the unit and scaling of the channels of the reading.
+    FullScale,
+    /// Number of measurements averaged into each sample.
+    Oversampling,
+}
+
+/// Describes a [`Setting`] supported by a sensor driver, see [`Sensor::settings()`].
+///
+/// [`Sensor::settings()`]: super::Sensor::settings()
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SettingInfo {
+    setting: Setting,
+    values: &'static [u32],
+}
+
+impl SettingInfo {
+    /// Creates a new [`SettingInfo`].
+    ///
+    /// This constructor is intended for sensor driver implementors only.
+    #[must_use]
+    pub const fn new(setting: Setting, values: &'static [u32]) -> Self {
+        Self { setting, values }
+    }
+
+    /// Returns the [`SettingInfo`] of `setting` among `settings`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SettingError::Unsupported`] if `setting` is not part of `settings`.
+    pub fn find(
+        settings: &'static [Self],
+        setting: Setting,
+    ) -> Result<&'static Self, SettingError> {
+        settings
+            .iter()
+            .find(|info| info.setting == setting)
+            .ok_or(SettingError::Unsupported)
+    }
+
+    /// Returns the [`Setting`] described.
+    #[must_use]
+    pub fn setting(&self) -> Setting {
+        self.setting
+    }
+
+    /// Returns the values the setting can be set to, in ascending order.
+    #[must_use]
+    pub fn values(&self) -> &'static [u32] {
+        self.values
+    }
+
+    /// Returns the index of `value` in [`Self::values()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SettingError::InvalidValue`] if `value` is not supported.
+    pub fn position(&self, value: u32) -> Result<usize, SettingError> {
+        self.values
+            .iter()
+            .position(|v| *v == value)
+            .ok_or(SettingError::InvalidValue)
+    }
+}
+
+/// Represents errors happening when accessing a [`Setting`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SettingError {
+    /// The sensor driver does not support this setting.
+    Unsupported,
+    /// The sensor driver does not support this value for the setting.
+    InvalidValue,
+}
+
+impl core::fmt::Display for SettingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "setting is not supported by the sensor driver"),
+            Self::InvalidValue => write!(f, "value is not supported for this setting"),
+        }
+    }
+}
+
+impl core::error::Error for SettingError {}
@@ -0,0 +1,160 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{Label, signal};
+
+use super::Samples;
+
+/// Condition under which a sensor driver notifies [`Event`]s, see [`Sensor::set_trigger()`].
+///
+/// [`Sensor::set_trigger()`]: super::Sensor::set_trigger()
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Trigger {
+    /// A new reading is available.
+    ///
+    /// The sensor device measures continuously, at a rate chosen by the sensor driver.
+    DataReady,
+    /// The sensor device detected motion, i.e., the change of a sample exceeded `threshold`.
+    ///
+    /// `threshold` uses the unit and scaling of the channels of the reading.
+    WakeUp {
+        /// Minimum change of a sample.
+        threshold: u32,
+    },
+    /// The sample of the channel labeled `label` crossed `value`, in `direction`.
+    ///
+    /// `value` uses the unit and scaling of that channel.
+    Threshold {
+        /// Label of the channel to monitor.
+        label: Label,
+        /// Threshold value of the sample.
+        value: i32,
+        /// Direction in which the threshold must be crossed.
+        direction: ThresholdDirection,
+    },
+}
+
+/// Direction in which a [`Trigger::Threshold`] must be crossed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ThresholdDirection {
+    /// The sample rises above the threshold value.
+    Above,
+    /// The sample falls below the threshold value.
+    Below,
+}
+
+/// Event notified by a sensor driver, see [`Sensor::wait_for_event()`].
+///
+/// [`Sensor::wait_for_event()`]: super::Sensor::wait_for_event()
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum Event {
+    /// A new reading is available, see [`Trigger::DataReady`].
+    ///
+    /// The reading can be interpreted in the same way as the ones returned by
+    /// [`Sensor::wait_for_reading()`](super::Sensor::wait_for_reading()).
+    DataReady(Samples),
+    /// Motion was detected, see [`Trigger::WakeUp`].
+    WakeUp,
+    /// The threshold was crossed, see [`Trigger::Threshold`].
+    Threshold,
+}
+
+/// Possible errors when attempting to configure a [`Trigger`].
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SetTriggerError {
+    /// The sensor driver does not support this trigger.
+    Unsupported,
+    /// The sensor driver is not enabled (e.g., it may be disabled or sleeping).
+    NonEnabled,
+}
+
+impl core::fmt::Display for SetTriggerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "trigger is not supported by the sensor driver"),
+            Self::NonEnabled => write!(f, "sensor driver is not enabled"),
+        }
+    }
+}
+
+impl core::error::Error for SetTriggerError {}
+
+/// Represents errors happening when waiting for an [`Event`].
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EventError {
+    /// No trigger is configured.
+    /// It is necessary to call [`Sensor::set_trigger()`](super::Sensor::set_trigger()) before
+    /// waiting for events.
+    NoTrigger,
+    /// Cannot access the sensor device (e.g., because of a bus error).
+    SensorAccess,
+}
+
+impl core::fmt::Display for EventError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoTrigger => write!(f, "no trigger is configured"),
+            Self::SensorAccess => write!(f, "sensor device could not be accessed"),
+        }
+    }
+}
+
+impl core::error::Error for EventError {}
+
+/// A specialized [`Result`] type for [`Event`]s.
+pub type EventResult = Result<Event, EventError>;
+
+/// Future returned by [`Sensor::wait_for_event()`](super::Sensor::wait_for_event()).
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct EventWaiter {
+    inner: EventWaiterInner,
+}
+
+enum EventWaiterInner {
+    Waiter(signal::ReceiveFuture<'static, EventResult>),
+    Err(EventError),
+}
+
+impl EventWaiter {
+    /// Creates a new [`Future`] to send back [`Event`]s.
+    ///
+    /// # Note
+    ///
+    /// For sensor driver implementors only.
+    pub fn new(fut: signal::ReceiveFuture<'static, EventResult>) -> Self {
+        Self {
+            inner: EventWaiterInner::Waiter(fut),
+        }
+    }
+
+    /// Creates a new [`Future`] to send back an error that happened when waiting for an event.
+    ///
+    /// # Note
+    ///
+    /// For sensor driver implementors only.
+    pub fn new_err(err: EventError) -> Self {
+        Self {
+            inner: EventWaiterInner::Err(err),
+        }
+    }
+}
+
+impl Future for EventWaiter {
+    type Output = EventResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.inner {
+            EventWaiterInner::Waiter(waiter) => Pin::new(waiter).poll(cx),
+            EventWaiterInner::Err(err) => Poll::Ready(Err(*err)),
+        }
+    }
+}
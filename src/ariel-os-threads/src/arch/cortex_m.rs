@@ -98,6 +98,8 @@ impl Arch for Cpu {
             let mut p = cortex_m::Peripherals::steal();
             p.SCB.set_priority(SystemHandler::PendSV, 0xFF);
         }
+        #[cfg(all(armv7m, feature = "stack-overflow-detection"))]
+        mpu::init();
         Self::schedule();
     }
 
@@ -311,6 +313,8 @@ unsafe extern "C" fn sched() -> u64 {
 
                     #[cfg(not(feature = "multi-core"))]
                     {
+                        #[cfg(feature = "cpu-time")]
+                        scheduler.account_switch(None);
                         Cpu::wfi();
                         // this fence seems necessary, see #310.
                         core::sync::atomic::fence(core::sync::atomic::Ordering::Acquire);
@@ -319,11 +323,15 @@ unsafe extern "C" fn sched() -> u64 {
                 }
             };
 
+            #[cfg(feature = "cpu-time")]
+            scheduler.account_switch(Some(next_tid));
+
             // `current_high_regs` will be null if there is no current thread.
             // This is only the case once, when the very first thread starts running.
             // The returned `r1` therefore will be null, and saving/ restoring
             // the context is skipped.
             let mut current_high_regs = core::ptr::null();
+            let mut _prev_tid = None;
             if let Some(current_tid_ref) = scheduler.current_tid_mut() {
                 if next_tid == *current_tid_ref {
                     return Some((0, 0));
@@ -333,10 +341,17 @@ unsafe extern "C" fn sched() -> u64 {
                 let current = scheduler.get_unchecked_mut(current_tid);
                 current.data.sp = cortex_m::register::psp::read() as usize;
                 current_high_regs = current.data.high_regs.as_ptr();
+                _prev_tid = Some(current_tid);
             } else {
                 *scheduler.current_tid_mut() = Some(next_tid);
             }
 
+            #[cfg(feature = "trace")]
+            crate::trace::record(crate::trace::Event::ContextSwitch {
+                from: _prev_tid,
+                to: next_tid,
+            });
+
             let next = scheduler.get_unchecked(next_tid);
             // SAFETY: changing the PSP as part of context switch
             unsafe { cortex_m::register::psp::write(next.data.sp as u32) };
@@ -347,8 +362,18 @@ unsafe extern "C" fn sched() -> u64 {
                 cortex_m::register::psplim::write(next.stack_lowest as u32)
             };
 
+            #[cfg(all(armv7m, feature = "stack-overflow-detection"))]
+            mpu::set_guard(next.stack_lowest, next.stack_highest);
+
             let next_high_regs = next.data.high_regs.as_ptr();
 
+            // This needs to happen after moving the guard region away from the previous thread's
+            // stack, so the canary can be restored.
+            #[cfg(feature = "stack-overflow-detection")]
+            if let Some(prev_tid) = _prev_tid {
+                scheduler.check_stack_overflow(prev_tid);
+            }
+
             Some((current_high_regs as u32, next_high_regs as u32))
         }) {
             break res;
@@ -364,3 +389,121 @@ unsafe extern "C" fn sched() -> u64 {
     // See https://github.com/ARM-software/abi-aa/blob/a82eef0433556b30539c0d4463768d9feb8cfd0b/aapcs32/aapcs32.rst#6111handling-values-larger-than-32-bits
     (current_high_regs as u64) | (next_high_regs as u64) << 32
 }
+
+/// Stack guard using the MPU of ARMv7-M.
+///
+/// The highest-numbered MPU region, which takes precedence over all others, is reserved for a
+/// read-only guard region at the bottom of the running thread's stack.
+/// Stack overflows thus trigger a `MemoryManagement` fault as soon as they write to the guard,
+/// before corrupting any memory below the stack.
+#[cfg(all(armv7m, feature = "stack-overflow-detection"))]
+mod mpu {
+    use cortex_m::peripheral::{MPU, SCB, scb::Exception};
+
+    use crate::SCHEDULER;
+
+    /// Size of the guard region, which is the smallest MPU region size.
+    const GUARD_SIZE: usize = 32;
+
+    const CTRL_ENABLE: u32 = 1 << 0;
+    const CTRL_PRIVDEFENA: u32 = 1 << 2;
+    const RBAR_VALID: u32 = 1 << 4;
+    const RASR_ENABLE: u32 = 1 << 0;
+    /// `SIZE` field of a 32-byte region, log2(32) - 1.
+    const RASR_SIZE_32: u32 = 4 << 1;
+    /// Read-only for both privileged and unprivileged accesses.
+    ///
+    /// Reads are allowed so that the stack can still be inspected, e.g., for stack usage.
+    const RASR_AP_RO: u32 = 0b110 << 24;
+    const RASR_XN: u32 = 1 << 28;
+
+    const CFSR_MSTKERR: u32 = 1 << 4;
+    const CFSR_MMARVALID: u32 = 1 << 7;
+
+    /// Returns the MPU region reserved for the guard, or `None` if there is no MPU.
+    fn guard_region(mpu: &cortex_m::peripheral::mpu::RegisterBlock) -> Option<u32> {
+        let regions = (mpu._type.read() >> 8) & 0xFF;
+        regions.checked_sub(1)
+    }
+
+    /// Returns the start address of the guard region of a stack, if the stack is large enough.
+    fn guard_start(stack_lowest: usize, stack_highest: usize) -> Option<usize> {
+        let start = stack_lowest.next_multiple_of(GUARD_SIZE);
+        (start + GUARD_SIZE <= stack_highest).then_some(start)
+    }
+
+    /// Enables the MPU and the `MemoryManagement` fault, if there is an MPU.
+    pub fn init() {
+        // SAFETY: only the MPU control register and the enabled system handlers are modified.
+        let mut p = unsafe { cortex_m::Peripherals::steal() };
+        if guard_region(&p.MPU).is_none() {
+            return;
+        }
+        p.SCB.enable(Exception::MemoryManagement);
+        // SAFETY: with `PRIVDEFENA`, the default memory map still applies outside of the regions.
+        unsafe {
+            p.MPU
+                .ctrl
+                .modify(|ctrl| ctrl | CTRL_ENABLE | CTRL_PRIVDEFENA);
+        }
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    /// Moves the guard region to the bottom of the given stack.
+    pub fn set_guard(stack_lowest: usize, stack_highest: usize) {
+        // SAFETY: the guard region is reserved for this module.
+        let mpu = unsafe { &*MPU::PTR };
+        let Some(region) = guard_region(mpu) else {
+            return;
+        };
+        match guard_start(stack_lowest, stack_highest) {
+            // SAFETY: the region only makes part of the stack read-only.
+            Some(start) => unsafe {
+                mpu.rbar.write(start as u32 | RBAR_VALID | region);
+                mpu.rasr
+                    .write(RASR_XN | RASR_AP_RO | RASR_SIZE_32 | RASR_ENABLE);
+            },
+            // The stack is too small to spare a guard region.
+            // SAFETY: disabling the guard region does not restrict accesses.
+            None => unsafe {
+                mpu.rnr.write(region);
+                mpu.rasr.write(0);
+            },
+        }
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    #[allow(non_snake_case)]
+    #[cortex_m_rt::exception]
+    fn MemoryManagement() -> ! {
+        // SAFETY: the fault status registers are only read.
+        let scb = unsafe { &*SCB::PTR };
+        let cfsr = scb.cfsr.read();
+        let fault_addr = (cfsr & CFSR_MMARVALID != 0).then(|| scb.mmfar.read() as usize);
+
+        let overflowed = critical_section::with(|cs| {
+            // SAFETY: the scheduler is only read, and the faulting thread does not resume, so it
+            // doesn't matter if it was modifying the scheduler.
+            let scheduler = unsafe { &*SCHEDULER.as_ptr(cs) };
+            let thread_id = scheduler.current_tid()?;
+            let thread = scheduler.get_unchecked(thread_id);
+            let guard = guard_start(thread.stack_lowest, thread.stack_highest)?;
+            // Exception entry stacking faults report no address.
+            let hit_guard =
+                fault_addr.is_some_and(|addr| (guard..guard + GUARD_SIZE).contains(&addr));
+            (hit_guard || cfsr & CFSR_MSTKERR != 0).then_some(thread_id)
+        });
+
+        ariel_os_log::error!(
+            "memory management fault: CFSR: 0x{:x}, MMFAR: {:?}",
+            cfsr,
+            fault_addr
+        );
+        if let Some(thread_id) = overflowed {
+            crate::stack_overflow::report_overflow(thread_id);
+        }
+        panic!("memory management fault");
+    }
+}
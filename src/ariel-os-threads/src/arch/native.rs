@@ -16,11 +16,18 @@ static THREAD_RUNNABLE: [AtomicU32; crate::THREAD_COUNT] =
 #[derive(Debug)]
 pub struct ThreadData {
     thread: Option<std::thread::Thread>,
+    /// Lowest address of the part of the host thread stack that the thread may use.
+    #[cfg(feature = "stack-overflow-detection")]
+    pub stack_lowest: usize,
 }
 
 impl ThreadData {
     pub const fn new() -> Self {
-        Self { thread: None }
+        Self {
+            thread: None,
+            #[cfg(feature = "stack-overflow-detection")]
+            stack_lowest: 0,
+        }
     }
 }
 
@@ -34,13 +41,21 @@ impl Arch for Cpu {
     type ThreadData = ThreadData;
     const DEFAULT_THREAD_DATA: Self::ThreadData = ThreadData::new();
 
-    fn setup_stack(thread: &mut Thread, _stack: &mut [u8], func: fn(), arg: Option<usize>) {
+    fn setup_stack(thread: &mut Thread, stack: &mut [u8], func: fn(), arg: Option<usize>) {
         let thread_id = thread.tid;
+        #[cfg(feature = "stack-overflow-detection")]
+        let stack_size = stack.len();
+        #[cfg(not(feature = "stack-overflow-detection"))]
+        let _ = stack;
 
         let handle = std::thread::spawn(move || {
             ThreadData::ID.with(|x| x.set(Some(thread_id)));
             atomic_wait::wait(&THREAD_RUNNABLE[usize::from(thread_id)], 0);
 
+            // The thread runs on the stack of this host thread, so that is where the canary goes.
+            #[cfg(feature = "stack-overflow-detection")]
+            crate::stack_overflow::init_host_stack_canary(thread_id, stack_size);
+
             // We use catch_unwind here to catch if a thread panics.
             // In that case, we abort the process, which is as close as it gets
             // to an actual MCU target panicking.
@@ -73,8 +88,11 @@ impl Arch for Cpu {
                 std::process::abort();
             }
 
+            #[cfg(feature = "thread-local")]
+            crate::thread_local::destroy_all(thread_id);
+
             SCHEDULER.with_mut(|mut scheduler| {
-                scheduler.set_state(thread_id, ThreadState::Invalid);
+                scheduler.finish(thread_id);
             });
         });
 
@@ -82,6 +100,9 @@ impl Arch for Cpu {
     }
 
     fn start_threading() {
+        #[cfg(feature = "trace")]
+        crate::trace::write_file_at_exit();
+
         loop {
             SCHEDULER.with(|scheduler| {
                 for (n, thread) in scheduler.threads.iter().enumerate() {
@@ -338,6 +338,17 @@ extern "C" fn sched() {
             "idle threads should be enabled, the scheduler should always have a thread ready",
         );
 
+        #[cfg(feature = "cpu-time")]
+        scheduler.account_switch(Some(next_tid));
+
+        #[cfg(feature = "trace")]
+        if scheduler.current_tid() != Some(next_tid) {
+            crate::trace::record(crate::trace::Event::ContextSwitch {
+                from: scheduler.current_tid(),
+                to: next_tid,
+            });
+        }
+
         let mut current_high_regs = core::ptr::null_mut();
 
         if let Some(current_tid_ref) = scheduler.current_tid_mut() {
@@ -349,6 +360,8 @@ extern "C" fn sched() {
             let current = scheduler.get_unchecked_mut(current_tid);
             current.data.mepc = register::mepc::read();
             current_high_regs = &raw mut current.data;
+            #[cfg(feature = "stack-overflow-detection")]
+            scheduler.check_stack_overflow(current_tid);
         } else {
             *scheduler.current_tid_mut() = Some(next_tid);
         }
@@ -120,14 +120,29 @@ unsafe fn sched(trap_frame: &mut TrapFrame) {
             scheduler.add_current_thread_to_rq();
 
             let Some(next_tid) = scheduler.get_next_tid() else {
+                #[cfg(feature = "cpu-time")]
+                scheduler.account_switch(None);
                 return false;
             };
 
+            #[cfg(feature = "cpu-time")]
+            scheduler.account_switch(Some(next_tid));
+
+            #[cfg(feature = "trace")]
+            if scheduler.current_tid() != Some(next_tid) {
+                crate::trace::record(crate::trace::Event::ContextSwitch {
+                    from: scheduler.current_tid(),
+                    to: next_tid,
+                });
+            }
+
             if let Some(current_tid) = scheduler.current_tid() {
                 if next_tid == current_tid {
                     return true;
                 }
                 scheduler.threads[usize::from(current_tid)].data = *trap_frame;
+                #[cfg(feature = "stack-overflow-detection")]
+                scheduler.check_stack_overflow(current_tid);
             }
             *scheduler.current_tid_mut() = Some(next_tid);
 
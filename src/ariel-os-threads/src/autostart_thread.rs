@@ -1,10 +1,10 @@
 /// Starts the `fn_name` function in a dedicated thread at startup.
 ///
-/// The thread is given a `stacksize`-byte stack, and has priority `priority`.
+/// The thread is given a `stacksize`-byte stack, has priority `priority` and is named `name`.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! autostart_thread {
-    ($fn_name:ident, stacksize = $stacksize:expr, priority = $priority:expr, affinity = $affinity:expr) => {
+    ($fn_name:ident, stacksize = $stacksize:expr, priority = $priority:expr, affinity = $affinity:expr, name = $name:expr) => {
         $crate::macro_reexports::paste::paste! {
             #[allow(non_snake_case)]
             #[$crate::macro_reexports::linkme::distributed_slice($crate::THREAD_FNS)]
@@ -12,7 +12,8 @@ macro_rules! autostart_thread {
             fn [<__start_thread_ $fn_name>] () {
                 use $crate::macro_reexports::static_cell::ConstStaticCell;
                 static STACK: ConstStaticCell<[u8; $stacksize]> = ConstStaticCell::new([0u8; $stacksize]);
-                $crate::create_noarg($fn_name, STACK.take(), $priority, $affinity);
+                // Dropping the handle detaches the thread.
+                let _ = $crate::create_noarg_named($fn_name, STACK.take(), $priority, $affinity, $name);
             }
         }
     };
@@ -0,0 +1,170 @@
+//! Per-thread CPU time accounting.
+//!
+//! When the `cpu-time` feature is enabled, the scheduler accumulates, on every context switch,
+//! the time each thread has been running and how often it has been switched to, as well as the
+//! time each core has been idle (either running its idle thread or waiting for a thread to
+//! become ready).
+//!
+//! The time is measured using the `embassy-time` driver, so its resolution is that of the
+//! system timer.
+//!
+//! The CPU load over an interval can be computed by calling [`reset()`] at the beginning of the
+//! interval and comparing [`idle_time()`] to [`elapsed()`] at its end.
+//!
+//! On native, threads are not scheduled by Ariel OS, so all counters stay at zero.
+use embassy_time::Duration;
+
+use crate::{CORE_COUNT, CoreId, SCHEDULER, Scheduler, ThreadId, core_id};
+
+/// CPU time statistics of a thread, see [`thread_time()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ThreadCpuTime {
+    /// Total time the thread has been running since the last [`reset()`].
+    pub run_time: Duration,
+    /// Number of times the thread has been switched to since the last [`reset()`].
+    pub switches: u32,
+}
+
+/// Accounting state of the scheduler.
+pub(crate) struct Accounting {
+    /// Timestamp of the last accounting point, per core.
+    since: [u64; CORE_COUNT],
+    /// Thread the time since the last accounting point is charged to, per core.
+    ///
+    /// `None` while the core is waiting for a thread to become ready.
+    running: [Option<ThreadId>; CORE_COUNT],
+    /// Accumulated idle time, per core.
+    idle_ticks: [u64; CORE_COUNT],
+    /// Timestamp of the last reset.
+    reset_at: u64,
+    /// The idle threads, whose run time counts as idle time.
+    #[cfg(feature = "idle-threads")]
+    idle_threads: [Option<ThreadId>; CORE_COUNT],
+}
+
+impl Accounting {
+    pub(crate) const fn new() -> Self {
+        Self {
+            since: [0; CORE_COUNT],
+            running: [None; CORE_COUNT],
+            idle_ticks: [0; CORE_COUNT],
+            reset_at: 0,
+            #[cfg(feature = "idle-threads")]
+            idle_threads: [None; CORE_COUNT],
+        }
+    }
+
+    /// Returns whether the time charged to `thread_id` counts as idle time.
+    #[cfg_attr(not(feature = "idle-threads"), expect(clippy::unused_self))]
+    fn is_idle(&self, thread_id: Option<ThreadId>) -> bool {
+        #[cfg(feature = "idle-threads")]
+        if thread_id.is_some() && self.idle_threads.contains(&thread_id) {
+            return true;
+        }
+        thread_id.is_none()
+    }
+}
+
+impl Scheduler {
+    /// Charges the time since the last accounting point on the current core, and starts
+    /// charging `next` from now on.
+    ///
+    /// Must be called by the arch-specific scheduler whenever it has picked the next thread to
+    /// run, or `None` if it is about to wait for a thread to become ready.
+    pub(crate) fn account_switch(&mut self, next: Option<ThreadId>) {
+        let now = embassy_time_driver::now();
+        let core = usize::from(core_id());
+        let prev = self.cpu_time.running[core];
+        let elapsed = now.saturating_sub(self.cpu_time.since[core]);
+        self.cpu_time.since[core] = now;
+
+        if let Some(prev) = prev {
+            let thread = self.get_unchecked_mut(prev);
+            thread.run_ticks = thread.run_ticks.saturating_add(elapsed);
+        }
+        if self.cpu_time.is_idle(prev) {
+            self.cpu_time.idle_ticks[core] = self.cpu_time.idle_ticks[core].saturating_add(elapsed);
+        }
+
+        if next != prev {
+            if let Some(next) = next {
+                let thread = self.get_unchecked_mut(next);
+                thread.switches = thread.switches.saturating_add(1);
+            }
+            self.cpu_time.running[core] = next;
+        }
+    }
+
+    /// Registers an idle thread, so that its run time is accounted as idle time.
+    #[cfg(feature = "idle-threads")]
+    pub(crate) fn register_idle_thread(&mut self, core: usize, thread_id: ThreadId) {
+        self.cpu_time.idle_threads[core] = Some(thread_id);
+    }
+
+    /// Returns the time since the last accounting point on `core`.
+    fn pending_ticks(&self, core: usize, now: u64) -> u64 {
+        now.saturating_sub(self.cpu_time.since[core])
+    }
+}
+
+/// Returns the CPU time statistics of a thread.
+///
+/// The run time includes the time the thread has been running since it was last switched to, if
+/// it is currently running.
+///
+/// Returns `None` if this is not a valid thread.
+pub fn thread_time(thread_id: ThreadId) -> Option<ThreadCpuTime> {
+    SCHEDULER.with(|scheduler| {
+        if !scheduler.is_valid_tid(thread_id) {
+            return None;
+        }
+        let now = embassy_time_driver::now();
+        let thread = scheduler.get_unchecked(thread_id);
+        let pending: u64 = (0..CORE_COUNT)
+            .filter(|&core| scheduler.cpu_time.running[core] == Some(thread_id))
+            .map(|core| scheduler.pending_ticks(core, now))
+            .sum();
+        Some(ThreadCpuTime {
+            run_time: Duration::from_ticks(thread.run_ticks.saturating_add(pending)),
+            switches: thread.switches,
+        })
+    })
+}
+
+/// Returns the time `core` has been idle since the last [`reset()`].
+///
+/// This includes the time spent running the core's idle thread, if idle threads are enabled.
+pub fn idle_time(core: CoreId) -> Duration {
+    SCHEDULER.with(|scheduler| {
+        let core = usize::from(core);
+        let mut idle = scheduler.cpu_time.idle_ticks[core];
+        if scheduler.cpu_time.is_idle(scheduler.cpu_time.running[core]) {
+            let now = embassy_time_driver::now();
+            idle = idle.saturating_add(scheduler.pending_ticks(core, now));
+        }
+        Duration::from_ticks(idle)
+    })
+}
+
+/// Returns the time elapsed since the last [`reset()`], or since boot if it has never been
+/// called.
+pub fn elapsed() -> Duration {
+    SCHEDULER.with(|scheduler| {
+        Duration::from_ticks(embassy_time_driver::now().saturating_sub(scheduler.cpu_time.reset_at))
+    })
+}
+
+/// Resets all counters.
+pub fn reset() {
+    SCHEDULER.with_mut(|mut scheduler| {
+        let now = embassy_time_driver::now();
+        for thread in &mut scheduler.threads {
+            thread.run_ticks = 0;
+            thread.switches = 0;
+        }
+        scheduler.cpu_time.since = [now; CORE_COUNT];
+        scheduler.cpu_time.idle_ticks = [0; CORE_COUNT];
+        scheduler.cpu_time.reset_at = now;
+    });
+}
@@ -0,0 +1,121 @@
+//! Provides introspection of the existing threads.
+use crate::{CoreAffinity, RunqueueId, SCHEDULER, THREAD_COUNT, ThreadId, ThreadState, thread};
+
+/// Snapshot of a thread's information, as returned by [`threads()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ThreadInfo {
+    /// Id of the thread.
+    pub tid: ThreadId,
+    /// Name of the thread, if any.
+    pub name: Option<&'static str>,
+    /// State of the thread when the snapshot was taken.
+    pub state: ThreadState,
+    /// Priority of the thread when the snapshot was taken, including inherited priorities.
+    pub prio: RunqueueId,
+    /// Cores the thread may be scheduled on.
+    pub core_affinity: CoreAffinity,
+    /// Total size of the thread's stack, in bytes.
+    pub stack_size: usize,
+    /// Peak stack usage of the thread, in bytes.
+    ///
+    /// This is measured by checking how much of the stack paint applied at thread creation has
+    /// been overwritten, so it is a lower bound of the actual peak usage.
+    /// Always zero on native, where thread stacks are managed by the host.
+    pub stack_peak_usage: usize,
+}
+
+/// Iterator over all live threads, see [`threads()`].
+pub struct Threads {
+    next: usize,
+}
+
+impl Iterator for Threads {
+    type Item = ThreadInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next < THREAD_COUNT {
+            let tid = ThreadId::new(self.next as u8);
+            self.next += 1;
+
+            // Copy what is needed while holding the scheduler. Static stacks are measured outside
+            // of the critical section, as that runs in `O(n)`, but heap-allocated stacks may be
+            // freed as soon as the critical section ends.
+            let Some((info, static_stack_lowest)) = SCHEDULER.with(|scheduler| {
+                let thread = scheduler.get_unchecked(tid);
+                if thread.state == ThreadState::Invalid {
+                    return None;
+                }
+                #[cfg_attr(not(feature = "alloc"), expect(unused_mut))]
+                let mut info = ThreadInfo {
+                    tid,
+                    name: thread.name,
+                    state: thread.state,
+                    prio: thread.prio,
+                    #[cfg(feature = "core-affinity")]
+                    core_affinity: thread.core_affinity,
+                    #[cfg(not(feature = "core-affinity"))]
+                    core_affinity: CoreAffinity::no_affinity(),
+                    stack_size: thread.stack_highest - thread.stack_lowest,
+                    stack_peak_usage: 0,
+                };
+                #[cfg(feature = "alloc")]
+                if thread.heap_stack.is_some() {
+                    // SAFETY: the stack is only freed while holding the scheduler, and the bounds
+                    // are the ones of a stack that was set up for a thread.
+                    let free =
+                        unsafe { thread::stack_painted_len(thread.stack_lowest, info.stack_size) };
+                    info.stack_peak_usage = info.stack_size - free;
+                    return Some((info, None));
+                }
+                Some((info, Some(thread.stack_lowest)))
+            }) else {
+                continue;
+            };
+
+            let Some(stack_lowest) = static_stack_lowest else {
+                return Some(info);
+            };
+            // SAFETY: the stack is not heap-allocated, so it is `'static` and never freed, and the
+            // bounds are the ones of a stack that was set up for a thread.
+            let free = unsafe { thread::stack_painted_len(stack_lowest, info.stack_size) };
+            return Some(ThreadInfo {
+                stack_peak_usage: info.stack_size - free,
+                ..info
+            });
+        }
+        None
+    }
+}
+
+/// Returns an iterator over snapshots of all live threads, in order of their [`ThreadId`].
+///
+/// Each snapshot is taken when the iterator advances, so that the iterator can be used while
+/// logging without blocking the scheduler for long.
+///
+/// Finished threads that have not been joined yet are included, with
+/// [`ThreadState::Finished`].
+#[must_use]
+pub fn threads() -> Threads {
+    Threads { next: 0 }
+}
+
+/// Logs a `ps`-style overview of all live threads, at `info` level.
+pub fn log_threads() {
+    ariel_os_log::info!("tid | name | state | prio | stack used/size");
+    #[allow(
+        unused_variables,
+        reason = "FP due to macro usage and conditional compilation"
+    )]
+    for info in threads() {
+        ariel_os_log::info!(
+            "{:?} | {:?} | {:?} | {:?} | {}/{}",
+            info.tid,
+            info.name,
+            info.state,
+            info.prio,
+            info.stack_peak_usage,
+            info.stack_size
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Provides [`JoinHandle`] to wait for threads to finish.
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    SCHEDULER, Scheduler, ThreadId, ThreadState,
+    timeout::{block_until, deadline_from},
+};
+
+/// An owned permission to join on a thread (block on its termination).
+///
+/// Returned by the thread creation functions.
+///
+/// While a [`JoinHandle`] exists, the thread slot of a finished thread is not reused, so that
+/// its exit value can still be retrieved with [`JoinHandle::join()`].
+/// Dropping the [`JoinHandle`] *detaches* the thread: its slot is released as soon as it
+/// finishes, and its exit value is discarded.
+///
+/// Each thread slot has a generation counter that is incremented every time the slot is reused,
+/// so that a [`JoinHandle`] can never refer to a different thread than the one it was created
+/// for, even though [`ThreadId`]s are recycled.
+///
+/// Note that a panic in a thread is not recoverable in Ariel OS and thus is never observed
+/// through a [`JoinHandle`]: a joined thread always returned normally from its function.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct JoinHandle {
+    thread_id: ThreadId,
+    generation: u16,
+}
+
+impl JoinHandle {
+    pub(crate) fn new(thread_id: ThreadId, generation: u16) -> Self {
+        Self {
+            thread_id,
+            generation,
+        }
+    }
+
+    /// Returns the [`ThreadId`] of the thread.
+    ///
+    /// The [`ThreadId`] may be reused for another thread once this thread has been joined or,
+    /// if the handle has been dropped, once it has finished.
+    #[must_use]
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread_id
+    }
+
+    /// Returns whether the thread has finished running its function.
+    pub fn is_finished(&self) -> bool {
+        SCHEDULER.with(|scheduler| scheduler.is_finished(self))
+    }
+
+    /// Blocks the current thread until the thread has finished.
+    ///
+    /// Returns the exit value of the thread, if it was created with
+    /// [`create_returning()`](crate::create_returning), or `None` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context, or if a thread tries to join
+    /// itself.
+    #[expect(
+        clippy::must_use_candidate,
+        reason = "joining is commonly used only to wait for the thread to finish"
+    )]
+    pub fn join(self) -> Option<usize> {
+        critical_section::with(|cs| {
+            SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+                if !scheduler.is_finished(&self) {
+                    scheduler.join_block(&self);
+                }
+            });
+        });
+        self.reap()
+    }
+
+    /// Blocks the current thread until the thread has finished, or `deadline` is reached.
+    ///
+    /// Behaves like [`Self::join()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`JoinHandle`] back if the thread did not finish before `deadline`, so that
+    /// joining can be retried later.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context, or if a thread tries to join
+    /// itself.
+    pub fn join_until(self, deadline: Instant) -> Result<Option<usize>, Self> {
+        // SAFETY: `on_timeout` takes care of unregistering the thread as joiner.
+        let res = unsafe {
+            block_until(
+                deadline,
+                |cs| SCHEDULER.with_cs(cs, |scheduler| scheduler.is_finished(&self)),
+                |cs| SCHEDULER.with_mut_cs(cs, |mut scheduler| scheduler.join_block(&self)),
+                |cs| {
+                    SCHEDULER
+                        .with_mut_cs(cs, |mut scheduler| {
+                            scheduler.get_unchecked_mut(self.thread_id).joiner.take()
+                        })
+                        .is_some()
+                },
+            )
+        };
+        match res {
+            Ok(()) => Ok(self.reap()),
+            Err(_) => Err(self),
+        }
+    }
+
+    /// Blocks the current thread until the thread has finished, for at most `timeout`.
+    ///
+    /// See [`Self::join_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`JoinHandle`] back if the thread did not finish within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context, or if a thread tries to join
+    /// itself.
+    pub fn join_timeout(self, timeout: Duration) -> Result<Option<usize>, Self> {
+        self.join_until(deadline_from(timeout))
+    }
+
+    /// Releases the slot of the finished thread and returns its exit value.
+    fn reap(self) -> Option<usize> {
+        // Releasing the slot is taken care of by `Drop`.
+        SCHEDULER.with_mut(|mut scheduler| {
+            debug_assert!(scheduler.is_finished(&self));
+            if scheduler.is_current_generation(&self) {
+                scheduler
+                    .get_unchecked_mut(self.thread_id)
+                    .exit_value
+                    .take()
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Drop for JoinHandle {
+    fn drop(&mut self) {
+        SCHEDULER.with_mut(|mut scheduler| {
+            if !scheduler.is_current_generation(self) {
+                return;
+            }
+            let thread = scheduler.get_unchecked_mut(self.thread_id);
+            #[cfg(feature = "alloc")]
+            if thread.state == ThreadState::Finished && thread.heap_stack.is_some() {
+                thread.detached = true;
+                scheduler.reclaim_heap_stacks();
+                return;
+            }
+            if thread.state == ThreadState::Finished {
+                thread.state = ThreadState::Invalid;
+            } else {
+                thread.detached = true;
+            }
+        });
+    }
+}
+
+impl Scheduler {
+    /// Checks if the thread slot still belongs to the thread of `handle`.
+    fn is_current_generation(&self, handle: &JoinHandle) -> bool {
+        let thread = self.get_unchecked(handle.thread_id);
+        thread.state != ThreadState::Invalid && thread.generation == handle.generation
+    }
+
+    /// Checks if the thread of `handle` has finished.
+    fn is_finished(&self, handle: &JoinHandle) -> bool {
+        !self.is_current_generation(handle)
+            || self.get_unchecked(handle.thread_id).state == ThreadState::Finished
+    }
+
+    /// Blocks the current thread until the thread of `handle` has finished.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a thread context, or if `handle` refers to the current thread.
+    fn join_block(&mut self, handle: &JoinHandle) {
+        let current = self
+            .current_tid()
+            .expect("Function should be called inside a thread context.");
+        assert_ne!(current, handle.thread_id, "a thread cannot join itself");
+        self.get_unchecked_mut(handle.thread_id).joiner = Some(current);
+        self.set_state(current, ThreadState::JoinBlocked);
+    }
+
+    /// Terminates a thread after its function returned.
+    ///
+    /// Wakes up the joining thread, if any.
+    /// Detached threads release their slot right away, otherwise the slot is kept until the
+    /// thread is joined.
+    /// Detached threads with a heap-allocated stack can't free it while still running on it, so
+    /// their slot is kept until [`Self::reclaim_heap_stacks()`] releases it.
+    pub(crate) fn finish(&mut self, thread_id: ThreadId) {
+        let thread = self.get_unchecked_mut(thread_id);
+        #[cfg(feature = "alloc")]
+        if thread.detached && thread.heap_stack.is_some() {
+            self.set_state(thread_id, ThreadState::Finished);
+            return;
+        }
+        if thread.detached {
+            self.set_state(thread_id, ThreadState::Invalid);
+            return;
+        }
+        let joiner = thread.joiner.take();
+        self.set_state(thread_id, ThreadState::Finished);
+        if let Some(joiner) = joiner {
+            self.set_state(joiner, ThreadState::Running);
+        }
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! Implements a scheduler based on fixed priorities and preemption.
 //! Within one priority level, threads are scheduled cooperatively.
-//! This means that there is no time slicing that would equally distribute CPU time among same-priority threads.
+//! This means that by default there is no time slicing that would equally distribute CPU time among same-priority threads.
 //! **Instead, you need to use [`yield_same()`] to explicitly yield to another thread with the same priority.**
+//! Optionally, the `time-slicing` feature enables round-robin time slicing among same-priority
+//! threads, see [`TIME_SLICE`].
 //! If no thread is ready, the core is prompted to enter deep sleep until a next thread is ready.
 //!
 //! Threads should be implemented using the `ariel_os_macros::thread` proc macro, which takes care
@@ -14,11 +16,39 @@
 //! Optionally, the stacksize and a priority between 1 and [`SCHED_PRIO_LEVELS`] can be configured.
 //! By default, the stack size is 2048 bytes and priority is 1.
 //!
+//! Threads created at runtime with [`create()`] and friends return a [`JoinHandle`], which allows
+//! waiting for the thread to finish and retrieving its exit value.
+//! With the `alloc` feature, `spawn()` creates threads running a closure on a heap-allocated
+//! stack, which is freed once the thread has been joined.
+//!
+//! With the `stack-overflow-detection` feature, the scheduler checks a canary at the bottom of
+//! each thread stack on every context switch, see `set_stack_overflow_handler()`.
+//!
+//! With the `trace` feature, the scheduler records its events for later analysis, see the
+//! `trace` module.
+//!
+//! The [`work_queue`] module allows deferring work, e.g., from interrupt handlers, to worker
+//! threads.
+//!
+//! With the `thread-local` feature, [`thread_local!`] declares values that are separate for
+//! every thread, see [`LocalKey`].
+//!
+//! [`threads()`] returns snapshots of all live threads, including their names, states and stack
+//! usage, e.g., for a `ps`-style overview as provided by [`log_threads()`].
+//!
 //! # Synchronization
 //!
-//! The `threading` module supports three basic synchronization primitives:
+//! The `threading` module supports the following basic synchronization primitives:
 //! - [`Channel`](sync::Channel): synchronous (blocking) channel for sending data between threads
+//! - [`Queue`](sync::Queue): bounded, buffered queue for sending data between threads
 //! - [`Lock`](sync::Lock): basic locking object
+//! - [`Semaphore`](sync::Semaphore): counting semaphore limiting concurrent access to a resource
+//! - [`RwLock`](sync::RwLock): lock allowing either multiple readers or a single writer
+//! - [`Condvar`](sync::Condvar): condition variable for waiting on data protected by a
+//!   [`Mutex`](sync::Mutex)
+//! - [`EventGroup`](sync::EventGroup): shared event bits that multiple threads can wait on
+//! - [`CeilingMutex`](sync::CeilingMutex): mutex implementing the immediate priority ceiling
+//!   protocol
 //! - [`thread_flags`]: thread-flag implementation for signaling between threads
 
 #![cfg_attr(not(any(test, context = "native")), no_std)]
@@ -34,20 +64,43 @@
     reason = "should be addressed eventually"
 )]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 mod arch;
 mod autostart_thread;
 mod blocker;
 mod core_affinity;
 mod ensure_once;
+mod info;
+mod join;
+#[cfg(feature = "alloc")]
+mod spawn;
+#[cfg(feature = "stack-overflow-detection")]
+mod stack_overflow;
 mod thread;
+#[cfg(feature = "thread-local")]
+mod thread_local;
 mod threadlist;
 mod timeout;
 
+#[cfg(feature = "trace")]
+pub mod trace;
+
+#[cfg(all(
+    feature = "time-slicing",
+    any(feature = "single-core", feature = "multi-core")
+))]
+mod time_slice;
+
 #[cfg(feature = "multi-core")]
 mod smp;
 
+#[cfg(feature = "cpu-time")]
+pub mod cpu_time;
 pub mod sync;
 pub mod thread_flags;
+pub mod work_queue;
 
 #[doc(hidden)]
 pub mod macro_reexports {
@@ -67,8 +120,23 @@ pub mod events {
 pub use ariel_os_runqueue::{RunqueueId, ThreadId};
 pub use blocker::block_on;
 pub use core_affinity::CoreAffinity;
+pub use info::{ThreadInfo, Threads, log_threads, threads};
+pub use join::JoinHandle;
+#[cfg(feature = "alloc")]
+pub use spawn::spawn;
+#[cfg(feature = "stack-overflow-detection")]
+pub use stack_overflow::set_stack_overflow_handler;
+pub use thread::ThreadState;
 pub use thread_flags as flags;
-pub use timeout::{sleep, sleep_until};
+#[cfg(feature = "thread-local")]
+pub use thread_local::{LocalKey, THREAD_LOCAL_SLOTS};
+pub use timeout::{TimeoutError, sleep, sleep_until};
+
+#[cfg(all(
+    feature = "time-slicing",
+    any(feature = "single-core", feature = "multi-core")
+))]
+pub use time_slice::TIME_SLICE;
 
 #[cfg(feature = "multi-core")]
 pub use smp::isr_stack_core1_get_limits;
@@ -79,7 +147,7 @@ use arch::{Arch, Cpu, ThreadData, schedule};
 use ariel_os_runqueue::RunQueue;
 
 use ensure_once::EnsureOnce;
-use thread::{Thread, ThreadState};
+use thread::Thread;
 
 #[cfg(feature = "multi-core")]
 use smp::{Multicore, schedule_on_core};
@@ -87,10 +155,35 @@ use smp::{Multicore, schedule_on_core};
 use static_cell::ConstStaticCell;
 
 /// The number of possible priority levels.
-pub const SCHED_PRIO_LEVELS: usize = THREAD_COUNT;
+///
+/// Can be configured with the `CONFIG_SCHED_PRIO_LEVELS` environment variable, up to the number
+/// of bits of a `usize`.
+pub const SCHED_PRIO_LEVELS: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_SCHED_PRIO_LEVELS",
+    16,
+    "number of thread priority levels"
+);
 
 /// The maximum number of concurrent threads that can be created.
-pub const THREAD_COUNT: usize = 16;
+///
+/// Can be configured with the `CONFIG_THREAD_COUNT` environment variable, up to 254.
+pub const THREAD_COUNT: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_THREAD_COUNT",
+    16,
+    "maximum number of concurrent threads"
+);
+
+// These are the limits of the runqueue implementation.
+const _: () = {
+    assert!(
+        THREAD_COUNT > 0 && THREAD_COUNT < 0xFF,
+        "`CONFIG_THREAD_COUNT` must be between 1 and 254"
+    );
+    assert!(
+        SCHED_PRIO_LEVELS > 0 && SCHED_PRIO_LEVELS <= usize::BITS as usize,
+        "`CONFIG_SCHED_PRIO_LEVELS` must be between 1 and the number of bits of a `usize`"
+    );
+};
 
 /// Number of processor cores.
 pub const CORE_COUNT: usize = {
@@ -127,6 +220,10 @@ struct Scheduler {
     current_threads: [Option<ThreadId>; CORE_COUNT],
     #[cfg(feature = "single-core")]
     current_thread: Option<ThreadId>,
+
+    /// CPU time accounting state.
+    #[cfg(feature = "cpu-time")]
+    cpu_time: cpu_time::Accounting,
 }
 
 impl Scheduler {
@@ -140,6 +237,8 @@ impl Scheduler {
             current_threads: [None; CORE_COUNT],
             #[cfg(feature = "single-core")]
             current_thread: None,
+            #[cfg(feature = "cpu-time")]
+            cpu_time: cpu_time::Accounting::new(),
         }
     }
 
@@ -206,22 +305,59 @@ impl Scheduler {
         stack: &'static mut [u8],
         prio: RunqueueId,
         _core_affinity: Option<CoreAffinity>,
-    ) -> Option<ThreadId> {
+    ) -> Option<JoinHandle> {
+        // Finished detached threads with heap-allocated stacks keep their slot until reclaimed.
+        #[cfg(feature = "alloc")]
+        self.reclaim_heap_stacks();
         let (thread, tid) = self.get_unused()?;
         thread.prio = prio;
         thread.tid = tid;
         thread.state = ThreadState::Parked;
+        thread.name = None;
+        thread.generation = thread.generation.wrapping_add(1);
+        thread.detached = false;
+        thread.joiner = None;
+        thread.returning_fn = None;
+        thread.exit_value = None;
+        thread.ceiling_base_prio = None;
+        #[cfg(feature = "alloc")]
+        debug_assert!(thread.heap_stack.is_none());
+        #[cfg(feature = "deadlock-detection")]
+        {
+            thread.blocked_on_mutex = None;
+        }
+        // The values of the previous thread have been dropped when it finished.
+        #[cfg(feature = "thread-local")]
+        debug_assert!(thread.locals.iter().all(Option::is_none));
+        #[cfg(feature = "cpu-time")]
+        {
+            thread.run_ticks = 0;
+            thread.switches = 0;
+        }
 
         // At least native needs the `tid` field populated, so we call this
         // after populating `thread` with the already known info.
         Cpu::setup_stack(thread, stack, func, arg);
+        // On native, the host thread writes the canary to its own stack when it starts.
+        #[cfg(all(feature = "stack-overflow-detection", not(context = "native")))]
+        // SAFETY: the stack has just been set up and the thread hasn't started yet.
+        unsafe {
+            thread.stack_canary_init();
+        }
 
         #[cfg(feature = "core-affinity")]
         {
             thread.core_affinity = _core_affinity.unwrap_or_default();
         }
 
-        Some(tid)
+        #[cfg(feature = "trace")]
+        trace::record(trace::Event::StateChange {
+            thread_id: tid,
+            from: ThreadState::Invalid,
+            to: ThreadState::Parked,
+        });
+
+        Some(JoinHandle::new(tid, thread.generation))
     }
 
     /// Returns immutable access to any thread data.
@@ -277,9 +413,22 @@ impl Scheduler {
         let thread = self.get_unchecked_mut(tid);
         let old_state = core::mem::replace(&mut thread.state, state);
         let prio = thread.prio;
+        #[cfg(feature = "trace")]
+        if old_state != state {
+            trace::record(trace::Event::StateChange {
+                thread_id: tid,
+                from: old_state,
+                to: state,
+            });
+        }
         if state == ThreadState::Running {
             #[cfg(not(feature = "infini-core"))]
             self.runqueue.add(tid, prio);
+            #[cfg(all(
+                feature = "time-slicing",
+                any(feature = "single-core", feature = "multi-core")
+            ))]
+            self.start_time_slice_on_ready(tid);
             self.schedule_if_higher_prio(tid, prio);
 
             #[cfg(feature = "infini-core")]
@@ -291,6 +440,8 @@ impl Scheduler {
 
             #[cfg(feature = "infini-core")]
             Cpu::set_stopped(tid);
+            #[cfg(all(feature = "infini-core", feature = "stack-overflow-detection"))]
+            self.check_stack_overflow(tid);
 
             // On multi-core, the currently running thread is not in the runqueue
             // anyway, so we don't need to remove it here.
@@ -440,9 +591,26 @@ impl Scheduler {
     /// On multi-core, the thread is removed so that subsequent calls will each
     /// return a different thread. This prevents that a thread is picked multiple
     /// times by the scheduler when it is invoked on different cores.
+    ///
+    /// With time slicing enabled, this also rotates the runqueue if the time slice of the current
+    /// thread has expired, and starts the time slice of the returned thread.
     #[allow(dead_code, reason = "used in scheduler implementation")]
     #[cfg(any(feature = "single-core", feature = "multi-core"))]
     fn get_next_tid(&mut self) -> Option<ThreadId> {
+        #[cfg(all(feature = "time-slicing", feature = "single-core"))]
+        self.rotate_on_expired_time_slice();
+
+        let next = self.get_next_tid_from_rq();
+
+        #[cfg(feature = "time-slicing")]
+        self.start_time_slice(next);
+
+        next
+    }
+
+    /// Returns the next thread from the runqueue, see [`Self::get_next_tid()`].
+    #[cfg(any(feature = "single-core", feature = "multi-core"))]
+    fn get_next_tid_from_rq(&mut self) -> Option<ThreadId> {
         // On single-core, only read the head of the runqueue.
         #[cfg(feature = "single-core")]
         {
@@ -590,8 +758,13 @@ fn setup_idle_threads() {
         [const { ConstStaticCell::new([0u8; Cpu::IDLE_THREAD_STACK_SIZE]) }; CORE_COUNT];
 
     // Create one idle thread for each core with lowest priority.
-    for stack in &IDLE_THREAD_STACKS {
-        create_noarg(idle_thread, stack.take(), 0, None);
+    #[cfg_attr(not(feature = "cpu-time"), expect(unused_variables))]
+    for (core, stack) in IDLE_THREAD_STACKS.iter().enumerate() {
+        let handle = create_noarg(idle_thread, stack.take(), 0, None);
+        #[cfg(feature = "cpu-time")]
+        SCHEDULER.with_mut(|mut scheduler| {
+            scheduler.register_idle_thread(core, handle.thread_id());
+        });
     }
 }
 
@@ -632,6 +805,8 @@ unsafe impl<T: Sync + Sized> Arguable for &'static T {
 /// This sets up the stack for the thread and adds it to
 /// the runqueue.
 ///
+/// Dropping the returned [`JoinHandle`] detaches the thread.
+///
 /// # Panics
 ///
 /// Panics if more than [`THREAD_COUNT`] concurrent threads have been created.
@@ -641,7 +816,7 @@ pub fn create<T>(
     stack: &'static mut [u8],
     prio: u8,
     core_affinity: Option<CoreAffinity>,
-) -> ThreadId
+) -> JoinHandle
 where
     T: Arguable + Send,
 {
@@ -659,8 +834,74 @@ where
     unsafe { create_raw(func, arg, stack, prio, core_affinity) }
 }
 
+/// Low-level function to create a thread that runs `func` with `arg`, and whose return value
+/// is passed to [`JoinHandle::join()`].
+///
+/// See [`create()`].
+///
+/// # Panics
+///
+/// Panics if more than [`THREAD_COUNT`] concurrent threads have been created.
+pub fn create_returning<T>(
+    func: fn(T) -> usize,
+    arg: T,
+    stack: &'static mut [u8],
+    prio: u8,
+    core_affinity: Option<CoreAffinity>,
+) -> JoinHandle
+where
+    T: Arguable + Send,
+{
+    /// Calls the actual thread function and stores its return value in the thread slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    fn returning_trampoline(arg: usize) {
+        let func = SCHEDULER.with_mut(|mut scheduler| scheduler.current().unwrap().returning_fn);
+        let exit_value = func.map(|func| func(arg));
+        SCHEDULER.with_mut(|mut scheduler| scheduler.current().unwrap().exit_value = exit_value);
+    }
+
+    let arg = arg.into_arg();
+
+    // Convert `fn(T) -> usize` into `fn(usize) -> usize`, must go through *const().
+    let func = {
+        let func = func as *const ();
+        // SAFETY:
+        // https://doc.rust-lang.org/stable/std/primitive.fn.html#casting-to-and-from-integers
+        // "Transmuting between raw pointers and function pointers (i.e., two pointer types) is fine."
+        // Calling the resulting function pointer is fine as per the `Arguable` contract, see
+        // `create()`.
+        unsafe { core::mem::transmute::<*const (), fn(usize) -> usize>(func) }
+    };
+
+    let trampoline = {
+        let trampoline = returning_trampoline as fn(usize) as *const ();
+        // SAFETY: see above.
+        unsafe { core::mem::transmute::<*const (), fn()>(trampoline) }
+    };
+
+    SCHEDULER.with_mut(|mut scheduler| {
+        let handle = scheduler
+            .create(
+                trampoline,
+                Some(arg),
+                stack,
+                RunqueueId::new(prio),
+                core_affinity,
+            )
+            .expect("Max `THREAD_COUNT` concurrent threads should be created.");
+        scheduler.get_unchecked_mut(handle.thread_id()).returning_fn = Some(func);
+        scheduler.set_state(handle.thread_id(), ThreadState::Running);
+        handle
+    })
+}
+
 /// Low-level function to create a thread without argument.
 ///
+/// Dropping the returned [`JoinHandle`] detaches the thread.
+///
 /// # Panics
 ///
 /// Panics if more than [`THREAD_COUNT`] concurrent threads have been created.
@@ -669,10 +910,35 @@ pub fn create_noarg(
     stack: &'static mut [u8],
     prio: u8,
     core_affinity: Option<CoreAffinity>,
-) -> ThreadId {
+) -> JoinHandle {
     unsafe { create_raw(func, None, stack, prio, core_affinity) }
 }
 
+/// Low-level function to create a named thread without argument, see [`create_noarg()`].
+///
+/// The thread is named before it becomes runnable, so it is never observed without its name.
+///
+/// # Panics
+///
+/// Panics if more than [`THREAD_COUNT`] concurrent threads have been created.
+#[doc(hidden)]
+pub fn create_noarg_named(
+    func: fn(),
+    stack: &'static mut [u8],
+    prio: u8,
+    core_affinity: Option<CoreAffinity>,
+    name: &'static str,
+) -> JoinHandle {
+    SCHEDULER.with_mut(|mut scheduler| {
+        let handle = scheduler
+            .create(func, None, stack, RunqueueId::new(prio), core_affinity)
+            .expect("Max `THREAD_COUNT` concurrent threads should be created.");
+        scheduler.get_unchecked_mut(handle.thread_id()).name = Some(name);
+        scheduler.set_state(handle.thread_id(), ThreadState::Running);
+        handle
+    })
+}
+
 /// Creates a thread, low-level.
 ///
 /// # Safety
@@ -685,13 +951,13 @@ pub unsafe fn create_raw(
     stack: &'static mut [u8],
     prio: u8,
     core_affinity: Option<CoreAffinity>,
-) -> ThreadId {
+) -> JoinHandle {
     SCHEDULER.with_mut(|mut scheduler| {
-        let thread_id = scheduler
+        let handle = scheduler
             .create(func, arg, stack, RunqueueId::new(prio), core_affinity)
             .expect("Max `THREAD_COUNT` concurrent threads should be created.");
-        scheduler.set_state(thread_id, ThreadState::Running);
-        thread_id
+        scheduler.set_state(handle.thread_id(), ThreadState::Running);
+        handle
     })
 }
 
@@ -731,9 +997,12 @@ pub fn is_valid_tid(thread_id: ThreadId) -> bool {
 /// Panics if this is called outside of a thread context.
 #[allow(unused)]
 fn cleanup() -> ! {
+    #[cfg(feature = "thread-local")]
+    thread_local::destroy_all(current_tid().unwrap());
+
     SCHEDULER.with_mut(|mut scheduler| {
         let thread_id = scheduler.current_tid().unwrap();
-        scheduler.set_state(thread_id, ThreadState::Invalid);
+        scheduler.finish(thread_id);
     });
 
     unreachable!();
@@ -822,6 +1091,22 @@ pub fn set_priority(thread_id: ThreadId, prio: RunqueueId) {
     SCHEDULER.with_mut(|mut scheduler| scheduler.set_priority(thread_id, prio));
 }
 
+/// Sets the name of a thread.
+///
+/// The name is only used for introspection, see [`threads()`].
+/// Threads started with the `ariel_os::thread` macro are named after their function by default.
+///
+/// Returns `false` if this is not a valid thread.
+pub fn set_name(thread_id: ThreadId, name: &'static str) -> bool {
+    SCHEDULER.with_mut(|mut scheduler| {
+        if !scheduler.is_valid_tid(thread_id) {
+            return false;
+        }
+        scheduler.get_unchecked_mut(thread_id).name = Some(name);
+        true
+    })
+}
+
 /// Returns the current thread's stack limits (lowest, highest).
 pub fn current_stack_limits() -> Option<(usize, usize)> {
     SCHEDULER.with_mut(|mut scheduler| {
@@ -0,0 +1,152 @@
+//! Provides [`spawn()`] to create threads with heap-allocated stacks.
+use alloc::boxed::Box;
+use core::alloc::Layout;
+
+use crate::{
+    CoreAffinity, JoinHandle, RunqueueId, SCHEDULER, Scheduler, THREAD_COUNT, ThreadId, ThreadState,
+};
+
+/// Alignment of heap-allocated stacks, which satisfies the stack alignment requirements of all
+/// supported architectures.
+const STACK_ALIGN: usize = 16;
+
+/// A heap-allocated thread stack.
+///
+/// The address is stored as `usize` so that the thread data stays `Send`.
+#[derive(Debug)]
+pub(crate) struct HeapStack {
+    addr: usize,
+    layout: Layout,
+}
+
+impl HeapStack {
+    /// Allocates a stack of `size` bytes and leaks it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero or too large for a [`Layout`].
+    fn alloc(size: usize) -> (Self, &'static mut [u8]) {
+        assert!(size > 0, "the stack size should not be zero");
+        let layout =
+            Layout::from_size_align(size, STACK_ALIGN).expect("the stack size should not overflow");
+        // SAFETY: the layout has a non-zero size.
+        let ptr = unsafe { alloc::alloc::alloc(layout) };
+        if ptr.is_null() {
+            alloc::alloc::handle_alloc_error(layout);
+        }
+        // SAFETY: the allocation is valid for `size` bytes and is only freed by `Self::free()`,
+        // once the thread that uses it has finished.
+        let stack = unsafe { core::slice::from_raw_parts_mut(ptr, size) };
+        let heap_stack = Self {
+            addr: ptr as usize,
+            layout,
+        };
+        (heap_stack, stack)
+    }
+
+    /// Frees the stack.
+    ///
+    /// # Safety
+    ///
+    /// The stack must not be in use anymore.
+    unsafe fn free(self) {
+        // SAFETY: the stack was allocated with this layout in `Self::alloc()`, and the caller
+        // ensures that it's not in use anymore.
+        unsafe { alloc::alloc::dealloc(self.addr as *mut u8, self.layout) };
+    }
+}
+
+impl Scheduler {
+    /// Releases the slots of finished, detached threads that have a heap-allocated stack, and
+    /// frees their stacks.
+    ///
+    /// Threads that are still the current thread of a core are skipped, as the core might still
+    /// write to their stack until it switches to another thread.
+    pub(crate) fn reclaim_heap_stacks(&mut self) {
+        for tid in 0..THREAD_COUNT {
+            let tid = ThreadId::new(tid as u8);
+            let thread = self.get_unchecked(tid);
+            if thread.state != ThreadState::Finished
+                || !thread.detached
+                || thread.heap_stack.is_none()
+            {
+                continue;
+            }
+            #[cfg(any(feature = "single-core", feature = "multi-core"))]
+            if self.is_running(tid).is_some() {
+                continue;
+            }
+
+            let thread = self.get_unchecked_mut(tid);
+            if let Some(stack) = thread.heap_stack.take() {
+                thread.state = ThreadState::Invalid;
+                // SAFETY: the thread has finished and no core is running it anymore.
+                unsafe { stack.free() };
+            }
+        }
+    }
+}
+
+/// Spawns a thread that runs `f` on a heap-allocated stack of `stack_size` bytes.
+///
+/// Unlike [`create()`](crate::create), this does not require a statically allocated stack, and
+/// `f` can be any closure.
+///
+/// The stack is freed once the thread has finished and has been joined using the returned
+/// [`JoinHandle`].
+/// If the [`JoinHandle`] is dropped before the thread finished, its thread slot and stack are
+/// only released by the next thread creation after the thread finished.
+///
+/// # Panics
+///
+/// Panics if more than [`THREAD_COUNT`] concurrent threads have been created, or if `stack_size`
+/// is zero.
+pub fn spawn<F>(
+    f: F,
+    stack_size: usize,
+    prio: u8,
+    core_affinity: Option<CoreAffinity>,
+) -> JoinHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    // Calls the closure leaked by `spawn()`.
+    fn trampoline(arg: usize) {
+        // SAFETY: `arg` is the pointer leaked below, and the trampoline runs exactly once.
+        let f = unsafe { Box::from_raw(arg as *mut Box<dyn FnOnce() + Send>) };
+        f();
+    }
+
+    // Free the stacks of finished threads before allocating a new one.
+    SCHEDULER.with_mut(|mut scheduler| scheduler.reclaim_heap_stacks());
+
+    let (heap_stack, stack) = HeapStack::alloc(stack_size);
+    // The closure is boxed twice to get a thin pointer that fits the thread argument.
+    let f: Box<dyn FnOnce() + Send> = Box::new(f);
+    let arg = Box::into_raw(Box::new(f)) as usize;
+
+    let trampoline = {
+        let trampoline = trampoline as fn(usize) as *const ();
+        // SAFETY:
+        // https://doc.rust-lang.org/stable/std/primitive.fn.html#casting-to-and-from-integers
+        // "Transmuting between raw pointers and function pointers (i.e., two pointer types) is fine."
+        // Calling the resulting function pointer is fine as per the `Arguable` contract, see
+        // `create()`.
+        unsafe { core::mem::transmute::<*const (), fn()>(trampoline) }
+    };
+
+    SCHEDULER.with_mut(|mut scheduler| {
+        let handle = scheduler
+            .create(
+                trampoline,
+                Some(arg),
+                stack,
+                RunqueueId::new(prio),
+                core_affinity,
+            )
+            .expect("Max `THREAD_COUNT` concurrent threads should be created.");
+        scheduler.get_unchecked_mut(handle.thread_id()).heap_stack = Some(heap_stack);
+        scheduler.set_state(handle.thread_id(), ThreadState::Running);
+        handle
+    })
+}
@@ -0,0 +1,160 @@
+//! Stack overflow detection.
+//!
+//! When the `stack-overflow-detection` feature is enabled, a canary is written to the lowest
+//! bytes of each thread stack when the thread is created.
+//! Whenever the scheduler switches away from a thread, it checks that the canary is still
+//! intact, and calls the overflow handler with the [`ThreadId`] of the offending thread
+//! otherwise, see [`set_stack_overflow_handler()`].
+//!
+//! The canary only catches overflows after the fact, and only if they overwrote the canary.
+//! To catch overflows precisely when they happen:
+//!
+//! - on ARMv7-M with an MPU, a read-only guard region is placed at the bottom
+//!   of the running thread's stack, and the resulting `MemoryManagement` fault calls the overflow
+//!   handler;
+//! - on ARMv8-M, the stack pointer limit register, which is always set up for the running
+//!   thread, already faults on overflow.
+//!
+//! On native, threads run on the stacks of host threads instead of the ones they were created
+//! with, so the canary is placed on the host thread stack instead, as far below the thread's first
+//! frame as the stack it was created with is large.
+//! Host code, especially in debug builds, may need larger stacks than it would on a device.
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+use crate::{Scheduler, ThreadId, ThreadState, thread::Thread};
+
+/// Canary written to the lowest bytes of each thread stack.
+pub(crate) const STACK_CANARY: [u8; 8] = [0xDE, 0xAD, 0xC0, 0xDE, 0x5A, 0xFE, 0x57, 0xAC];
+
+/// Returns the length of the canary of a stack of `stack_size` bytes.
+///
+/// Stacks too small to hold the canary don't get one.
+pub(crate) const fn canary_len(stack_size: usize) -> usize {
+    if stack_size < STACK_CANARY.len() {
+        0
+    } else {
+        STACK_CANARY.len()
+    }
+}
+
+static OVERFLOW_HANDLER: Mutex<Cell<fn(ThreadId)>> = Mutex::new(Cell::new(default_handler));
+
+/// Handler used unless another one is set.
+///
+/// # Panics
+///
+/// Always panics.
+fn default_handler(thread_id: ThreadId) {
+    panic!("stack overflow in thread {thread_id:?}");
+}
+
+/// Sets the function that is called when a stack overflow is detected.
+///
+/// The handler is called with the [`ThreadId`] of the thread whose stack overflowed, from within
+/// the scheduler or from a fault handler, so it must not call any function of this crate.
+/// By default, it panics.
+///
+/// If the handler returns after an overflow detected by the canary check, the canary is
+/// restored and the thread keeps running, with neighbouring memory possibly corrupted.
+/// If it returns after a fault, a panic follows, as the thread cannot resume.
+pub fn set_stack_overflow_handler(handler: fn(ThreadId)) {
+    critical_section::with(|cs| OVERFLOW_HANDLER.borrow(cs).set(handler));
+}
+
+/// Calls the overflow handler.
+pub(crate) fn report_overflow(thread_id: ThreadId) {
+    ariel_os_log::error!("stack overflow in thread {:?}", thread_id);
+    let handler = critical_section::with(|cs| OVERFLOW_HANDLER.borrow(cs).get());
+    handler(thread_id);
+}
+
+/// Writes the canary of the current thread to the stack of the host thread it runs on.
+///
+/// Must be called by the host thread of `thread_id`, before the thread function runs.
+#[cfg(context = "native")]
+pub(crate) fn init_host_stack_canary(thread_id: ThreadId, stack_size: usize) {
+    let first_frame = core::hint::black_box(0u8);
+    let stack_lowest = (&raw const first_frame as usize).saturating_sub(stack_size);
+    crate::SCHEDULER.with_mut(|mut scheduler| {
+        let thread = scheduler.get_unchecked_mut(thread_id);
+        thread.data.stack_lowest = stack_lowest;
+        // SAFETY: host thread stacks are much larger than the stacks threads are created with,
+        // and the canary is placed below all frames that are in use.
+        unsafe { thread.stack_canary_init() };
+    });
+}
+
+impl Thread {
+    /// Returns the address of the canary, at the bottom of the stack that the thread runs on.
+    fn canary_pos(&self) -> usize {
+        cfg_select! {
+            context = "native" => self.data.stack_lowest,
+            _ => self.stack_lowest,
+        }
+    }
+
+    /// Writes the canary to the lowest bytes of the stack.
+    ///
+    /// # Safety
+    /// - the stack must have been set up by `arch::setup_stack()`, and must not be in use yet.
+    pub(crate) unsafe fn stack_canary_init(&mut self) {
+        let len = canary_len(self.stack_highest - self.stack_lowest);
+        for (pos, &byte) in (self.canary_pos()..).zip(&STACK_CANARY[..len]) {
+            // SAFETY: writing to the slice that was passed to `setup_stack()` is fine.
+            unsafe { core::ptr::write_volatile(pos as *mut u8, byte) };
+        }
+    }
+
+    /// Checks whether the canary at the lowest bytes of the stack is intact.
+    fn stack_canary_intact(&self) -> bool {
+        let len = canary_len(self.stack_highest - self.stack_lowest);
+        (self.canary_pos()..)
+            .zip(&STACK_CANARY[..len])
+            .all(|(pos, &byte)| {
+                // SAFETY: reading from the slice that was passed to `setup_stack()` is fine, it is
+                // not freed before the thread has been switched away from for the last time.
+                // On native, this only runs on the host thread whose stack holds the canary.
+                unsafe { core::ptr::read_volatile(pos as *const u8) == byte }
+            })
+    }
+}
+
+impl Scheduler {
+    /// Checks the stack canary of a thread, and calls the overflow handler if it has been
+    /// overwritten.
+    ///
+    /// Must be called whenever the scheduler switches away from a thread.
+    pub(crate) fn check_stack_overflow(&mut self, thread_id: ThreadId) {
+        let thread = self.get_unchecked_mut(thread_id);
+        if thread.state == ThreadState::Invalid || thread.stack_canary_intact() {
+            return;
+        }
+        report_overflow(thread_id);
+        // Re-arm the canary, so that the handler is only called once per overflow.
+        // SAFETY: the canary is part of the thread's stack.
+        unsafe { thread.stack_canary_init() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canary_detects_overwrite() {
+        let mut stack = [0u8; 64];
+        let mut thread = Thread::default();
+        thread.stack_lowest = stack.as_mut_ptr() as usize;
+        thread.stack_highest = thread.stack_lowest + stack.len();
+
+        // SAFETY: the stack bounds are those of `stack`, which is not in use.
+        unsafe { thread.stack_canary_init() };
+        assert!(thread.stack_canary_intact());
+
+        // SAFETY: see above.
+        unsafe { core::ptr::write_volatile((thread.stack_lowest + 3) as *mut u8, 0) };
+        assert!(!thread.stack_canary_intact());
+    }
+}
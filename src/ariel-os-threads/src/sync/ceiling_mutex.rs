@@ -0,0 +1,240 @@
+//! This module provides a mutex implementing the immediate priority ceiling protocol.
+
+#![deny(missing_docs)]
+
+use core::{
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+
+use ariel_os_runqueue::RunqueueId;
+use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    SCHEDULER,
+    timeout::{TimeoutError, deadline_from},
+};
+
+use super::{Mutex, MutexGuard};
+
+/// A mutex implementing the immediate priority ceiling protocol.
+///
+/// Unlike [`Mutex`], which only raises the priority of its owner once a higher priority thread
+/// blocks on it, a [`CeilingMutex`] raises the priority of the thread locking it to its ceiling
+/// priority right away.
+/// Given that the ceiling is at least the priority of every thread using the mutex, no other
+/// user of the mutex can preempt the owner, which bounds the time a thread may be blocked to
+/// the longest critical section.
+///
+/// A thread already running with a priority higher than the ceiling, e.g., because it holds a
+/// [`CeilingMutex`] with a higher ceiling, keeps its priority.
+/// The priority the owner had before locking is restored once the mutex is released. This means
+/// that a **user can not change a thread's priority while it holds the lock**, because it will be
+/// changed back after release!
+/// For the same reason, nested [`CeilingMutex`]es must be released in the reverse order they
+/// were locked in.
+pub struct CeilingMutex<T> {
+    ceiling: RunqueueId,
+    mutex: Mutex<T>,
+}
+
+/// Priority to restore once a [`CeilingMutex`] is released.
+#[derive(Copy, Clone)]
+struct SavedPriority {
+    /// Priority of the owner before locking the mutex.
+    prio: RunqueueId,
+    /// Whether this is the outermost [`CeilingMutex`] held by the owner.
+    outermost: bool,
+}
+
+impl<T> CeilingMutex<T> {
+    /// Creates a new **unlocked** [`CeilingMutex`] with the given ceiling priority.
+    ///
+    /// The ceiling must be at least the priority of every thread that locks the mutex.
+    pub const fn new(ceiling: RunqueueId, value: T) -> Self {
+        Self {
+            ceiling,
+            mutex: Mutex::new(value),
+        }
+    }
+
+    /// Returns the ceiling priority of the mutex.
+    #[must_use]
+    pub fn ceiling(&self) -> RunqueueId {
+        self.ceiling
+    }
+
+    /// Returns whether the mutex is locked.
+    pub fn is_locked(&self) -> bool {
+        self.mutex.is_locked()
+    }
+
+    /// Acquires the mutex, blocking the current thread until it is able to do so.
+    ///
+    /// Once the mutex is acquired, the current thread runs with at least the ceiling priority
+    /// until it releases the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context, or if the base priority of the current
+    /// thread, i.e., its priority before locking any [`CeilingMutex`], is higher than the ceiling.
+    pub fn lock(&self) -> CeilingMutexGuard<'_, T> {
+        // The priority is raised before locking, so that no other user of the mutex can preempt
+        // the current thread once it owns the mutex.
+        let saved = critical_section::with(|cs| self.raise_cs(cs));
+        CeilingMutexGuard::new(self.mutex.lock(), saved)
+    }
+
+    /// Acquires the mutex, blocking the current thread at most until `deadline`.
+    ///
+    /// Behaves like [`Self::lock()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the mutex could not be acquired before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context, or if the base priority of the current
+    /// thread, i.e., its priority before locking any [`CeilingMutex`], is higher than the ceiling.
+    pub fn lock_until(&self, deadline: Instant) -> Result<CeilingMutexGuard<'_, T>, TimeoutError> {
+        let saved = critical_section::with(|cs| self.raise_cs(cs));
+        match self.mutex.lock_until(deadline) {
+            Ok(guard) => Ok(CeilingMutexGuard::new(guard, saved)),
+            Err(err) => {
+                critical_section::with(|cs| restore_cs(cs, saved));
+                Err(err)
+            }
+        }
+    }
+
+    /// Acquires the mutex, blocking the current thread for at most `timeout`.
+    ///
+    /// See [`Self::lock_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the mutex could not be acquired within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context, or if the base priority of the current
+    /// thread, i.e., its priority before locking any [`CeilingMutex`], is higher than the ceiling.
+    pub fn lock_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<CeilingMutexGuard<'_, T>, TimeoutError> {
+        self.lock_until(deadline_from(timeout))
+    }
+
+    /// Attempts to acquire the mutex, in a non-blocking fashion.
+    ///
+    /// Returns `None` if the mutex was locked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context, or if the base priority of the current
+    /// thread, i.e., its priority before locking any [`CeilingMutex`], is higher than the ceiling.
+    pub fn try_lock(&self) -> Option<CeilingMutexGuard<'_, T>> {
+        critical_section::with(|cs| {
+            let saved = self.raise_cs(cs);
+            let guard = self.mutex.try_lock();
+            if guard.is_none() {
+                restore_cs(cs, saved);
+            }
+            guard.map(|guard| CeilingMutexGuard::new(guard, saved))
+        })
+    }
+
+    /// Raises the current thread to the ceiling, unless it already runs with a higher priority.
+    ///
+    /// Returns the priority to restore once the mutex is released.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context, or if the base priority of the current
+    /// thread is higher than the ceiling.
+    fn raise_cs(&self, cs: CriticalSection<'_>) -> SavedPriority {
+        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+            let current = scheduler
+                .current()
+                .expect("Function should be called inside a thread context.");
+            let saved = SavedPriority {
+                prio: current.prio,
+                outermost: current.ceiling_base_prio.is_none(),
+            };
+            let base_prio = *current.ceiling_base_prio.get_or_insert(current.prio);
+            assert!(
+                base_prio <= self.ceiling,
+                "a thread with a priority higher than the ceiling tried to lock a `CeilingMutex`"
+            );
+            let tid = current.tid;
+            scheduler.set_priority(tid, saved.prio.max(self.ceiling));
+            saved
+        })
+    }
+}
+
+/// Restores the priority of the current thread saved when locking a [`CeilingMutex`].
+///
+/// # Panics
+///
+/// Panics if called outside of a thread context.
+fn restore_cs(cs: CriticalSection<'_>, saved: SavedPriority) {
+    SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+        let current = scheduler
+            .current()
+            .expect("Function should be called inside a thread context.");
+        if saved.outermost {
+            current.ceiling_base_prio = None;
+        }
+        let tid = current.tid;
+        scheduler.set_priority(tid, saved.prio);
+    });
+}
+
+/// Grants access to the [`CeilingMutex`] inner data.
+///
+/// Dropping the [`CeilingMutexGuard`] will unlock the [`CeilingMutex`].
+#[must_use = "if unused the CeilingMutex will immediately unlock"]
+pub struct CeilingMutexGuard<'a, T> {
+    guard: ManuallyDrop<MutexGuard<'a, T>>,
+    saved: SavedPriority,
+}
+
+impl<'a, T> CeilingMutexGuard<'a, T> {
+    fn new(guard: MutexGuard<'a, T>, saved: SavedPriority) -> Self {
+        Self {
+            guard: ManuallyDrop::new(guard),
+            saved,
+        }
+    }
+}
+
+impl<T> Deref for CeilingMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for CeilingMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for CeilingMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        // Release the mutex and restore the priority in the same critical section, so that the
+        // thread cannot be preempted while still holding the mutex with its restored priority.
+        critical_section::with(|cs| {
+            // SAFETY: the inner guard is not used anymore after this.
+            let guard = unsafe { ManuallyDrop::take(&mut self.guard) };
+            guard.into_mutex().release_cs(cs);
+            restore_cs(cs, self.saved);
+        });
+    }
+}
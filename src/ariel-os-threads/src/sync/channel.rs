@@ -9,20 +9,36 @@
 use core::cell::UnsafeCell;
 use core::marker::PhantomData;
 use core::mem::MaybeUninit;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+use critical_section::{CriticalSection, with};
+use embassy_time::{Duration, Instant};
 
 use crate::ThreadState;
 use crate::threadlist::ThreadList;
-use critical_section::with;
+use crate::timeout::{TimeoutError, block_until, deadline_from};
+
+#[cfg(feature = "async")]
+use super::wakers::Wakers;
 
-enum ChannelState {
-    Idle,
-    SendersWaiting(ThreadList),
-    ReceiversWaiting(ThreadList),
+/// Waiting threads of a [`Channel`].
+///
+/// Only one of the lists holds blocked threads at any time. Both may additionally hold threads
+/// whose deadline has been reached, until they remove themselves.
+struct ChannelState {
+    senders: ThreadList,
+    receivers: ThreadList,
 }
 
 /// Blocking channel for sending data between threads.
+///
+/// With the `async` feature, async tasks can exchange data with threads over the channel as
+/// well, see [`Self::send_async()`] and [`Self::recv_async()`].
 pub struct Channel<T> {
     state: UnsafeCell<ChannelState>,
+    #[cfg(feature = "async")]
+    wakers: UnsafeCell<Wakers>,
     phantom: core::marker::PhantomData<T>,
 }
 
@@ -33,7 +49,12 @@ impl<T: Copy + Send> Channel<T> {
     #[must_use]
     pub const fn new() -> Self {
         Channel {
-            state: UnsafeCell::new(ChannelState::Idle),
+            state: UnsafeCell::new(ChannelState {
+                senders: ThreadList::new(),
+                receivers: ThreadList::new(),
+            }),
+            #[cfg(feature = "async")]
+            wakers: UnsafeCell::new(Wakers::new()),
             phantom: PhantomData,
         }
     }
@@ -48,70 +69,102 @@ impl<T: Copy + Send> Channel<T> {
     /// Panics if this is called outside of a thread context.
     pub fn send(&self, something: &T) {
         with(|cs| {
-            let state = unsafe { &mut *self.state.get() };
-            match state {
-                ChannelState::Idle => {
-                    let mut waiters = ThreadList::new();
-                    waiters.put_current(
-                        cs,
-                        crate::ThreadState::ChannelTxBlocked(
-                            core::ptr::from_ref::<T>(something) as usize
-                        ),
-                    );
-                    *state = ChannelState::SendersWaiting(waiters);
-                }
-                ChannelState::ReceiversWaiting(waiters) => {
-                    if let Some((_, head_state)) = waiters.pop(cs) {
-                        if waiters.is_empty(cs) {
-                            *state = ChannelState::Idle;
-                        }
-                        if let ThreadState::ChannelRxBlocked(ptr) = head_state {
-                            // copy over `something`
-                            unsafe { (ptr as *mut T).write(*something) };
-                        } else {
-                            unreachable!("unexpected thread state");
-                        }
-                    } else {
-                        unreachable!("unexpected empty thread list");
-                    }
-                }
-                ChannelState::SendersWaiting(waiters) => {
-                    waiters.put_current(
-                        cs,
-                        crate::ThreadState::ChannelTxBlocked(core::ptr::from_ref(self) as usize),
-                    );
-                }
+            if !self.try_send_cs(cs, something) {
+                self.block_send_cs(cs, something);
             }
         });
     }
 
+    /// Send on the channel, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::send()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if no receiver took the data before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn send_until(&self, something: &T, deadline: Instant) -> Result<(), TimeoutError> {
+        // SAFETY: `on_timeout` takes care of removing the thread from the waiters.
+        unsafe {
+            block_until(
+                deadline,
+                |cs| self.try_send_cs(cs, something),
+                |cs| self.block_send_cs(cs, something),
+                |cs| self.remove_current_cs(cs),
+            )
+        }
+    }
+
+    /// Send on the channel, blocking for at most `timeout`.
+    ///
+    /// See [`Self::send_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if no receiver took the data within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn send_timeout(&self, something: &T, timeout: Duration) -> Result<(), TimeoutError> {
+        self.send_until(something, deadline_from(timeout))
+    }
+
+    /// Send on the channel, asynchronously.
+    ///
+    /// Unlike [`Self::send()`], this can be used from async tasks, without blocking the thread
+    /// that runs them.
+    /// As the channel has no buffer, this waits for a receiving thread; an async sender and an
+    /// async receiver never meet.
+    #[cfg(feature = "async")]
+    pub async fn send_async(&self, something: &T) {
+        poll_fn(|cx| {
+            with(|cs| {
+                if self.try_send_cs(cs, something) {
+                    return Poll::Ready(());
+                }
+                unsafe { &mut *self.wakers.get() }.register(cx.waker());
+                Poll::Pending
+            })
+        })
+        .await;
+    }
+
     /// Try to send on the channel (non-blocking).
     ///
     /// Returns `true` if a receiver was waiting and received
     /// the data, `false` otherwise.
     pub fn try_send(&self, something: &T) -> bool {
-        with(|cs| {
-            let state = unsafe { &mut *self.state.get() };
-            match state {
-                ChannelState::ReceiversWaiting(waiters) => {
-                    if let Some((_, head_state)) = waiters.pop(cs) {
-                        if waiters.is_empty(cs) {
-                            *state = ChannelState::Idle;
-                        }
-                        if let ThreadState::ChannelRxBlocked(ptr) = head_state {
-                            // copy over `something`
-                            unsafe { (ptr as *mut T).write(*something) };
-                        } else {
-                            unreachable!("unexpected thread state");
-                        }
-                    } else {
-                        unreachable!("unexpected empty thread list");
-                    }
-                    true
-                }
-                _ => false,
-            }
-        })
+        with(|cs| self.try_send_cs(cs, something))
+    }
+
+    fn try_send_cs(&self, cs: CriticalSection<'_>, something: &T) -> bool {
+        let state = unsafe { &mut *self.state.get() };
+        // Receivers whose deadline has been reached are skipped, they remove themselves.
+        let Some((_, ThreadState::ChannelRxBlocked(ptr))) = state.receivers.pop_if(cs, |state| {
+            matches!(state, ThreadState::ChannelRxBlocked(_))
+        }) else {
+            return false;
+        };
+        // copy over `something`
+        unsafe { (ptr as *mut T).write(*something) };
+        true
+    }
+
+    /// Blocks the current thread until a receiver copies `something`.
+    ///
+    /// Must only be called if there is no receiver waiting.
+    fn block_send_cs(&self, cs: CriticalSection<'_>, something: &T) {
+        let state = unsafe { &mut *self.state.get() };
+        let thread_state =
+            ThreadState::ChannelTxBlocked(core::ptr::from_ref::<T>(something) as usize);
+        state.senders.put_current(cs, thread_state);
+        // Let waiting async receivers take the data.
+        #[cfg(feature = "async")]
+        unsafe { &mut *self.wakers.get() }.wake();
     }
 
     /// Receive on the channel (blocking).
@@ -126,33 +179,10 @@ impl<T: Copy + Send> Channel<T> {
         let mut res: MaybeUninit<T> = MaybeUninit::uninit();
 
         with(|cs| {
-            let state = unsafe { &mut *self.state.get() };
             let ptr = res.as_mut_ptr();
-            match state {
-                ChannelState::Idle => {
-                    let mut waiters = ThreadList::new();
-                    waiters.put_current(cs, crate::ThreadState::ChannelRxBlocked(ptr as usize));
-                    *state = ChannelState::ReceiversWaiting(waiters);
-                }
-                ChannelState::ReceiversWaiting(waiters) => {
-                    waiters.put_current(cs, crate::ThreadState::ChannelRxBlocked(ptr as usize));
-                    // sender will copy message
-                }
-                ChannelState::SendersWaiting(waiters) => {
-                    if let Some((_, head_state)) = waiters.pop(cs) {
-                        if waiters.is_empty(cs) {
-                            *state = ChannelState::Idle;
-                        }
-                        if let ThreadState::ChannelTxBlocked(other_ptr) = head_state {
-                            // copy over `something`
-                            unsafe { ptr.write(*(other_ptr as *const T)) };
-                        } else {
-                            unreachable!("unexpected thread state");
-                        }
-                    } else {
-                        unreachable!("unexpected empty thread list");
-                    }
-                }
+            if !self.try_recv_cs(cs, ptr) {
+                // sender will copy message
+                self.block_recv_cs(cs, ptr);
             }
         });
 
@@ -163,35 +193,82 @@ impl<T: Copy + Send> Channel<T> {
         unsafe { res.assume_init() }
     }
 
+    /// Receive on the channel, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::recv()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if no sender provided data before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn recv_until(&self, deadline: Instant) -> Result<T, TimeoutError> {
+        let mut res: MaybeUninit<T> = MaybeUninit::uninit();
+        let ptr = res.as_mut_ptr();
+
+        // SAFETY: `on_timeout` takes care of removing the thread from the waiters.
+        unsafe {
+            block_until(
+                deadline,
+                |cs| self.try_recv_cs(cs, ptr),
+                |cs| self.block_recv_cs(cs, ptr),
+                |cs| self.remove_current_cs(cs),
+            )
+        }?;
+
+        // ensure the compiler honors what happened to memory while the thread
+        // was scheduled away.
+        core::sync::atomic::fence(core::sync::atomic::Ordering::Acquire);
+
+        Ok(unsafe { res.assume_init() })
+    }
+
+    /// Receive on the channel, blocking for at most `timeout`.
+    ///
+    /// See [`Self::recv_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if no sender provided data within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, TimeoutError> {
+        self.recv_until(deadline_from(timeout))
+    }
+
+    /// Receive on the channel, asynchronously.
+    ///
+    /// Unlike [`Self::recv()`], this can be used from async tasks, without blocking the thread
+    /// that runs them.
+    /// As the channel has no buffer, this waits for a sending thread; an async sender and an
+    /// async receiver never meet.
+    #[cfg(feature = "async")]
+    pub async fn recv_async(&self) -> T {
+        poll_fn(|cx| {
+            with(|cs| {
+                let mut res: MaybeUninit<T> = MaybeUninit::uninit();
+                if self.try_recv_cs(cs, res.as_mut_ptr()) {
+                    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Acquire);
+                    return Poll::Ready(unsafe { res.assume_init() });
+                }
+                unsafe { &mut *self.wakers.get() }.register(cx.waker());
+                Poll::Pending
+            })
+        })
+        .await
+    }
+
     /// Try to send on the channel (non-blocking).
     ///
     /// Returns `Some` data if a sender was waiting and the
     /// data could be received, `None` otherwise.
     pub fn try_recv(&self) -> Option<T> {
         let mut res: MaybeUninit<T> = MaybeUninit::uninit();
-        let have_received = with(|cs| {
-            let state = unsafe { &mut *self.state.get() };
-            match state {
-                ChannelState::SendersWaiting(waiters) => {
-                    let ptr = res.as_mut_ptr();
-                    if let Some((_, head_state)) = waiters.pop(cs) {
-                        if waiters.is_empty(cs) {
-                            *state = ChannelState::Idle;
-                        }
-                        if let ThreadState::ChannelTxBlocked(other_ptr) = head_state {
-                            // copy over `something`
-                            unsafe { ptr.write(*(other_ptr as *const T)) };
-                        } else {
-                            unreachable!("unexpected thread state");
-                        }
-                        true
-                    } else {
-                        unreachable!("unexpected empty thread list");
-                    }
-                }
-                _ => false,
-            }
-        });
+        let have_received = with(|cs| self.try_recv_cs(cs, res.as_mut_ptr()));
 
         if have_received {
             core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::Acquire);
@@ -200,6 +277,42 @@ impl<T: Copy + Send> Channel<T> {
             None
         }
     }
+
+    fn try_recv_cs(&self, cs: CriticalSection<'_>, ptr: *mut T) -> bool {
+        let state = unsafe { &mut *self.state.get() };
+        // Senders whose deadline has been reached are skipped, they remove themselves.
+        let Some((_, ThreadState::ChannelTxBlocked(other_ptr))) =
+            state.senders.pop_if(cs, |state| {
+                matches!(state, ThreadState::ChannelTxBlocked(_))
+            })
+        else {
+            return false;
+        };
+        // copy over `something`
+        unsafe { ptr.write(*(other_ptr as *const T)) };
+        true
+    }
+
+    /// Blocks the current thread until a sender copies its data to `ptr`.
+    ///
+    /// Must only be called if there is no sender waiting.
+    fn block_recv_cs(&self, cs: CriticalSection<'_>, ptr: *mut T) {
+        let state = unsafe { &mut *self.state.get() };
+        let thread_state = ThreadState::ChannelRxBlocked(ptr as usize);
+        state.receivers.put_current(cs, thread_state);
+        // Let waiting async senders provide the data.
+        #[cfg(feature = "async")]
+        unsafe { &mut *self.wakers.get() }.wake();
+    }
+
+    /// Removes the current thread from the waiters, e.g., after a timeout.
+    ///
+    /// Returns `false` if the thread was not waiting (anymore), which means that the
+    /// data has been exchanged.
+    fn remove_current_cs(&self, cs: CriticalSection<'_>) -> bool {
+        let state = unsafe { &mut *self.state.get() };
+        state.senders.remove_current(cs) || state.receivers.remove_current(cs)
+    }
 }
 
 impl<T: Copy + Send> Default for Channel<T> {
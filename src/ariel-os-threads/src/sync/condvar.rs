@@ -0,0 +1,189 @@
+//! This module provides a condition variable.
+
+#![expect(unsafe_code)]
+#![deny(missing_docs)]
+
+use core::cell::{Cell, UnsafeCell};
+
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    ThreadState,
+    sync::MutexGuard,
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+/// A condition variable, to be used together with a [`Mutex`](super::Mutex).
+///
+/// Allows threads to block until some condition on the data protected by a [`Mutex`](super::Mutex) becomes
+/// true.
+/// Waiting atomically releases the mutex and blocks the thread; once notified, the thread
+/// re-acquires the mutex before returning.
+///
+/// As with any condition variable, wakeups may be spurious, e.g., another thread may have
+/// changed the data again before the notified thread re-acquired the mutex.
+/// The condition should therefore always be re-checked in a loop, or
+/// [`Self::wait_while()`] should be used.
+pub struct Condvar {
+    waiters: UnsafeCell<ThreadList>,
+}
+
+// SAFETY: `Condvar`'s methods are safe to call from multiple threads through using critical
+// sections.
+unsafe impl Sync for Condvar {}
+
+impl Condvar {
+    /// Creates a new [`Condvar`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            waiters: UnsafeCell::new(ThreadList::new()),
+        }
+    }
+
+    /// Blocks the current thread until this condition variable is notified.
+    ///
+    /// Atomically releases the mutex held by `guard` and blocks the current thread.
+    /// When notified, the mutex is re-acquired before this function returns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.into_mutex();
+        critical_section::with(|cs| {
+            // Release the mutex first so that its owner's original priority is restored before
+            // the thread gets inserted into the wait list.
+            mutex.release_cs(cs);
+            // SAFETY: access to the waiters only happens in critical sections, so it's always
+            // unique.
+            let waiters = unsafe { &mut *self.waiters.get() };
+            waiters.put_current(cs, ThreadState::CondvarBlocked);
+        });
+        mutex.lock()
+    }
+
+    /// Blocks the current thread until this condition variable is notified or `deadline` is
+    /// reached.
+    ///
+    /// Behaves like [`Self::wait()`], but gives up waiting once `deadline` is reached.
+    /// The mutex is re-acquired before this function returns in either case, and the guard is
+    /// returned together with the outcome.
+    /// If `deadline` is already in the past, the mutex is not released at all.
+    ///
+    /// # Errors
+    ///
+    /// The returned result is [`TimeoutError`] if the condition variable was not notified
+    /// before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait_until<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        deadline: Instant,
+    ) -> (MutexGuard<'a, T>, Result<(), TimeoutError>) {
+        let mutex = guard.into_mutex();
+        let blocked = Cell::new(false);
+        // SAFETY: `on_timeout` takes care of removing the thread from the waiters; access to
+        // the waiters only happens in critical sections, so it's always unique.
+        let res = unsafe {
+            block_until(
+                deadline,
+                |_| false,
+                |cs| {
+                    mutex.release_cs(cs);
+                    let waiters = &mut *self.waiters.get();
+                    waiters.put_current(cs, ThreadState::CondvarBlocked);
+                    blocked.set(true);
+                },
+                |cs| {
+                    let waiters = &mut *self.waiters.get();
+                    waiters.remove_current(cs)
+                },
+            )
+        };
+        let guard = if blocked.get() {
+            mutex.lock()
+        } else {
+            // The mutex was never released.
+            MutexGuard::new(mutex)
+        };
+        (guard, res)
+    }
+
+    /// Blocks the current thread until this condition variable is notified, for at most
+    /// `timeout`.
+    ///
+    /// See [`Self::wait_until()`].
+    ///
+    /// # Errors
+    ///
+    /// The returned result is [`TimeoutError`] if the condition variable was not notified
+    /// within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> (MutexGuard<'a, T>, Result<(), TimeoutError>) {
+        self.wait_until(guard, deadline_from(timeout))
+    }
+
+    /// Blocks the current thread while `condition` returns `true`.
+    ///
+    /// `condition` is called with the mutex held; the thread waits for a notification
+    /// each time it returns `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Wakes up the highest priority waiting thread, if any.
+    ///
+    /// This can be called from interrupt handlers.
+    pub fn notify_one(&self) {
+        critical_section::with(|cs| {
+            // SAFETY: access to the waiters only happens in critical sections, so it's always
+            // unique.
+            let waiters = unsafe { &mut *self.waiters.get() };
+            waiters.pop(cs);
+        });
+    }
+
+    /// Wakes up all waiting threads.
+    ///
+    /// This can be called from interrupt handlers.
+    pub fn notify_all(&self) {
+        critical_section::with(|cs| {
+            // SAFETY: access to the waiters only happens in critical sections, so it's always
+            // unique.
+            let waiters = unsafe { &mut *self.waiters.get() };
+            while waiters.pop(cs).is_some() {}
+        });
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,82 @@
+//! Deadlock detection for [`Mutex`](super::Mutex).
+//!
+//! When the `deadlock-detection` feature is enabled, a thread that is about to block on a
+//! mutex first follows the chain of owners that are themselves blocked on a mutex.
+//! If that chain leads back to the thread, blocking would deadlock, so the deadlock handler is
+//! called with the threads involved, see [`set_deadlock_handler()`].
+//! Locking a mutex that the current thread already owns is reported the same way.
+//!
+//! Walking the chain takes up to [`THREAD_COUNT`] steps every time a thread blocks on a mutex,
+//! so this is meant for debug builds.
+use core::cell::Cell;
+
+use critical_section::{CriticalSection, Mutex};
+
+use crate::{SCHEDULER, THREAD_COUNT, ThreadId};
+
+/// Handler called with the threads involved in a deadlock.
+type DeadlockHandler = fn(&[ThreadId]);
+
+static DEADLOCK_HANDLER: Mutex<Cell<DeadlockHandler>> = Mutex::new(Cell::new(default_handler));
+
+/// Handler used unless another one is set.
+///
+/// # Panics
+///
+/// Always panics.
+fn default_handler(cycle: &[ThreadId]) {
+    panic!("deadlock between threads {cycle:?}");
+}
+
+/// Sets the function that is called when a thread is about to deadlock on a
+/// [`Mutex`](super::Mutex).
+///
+/// The handler is called with the cycle of threads that wait for each other, starting with the
+/// thread that is about to block: each thread waits for a mutex owned by the next one, and the
+/// last one for a mutex owned by the first one.
+/// When a thread tries to lock a mutex it already owns, the cycle consists of only that thread.
+///
+/// The handler is called from the blocking thread, within a critical section.
+/// If it returns, the thread blocks and stays deadlocked.
+/// By default, it panics.
+pub fn set_deadlock_handler(handler: DeadlockHandler) {
+    critical_section::with(|cs| DEADLOCK_HANDLER.borrow(cs).set(handler));
+}
+
+/// Checks whether the current thread would deadlock by blocking on the mutex whose state is at
+/// `mutex`, and calls the deadlock handler if so.
+///
+/// Must be called before the current thread is added to the waitlist of the mutex.
+pub(super) fn check_cs(cs: CriticalSection<'_>, mutex: usize) {
+    let mut cycle = [ThreadId::new(0); THREAD_COUNT];
+    let len = SCHEDULER.with_cs(cs, |scheduler| {
+        let current = scheduler.current_tid()?;
+        cycle[0] = current;
+        let mut len = 1;
+        let mut mutex = mutex;
+        // SAFETY: the mutex the current thread is blocking on is alive, and so are the mutexes
+        // the other threads are blocked on, as they are borrowed by those threads until they
+        // stop waiting, which clears `blocked_on_mutex`.
+        while let Some(owner) = unsafe { super::mutex::owner_at(mutex) } {
+            if owner == current {
+                return Some(len);
+            }
+            if len == THREAD_COUNT {
+                // There is a cycle that doesn't include the current thread, which has already
+                // been reported when it formed.
+                return None;
+            }
+            cycle[len] = owner;
+            len += 1;
+            mutex = scheduler.get_unchecked(owner).blocked_on_mutex?;
+        }
+        None
+    });
+
+    if let Some(len) = len {
+        let cycle = &cycle[..len];
+        ariel_os_log::error!("deadlock between threads {:?}", cycle);
+        let handler = DEADLOCK_HANDLER.borrow(cs).get();
+        handler(cycle);
+    }
+}
@@ -8,16 +8,32 @@
 )]
 
 use core::cell::UnsafeCell;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
 
-use crate::{ThreadState, threadlist::ThreadList};
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    ThreadState,
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+#[cfg(feature = "async")]
+use super::wakers::Wakers;
 
 /// An [`Event`], allowing to notify multiple threads that some event has happened.
 ///
 /// An [`Event`] manages an internal flag that can be set to true with the [`Self::set()`] method and reset
 /// to false with the [`Self::clear()`] method. The [`Self::wait()`] method blocks until the flag is set to true. The
 /// flag is set to false initially.
+///
+/// With the `async` feature, async tasks can wait for the event as well, see
+/// [`Self::wait_async()`].
 pub struct Event {
     state: UnsafeCell<LockState>,
+    #[cfg(feature = "async")]
+    wakers: UnsafeCell<Wakers>,
 }
 
 unsafe impl Sync for Event {}
@@ -40,6 +56,8 @@ impl Event {
     pub const fn new() -> Self {
         Self {
             state: UnsafeCell::new(LockState::Locked(ThreadList::new())),
+            #[cfg(feature = "async")]
+            wakers: UnsafeCell::new(Wakers::new()),
         }
     }
 
@@ -48,6 +66,8 @@ impl Event {
     pub const fn new_set() -> Self {
         Self {
             state: UnsafeCell::new(LockState::Unlocked),
+            #[cfg(feature = "async")]
+            wakers: UnsafeCell::new(Wakers::new()),
         }
     }
 
@@ -80,6 +100,77 @@ impl Event {
         });
     }
 
+    /// Waits for this [`Event`] to be set, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::wait()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the event did not get set before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait_until(&self, deadline: Instant) -> Result<(), TimeoutError> {
+        // SAFETY: `on_timeout` takes care of removing the thread from the waiters.
+        unsafe {
+            block_until(
+                deadline,
+                |_| {
+                    let state = &*self.state.get();
+                    matches!(state, LockState::Unlocked)
+                },
+                |cs| {
+                    let state = &mut *self.state.get();
+                    if let LockState::Locked(waiters) = state {
+                        waiters.put_current(cs, ThreadState::LockBlocked);
+                    }
+                },
+                |cs| {
+                    let state = &mut *self.state.get();
+                    match state {
+                        LockState::Locked(waiters) => waiters.remove_current(cs),
+                        LockState::Unlocked => false,
+                    }
+                },
+            )
+        }
+    }
+
+    /// Waits for this [`Event`] to be set, blocking for at most `timeout`.
+    ///
+    /// See [`Self::wait_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the event did not get set within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<(), TimeoutError> {
+        self.wait_until(deadline_from(timeout))
+    }
+
+    /// Waits for this [`Event`] to be set, asynchronously.
+    ///
+    /// Unlike [`Self::wait()`], this can be used from async tasks, without blocking the thread
+    /// that runs them.
+    #[cfg(feature = "async")]
+    pub async fn wait_async(&self) {
+        poll_fn(|cx| {
+            critical_section::with(|_| {
+                let state = unsafe { &*self.state.get() };
+                if matches!(state, LockState::Unlocked) {
+                    return Poll::Ready(());
+                }
+                unsafe { &mut *self.wakers.get() }.register(cx.waker());
+                Poll::Pending
+            })
+        })
+        .await;
+    }
+
     /// Clears the event (non-blocking).
     ///
     /// If the event was set, it will be cleared and the function returns true.
@@ -100,7 +191,7 @@ impl Event {
     /// Sets the event.
     ///
     /// If the event was unset, and there were waiters, all waiters will be
-    /// woken up.
+    /// woken up, including async tasks.
     /// If the event was already set, the function just returns.
     pub fn set(&self) {
         critical_section::with(|cs| {
@@ -112,6 +203,8 @@ impl Event {
                     // TODO (opt): A to-be-written `pop_all()` might save cycles.
                     while waiters.pop(cs).is_some() {}
                     *state = LockState::Unlocked;
+                    #[cfg(feature = "async")]
+                    unsafe { &mut *self.wakers.get() }.wake();
                 }
             }
         });
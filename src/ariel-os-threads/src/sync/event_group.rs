@@ -0,0 +1,254 @@
+//! This module provides a group of event bits that multiple threads can wait on.
+
+#![expect(unsafe_code)]
+#![deny(missing_docs)]
+
+use core::cell::{Cell, UnsafeCell};
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    ThreadState,
+    thread_flags::{ThreadFlags, WaitMode},
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+#[cfg(feature = "async")]
+use super::wakers::Wakers;
+
+/// A group of event bits, allowing to broadcast combinations of conditions to multiple threads.
+///
+/// Unlike [`thread_flags`](crate::thread_flags), which are per thread, the bits of an
+/// [`EventGroup`] are shared: any thread or interrupt handler can [`set`](Self::set()) or
+/// [`clear`](Self::clear()) them, and any number of threads can wait until any or all bits of a
+/// mask are set, using the same [`WaitMode`]s as thread flags.
+///
+/// Waiting threads can optionally clear the bits of their mask when they return, to consume
+/// them.
+/// When bits get set, all waiters whose condition is met are woken up; they check their condition
+/// again once they run, in priority order, so that a waiter that clears bits on return can
+/// consume them before lower priority waiters do.
+///
+/// With the `async` feature, async tasks can wait on the bits as well, see
+/// [`Self::wait_async()`].
+pub struct EventGroup {
+    state: UnsafeCell<EventGroupState>,
+}
+
+// SAFETY: all accesses to the state happen within critical sections.
+unsafe impl Sync for EventGroup {}
+
+struct EventGroupState {
+    bits: ThreadFlags,
+    waiters: ThreadList,
+    #[cfg(feature = "async")]
+    wakers: Wakers,
+}
+
+/// Returns the bits of `mode`'s mask that are set in `bits`, if `mode` is satisfied.
+fn satisfied(bits: ThreadFlags, mode: WaitMode) -> Option<ThreadFlags> {
+    match mode {
+        WaitMode::Any(mask) => (bits & mask != 0).then_some(bits & mask),
+        WaitMode::All(mask) => (bits & mask == mask).then_some(mask),
+    }
+}
+
+impl EventGroup {
+    /// Creates a new [`EventGroup`] with all bits cleared.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(EventGroupState {
+                bits: 0,
+                waiters: ThreadList::new(),
+                #[cfg(feature = "async")]
+                wakers: Wakers::new(),
+            }),
+        }
+    }
+
+    /// Returns the currently set bits.
+    pub fn get(&self) -> ThreadFlags {
+        critical_section::with(|cs| self.state(cs).bits)
+    }
+
+    /// Sets the bits in `mask`.
+    ///
+    /// Waiting threads whose condition is now met are woken up, as are waiting async tasks.
+    ///
+    /// This can be called from interrupt handlers.
+    pub fn set(&self, mask: ThreadFlags) {
+        critical_section::with(|cs| {
+            let state = self.state(cs);
+            state.bits |= mask;
+            let bits = state.bits;
+            state.waiters.pop_matching(cs, |thread_state| {
+                matches!(
+                    thread_state,
+                    ThreadState::EventGroupBlocked(mode) if satisfied(bits, mode).is_some()
+                )
+            });
+            #[cfg(feature = "async")]
+            state.wakers.wake();
+        });
+    }
+
+    /// Clears the bits in `mask`.
+    ///
+    /// Returns the bits of `mask` that were set.
+    ///
+    /// This can be called from interrupt handlers.
+    pub fn clear(&self, mask: ThreadFlags) -> ThreadFlags {
+        critical_section::with(|cs| {
+            let state = self.state(cs);
+            let res = state.bits & mask;
+            state.bits &= !mask;
+            res
+        })
+    }
+
+    /// Waits until the bits satisfy `mode` (blocking).
+    ///
+    /// Returns the set bits of the mask of `mode`.
+    /// If `clear_on_exit` is `true`, these bits are cleared before returning.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait(&self, mode: WaitMode, clear_on_exit: bool) -> ThreadFlags {
+        loop {
+            let bits = critical_section::with(|cs| {
+                let bits = self.try_take_cs(cs, mode, clear_on_exit);
+                if bits.is_none() {
+                    self.block_cs(cs, mode);
+                }
+                bits
+            });
+            if let Some(bits) = bits {
+                return bits;
+            }
+        }
+    }
+
+    /// Waits until the bits satisfy `mode`, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::wait()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the bits did not satisfy `mode` before `deadline`; no bits are
+    /// cleared in that case.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait_until(
+        &self,
+        mode: WaitMode,
+        clear_on_exit: bool,
+        deadline: Instant,
+    ) -> Result<ThreadFlags, TimeoutError> {
+        let bits = Cell::new(None);
+        loop {
+            // SAFETY: `on_timeout` takes care of removing the thread from the waiters.
+            unsafe {
+                block_until(
+                    deadline,
+                    |cs| {
+                        bits.set(self.try_take_cs(cs, mode, clear_on_exit));
+                        bits.get().is_some()
+                    },
+                    |cs| self.block_cs(cs, mode),
+                    |cs| self.state(cs).waiters.remove_current(cs),
+                )
+            }?;
+            if let Some(bits) = bits.get() {
+                return Ok(bits);
+            }
+            // Woken up, but the bits might have been cleared again in the meantime; check again
+            // in the next iteration.
+        }
+    }
+
+    /// Waits until the bits satisfy `mode`, blocking for at most `timeout`.
+    ///
+    /// See [`Self::wait_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the bits did not satisfy `mode` within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn wait_timeout(
+        &self,
+        mode: WaitMode,
+        clear_on_exit: bool,
+        timeout: Duration,
+    ) -> Result<ThreadFlags, TimeoutError> {
+        self.wait_until(mode, clear_on_exit, deadline_from(timeout))
+    }
+
+    /// Waits until the bits satisfy `mode`, asynchronously.
+    ///
+    /// Unlike [`Self::wait()`], this can be used from async tasks, without blocking the thread
+    /// that runs them.
+    #[cfg(feature = "async")]
+    pub async fn wait_async(&self, mode: WaitMode, clear_on_exit: bool) -> ThreadFlags {
+        poll_fn(|cx| {
+            critical_section::with(|cs| {
+                if let Some(bits) = self.try_take_cs(cs, mode, clear_on_exit) {
+                    return Poll::Ready(bits);
+                }
+                self.state(cs).wakers.register(cx.waker());
+                Poll::Pending
+            })
+        })
+        .await
+    }
+
+    fn try_take_cs(
+        &self,
+        cs: CriticalSection<'_>,
+        mode: WaitMode,
+        clear_on_exit: bool,
+    ) -> Option<ThreadFlags> {
+        let state = self.state(cs);
+        let bits = satisfied(state.bits, mode)?;
+        if clear_on_exit {
+            state.bits &= !bits;
+        }
+        Some(bits)
+    }
+
+    fn block_cs(&self, cs: CriticalSection<'_>, mode: WaitMode) {
+        // The thread is woken up in `set()` once its condition is met.
+        self.state(cs)
+            .waiters
+            .put_current(cs, ThreadState::EventGroupBlocked(mode));
+    }
+
+    /// Returns mutable access to the event group state.
+    ///
+    /// The critical section token is required as proof of unique access.
+    #[expect(
+        clippy::mut_from_ref,
+        reason = "uniqueness is ensured by the critical section"
+    )]
+    fn state(&self, _cs: CriticalSection<'_>) -> &mut EventGroupState {
+        // SAFETY: access to the state only happens in critical sections, and references to it
+        // never escape them, so it's always unique.
+        unsafe { &mut *self.state.get() }
+    }
+}
+
+impl Default for EventGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
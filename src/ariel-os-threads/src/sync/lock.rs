@@ -8,7 +8,14 @@
 
 use core::cell::UnsafeCell;
 
-use crate::{ThreadState, threadlist::ThreadList};
+use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    ThreadState,
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
 
 /// A basic locking object.
 ///
@@ -73,21 +80,79 @@ impl Lock {
         });
     }
 
+    /// Get this lock, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::acquire()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the lock could not be acquired before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn acquire_until(&self, deadline: Instant) -> Result<(), TimeoutError> {
+        // SAFETY: `on_timeout` takes care of removing the thread from the waiters.
+        unsafe {
+            block_until(
+                deadline,
+                |cs| self.try_acquire_cs(cs),
+                |cs| {
+                    if let LockState::Locked(waiters) = self.state(cs) {
+                        waiters.put_current(cs, ThreadState::LockBlocked);
+                    }
+                },
+                |cs| match self.state(cs) {
+                    LockState::Locked(waiters) => waiters.remove_current(cs),
+                    LockState::Unlocked => false,
+                },
+            )
+        }
+    }
+
+    /// Get this lock, blocking for at most `timeout`.
+    ///
+    /// See [`Self::acquire_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the lock could not be acquired within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn acquire_timeout(&self, timeout: Duration) -> Result<(), TimeoutError> {
+        self.acquire_until(deadline_from(timeout))
+    }
+
     /// Get the lock (non-blocking).
     ///
     /// If the lock was unlocked, it will be locked and the function returns true.
     /// If the lock was locked, the function returns false.
     pub fn try_acquire(&self) -> bool {
-        critical_section::with(|_| {
-            let state = unsafe { &mut *self.state.get() };
-            match state {
-                LockState::Unlocked => {
-                    *state = LockState::Locked(ThreadList::new());
-                    true
-                }
-                LockState::Locked(_) => false,
+        critical_section::with(|cs| self.try_acquire_cs(cs))
+    }
+
+    fn try_acquire_cs(&self, cs: CriticalSection<'_>) -> bool {
+        let state = self.state(cs);
+        match state {
+            LockState::Unlocked => {
+                *state = LockState::Locked(ThreadList::new());
+                true
             }
-        })
+            LockState::Locked(_) => false,
+        }
+    }
+
+    /// Returns mutable access to the lock state.
+    ///
+    /// The critical section token is required as proof of unique access.
+    #[expect(
+        clippy::mut_from_ref,
+        reason = "uniqueness is ensured by the critical section"
+    )]
+    fn state(&self, _cs: CriticalSection<'_>) -> &mut LockState {
+        unsafe { &mut *self.state.get() }
     }
 
     /// Releases the lock.
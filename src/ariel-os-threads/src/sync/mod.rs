@@ -1,12 +1,32 @@
 //! Synchronization primitives.
+mod ceiling_mutex;
 mod channel;
+mod condvar;
+#[cfg(feature = "deadlock-detection")]
+mod deadlock;
 mod event;
+mod event_group;
 mod lock;
 mod mutex;
+mod queue;
+mod rwlock;
+mod semaphore;
 mod wait_queue;
+#[cfg(feature = "async")]
+pub(crate) mod wakers;
 
+pub use ceiling_mutex::{CeilingMutex, CeilingMutexGuard};
 pub use channel::Channel;
+pub use condvar::Condvar;
+#[cfg(feature = "deadlock-detection")]
+pub use deadlock::set_deadlock_handler;
 pub use event::Event;
+pub use event_group::EventGroup;
 pub use lock::Lock;
 pub use mutex::{Mutex, MutexGuard};
+pub use queue::{Queue, SendTimeoutError};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::Semaphore;
 pub use wait_queue::WaitQueue;
+#[cfg(feature = "async")]
+pub use wakers::ASYNC_WAITERS;
@@ -10,25 +10,43 @@ use core::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
 
 use ariel_os_runqueue::{RunqueueId, ThreadId};
 use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
 
-use crate::{SCHEDULER, thread::ThreadState, threadlist::ThreadList};
+use crate::{
+    SCHEDULER,
+    thread::ThreadState,
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+#[cfg(feature = "async")]
+use super::wakers::Wakers;
 
 /// A basic mutex with priority inheritance.
+///
+/// With the `async` feature, async tasks can lock the mutex as well, see
+/// [`Self::lock_async()`].
 pub struct Mutex<T> {
     state: UnsafeCell<LockState>,
     inner: UnsafeCell<T>,
+    #[cfg(feature = "async")]
+    wakers: UnsafeCell<Wakers>,
 }
 
 /// State of a [`Mutex`].
 enum LockState {
     Unlocked,
     Locked {
-        //. The current owner of the lock.
-        owner_id: ThreadId,
+        //. The current owner of the lock, `None` if owned by an async task.
+        owner_id: Option<ThreadId>,
         /// The original priority of the current owner (without priority inheritance).
+        ///
+        /// Unused if the owner is an async task.
         owner_prio: RunqueueId,
         //. Waiters for the mutex.
         waiters: ThreadList,
@@ -51,7 +69,7 @@ impl LockState {
         });
         LockState::Locked {
             waiters: ThreadList::new(),
-            owner_id,
+            owner_id: Some(owner_id),
             owner_prio,
         }
     }
@@ -63,6 +81,8 @@ impl<T> Mutex<T> {
         Self {
             state: UnsafeCell::new(LockState::Unlocked),
             inner: UnsafeCell::new(value),
+            #[cfg(feature = "async")]
+            wakers: UnsafeCell::new(Wakers::new()),
         }
     }
 }
@@ -92,31 +112,8 @@ impl<T> Mutex<T> {
     /// Panics if called outside of a thread context.
     pub fn lock(&self) -> MutexGuard<'_, T> {
         critical_section::with(|cs| {
-            // SAFETY: access to the state only happens in critical sections, so it's always unique.
-            let state = unsafe { &mut *self.state.get() };
-            match state {
-                LockState::Unlocked => {
-                    *state = LockState::locked_with_current(cs);
-                }
-                LockState::Locked {
-                    waiters,
-                    owner_id,
-                    owner_prio,
-                } => {
-                    // Insert thread in waitlist, which also triggers the scheduler.
-                    match waiters.put_current(cs, ThreadState::LockBlocked) {
-                        // `Some` when the inserted thread is the highest priority
-                        // thread in the waitlist.
-                        Some(waiter_prio) if waiter_prio > *owner_prio => {
-                            // Current mutex owner inherits the priority.
-                            SCHEDULER.with_mut_cs(cs, |mut scheduler| {
-                                scheduler.set_priority(*owner_id, waiter_prio);
-                            });
-                        }
-                        _ => {}
-                    }
-                    // Context switch happens here as soon as we leave the critical section.
-                }
+            if !self.try_lock_cs(cs) {
+                self.block_cs(cs);
             }
         });
         // Mutex was either directly acquired because it was unlocked, or the current thread was entered
@@ -126,52 +123,240 @@ impl<T> Mutex<T> {
         MutexGuard::new(self)
     }
 
+    /// Acquires a mutex, blocking the current thread at most until `deadline`.
+    ///
+    /// Behaves like [`Self::lock()`], but gives up once `deadline` is reached.
+    /// In that case, the priority that the owner of the mutex may have inherited from the current
+    /// thread is given up again.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the mutex could not be acquired before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn lock_until(&self, deadline: Instant) -> Result<MutexGuard<'_, T>, TimeoutError> {
+        // SAFETY: `on_timeout` takes care of removing the thread from the waitlist.
+        unsafe {
+            block_until(
+                deadline,
+                |cs| self.try_lock_cs(cs),
+                |cs| self.block_cs(cs),
+                |cs| self.remove_current_cs(cs),
+            )
+        }?;
+
+        Ok(MutexGuard::new(self))
+    }
+
+    /// Acquires a mutex, blocking the current thread for at most `timeout`.
+    ///
+    /// See [`Self::lock_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the mutex could not be acquired within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn lock_timeout(&self, timeout: Duration) -> Result<MutexGuard<'_, T>, TimeoutError> {
+        self.lock_until(deadline_from(timeout))
+    }
+
+    /// Acquires a mutex, asynchronously.
+    ///
+    /// Unlike [`Self::lock()`], this can be used from async tasks, without blocking the thread
+    /// that runs them.
+    /// While the mutex is owned by an async task, there is no owner thread that could inherit
+    /// the priority of blocked threads.
+    /// When the mutex is released, waiting threads are handed the mutex before async tasks.
+    #[cfg(feature = "async")]
+    pub async fn lock_async(&self) -> MutexGuard<'_, T> {
+        poll_fn(|cx| {
+            critical_section::with(|_| {
+                // SAFETY: access to the state only happens in critical sections, so it's always
+                // unique.
+                let state = unsafe { &mut *self.state.get() };
+                if let LockState::Unlocked = *state {
+                    *state = LockState::Locked {
+                        waiters: ThreadList::new(),
+                        owner_id: None,
+                        owner_prio: RunqueueId::new(0),
+                    };
+                    return Poll::Ready(());
+                }
+                unsafe { &mut *self.wakers.get() }.register(cx.waker());
+                Poll::Pending
+            })
+        })
+        .await;
+
+        MutexGuard::new(self)
+    }
+
     /// Attempts to acquire this lock, in a non-blocking fashion.
     ///
     /// If the mutex was unlocked, it will be locked and a [`MutexGuard`] is returned.
     /// If the mutex was locked `None` is returned.
     pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
-        critical_section::with(|cs| {
-            // SAFETY: access to the state only happens in critical sections, so it's always unique.
-            let state = unsafe { &mut *self.state.get() };
-            if let LockState::Unlocked = *state {
-                *state = LockState::locked_with_current(cs);
-                Some(MutexGuard::new(self))
-            } else {
-                None
+        critical_section::with(|cs| self.try_lock_cs(cs).then(|| MutexGuard::new(self)))
+    }
+
+    /// Locks the mutex if it is unlocked.
+    ///
+    /// Returns whether the current thread now owns the mutex.
+    fn try_lock_cs(&self, cs: CriticalSection<'_>) -> bool {
+        // SAFETY: access to the state only happens in critical sections, so it's always unique.
+        let state = unsafe { &mut *self.state.get() };
+        if let LockState::Unlocked = *state {
+            *state = LockState::locked_with_current(cs);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Inserts the current thread into the waitlist of the locked mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    fn block_cs(&self, cs: CriticalSection<'_>) {
+        #[cfg(feature = "deadlock-detection")]
+        super::deadlock::check_cs(cs, self.state.get() as usize);
+
+        // SAFETY: access to the state only happens in critical sections, so it's always unique.
+        let state = unsafe { &mut *self.state.get() };
+        if let LockState::Locked {
+            waiters,
+            owner_id,
+            owner_prio,
+        } = state
+        {
+            // Insert thread in waitlist, which also triggers the scheduler.
+            match waiters.put_current(cs, ThreadState::LockBlocked) {
+                // `Some` when the inserted thread is the highest priority
+                // thread in the waitlist.
+                Some(waiter_prio) if waiter_prio > *owner_prio => {
+                    // Current mutex owner inherits the priority.
+                    if let Some(owner_id) = *owner_id {
+                        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+                            scheduler.set_priority(owner_id, waiter_prio);
+                        });
+                    }
+                }
+                _ => {}
             }
-        })
+            #[cfg(feature = "deadlock-detection")]
+            SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+                let current = scheduler.current().unwrap();
+                current.blocked_on_mutex = Some(self.state.get() as usize);
+            });
+            // Context switch happens here as soon as we leave the critical section.
+        }
+    }
+
+    /// Removes the current thread from the waitlist, e.g., after a timeout.
+    ///
+    /// Returns `false` if the thread was not in the waitlist (anymore), which means that it
+    /// was handed the mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    fn remove_current_cs(&self, cs: CriticalSection<'_>) -> bool {
+        // SAFETY: access to the state only happens in critical sections, so it's always unique.
+        let state = unsafe { &mut *self.state.get() };
+        let LockState::Locked {
+            waiters,
+            owner_id,
+            owner_prio,
+        } = state
+        else {
+            return false;
+        };
+        if !waiters.remove_current(cs) {
+            return false;
+        }
+        #[cfg(feature = "deadlock-detection")]
+        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+            scheduler.current().unwrap().blocked_on_mutex = None;
+        });
+        // The owner might have inherited the priority of the removed thread, so re-compute it
+        // from the remaining waiters.
+        let prio = waiters
+            .head_prio(cs)
+            .map_or(*owner_prio, |waiter_prio| waiter_prio.max(*owner_prio));
+        if let Some(owner_id) = *owner_id {
+            SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+                scheduler.set_priority(owner_id, prio);
+            });
+        }
+        true
     }
 
     /// Releases the mutex.
     ///
     /// If there are waiters, the first waiter will be woken up.
     fn release(&self) {
-        critical_section::with(|cs| {
-            // SAFETY: access to the state only happens in critical sections, so it's always unique.
-            let state = unsafe { &mut *self.state.get() };
-            if let LockState::Locked {
-                waiters,
-                owner_id,
-                owner_prio,
-            } = state
-            {
-                // Reset original priority of owner.
+        critical_section::with(|cs| self.release_cs(cs));
+    }
+
+    /// Releases the mutex within an existing critical section.
+    ///
+    /// If there are waiters, the first waiter will be woken up.
+    pub(super) fn release_cs(&self, cs: CriticalSection<'_>) {
+        // SAFETY: access to the state only happens in critical sections, so it's always unique.
+        let state = unsafe { &mut *self.state.get() };
+        if let LockState::Locked {
+            waiters,
+            owner_id,
+            owner_prio,
+        } = state
+        {
+            // Reset original priority of owner.
+            if let Some(owner_id) = *owner_id {
                 SCHEDULER.with_mut_cs(cs, |mut scheduler| {
-                    scheduler.set_priority(*owner_id, *owner_prio);
+                    scheduler.set_priority(owner_id, *owner_prio);
                 });
-                // Pop next thread from waitlist so that it can acquire the mutex.
-                if let Some((tid, _)) = waiters.pop(cs) {
-                    SCHEDULER.with_mut_cs(cs, |scheduler| {
-                        *owner_id = tid;
-                        *owner_prio = scheduler.get_unchecked(tid).prio;
-                    });
-                } else {
-                    // Unlock if waitlist was empty.
-                    *state = LockState::Unlocked;
-                }
             }
-        });
+            // Pop next thread from waitlist so that it can acquire the mutex.
+            if let Some((tid, _)) = waiters.pop(cs) {
+                SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+                    *owner_id = Some(tid);
+                    let thread = scheduler.get_unchecked_mut(tid);
+                    *owner_prio = thread.prio;
+                    #[cfg(feature = "deadlock-detection")]
+                    {
+                        thread.blocked_on_mutex = None;
+                    }
+                });
+            } else {
+                // Unlock if waitlist was empty.
+                *state = LockState::Unlocked;
+                // Let waiting async tasks try to acquire the mutex.
+                #[cfg(feature = "async")]
+                unsafe { &mut *self.wakers.get() }.wake();
+            }
+        }
+    }
+}
+
+/// Returns the owner of the mutex whose state is at `state`, or `None` if it is unlocked or
+/// owned by an async task.
+///
+/// # Safety
+///
+/// `state` must be the address of the state of a [`Mutex`] that is still alive, and this must
+/// be called within a critical section, without any other reference to that state.
+#[cfg(feature = "deadlock-detection")]
+pub(super) unsafe fn owner_at(state: usize) -> Option<ThreadId> {
+    // SAFETY: the caller ensures that the state is alive and not otherwise referenced.
+    match unsafe { &*(state as *const LockState) } {
+        LockState::Unlocked => None,
+        LockState::Locked { owner_id, .. } => *owner_id,
     }
 }
 
@@ -187,12 +372,21 @@ pub struct MutexGuard<'a, T> {
 }
 
 impl<'a, T> MutexGuard<'a, T> {
-    fn new(mutex: &'a Mutex<T>) -> Self {
+    pub(super) fn new(mutex: &'a Mutex<T>) -> Self {
         Self {
             mutex,
             _not_send: PhantomData,
         }
     }
+
+    /// Consumes the guard **without** releasing the [`Mutex`], returning the mutex.
+    ///
+    /// The caller is responsible for releasing the mutex.
+    pub(super) fn into_mutex(self) -> &'a Mutex<T> {
+        let mutex = self.mutex;
+        core::mem::forget(self);
+        mutex
+    }
 }
 
 impl<T> Deref for MutexGuard<'_, T> {
@@ -0,0 +1,376 @@
+//! This module provides a bounded, buffered message queue.
+
+#![expect(unsafe_code)]
+#![deny(missing_docs)]
+
+use core::{
+    cell::{Cell, UnsafeCell},
+    mem::MaybeUninit,
+};
+
+use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    ThreadState,
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+/// A bounded, buffered, multi-producer multi-consumer queue for sending data between threads.
+///
+/// Compared to [`Channel`](super::Channel), which requires a sender and a receiver to
+/// rendezvous, a [`Queue`] buffers up to `N` elements, so that sending only blocks when
+/// the queue is full and receiving only blocks when the queue is empty.
+/// Elements are moved through the queue, so `T` does not need to be [`Copy`].
+///
+/// Blocked senders and receivers are woken up in order of their priority.
+///
+/// [`Queue::try_send()`] and [`Queue::try_recv()`] never block and can be used from
+/// interrupt handlers.
+pub struct Queue<T, const N: usize> {
+    state: UnsafeCell<QueueState<T, N>>,
+}
+
+// SAFETY: all accesses to the state happen within critical sections, and elements are moved
+// between threads, which requires `T: Send`.
+unsafe impl<T: Send, const N: usize> Sync for Queue<T, N> {}
+
+struct QueueState<T, const N: usize> {
+    buf: [MaybeUninit<T>; N],
+    /// Index of the oldest element.
+    head: usize,
+    /// Number of elements currently stored.
+    len: usize,
+    /// Threads waiting for free space.
+    senders: ThreadList,
+    /// Threads waiting for an element.
+    receivers: ThreadList,
+}
+
+impl<T, const N: usize> QueueState<T, N> {
+    /// Moves `value` to the back of the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(value)` if the buffer is full.
+    fn push(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+        let pos = (self.head + self.len) % N;
+        self.buf[pos].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Moves the front element out of the buffer.
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let pos = self.head;
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        // SAFETY: every position between `head` and `head + len` has been written by `push()`,
+        // and the element is not read again after `head` has been advanced past it.
+        Some(unsafe { self.buf[pos].assume_init_read() })
+    }
+}
+
+impl<T, const N: usize> Queue<T, N> {
+    /// Creates a new, empty [`Queue`].
+    ///
+    /// # Panics
+    ///
+    /// Fails to compile if `N` is zero.
+    #[must_use]
+    pub const fn new() -> Self {
+        const {
+            assert!(N > 0, "a `Queue` needs a capacity of at least one element");
+        }
+        Self {
+            state: UnsafeCell::new(QueueState {
+                buf: [const { MaybeUninit::uninit() }; N],
+                head: 0,
+                len: 0,
+                senders: ThreadList::new(),
+                receivers: ThreadList::new(),
+            }),
+        }
+    }
+
+    /// Returns the maximum number of elements the queue can hold.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of elements currently in the queue.
+    pub fn len(&self) -> usize {
+        critical_section::with(|cs| self.state(cs).len)
+    }
+
+    /// Returns whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether the queue is full.
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    /// Sends a value on the queue (blocking).
+    ///
+    /// If the queue is full, the current thread is suspended until a receiver
+    /// has made space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context while the queue is full.
+    pub fn send(&self, value: T) {
+        let mut value = value;
+        loop {
+            match critical_section::with(|cs| {
+                self.try_send_cs(cs, value).inspect_err(|_| {
+                    self.state(cs)
+                        .senders
+                        .put_current(cs, ThreadState::QueueTxBlocked);
+                })
+            }) {
+                Ok(()) => return,
+                // We got woken up because space was made, but another thread might
+                // have been faster, so try again.
+                Err(v) => value = v,
+            }
+        }
+    }
+
+    /// Sends a value on the queue, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::send()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value back inside a [`SendTimeoutError`] if the queue stayed full until
+    /// `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context while the queue is full.
+    pub fn send_until(&self, value: T, deadline: Instant) -> Result<(), SendTimeoutError<T>> {
+        let value = Cell::new(Some(value));
+        loop {
+            // SAFETY: `on_timeout` takes care of removing the thread from the senders.
+            let res = unsafe {
+                block_until(
+                    deadline,
+                    |cs| match self.try_send_cs(cs, value.take().unwrap()) {
+                        Ok(()) => true,
+                        Err(v) => {
+                            value.set(Some(v));
+                            false
+                        }
+                    },
+                    |cs| {
+                        self.state(cs)
+                            .senders
+                            .put_current(cs, ThreadState::QueueTxBlocked);
+                    },
+                    |cs| self.state(cs).senders.remove_current(cs),
+                )
+            };
+            match (res, value.take()) {
+                (_, None) => return Ok(()),
+                (Err(TimeoutError), Some(v)) => return Err(SendTimeoutError(v)),
+                // We got woken up because space was made, but another thread might
+                // have been faster, so try again.
+                (Ok(()), Some(v)) => value.set(Some(v)),
+            }
+        }
+    }
+
+    /// Sends a value on the queue, blocking for at most `timeout`.
+    ///
+    /// See [`Self::send_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the value back inside a [`SendTimeoutError`] if the queue stayed full for
+    /// `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context while the queue is full.
+    pub fn send_timeout(&self, value: T, timeout: Duration) -> Result<(), SendTimeoutError<T>> {
+        self.send_until(value, deadline_from(timeout))
+    }
+
+    /// Tries to send a value on the queue (non-blocking).
+    ///
+    /// Returns the value back if the queue is full.
+    ///
+    /// This can be called from interrupt handlers.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(value)` if the queue is full.
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        critical_section::with(|cs| self.try_send_cs(cs, value))
+    }
+
+    /// Sends `value` without blocking, waking up the highest priority receiver.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(value)` if the queue is full.
+    fn try_send_cs(&self, cs: CriticalSection<'_>, value: T) -> Result<(), T> {
+        let state = self.state(cs);
+        state.push(value)?;
+        // Wake up the highest priority receiver, if any.
+        state.receivers.pop(cs);
+        Ok(())
+    }
+
+    /// Receives a value from the queue (blocking).
+    ///
+    /// If the queue is empty, the current thread is suspended until a sender
+    /// has sent a value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context while the queue is empty.
+    pub fn recv(&self) -> T {
+        loop {
+            if let Some(value) = critical_section::with(|cs| {
+                let value = self.try_recv_cs(cs);
+                if value.is_none() {
+                    self.state(cs)
+                        .receivers
+                        .put_current(cs, ThreadState::QueueRxBlocked);
+                }
+                value
+            }) {
+                return value;
+            }
+            // We got woken up because a value was sent, but another thread might
+            // have been faster, so try again.
+        }
+    }
+
+    /// Receives a value from the queue, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::recv()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the queue stayed empty until `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context while the queue is empty.
+    pub fn recv_until(&self, deadline: Instant) -> Result<T, TimeoutError> {
+        let value = Cell::new(None);
+        loop {
+            // SAFETY: `on_timeout` takes care of removing the thread from the receivers.
+            let res = unsafe {
+                block_until(
+                    deadline,
+                    |cs| {
+                        let received = self.try_recv_cs(cs);
+                        let is_some = received.is_some();
+                        value.set(received);
+                        is_some
+                    },
+                    |cs| {
+                        self.state(cs)
+                            .receivers
+                            .put_current(cs, ThreadState::QueueRxBlocked);
+                    },
+                    |cs| self.state(cs).receivers.remove_current(cs),
+                )
+            };
+            match (res, value.take()) {
+                (_, Some(v)) => return Ok(v),
+                (Err(err), None) => return Err(err),
+                // We got woken up because a value was sent, but another thread might
+                // have been faster, so try again.
+                (Ok(()), None) => {}
+            }
+        }
+    }
+
+    /// Receives a value from the queue, blocking for at most `timeout`.
+    ///
+    /// See [`Self::recv_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if the queue stayed empty for `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context while the queue is empty.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, TimeoutError> {
+        self.recv_until(deadline_from(timeout))
+    }
+
+    /// Tries to receive a value from the queue (non-blocking).
+    ///
+    /// Returns `None` if the queue is empty.
+    ///
+    /// This can be called from interrupt handlers.
+    pub fn try_recv(&self) -> Option<T> {
+        critical_section::with(|cs| self.try_recv_cs(cs))
+    }
+
+    fn try_recv_cs(&self, cs: CriticalSection<'_>) -> Option<T> {
+        let state = self.state(cs);
+        let value = state.pop()?;
+        // Wake up the highest priority sender, if any.
+        state.senders.pop(cs);
+        Some(value)
+    }
+
+    /// Returns mutable access to the queue state.
+    ///
+    /// The critical section token is required as proof of unique access.
+    #[expect(
+        clippy::mut_from_ref,
+        reason = "uniqueness is ensured by the critical section"
+    )]
+    fn state(&self, _cs: CriticalSection<'_>) -> &mut QueueState<T, N> {
+        // SAFETY: access to the state only happens in critical sections, and references to it
+        // never escape them, so it's always unique.
+        unsafe { &mut *self.state.get() }
+    }
+}
+
+impl<T, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for Queue<T, N> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut();
+        while state.pop().is_some() {}
+    }
+}
+
+/// Error returned by [`Queue::send_until()`] and [`Queue::send_timeout()`].
+///
+/// Contains the value that could not be sent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SendTimeoutError<T>(pub T);
+
+impl<T> core::fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "deadline reached while the queue was full")
+    }
+}
+
+impl<T: core::fmt::Debug> core::error::Error for SendTimeoutError<T> {}
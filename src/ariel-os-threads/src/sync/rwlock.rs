@@ -0,0 +1,462 @@
+//! This module provides a reader-writer lock.
+
+#![expect(unsafe_code)]
+#![deny(missing_docs)]
+
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use ariel_os_runqueue::{RunqueueId, ThreadId};
+use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    SCHEDULER,
+    thread::ThreadState,
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+/// A writer-preferring reader-writer lock with priority inheritance.
+///
+/// Allows either any number of readers or one single writer to access the inner data at
+/// the same time.
+///
+/// The lock is writer-preferring: as soon as a writer is waiting, new readers are blocked
+/// until the writer has acquired and released the lock.
+/// When a writer releases the lock, it is handed to the next waiting writer first.
+///
+/// If the current writer has a lower priority than a thread waiting for the lock, it
+/// inherits the waiting thread's priority until it releases the lock, just like with
+/// [`Mutex`](super::Mutex).
+/// As readers are not tracked individually, they do not inherit priorities.
+pub struct RwLock<T> {
+    state: UnsafeCell<RwLockState>,
+    inner: UnsafeCell<T>,
+}
+
+// SAFETY: access to the state only happens in critical sections; the inner data is shared
+// between readers, so it must be `Sync`, and handed between writers, so it must be `Send`.
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+/// Exclusive owner of a [`RwLock`].
+struct Writer {
+    /// The current writer.
+    id: ThreadId,
+    /// The original priority of the writer (without priority inheritance).
+    prio: RunqueueId,
+}
+
+/// State of a [`RwLock`].
+struct RwLockState {
+    /// Number of active readers.
+    readers: usize,
+    /// The active writer, if any.
+    writer: Option<Writer>,
+    /// Threads waiting for shared access.
+    read_waiters: ThreadList,
+    /// Threads waiting for exclusive access.
+    write_waiters: ThreadList,
+}
+
+impl RwLockState {
+    /// Raises the writer's priority to the current thread's priority if the latter is higher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    fn inherit_priority(&self, cs: CriticalSection<'_>) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+            let waiter_prio = scheduler
+                .current()
+                .expect("Function should be called inside a thread context.")
+                .prio;
+            if waiter_prio > scheduler.get_unchecked(writer.id).prio {
+                scheduler.set_priority(writer.id, waiter_prio);
+            }
+        });
+    }
+
+    /// Recomputes the writer's inherited priority from the remaining waiters.
+    ///
+    /// Needed after a waiter stopped waiting, e.g., because of a timeout.
+    fn update_inherited_priority(&self, cs: CriticalSection<'_>) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let prio = [
+            self.read_waiters.head_prio(cs),
+            self.write_waiters.head_prio(cs),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(writer.prio, core::cmp::max);
+        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+            scheduler.set_priority(writer.id, prio);
+        });
+    }
+
+    /// Hands the lock over to the next waiter(s).
+    ///
+    /// Must only be called when the lock is neither held by readers nor by a writer.
+    fn wake_next(&mut self, cs: CriticalSection<'_>) {
+        debug_assert!(self.readers == 0 && self.writer.is_none());
+        if let Some((id, _)) = self.write_waiters.pop(cs) {
+            let prio = SCHEDULER.with_cs(cs, |scheduler| scheduler.get_unchecked(id).prio);
+            self.writer = Some(Writer { id, prio });
+        } else {
+            while self.read_waiters.pop(cs).is_some() {
+                self.readers += 1;
+            }
+        }
+    }
+}
+
+impl<T> RwLock<T> {
+    /// Creates a new **unlocked** [`RwLock`].
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: UnsafeCell::new(RwLockState {
+                readers: 0,
+                writer: None,
+                read_waiters: ThreadList::new(),
+                write_waiters: ThreadList::new(),
+            }),
+            inner: UnsafeCell::new(value),
+        }
+    }
+
+    /// Returns whether the lock is currently held by a writer.
+    pub fn is_write_locked(&self) -> bool {
+        critical_section::with(|cs| self.state(cs).writer.is_some())
+    }
+
+    /// Acquires shared read access, blocking the current thread until it is able to do so.
+    ///
+    /// The current thread is blocked while a writer holds the lock or while writers are
+    /// waiting for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        critical_section::with(|cs| {
+            if !self.try_read_cs(cs) {
+                self.block_read_cs(cs);
+            }
+        });
+
+        RwLockReadGuard::new(self)
+    }
+
+    /// Acquires shared read access, blocking the current thread at most until `deadline`.
+    ///
+    /// Behaves like [`Self::read()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if read access could not be acquired before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn read_until(&self, deadline: Instant) -> Result<RwLockReadGuard<'_, T>, TimeoutError> {
+        // SAFETY: `on_timeout` takes care of removing the thread from the read waiters.
+        unsafe {
+            block_until(
+                deadline,
+                |cs| self.try_read_cs(cs),
+                |cs| self.block_read_cs(cs),
+                |cs| {
+                    let state = self.state(cs);
+                    let removed = state.read_waiters.remove_current(cs);
+                    if removed {
+                        state.update_inherited_priority(cs);
+                    }
+                    removed
+                },
+            )
+        }
+        .map(|()| RwLockReadGuard::new(self))
+    }
+
+    /// Acquires shared read access, blocking the current thread for at most `timeout`.
+    ///
+    /// See [`Self::read_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if read access could not be acquired within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn read_timeout(&self, timeout: Duration) -> Result<RwLockReadGuard<'_, T>, TimeoutError> {
+        self.read_until(deadline_from(timeout))
+    }
+
+    /// Attempts to acquire shared read access, in a non-blocking fashion.
+    ///
+    /// Returns `None` if a writer holds the lock or is waiting for it.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        critical_section::with(|cs| self.try_read_cs(cs)).then(|| RwLockReadGuard::new(self))
+    }
+
+    fn try_read_cs(&self, cs: CriticalSection<'_>) -> bool {
+        let state = self.state(cs);
+        if state.writer.is_none() && state.write_waiters.is_empty(cs) {
+            state.readers += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn block_read_cs(&self, cs: CriticalSection<'_>) {
+        let state = self.state(cs);
+        state.inherit_priority(cs);
+        // Read access is granted in `wake_next()`.
+        state
+            .read_waiters
+            .put_current(cs, ThreadState::RwLockReadBlocked);
+    }
+
+    /// Acquires exclusive write access, blocking the current thread until it is able to do so.
+    ///
+    /// If the lock is held by a writer with a lower priority than the current thread, the
+    /// writer inherits the current thread's priority until it releases the lock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        critical_section::with(|cs| {
+            if !self.try_write_cs(cs) {
+                self.block_write_cs(cs);
+            }
+        });
+
+        RwLockWriteGuard::new(self)
+    }
+
+    /// Acquires exclusive write access, blocking the current thread at most until `deadline`.
+    ///
+    /// Behaves like [`Self::write()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if write access could not be acquired before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn write_until(&self, deadline: Instant) -> Result<RwLockWriteGuard<'_, T>, TimeoutError> {
+        // SAFETY: `on_timeout` takes care of removing the thread from the write waiters.
+        unsafe {
+            block_until(
+                deadline,
+                |cs| self.try_write_cs(cs),
+                |cs| self.block_write_cs(cs),
+                |cs| {
+                    let state = self.state(cs);
+                    let removed = state.write_waiters.remove_current(cs);
+                    if removed {
+                        state.update_inherited_priority(cs);
+                        // Readers might have been held back only because of this writer.
+                        if state.writer.is_none() && state.write_waiters.is_empty(cs) {
+                            while state.read_waiters.pop(cs).is_some() {
+                                state.readers += 1;
+                            }
+                        }
+                    }
+                    removed
+                },
+            )
+        }
+        .map(|()| RwLockWriteGuard::new(self))
+    }
+
+    /// Acquires exclusive write access, blocking the current thread for at most `timeout`.
+    ///
+    /// See [`Self::write_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if write access could not be acquired within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    pub fn write_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<RwLockWriteGuard<'_, T>, TimeoutError> {
+        self.write_until(deadline_from(timeout))
+    }
+
+    /// Attempts to acquire exclusive write access, in a non-blocking fashion.
+    ///
+    /// Returns `None` if the lock is held by readers or by a writer.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        critical_section::with(|cs| self.try_write_cs(cs)).then(|| RwLockWriteGuard::new(self))
+    }
+
+    fn try_write_cs(&self, cs: CriticalSection<'_>) -> bool {
+        let state = self.state(cs);
+        if state.writer.is_none() && state.readers == 0 {
+            state.writer = Some(Self::current_writer(cs));
+            true
+        } else {
+            false
+        }
+    }
+
+    fn block_write_cs(&self, cs: CriticalSection<'_>) {
+        let state = self.state(cs);
+        state.inherit_priority(cs);
+        // Write access is granted in `wake_next()`.
+        state
+            .write_waiters
+            .put_current(cs, ThreadState::RwLockWriteBlocked);
+    }
+
+    /// Returns mutable access to the lock state.
+    ///
+    /// The critical section token is required as proof of unique access.
+    #[expect(
+        clippy::mut_from_ref,
+        reason = "uniqueness is ensured by the critical section"
+    )]
+    fn state(&self, _cs: CriticalSection<'_>) -> &mut RwLockState {
+        // SAFETY: access to the state only happens in critical sections, and references to it
+        // never escape them, so it's always unique.
+        unsafe { &mut *self.state.get() }
+    }
+
+    /// Returns a [`Writer`] for the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a thread context.
+    fn current_writer(cs: CriticalSection<'_>) -> Writer {
+        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+            let current = scheduler
+                .current()
+                .expect("Function should be called inside a thread context.");
+            Writer {
+                id: current.tid,
+                prio: current.prio,
+            }
+        })
+    }
+
+    /// Releases shared read access.
+    fn release_read(&self) {
+        critical_section::with(|cs| {
+            let state = self.state(cs);
+            state.readers -= 1;
+            if state.readers == 0 {
+                state.wake_next(cs);
+            }
+        });
+    }
+
+    /// Releases exclusive write access.
+    fn release_write(&self) {
+        critical_section::with(|cs| {
+            let state = self.state(cs);
+            if let Some(writer) = state.writer.take() {
+                // Reset original priority of the writer.
+                SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+                    scheduler.set_priority(writer.id, writer.prio);
+                });
+            }
+            state.wake_next(cs);
+        });
+    }
+}
+
+/// Grants shared access to the [`RwLock`] inner data.
+///
+/// Dropping the [`RwLockReadGuard`] releases the read access.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, T> RwLockReadGuard<'a, T> {
+    fn new(lock: &'a RwLock<T>) -> Self {
+        Self {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: no writer exists while a read guard exists.
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_read();
+    }
+}
+
+// SAFETY: the guard only gives out shared references.
+unsafe impl<T: Sync> Sync for RwLockReadGuard<'_, T> {}
+
+/// Grants exclusive access to the [`RwLock`] inner data.
+///
+/// Dropping the [`RwLockWriteGuard`] releases the write access.
+#[must_use = "if unused the RwLock will immediately unlock"]
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, T> RwLockWriteGuard<'a, T> {
+    fn new(lock: &'a RwLock<T>) -> Self {
+        Self {
+            lock,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: RwLockWriteGuard always has unique access.
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: RwLockWriteGuard always has unique access.
+        unsafe { &mut *self.lock.inner.get() }
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.release_write();
+    }
+}
+
+// SAFETY: the guard only gives out shared references to other threads.
+unsafe impl<T: Sync> Sync for RwLockWriteGuard<'_, T> {}
@@ -0,0 +1,179 @@
+//! This module provides a counting semaphore.
+
+#![expect(unsafe_code)]
+#![deny(missing_docs)]
+
+use core::cell::UnsafeCell;
+
+use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    ThreadState,
+    threadlist::ThreadList,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+/// A counting semaphore.
+///
+/// A [`Semaphore`] manages a number of permits.
+/// [`Self::acquire()`] takes a permit, blocking the current thread until one is available;
+/// [`Self::release()`] gives a permit back.
+///
+/// Released permits are handed over directly to the highest priority waiting thread.
+///
+/// [`Self::release()`] and [`Self::try_acquire()`] never block and can be used from interrupt
+/// handlers.
+pub struct Semaphore {
+    state: UnsafeCell<SemaphoreState>,
+}
+
+// SAFETY: all accesses to the state happen within critical sections.
+unsafe impl Sync for Semaphore {}
+
+struct SemaphoreState {
+    permits: usize,
+    waiters: ThreadList,
+}
+
+impl Semaphore {
+    /// Creates a new [`Semaphore`] with `permits` available permits.
+    #[must_use]
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            state: UnsafeCell::new(SemaphoreState {
+                permits,
+                waiters: ThreadList::new(),
+            }),
+        }
+    }
+
+    /// Returns the number of currently available permits.
+    pub fn available_permits(&self) -> usize {
+        critical_section::with(|cs| self.state(cs).permits)
+    }
+
+    /// Acquires a permit (blocking).
+    ///
+    /// If no permit is available, this function will block the current thread until
+    /// a permit gets released elsewhere.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn acquire(&self) {
+        critical_section::with(|cs| {
+            if !self.try_acquire_cs(cs) {
+                self.block_cs(cs);
+            }
+        });
+    }
+
+    /// Acquires a permit, blocking at most until `deadline`.
+    ///
+    /// Behaves like [`Self::acquire()`], but gives up once `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if no permit could be acquired before `deadline`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn acquire_until(&self, deadline: Instant) -> Result<(), TimeoutError> {
+        // SAFETY: `on_timeout` takes care of removing the thread from the waiters.
+        unsafe {
+            block_until(
+                deadline,
+                |cs| self.try_acquire_cs(cs),
+                |cs| self.block_cs(cs),
+                |cs| self.state(cs).waiters.remove_current(cs),
+            )
+        }
+    }
+
+    /// Acquires a permit, blocking for at most `timeout`.
+    ///
+    /// See [`Self::acquire_until()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimeoutError`] if no permit could be acquired within `timeout`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn acquire_timeout(&self, timeout: Duration) -> Result<(), TimeoutError> {
+        self.acquire_until(deadline_from(timeout))
+    }
+
+    /// Acquires a permit (non-blocking).
+    ///
+    /// Returns `true` if a permit was acquired, `false` if none was available.
+    pub fn try_acquire(&self) -> bool {
+        critical_section::with(|cs| self.try_acquire_cs(cs))
+    }
+
+    fn try_acquire_cs(&self, cs: CriticalSection<'_>) -> bool {
+        let state = self.state(cs);
+        if state.permits > 0 {
+            state.permits -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn block_cs(&self, cs: CriticalSection<'_>) {
+        // The permit is handed over in `release()`.
+        self.state(cs)
+            .waiters
+            .put_current(cs, ThreadState::SemaphoreBlocked);
+    }
+
+    /// Returns mutable access to the semaphore state.
+    ///
+    /// The critical section token is required as proof of unique access.
+    #[expect(
+        clippy::mut_from_ref,
+        reason = "uniqueness is ensured by the critical section"
+    )]
+    fn state(&self, _cs: CriticalSection<'_>) -> &mut SemaphoreState {
+        // SAFETY: access to the state only happens in critical sections, and references to it
+        // never escape them, so it's always unique.
+        unsafe { &mut *self.state.get() }
+    }
+
+    /// Releases a permit.
+    ///
+    /// If there are waiters, the permit is handed to the highest priority waiter, which
+    /// is woken up.
+    /// Otherwise, the number of available permits is increased.
+    ///
+    /// This can be called from interrupt handlers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of available permits would overflow.
+    pub fn release(&self) {
+        critical_section::with(|cs| {
+            let state = self.state(cs);
+            if state.waiters.pop(cs).is_none() {
+                state.permits = state
+                    .permits
+                    .checked_add(1)
+                    .expect("semaphore permits should not overflow");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_type_sizes() {
+        assert_eq!(size_of::<SemaphoreState>(), size_of::<usize>() * 2);
+    }
+}
@@ -0,0 +1,22 @@
+//! Async tasks waiting on synchronization primitives.
+use embassy_sync::waitqueue::MultiWakerRegistration;
+
+/// Number of async tasks that can wait on the same synchronization primitive, or on the flags of
+/// the same thread, at a time.
+///
+/// When more tasks wait, all of them are woken up spuriously and register again, so this is not
+/// a hard limit.
+/// Can be configured with the `CONFIG_THREAD_ASYNC_WAITERS` environment variable, defaults to 4.
+pub const ASYNC_WAITERS: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_THREAD_ASYNC_WAITERS",
+    4,
+    "number of async tasks waiting on a thread synchronization primitive"
+);
+
+const _: () = assert!(
+    ASYNC_WAITERS > 0,
+    "`CONFIG_THREAD_ASYNC_WAITERS` must not be zero"
+);
+
+/// Wakers of the async tasks waiting on a synchronization primitive.
+pub(crate) type Wakers = MultiWakerRegistration<ASYNC_WAITERS>;
@@ -2,6 +2,9 @@
 
 use crate::{Arch as _, Cpu, RunqueueId, ThreadData, ThreadId, thread_flags::ThreadFlags};
 
+/// Byte that's used to paint stacks.
+const STACK_PAINT_COLOR: u8 = 0xCC;
+
 /// Main struct for holding thread data.
 #[derive(Debug)]
 pub struct Thread {
@@ -20,6 +23,8 @@ pub struct Thread {
     pub tid: ThreadId,
     /// Flags set for the thread.
     pub flags: ThreadFlags,
+    /// Optional name of the thread.
+    pub name: Option<&'static str>,
     /// Arch-specific thread data.
     #[allow(dead_code)]
     pub(crate) data: ThreadData,
@@ -34,6 +39,36 @@ pub struct Thread {
     pub stack_lowest: usize,
     /// Highest stack address.
     pub stack_highest: usize,
+
+    /// Incremented every time the thread slot is reused, to detect stale [`crate::JoinHandle`]s.
+    pub generation: u16,
+    /// Whether the slot is released right away when the thread finishes.
+    pub detached: bool,
+    /// Thread waiting for this thread to finish.
+    pub joiner: Option<ThreadId>,
+    /// Function that produces the exit value, see [`crate::create_returning()`].
+    pub returning_fn: Option<fn(usize) -> usize>,
+    /// Value returned by `returning_fn`, once the thread finished.
+    pub exit_value: Option<usize>,
+    /// Stack allocated by [`crate::spawn()`], freed once the thread slot is released.
+    #[cfg(feature = "alloc")]
+    pub(crate) heap_stack: Option<crate::spawn::HeapStack>,
+    /// Priority of the thread before it was raised to the ceiling of the
+    /// [`crate::sync::CeilingMutex`]es it holds, `None` if it holds none.
+    pub(crate) ceiling_base_prio: Option<RunqueueId>,
+    /// Address of the state of the [`crate::sync::Mutex`] the thread is waiting for.
+    #[cfg(feature = "deadlock-detection")]
+    pub(crate) blocked_on_mutex: Option<usize>,
+    /// Thread-local keys whose value the thread has initialized.
+    #[cfg(feature = "thread-local")]
+    pub(crate) locals: crate::thread_local::Slots,
+
+    /// Accumulated run time, in system timer ticks.
+    #[cfg(feature = "cpu-time")]
+    pub run_ticks: u64,
+    /// Number of times the thread has been switched to.
+    #[cfg(feature = "cpu-time")]
+    pub switches: u32,
 }
 
 /// Possible states of a thread.
@@ -49,7 +84,7 @@ pub enum ThreadState {
     Running,
     /// Suspended / paused.
     Parked,
-    /// Waiting to acquire a [`super::lock::Lock`].
+    /// Waiting to acquire a [`crate::sync::Lock`] or for a [`crate::sync::Event`].
     LockBlocked,
     /// Waiting for [`ThreadFlags`] to be set.
     FlagBlocked(crate::thread_flags::WaitMode),
@@ -57,8 +92,28 @@ pub enum ThreadState {
     ChannelRxBlocked(usize),
     /// Waiting to send on a [`crate::sync::Channel`], i.e. waiting for the receiver.
     ChannelTxBlocked(usize),
-    /// Waiting for a [`super::wait_queue::WaitQueue`].
+    /// Waiting for a [`crate::sync::WaitQueue`].
     WaitQueueBlocked,
+    /// Waiting to receive on a [`crate::sync::Queue`], i.e. waiting for the queue to be non-empty.
+    QueueRxBlocked,
+    /// Waiting to send on a [`crate::sync::Queue`], i.e. waiting for the queue to be non-full.
+    QueueTxBlocked,
+    /// Waiting to acquire a permit of a [`crate::sync::Semaphore`].
+    SemaphoreBlocked,
+    /// Waiting to acquire shared (read) access to a [`crate::sync::RwLock`].
+    RwLockReadBlocked,
+    /// Waiting to acquire exclusive (write) access to a [`crate::sync::RwLock`].
+    RwLockWriteBlocked,
+    /// Waiting to be notified on a [`crate::sync::Condvar`].
+    CondvarBlocked,
+    /// Waiting for another thread to finish, see [`crate::JoinHandle`].
+    JoinBlocked,
+    /// Waiting for work to be submitted to a [`crate::work_queue::WorkQueue`].
+    WorkQueueIdle,
+    /// Waiting for bits of a [`crate::sync::EventGroup`].
+    EventGroupBlocked(crate::thread_flags::WaitMode),
+    /// Finished running, waiting to be joined.
+    Finished,
 }
 
 impl Thread {
@@ -68,6 +123,7 @@ impl Thread {
             state: ThreadState::Invalid,
             data: Cpu::DEFAULT_THREAD_DATA,
             flags: 0,
+            name: None,
             prio: RunqueueId::new(0),
             tid: ThreadId::new(0),
             #[cfg(feature = "core-affinity")]
@@ -75,6 +131,22 @@ impl Thread {
             deadline: None,
             stack_highest: 0,
             stack_lowest: 0,
+            generation: 0,
+            detached: false,
+            joiner: None,
+            returning_fn: None,
+            exit_value: None,
+            #[cfg(feature = "alloc")]
+            heap_stack: None,
+            ceiling_base_prio: None,
+            #[cfg(feature = "deadlock-detection")]
+            blocked_on_mutex: None,
+            #[cfg(feature = "thread-local")]
+            locals: crate::thread_local::EMPTY_SLOTS,
+            #[cfg(feature = "cpu-time")]
+            run_ticks: 0,
+            #[cfg(feature = "cpu-time")]
+            switches: 0,
         }
     }
 
@@ -84,9 +156,6 @@ impl Thread {
     /// - must only be called before the stack is active (within `arch::setup_stack()`).
     #[allow(dead_code, reason = "not used in all configurations")]
     pub(crate) unsafe fn stack_paint_init(&mut self, sp: usize) {
-        // Byte that's used to paint stacks.
-        const STACK_PAINT_COLOR: u8 = 0xCC;
-
         for pos in self.stack_lowest..sp {
             // SAFETY: Writing to the slice that was passed to `setup_stack()` is fine
             unsafe {
@@ -96,6 +165,31 @@ impl Thread {
     }
 }
 
+/// Returns the number of bytes of a painted stack that still contain the paint, starting from
+/// its lowest address.
+///
+/// # Safety
+/// - `lowest..lowest + size` must be the bounds of a thread stack set up in
+///   `arch::setup_stack()`, or `size` must be zero.
+/// - The stack must not be freed while this runs.
+pub(crate) unsafe fn stack_painted_len(lowest: usize, size: usize) -> usize {
+    // The canary at the bottom of the stack is only overwritten by overflows, so it counts as
+    // unused.
+    #[cfg(feature = "stack-overflow-detection")]
+    let canary_len = crate::stack_overflow::canary_len(size);
+    #[cfg(not(feature = "stack-overflow-detection"))]
+    let canary_len = 0;
+
+    canary_len
+        + (lowest + canary_len..lowest + size)
+            // SAFETY: Reading from the slice that was passed to `setup_stack()` is fine, the
+            // caller ensures that it is not freed.
+            .take_while(
+                |&pos| unsafe { core::ptr::read_volatile(pos as *const u8) } == STACK_PAINT_COLOR,
+            )
+            .count()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,6 +199,13 @@ mod tests {
         // `ThreadData` is arch-specific, and is replaced with a dummy value in tests; its size is
         // non-zero otherwise.
         assert_eq!(size_of::<ThreadData>(), 0);
-        assert_eq!(size_of::<Thread>(), size_of::<ThreadData>() + 56);
+        #[cfg(feature = "alloc")]
+        let heap_stack = size_of::<Option<crate::spawn::HeapStack>>();
+        #[cfg(not(feature = "alloc"))]
+        let heap_stack = 0;
+        assert_eq!(
+            size_of::<Thread>(),
+            size_of::<ThreadData>() + 104 + heap_stack
+        );
     }
 }
@@ -1,5 +1,25 @@
 //! Thread flags.
-use crate::{SCHEDULER, Scheduler, ThreadId, ThreadState};
+//!
+//! With the `async` feature, async tasks run by a thread, e.g., by the thread executor, can wait
+//! for the flags of that thread as well, see [`wait_any_async()`].
+use core::cell::Cell;
+#[cfg(feature = "async")]
+use core::{cell::RefCell, future::poll_fn, task::Poll};
+
+use embassy_time::{Duration, Instant};
+
+use crate::{
+    SCHEDULER, Scheduler, ThreadId, ThreadState,
+    timeout::{TimeoutError, block_until, deadline_from},
+};
+
+#[cfg(feature = "async")]
+use crate::{THREAD_COUNT, sync::wakers::Wakers};
+
+/// Wakers of the async tasks waiting for the flags of each thread.
+#[cfg(feature = "async")]
+static FLAG_WAKERS: critical_section::Mutex<RefCell<[Wakers; THREAD_COUNT]>> =
+    critical_section::Mutex::new(RefCell::new([const { Wakers::new() }; THREAD_COUNT]));
 
 /// Bitmask that represent the flags that are set for a thread.
 pub type ThreadFlags = u16;
@@ -18,12 +38,26 @@ pub enum WaitMode {
 ///
 /// If the thread was blocked on these flags it's unblocked and added
 /// to the runqueue.
+/// Async tasks waiting for the flags of the thread are woken up.
 ///
 /// # Panics
 ///
 /// Panics if `thread_id` is >= [`THREAD_COUNT`](crate::THREAD_COUNT).
 pub fn set(thread_id: ThreadId, mask: ThreadFlags) {
     SCHEDULER.with_mut(|mut scheduler| scheduler.flag_set(thread_id, mask));
+
+    #[cfg(feature = "async")]
+    {
+        // Take the wakers out first, as waking a task may set flags again, e.g., to wake up the
+        // thread executor.
+        let mut wakers = critical_section::with(|cs| {
+            core::mem::replace(
+                &mut FLAG_WAKERS.borrow_ref_mut(cs)[usize::from(thread_id)],
+                Wakers::new(),
+            )
+        });
+        wakers.wake();
+    }
 }
 
 /// Waits until all flags in `mask` are set for the current thread.
@@ -72,6 +106,214 @@ pub fn wait_one(mask: ThreadFlags) -> ThreadFlags {
     }
 }
 
+/// Waits until all flags in `mask` are set for the current thread, or `deadline` is reached.
+///
+/// Returns the set flags for this mask and clears them for the thread.
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if the flags were not all set before `deadline`; no flags are
+/// cleared in that case.
+///
+/// # Panics
+///
+/// Panics if this is called outside of a thread context.
+pub fn wait_all_until(mask: ThreadFlags, deadline: Instant) -> Result<ThreadFlags, TimeoutError> {
+    wait_until(deadline, WaitMode::All(mask), |scheduler| {
+        scheduler.flag_take_all(mask)
+    })
+}
+
+/// Waits until all flags in `mask` are set for the current thread, for at most `timeout`.
+///
+/// See [`wait_all_until`].
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if the flags were not all set within `timeout`.
+///
+/// # Panics
+///
+/// Panics if this is called outside of a thread context.
+pub fn wait_all_timeout(mask: ThreadFlags, timeout: Duration) -> Result<ThreadFlags, TimeoutError> {
+    wait_all_until(mask, deadline_from(timeout))
+}
+
+/// Waits until any flag in `mask` is set for the current thread, or `deadline` is reached.
+///
+/// Returns all set flags for this mask and clears them for the thread.
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if none of the flags was set before `deadline`.
+///
+/// # Panics
+///
+/// Panics if this is called outside of a thread context.
+pub fn wait_any_until(mask: ThreadFlags, deadline: Instant) -> Result<ThreadFlags, TimeoutError> {
+    wait_until(deadline, WaitMode::Any(mask), |scheduler| {
+        scheduler.flag_take_any(mask)
+    })
+}
+
+/// Waits until any flag in `mask` is set for the current thread, for at most `timeout`.
+///
+/// See [`wait_any_until`].
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if none of the flags was set within `timeout`.
+///
+/// # Panics
+///
+/// Panics if this is called outside of a thread context.
+pub fn wait_any_timeout(mask: ThreadFlags, timeout: Duration) -> Result<ThreadFlags, TimeoutError> {
+    wait_any_until(mask, deadline_from(timeout))
+}
+
+/// Waits until any flag in `mask` is set for the current thread, or `deadline` is reached.
+///
+/// Compared to [`wait_any_until`], this returns and clears only one flag
+/// from the mask.
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if none of the flags was set before `deadline`.
+///
+/// # Panics
+///
+/// Panics if this is called outside of a thread context.
+pub fn wait_one_until(mask: ThreadFlags, deadline: Instant) -> Result<ThreadFlags, TimeoutError> {
+    wait_until(deadline, WaitMode::Any(mask), |scheduler| {
+        scheduler.flag_take_one(mask)
+    })
+}
+
+/// Waits until any flag in `mask` is set for the current thread, for at most `timeout`.
+///
+/// See [`wait_one_until`].
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if none of the flags was set within `timeout`.
+///
+/// # Panics
+///
+/// Panics if this is called outside of a thread context.
+pub fn wait_one_timeout(mask: ThreadFlags, timeout: Duration) -> Result<ThreadFlags, TimeoutError> {
+    wait_one_until(mask, deadline_from(timeout))
+}
+
+/// Waits until all flags in `mask` are set for the current thread, asynchronously.
+///
+/// Unlike [`wait_all()`], this can be used from async tasks, without blocking the thread that
+/// runs them; the flags are those of that thread.
+///
+/// Returns the set flags for this mask and clears them for the thread.
+///
+/// # Panics
+///
+/// Panics if this is polled outside of a thread context.
+#[cfg(feature = "async")]
+pub async fn wait_all_async(mask: ThreadFlags) -> ThreadFlags {
+    wait_async(|scheduler| scheduler.flag_take_all(mask)).await
+}
+
+/// Waits until any flag in `mask` is set for the current thread, asynchronously.
+///
+/// Unlike [`wait_any()`], this can be used from async tasks, without blocking the thread that
+/// runs them; the flags are those of that thread.
+///
+/// Returns all set flags for this mask and clears them for the thread.
+///
+/// # Panics
+///
+/// Panics if this is polled outside of a thread context.
+#[cfg(feature = "async")]
+pub async fn wait_any_async(mask: ThreadFlags) -> ThreadFlags {
+    wait_async(|scheduler| scheduler.flag_take_any(mask)).await
+}
+
+/// Waits until any flag in `mask` is set for the current thread, asynchronously.
+///
+/// Compared to [`wait_any_async`], this returns and clears only one flag
+/// from the mask.
+///
+/// # Panics
+///
+/// Panics if this is polled outside of a thread context.
+#[cfg(feature = "async")]
+pub async fn wait_one_async(mask: ThreadFlags) -> ThreadFlags {
+    wait_async(|scheduler| scheduler.flag_take_one(mask)).await
+}
+
+/// Waits until `take()` returns flags for the current thread, asynchronously.
+///
+/// # Panics
+///
+/// Panics if this is polled outside of a thread context.
+#[cfg(feature = "async")]
+async fn wait_async(take: impl Fn(&mut Scheduler) -> Option<ThreadFlags>) -> ThreadFlags {
+    poll_fn(|cx| {
+        critical_section::with(|cs| {
+            let (thread_id, flags) = SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+                let thread_id = scheduler
+                    .current_tid()
+                    .expect("Function should be called inside a thread context.");
+                (thread_id, take(&mut scheduler))
+            });
+            if let Some(flags) = flags {
+                return Poll::Ready(flags);
+            }
+            FLAG_WAKERS.borrow_ref_mut(cs)[usize::from(thread_id)].register(cx.waker());
+            Poll::Pending
+        })
+    })
+    .await
+}
+
+/// Waits until `take()` returns flags for the current thread, or until `deadline` is reached.
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if `take()` did not return flags before `deadline`.
+///
+/// # Panics
+///
+/// Panics if this is called outside of a thread context.
+fn wait_until(
+    deadline: Instant,
+    mode: WaitMode,
+    take: impl Fn(&mut Scheduler) -> Option<ThreadFlags>,
+) -> Result<ThreadFlags, TimeoutError> {
+    let flags = Cell::new(None);
+    loop {
+        // SAFETY: a thread blocked on flags is not linked into any list, so there is nothing
+        // to unlink on timeout.
+        let res = unsafe {
+            block_until(
+                deadline,
+                |cs| {
+                    flags.set(SCHEDULER.with_mut_cs(cs, |mut scheduler| take(&mut scheduler)));
+                    flags.get().is_some()
+                },
+                |cs| SCHEDULER.with_mut_cs(cs, |mut scheduler| scheduler.flag_block(mode)),
+                // The flags might have been set right when the deadline was reached.
+                |cs| {
+                    flags.set(SCHEDULER.with_mut_cs(cs, |mut scheduler| take(&mut scheduler)));
+                    flags.get().is_none()
+                },
+            )
+        };
+        match (res, flags.get()) {
+            (_, Some(flags)) => return Ok(flags),
+            (Err(err), None) => return Err(err),
+            // Woken up because the flags were set, take them in the next iteration.
+            (Ok(()), None) => {}
+        }
+    }
+}
+
 /// Clears flags for the current thread.
 ///
 /// # Panics
@@ -99,6 +341,11 @@ pub fn get() -> ThreadFlags {
 impl Scheduler {
     // thread flags implementation
     pub(crate) fn flag_set(&mut self, thread_id: ThreadId, mask: ThreadFlags) {
+        #[cfg(feature = "trace")]
+        crate::trace::record(crate::trace::Event::FlagsSet {
+            thread_id,
+            flags: mask,
+        });
         let thread = self.get_unchecked_mut(thread_id);
         thread.flags |= mask;
         match thread.state {
@@ -113,48 +360,82 @@ impl Scheduler {
     ///
     /// Panics if called outside a thread context.
     fn flag_wait_all(&mut self, mask: ThreadFlags) -> Option<ThreadFlags> {
-        let thread = self.current().unwrap();
-        if thread.flags & mask == mask {
-            thread.flags &= !mask;
-            Some(mask)
-        } else {
-            let thread_id = thread.tid;
-            self.set_state(thread_id, ThreadState::FlagBlocked(WaitMode::All(mask)));
+        self.flag_take_all(mask).or_else(|| {
+            self.flag_block(WaitMode::All(mask));
             None
-        }
+        })
     }
 
     /// # Panics
     ///
     /// Panics if called outside a thread context.
     fn flag_wait_any(&mut self, mask: ThreadFlags) -> Option<ThreadFlags> {
-        let thread = self.current().unwrap();
-        if thread.flags & mask != 0 {
-            let res = thread.flags & mask;
-            thread.flags &= !res;
-            Some(res)
-        } else {
-            let thread_id = thread.tid;
-            self.set_state(thread_id, ThreadState::FlagBlocked(WaitMode::Any(mask)));
+        self.flag_take_any(mask).or_else(|| {
+            self.flag_block(WaitMode::Any(mask));
             None
-        }
+        })
     }
 
     /// # Panics
     ///
     /// Panics if called outside a thread context.
     fn flag_wait_one(&mut self, mask: ThreadFlags) -> Option<ThreadFlags> {
+        self.flag_take_one(mask).or_else(|| {
+            self.flag_block(WaitMode::Any(mask));
+            None
+        })
+    }
+
+    /// Takes all flags in `mask` if they are all set for the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a thread context.
+    fn flag_take_all(&mut self, mask: ThreadFlags) -> Option<ThreadFlags> {
+        let thread = self.current().unwrap();
+        (thread.flags & mask == mask).then(|| {
+            thread.flags &= !mask;
+            mask
+        })
+    }
+
+    /// Takes all flags in `mask` that are set for the current thread, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a thread context.
+    fn flag_take_any(&mut self, mask: ThreadFlags) -> Option<ThreadFlags> {
+        let thread = self.current().unwrap();
+        let res = thread.flags & mask;
+        (res != 0).then(|| {
+            thread.flags &= !res;
+            res
+        })
+    }
+
+    /// Takes the least significant flag in `mask` that is set for the current thread, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a thread context.
+    fn flag_take_one(&mut self, mask: ThreadFlags) -> Option<ThreadFlags> {
         let thread = self.current().unwrap();
-        if thread.flags & mask != 0 {
-            let mut res = thread.flags & mask;
+        let mut res = thread.flags & mask;
+        (res != 0).then(|| {
             // clear all but least significant bit
             res &= !res + 1;
             thread.flags &= !res;
-            Some(res)
-        } else {
-            let thread_id = thread.tid;
-            self.set_state(thread_id, ThreadState::FlagBlocked(WaitMode::Any(mask)));
-            None
-        }
+            res
+        })
+    }
+
+    /// Blocks the current thread on the flags described by `mode`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a thread context.
+    fn flag_block(&mut self, mode: WaitMode) {
+        let thread_id = self.current().unwrap().tid;
+        self.set_state(thread_id, ThreadState::FlagBlocked(mode));
     }
 }
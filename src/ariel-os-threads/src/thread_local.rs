@@ -0,0 +1,165 @@
+//! Thread-local storage.
+//!
+//! A [`LocalKey`], declared with [`thread_local!`](crate::thread_local!), holds a separate value
+//! for every thread, which is lazily initialized the first time the thread accesses it.
+//! Storage for the values of all [`THREAD_COUNT`] threads is allocated statically within the
+//! key.
+//!
+//! When a thread initializes the value of a key, the key is recorded in the thread's slot
+//! table, which holds up to [`THREAD_LOCAL_SLOTS`] keys.
+//! Once the thread function returns, the values of these keys are dropped, in the finishing
+//! thread.
+use core::{cell::UnsafeCell, fmt};
+
+use crate::{SCHEDULER, THREAD_COUNT, ThreadId};
+
+/// Maximum number of thread-local values a single thread can initialize.
+///
+/// Can be configured with the `CONFIG_THREAD_LOCAL_SLOTS` environment variable, defaults to 4.
+pub const THREAD_LOCAL_SLOTS: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_THREAD_LOCAL_SLOTS",
+    4,
+    "number of thread-local values per thread"
+);
+
+/// Keys whose value has been initialized by a thread.
+pub(crate) type Slots = [Option<&'static dyn Slot>; THREAD_LOCAL_SLOTS];
+
+/// Empty slot table.
+pub(crate) const EMPTY_SLOTS: Slots = [None; THREAD_LOCAL_SLOTS];
+
+/// Type-erased [`LocalKey`], as recorded in the slot table of a thread.
+pub(crate) trait Slot: Sync + fmt::Debug {
+    /// Drops the value of the thread.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called from the thread `thread_id`.
+    unsafe fn destroy(&self, thread_id: ThreadId);
+}
+
+/// Declares thread-local [`LocalKey`]s.
+///
+/// ```ignore
+/// use core::cell::Cell;
+///
+/// ariel_os::thread::thread_local! {
+///     static COUNTER: Cell<u32> = Cell::new(0);
+/// }
+///
+/// COUNTER.with(|counter| counter.set(counter.get() + 1));
+/// ```
+#[macro_export]
+macro_rules! thread_local {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)+) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::LocalKey<$ty> = $crate::LocalKey::new(|| $init);
+        )+
+    };
+}
+
+/// A key holding a separate value for every thread, see [`thread_local!`](crate::thread_local!).
+pub struct LocalKey<T: 'static> {
+    init: fn() -> T,
+    values: [UnsafeCell<Option<T>>; THREAD_COUNT],
+}
+
+// SAFETY: each value is only ever accessed by its thread.
+unsafe impl<T> Sync for LocalKey<T> {}
+
+impl<T: 'static> LocalKey<T> {
+    /// Creates a new [`LocalKey`], whose values are initialized with `init`.
+    ///
+    /// Use [`thread_local!`](crate::thread_local!) instead.
+    #[doc(hidden)]
+    pub const fn new(init: fn() -> T) -> Self {
+        Self {
+            init,
+            values: [const { UnsafeCell::new(None) }; THREAD_COUNT],
+        }
+    }
+
+    /// Calls `f` with a reference to the value of the current thread, initializing it first if
+    /// needed.
+    ///
+    /// Values must not be accessed from interrupt handlers, which would access the value of the
+    /// interrupted thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context, or if the current thread has
+    /// already initialized [`THREAD_LOCAL_SLOTS`] values.
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        let thread_id =
+            crate::current_tid().expect("Function should be called inside a thread context.");
+        let value = &self.values[usize::from(thread_id)];
+
+        // SAFETY: only the current thread accesses its value, and there is no mutable reference
+        // to it while the value is initialized.
+        if unsafe { &*value.get() }.is_none() {
+            // `init` may access this key as well, so it must not run while a reference to the
+            // value exists.
+            let init = (self.init)();
+            // SAFETY: see above.
+            if unsafe { &*value.get() }.is_none() {
+                register(thread_id, self);
+                // SAFETY: see above; there is no reference to the value yet.
+                unsafe { *value.get() = Some(init) };
+            }
+        }
+
+        // SAFETY: see above; the value has been initialized.
+        f(unsafe { (*value.get()).as_ref() }.unwrap())
+    }
+}
+
+impl<T: 'static> Slot for LocalKey<T> {
+    unsafe fn destroy(&self, thread_id: ThreadId) {
+        // SAFETY: only the thread accesses its value, and doesn't hold a reference to it while
+        // its values are destroyed.
+        let value = unsafe { (*self.values[usize::from(thread_id)].get()).take() };
+        drop(value);
+    }
+}
+
+impl<T: 'static> fmt::Debug for LocalKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalKey").finish_non_exhaustive()
+    }
+}
+
+/// Records `key` in the slot table of the thread.
+fn register(thread_id: ThreadId, key: &'static dyn Slot) {
+    SCHEDULER.with_mut(|mut scheduler| {
+        let slot = scheduler
+            .get_unchecked_mut(thread_id)
+            .locals
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .expect("too many thread-local values, see `CONFIG_THREAD_LOCAL_SLOTS`");
+        *slot = Some(key);
+    });
+}
+
+/// Drops the thread-local values of the current thread.
+///
+/// Must be called from the finishing thread, before it is marked as finished.
+pub(crate) fn destroy_all(thread_id: ThreadId) {
+    // Destructors may initialize other thread-local values, so repeat until none are left.
+    loop {
+        let slots = SCHEDULER.with_mut(|mut scheduler| {
+            core::mem::replace(
+                &mut scheduler.get_unchecked_mut(thread_id).locals,
+                EMPTY_SLOTS,
+            )
+        });
+        if slots.iter().all(Option::is_none) {
+            return;
+        }
+        for key in slots.into_iter().flatten() {
+            // SAFETY: this is called from the thread itself.
+            unsafe { key.destroy(thread_id) };
+        }
+    }
+}
@@ -68,26 +68,92 @@ impl ThreadList {
         })
     }
 
+    /// Removes the first thread whose [`ThreadState`] satisfies `f` from this [`ThreadList`].
+    ///
+    /// Other threads are left in the list, including threads that are not blocked anymore, e.g.,
+    /// because their deadline has been reached, until they remove themselves.
+    /// Sets the thread's [`ThreadState`] to [`ThreadState::Running`] and triggers the scheduler.
+    ///
+    /// Returns the thread's [`ThreadId`] and its previous [`ThreadState`].
+    pub(crate) fn pop_if(
+        &mut self,
+        cs: CriticalSection<'_>,
+        mut f: impl FnMut(ThreadState) -> bool,
+    ) -> Option<(ThreadId, ThreadState)> {
+        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+            let mut prev: Option<ThreadId> = None;
+            let mut next = self.head;
+            while let Some(thread_id) = next {
+                next = scheduler.thread_blocklist[usize::from(thread_id)];
+                if f(scheduler.get_unchecked(thread_id).state) {
+                    match prev {
+                        Some(prev) => scheduler.thread_blocklist[usize::from(prev)] = next,
+                        None => self.head = next,
+                    }
+                    scheduler.thread_blocklist[usize::from(thread_id)] = None;
+                    let old_state = scheduler.set_state(thread_id, ThreadState::Running);
+                    return Some((thread_id, old_state));
+                }
+                prev = Some(thread_id);
+            }
+            None
+        })
+    }
+
+    /// Removes all threads whose [`ThreadState`] satisfies `f` from this [`ThreadList`].
+    ///
+    /// Sets the threads' [`ThreadState`] to [`ThreadState::Running`] and triggers the scheduler.
+    pub(crate) fn pop_matching(
+        &mut self,
+        cs: CriticalSection<'_>,
+        mut f: impl FnMut(ThreadState) -> bool,
+    ) {
+        SCHEDULER.with_mut_cs(cs, |mut scheduler| {
+            let mut prev: Option<ThreadId> = None;
+            let mut next = self.head;
+            while let Some(thread_id) = next {
+                next = scheduler.thread_blocklist[usize::from(thread_id)];
+                if f(scheduler.get_unchecked(thread_id).state) {
+                    match prev {
+                        Some(prev) => scheduler.thread_blocklist[usize::from(prev)] = next,
+                        None => self.head = next,
+                    }
+                    scheduler.thread_blocklist[usize::from(thread_id)] = None;
+                    scheduler.set_state(thread_id, ThreadState::Running);
+                } else {
+                    prev = Some(thread_id);
+                }
+            }
+        });
+    }
+
     fn remove_inner(&mut self, scheduler: &mut Scheduler, thread_id: ThreadId) -> bool {
         ariel_os_log::trace!("remove_current() {:?}", thread_id);
-        if let Some(head) = self.head {
-            if head == thread_id {
-                self.head = scheduler.thread_blocklist[usize::from(head)].take();
-                scheduler.set_state(head, ThreadState::Running);
-                return true;
-            }
+        let Some(head) = self.head else {
+            return false;
+        };
+        if head == thread_id {
+            self.head = scheduler.thread_blocklist[usize::from(head)].take();
+        } else {
             let mut cur = head;
-            while let Some(next) = scheduler.thread_blocklist[usize::from(cur)] {
-                if next == thread_id {
-                    scheduler.thread_blocklist[usize::from(cur)] =
-                        scheduler.thread_blocklist[usize::from(next)].take();
-                    scheduler.set_state(next, ThreadState::Running);
-                    return true;
+            loop {
+                match scheduler.thread_blocklist[usize::from(cur)] {
+                    Some(next) if next == thread_id => {
+                        scheduler.thread_blocklist[usize::from(cur)] =
+                            scheduler.thread_blocklist[usize::from(next)].take();
+                        break;
+                    }
+                    Some(next) => cur = next,
+                    None => return false,
                 }
-                cur = next;
             }
         }
-        false
+        // The thread might have already been set running, e.g., by its deadline timer, in which
+        // case there is nothing left to do.
+        if scheduler.get_unchecked(thread_id).state != ThreadState::Running {
+            scheduler.set_state(thread_id, ThreadState::Running);
+        }
+        true
     }
 
     /// Removes the current thread from this [`ThreadList`].
@@ -104,6 +170,15 @@ impl ThreadList {
         })
     }
 
+    /// Returns the priority of the head of this [`ThreadList`], which is the highest priority
+    /// among its threads.
+    ///
+    /// Returns `None` if the list is empty.
+    pub(crate) fn head_prio(&self, cs: CriticalSection<'_>) -> Option<RunqueueId> {
+        let head = self.head?;
+        Some(SCHEDULER.with_cs(cs, |scheduler| scheduler.get_unchecked(head).prio))
+    }
+
     /// Determines if this [`ThreadList`] is empty.
     pub fn is_empty(&self, _cs: CriticalSection<'_>) -> bool {
         self.head.is_none()
@@ -0,0 +1,197 @@
+//! Round-robin time slicing among threads of the same priority.
+//!
+//! When the `time-slicing` feature is enabled, a thread that has been running for
+//! [`TIME_SLICE`] while another thread of the same priority is ready gets moved to the back of
+//! its runqueue, as if it had called [`yield_same()`](crate::yield_same).
+//!
+//! The time slice timer is only armed while the running thread shares its priority with another
+//! ready thread, so the scheduler stays tickless as long as there is at most one ready thread
+//! per priority.
+use core::{
+    cell::Cell,
+    task::{RawWaker, RawWakerVTable, Waker},
+};
+
+use critical_section::Mutex;
+use embassy_time::Duration;
+
+use crate::{CORE_COUNT, Scheduler, ThreadId, ThreadState, core_id};
+
+#[cfg(feature = "single-core")]
+use crate::thread::Thread;
+
+/// Length of a time slice.
+///
+/// Can be configured in microseconds with the `CONFIG_THREAD_TIME_SLICE_US` environment variable,
+/// defaults to 10 ms.
+pub const TIME_SLICE: Duration = Duration::from_micros(ariel_os_utils::usize_from_env_or!(
+    "CONFIG_THREAD_TIME_SLICE_US",
+    10_000,
+    "thread time slice length (in microseconds)"
+) as u64);
+
+const _: () = assert!(
+    TIME_SLICE.as_ticks() > 0,
+    "`CONFIG_THREAD_TIME_SLICE_US` must be at least one tick of the system timer"
+);
+
+#[derive(Clone, Copy)]
+enum Slice {
+    /// No time slice is running, because the current thread has no contender.
+    Inactive,
+    /// The current thread's time slice ends at the given timestamp.
+    Running(u64),
+    /// The current thread's time slice has ended and it needs to make way.
+    Expired,
+}
+
+/// Time slice state of each core.
+///
+/// This is kept outside of the scheduler because the timer callback might be invoked
+/// synchronously while the timer is armed from within the scheduler, which thus can't be
+/// accessed from there.
+static SLICES: Mutex<[Cell<Slice>; CORE_COUNT]> =
+    Mutex::new([const { Cell::new(Slice::Inactive) }; CORE_COUNT]);
+
+fn wake(ptr: *const ()) {
+    let core = ptr as usize;
+    let now = embassy_time_driver::now();
+    let reschedule = critical_section::with(|cs| {
+        let slice = &SLICES.borrow(cs)[core];
+        match slice.get() {
+            Slice::Running(end) if end <= now => {
+                slice.set(Slice::Expired);
+                true
+            }
+            // The timer was armed for a previous time slice that would have ended earlier, the
+            // scheduler re-arms it for the current one.
+            Slice::Running(_) => true,
+            Slice::Inactive | Slice::Expired => false,
+        }
+    });
+    if reschedule {
+        #[cfg(feature = "single-core")]
+        crate::schedule();
+        #[cfg(feature = "multi-core")]
+        crate::schedule_on_core(crate::CoreId(core as u8));
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(
+    // clone
+    |ptr| RawWaker::new(ptr, &VTABLE),
+    wake,
+    wake,
+    |_ptr| {},
+);
+
+/// Arms the time slice timer of `core` for `end`.
+fn arm(core: usize, end: u64) {
+    let raw_waker = RawWaker::new(core as *const (), &VTABLE);
+    // SAFETY: the data pointer is only used as core index and never dereferenced.
+    let waker = unsafe { Waker::from_raw(raw_waker) };
+    embassy_time_driver::schedule_wake(end, &waker);
+}
+
+impl Scheduler {
+    /// Moves the current thread to the back of its runqueue if its time slice has expired.
+    ///
+    /// Must be called before picking the next thread.
+    ///
+    /// This is not needed on multi-core, where the current thread is re-added at the tail of its
+    /// runqueue before picking the next thread anyway.
+    #[cfg(feature = "single-core")]
+    pub(crate) fn rotate_on_expired_time_slice(&mut self) {
+        let expired = critical_section::with(|cs| {
+            let slice = &SLICES.borrow(cs)[usize::from(core_id())];
+            let expired = matches!(slice.get(), Slice::Expired);
+            if expired {
+                slice.set(Slice::Inactive);
+            }
+            expired
+        });
+        if !expired {
+            return;
+        }
+        // A running thread is the head of its runqueue.
+        if let Some(&mut Thread {
+            prio,
+            state: ThreadState::Running,
+            ..
+        }) = self.current()
+        {
+            self.runqueue.advance(prio);
+        }
+    }
+
+    /// Starts a new time slice if `next` is not the thread that is already running on the
+    /// current core, and (re-)arms the timer if `next` has a contender.
+    ///
+    /// Must be called after picking the next thread.
+    pub(crate) fn start_time_slice(&mut self, next: Option<ThreadId>) {
+        let core = usize::from(core_id());
+        let current = critical_section::with(|cs| SLICES.borrow(cs)[core].get());
+        let slice = match next {
+            Some(next)
+                if matches!(current, Slice::Running(_)) && Some(next) == self.current_tid() =>
+            {
+                current
+            }
+            Some(next) if self.has_contender(next) => {
+                Slice::Running(embassy_time_driver::now() + TIME_SLICE.as_ticks())
+            }
+            _ => Slice::Inactive,
+        };
+        critical_section::with(|cs| SLICES.borrow(cs)[core].set(slice));
+        if let Slice::Running(end) = slice {
+            arm(core, end);
+        }
+    }
+
+    /// Starts a time slice on the cores that are running a thread with the same priority as
+    /// `thread_id` without contender, now that `thread_id` became ready.
+    pub(crate) fn start_time_slice_on_ready(&mut self, thread_id: ThreadId) {
+        let prio = self.get_unchecked(thread_id).prio;
+        for core in 0..CORE_COUNT {
+            #[cfg(feature = "single-core")]
+            let running = self.current_thread;
+            #[cfg(feature = "multi-core")]
+            let running = self.current_threads[core];
+
+            let Some(running) = running else {
+                continue;
+            };
+            let thread = self.get_unchecked(running);
+            if running == thread_id || thread.prio != prio || thread.state != ThreadState::Running {
+                continue;
+            }
+
+            let end = critical_section::with(|cs| {
+                let slice = &SLICES.borrow(cs)[core];
+                if !matches!(slice.get(), Slice::Inactive) {
+                    return None;
+                }
+                let end = embassy_time_driver::now() + TIME_SLICE.as_ticks();
+                slice.set(Slice::Running(end));
+                Some(end)
+            });
+            if let Some(end) = end {
+                arm(core, end);
+            }
+        }
+    }
+
+    /// Checks if another thread with the same priority as `thread_id` is ready to run.
+    fn has_contender(&mut self, thread_id: ThreadId) -> bool {
+        let prio = self.get_unchecked(thread_id).prio;
+        // On single-core, the running thread stays in the runqueue.
+        #[cfg(feature = "single-core")]
+        {
+            self.runqueue.has_multiple(prio)
+        }
+        #[cfg(feature = "multi-core")]
+        {
+            !self.runqueue.is_empty(prio)
+        }
+    }
+}
@@ -3,12 +3,14 @@ use core::task::{RawWaker, RawWakerVTable, Waker};
 use critical_section::CriticalSection;
 use embassy_time::Duration;
 
-use crate::{SCHEDULER, ThreadId, ThreadState, thread_flags::ThreadFlags};
+use crate::{SCHEDULER, ThreadId, ThreadState};
 
 use ariel_os_log::trace;
 
-const THREAD_FLAG_TIMEOUT: ThreadFlags = 2; // TODO: find more appropriate value
-
+/// Wakes up the thread once its deadline has been reached.
+///
+/// The timeout is recorded by clearing the thread's deadline, see [`clear_deadline()`], and not
+/// through its thread flags, so that all thread flags remain available to users.
 fn wake(ptr: *const ()) {
     #[expect(clippy::cast_possible_truncation)]
     let thread_id = ThreadId::new(ptr as usize as u8);
@@ -21,7 +23,8 @@ fn wake(ptr: *const ()) {
                     thread_id, deadline, now
                 );
                 scheduler.threads[usize::from(thread_id)].deadline = None;
-                scheduler.flag_set(thread_id, THREAD_FLAG_TIMEOUT);
+                #[cfg(feature = "trace")]
+                crate::trace::record(crate::trace::Event::TimerWakeup { thread_id });
                 match scheduler.get_state(thread_id) {
                     Some(ThreadState::Running) => {}
                     _ => {
@@ -69,7 +72,6 @@ fn set_deadline(cs: CriticalSection<'_>, deadline: u64) -> bool {
     let thread_id = SCHEDULER.with_mut_cs(cs, |mut scheduler| {
         let thread = scheduler.current().expect("must be called from a thread");
         thread.deadline = Some(deadline);
-        thread.flags &= !THREAD_FLAG_TIMEOUT;
         thread.tid
     });
 
@@ -203,10 +205,81 @@ pub(crate) unsafe fn with_deadline_check(
     }
 }
 
+/// Blocks the current thread until it gets woken up or `deadline` is reached.
+///
+/// 1. Calls `try_now()`. If that returns true, returns `Ok(())` without blocking.
+/// 2. If `deadline` is in the past, returns `Err(TimeoutError)`.
+/// 3. Otherwise, sets the thread's deadline and calls `block()`, which is expected to block the
+///    current thread.
+/// 4. Once the thread runs again, if the deadline has been reached, calls `on_timeout()`, which
+///    must unlink the thread from whatever it was blocked on and return whether it was still
+///    blocked on it.
+///
+/// # Errors
+///
+/// Returns [`TimeoutError`] if the deadline was reached while the thread was still blocked.
+///
+/// # Safety
+/// This will set the calling thread to `Runnable` after the timeout expires. Caller must ensure
+/// that `on_timeout()` unlinks the thread from any wait list it was put in by `block()`.
+pub(crate) unsafe fn block_until(
+    deadline: embassy_time::Instant,
+    try_now: impl FnOnce(CriticalSection<'_>) -> bool,
+    block: impl FnOnce(CriticalSection<'_>),
+    on_timeout: impl FnOnce(CriticalSection<'_>) -> bool,
+) -> Result<(), TimeoutError> {
+    let deadline = deadline.as_ticks();
+    let initial = critical_section::with(|cs| {
+        if try_now(cs) {
+            Some(Ok(()))
+        } else if set_deadline(cs, deadline) {
+            block(cs);
+            None
+        } else {
+            trace!("block_until: deadline {} was in the past", deadline);
+            Some(Err(TimeoutError))
+        }
+    });
+
+    match initial {
+        Some(res) => res,
+        None => critical_section::with(|cs| {
+            if clear_deadline(cs) {
+                Ok(())
+            } else {
+                trace!("block_until: timeout deadline {}", deadline);
+                if on_timeout(cs) {
+                    Err(TimeoutError)
+                } else {
+                    // The thread got woken up right when the deadline was reached.
+                    Ok(())
+                }
+            }
+        }),
+    }
+}
+
+/// Error returned when a deadline was reached before a blocking operation could complete.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimeoutError;
+
+impl core::fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "deadline reached")
+    }
+}
+
+impl core::error::Error for TimeoutError {}
+
+/// Returns the deadline that is `timeout` from now.
+pub(crate) fn deadline_from(timeout: Duration) -> embassy_time::Instant {
+    embassy_time::Instant::now().saturating_add(timeout)
+}
+
 /// Put the current thread to sleep for the given duration.
 pub fn sleep(duration: Duration) {
-    let deadline = embassy_time::Instant::now().saturating_add(duration);
-    sleep_until(deadline);
+    sleep_until(deadline_from(duration));
 }
 
 /// Put the current thread to sleep until the given deadline.
@@ -0,0 +1,380 @@
+//! Scheduler event tracing.
+//!
+//! When the `trace` feature is enabled, the scheduler records the following events, along with
+//! a timestamp and the core they happened on, into a ring buffer in RAM:
+//!
+//! - context switches,
+//! - thread state transitions, where the new [`ThreadState`] tells why a thread blocked,
+//! - thread flags being set,
+//! - timer wakeups of threads waiting with a timeout,
+//! - interrupt handler entries and exits, for handlers that call [`isr_enter()`] and
+//!   [`isr_exit()`].
+//!
+//! Interrupt handlers are not instrumented automatically, as they are provided by the HALs:
+//! handlers that should show up in the trace need to call [`isr_enter()`] and [`isr_exit()`]
+//! themselves.
+//!
+//! The ring buffer keeps the most recent [`TRACE_LEN`] events, older events get overwritten.
+//!
+//! [`dump()`] exports the recorded events in the binary format described below, and
+//! [`log_dump()`] prints that dump through the log.
+//! `scripts/trace-decode.rs` converts either of them into the Chrome trace event JSON format,
+//! which can be opened in [Perfetto](https://ui.perfetto.dev) or `chrome://tracing`.
+//!
+//! On native, threads run as host threads, so no context switches are recorded.
+//! The trace is written to the file named by the `ARIEL_OS_TRACE_FILE` environment variable,
+//! `ariel-os-trace.bin` by default, when the process exits.
+//!
+//! # Format
+//!
+//! All integers are little-endian.
+//! A dump starts with a 20-byte header:
+//!
+//! | Offset | Size | Content                                                    |
+//! |--------|------|------------------------------------------------------------|
+//! | 0      | 4    | magic, `b"ATRC"`                                           |
+//! | 4      | 1    | format version, currently 1                                |
+//! | 5      | 1    | record size, currently 16                                  |
+//! | 6      | 2    | reserved                                                   |
+//! | 8      | 4    | number of events overwritten before they could be dumped   |
+//! | 12     | 8    | timer frequency in Hz                                      |
+//!
+//! It is followed by the records, oldest first:
+//!
+//! | Offset | Size | Content                                    |
+//! |--------|------|--------------------------------------------|
+//! | 0      | 8    | timestamp, in timer ticks                  |
+//! | 8      | 1    | event kind                                 |
+//! | 9      | 1    | core                                       |
+//! | 10     | 1    | `a`, event-specific                        |
+//! | 11     | 1    | `b`, event-specific                        |
+//! | 12     | 4    | `value`, event-specific                    |
+//!
+//! | Kind | Event          | `a`                    | `b`        | `value`    |
+//! |------|----------------|------------------------|------------|------------|
+//! | 0    | context switch | previous thread or 255 | next thread| -          |
+//! | 1    | state change   | thread                 | old state  | new state  |
+//! | 2    | flags set      | thread                 | -          | flags      |
+//! | 3    | timer wakeup   | thread                 | -          | -          |
+//! | 4    | ISR enter      | -                      | -          | IRQ number |
+//! | 5    | ISR exit       | -                      | -          | IRQ number |
+//!
+//! Thread states ([`ThreadState`]) are encoded as follows; codes never change, and new states get
+//! the next unused code:
+//!
+//! | Code | State                |
+//! |------|----------------------|
+//! | 0    | `Invalid`            |
+//! | 1    | `Running`            |
+//! | 2    | `Parked`             |
+//! | 3    | `LockBlocked`        |
+//! | 4    | `FlagBlocked`        |
+//! | 5    | `ChannelRxBlocked`   |
+//! | 6    | `ChannelTxBlocked`   |
+//! | 7    | `WaitQueueBlocked`   |
+//! | 8    | `QueueRxBlocked`     |
+//! | 9    | `QueueTxBlocked`     |
+//! | 10   | `SemaphoreBlocked`   |
+//! | 11   | `RwLockReadBlocked`  |
+//! | 12   | `RwLockWriteBlocked` |
+//! | 13   | `CondvarBlocked`     |
+//! | 14   | `JoinBlocked`        |
+//! | 15   | `Finished`           |
+//! | 16   | `WorkQueueIdle`      |
+//! | 17   | `EventGroupBlocked`  |
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::{ThreadId, ThreadState, core_id, thread_flags::ThreadFlags};
+
+/// Number of events kept in the trace buffer.
+///
+/// Can be configured with the `CONFIG_THREAD_TRACE_LEN` environment variable, defaults to 256.
+pub const TRACE_LEN: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_THREAD_TRACE_LEN",
+    256,
+    "number of scheduler events kept in the trace buffer"
+);
+
+const _: () = assert!(TRACE_LEN > 0, "`CONFIG_THREAD_TRACE_LEN` must not be zero");
+
+const MAGIC: [u8; 4] = *b"ATRC";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 20;
+const RECORD_SIZE: usize = 16;
+
+/// Value of `a` for context switches that don't have a previous thread.
+const NO_THREAD: u8 = 0xFF;
+
+type Record = [u8; RECORD_SIZE];
+
+/// A scheduler event.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Event {
+    #[allow(dead_code, reason = "not recorded on native")]
+    ContextSwitch {
+        from: Option<ThreadId>,
+        to: ThreadId,
+    },
+    StateChange {
+        thread_id: ThreadId,
+        from: ThreadState,
+        to: ThreadState,
+    },
+    FlagsSet {
+        thread_id: ThreadId,
+        flags: ThreadFlags,
+    },
+    TimerWakeup {
+        thread_id: ThreadId,
+    },
+    IsrEnter {
+        irq: u16,
+    },
+    IsrExit {
+        irq: u16,
+    },
+}
+
+impl Event {
+    /// Encodes the event into a record.
+    fn encode(self, timestamp: u64, core: u8) -> Record {
+        let (kind, a, b, value) = match self {
+            Self::ContextSwitch { from, to } => {
+                (0, from.map_or(NO_THREAD, tid_code), tid_code(to), 0)
+            }
+            Self::StateChange {
+                thread_id,
+                from,
+                to,
+            } => (
+                1,
+                tid_code(thread_id),
+                state_code(from),
+                u32::from(state_code(to)),
+            ),
+            Self::FlagsSet { thread_id, flags } => (2, tid_code(thread_id), 0, u32::from(flags)),
+            Self::TimerWakeup { thread_id } => (3, tid_code(thread_id), 0, 0),
+            Self::IsrEnter { irq } => (4, 0, 0, u32::from(irq)),
+            Self::IsrExit { irq } => (5, 0, 0, u32::from(irq)),
+        };
+
+        let mut record = [0; RECORD_SIZE];
+        record[..8].copy_from_slice(&timestamp.to_le_bytes());
+        record[8] = kind;
+        record[9] = core;
+        record[10] = a;
+        record[11] = b;
+        record[12..].copy_from_slice(&value.to_le_bytes());
+        record
+    }
+}
+
+/// Returns the trace encoding of a thread ID.
+fn tid_code(thread_id: ThreadId) -> u8 {
+    // Thread IDs are below `THREAD_COUNT`, which is at most 254.
+    usize::from(thread_id) as u8
+}
+
+/// Returns the trace encoding of a thread state.
+///
+/// The codes are part of the trace format: existing codes never change, new states get the next
+/// unused code.
+fn state_code(state: ThreadState) -> u8 {
+    match state {
+        ThreadState::Invalid => 0,
+        ThreadState::Running => 1,
+        ThreadState::Parked => 2,
+        ThreadState::LockBlocked => 3,
+        ThreadState::FlagBlocked(_) => 4,
+        ThreadState::ChannelRxBlocked(_) => 5,
+        ThreadState::ChannelTxBlocked(_) => 6,
+        ThreadState::WaitQueueBlocked => 7,
+        ThreadState::QueueRxBlocked => 8,
+        ThreadState::QueueTxBlocked => 9,
+        ThreadState::SemaphoreBlocked => 10,
+        ThreadState::RwLockReadBlocked => 11,
+        ThreadState::RwLockWriteBlocked => 12,
+        ThreadState::CondvarBlocked => 13,
+        ThreadState::JoinBlocked => 14,
+        ThreadState::Finished => 15,
+        ThreadState::WorkQueueIdle => 16,
+        ThreadState::EventGroupBlocked(_) => 17,
+    }
+}
+
+/// Ring buffer of records.
+struct Ring {
+    records: [Record; TRACE_LEN],
+    /// Index of the oldest record.
+    head: usize,
+    len: usize,
+    /// Number of records overwritten since the last dump.
+    dropped: u32,
+}
+
+impl Ring {
+    const fn new() -> Self {
+        Self {
+            records: [[0; RECORD_SIZE]; TRACE_LEN],
+            head: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Appends a record, overwriting the oldest one if the buffer is full.
+    fn push(&mut self, record: Record) {
+        let tail = (self.head + self.len) % TRACE_LEN;
+        self.records[tail] = record;
+        if self.len == TRACE_LEN {
+            self.head = (self.head + 1) % TRACE_LEN;
+            self.dropped = self.dropped.saturating_add(1);
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Removes and returns the oldest record.
+    fn pop(&mut self) -> Option<Record> {
+        if self.len == 0 {
+            return None;
+        }
+        let record = self.records[self.head];
+        self.head = (self.head + 1) % TRACE_LEN;
+        self.len -= 1;
+        Some(record)
+    }
+}
+
+static RING: Mutex<RefCell<Ring>> = Mutex::new(RefCell::new(Ring::new()));
+
+/// Records an event on the current core.
+pub(crate) fn record(event: Event) {
+    let record = event.encode(embassy_time_driver::now(), core_id().0);
+    critical_section::with(|cs| RING.borrow_ref_mut(cs).push(record));
+}
+
+/// Records the entry into an interrupt handler.
+///
+/// Must be called at the start of the handler, and paired with a call to [`isr_exit()`] at its
+/// end; interrupt handlers are not traced otherwise.
+/// `irq` is only used to tell handlers apart in the trace, e.g., the interrupt number.
+pub fn isr_enter(irq: u16) {
+    record(Event::IsrEnter { irq });
+}
+
+/// Records the exit from an interrupt handler, see [`isr_enter()`].
+pub fn isr_exit(irq: u16) {
+    record(Event::IsrExit { irq });
+}
+
+/// Drains the trace buffer, passing the dump to `write` in chunks.
+///
+/// The first chunk is the header, each following chunk is one record, see the
+/// [module-level documentation](self) for the format.
+/// Only the events recorded before calling this are dumped, so that events caused by `write`
+/// itself don't keep the dump going.
+pub fn dump(mut write: impl FnMut(&[u8])) {
+    let (len, dropped) = critical_section::with(|cs| {
+        let mut ring = RING.borrow_ref_mut(cs);
+        (ring.len, core::mem::take(&mut ring.dropped))
+    });
+
+    let mut header = [0; HEADER_SIZE];
+    header[..4].copy_from_slice(&MAGIC);
+    header[4] = VERSION;
+    header[5] = RECORD_SIZE as u8;
+    header[8..12].copy_from_slice(&dropped.to_le_bytes());
+    header[12..].copy_from_slice(&embassy_time_driver::TICK_HZ.to_le_bytes());
+    write(&header);
+
+    for _ in 0..len {
+        let Some(record) = critical_section::with(|cs| RING.borrow_ref_mut(cs).pop()) else {
+            break;
+        };
+        write(&record);
+    }
+}
+
+/// Drains the trace buffer through the log, one `ariel-trace:` line per chunk of [`dump()`].
+pub fn log_dump() {
+    dump(|_chunk| {
+        ariel_os_log::info!("ariel-trace: {:?}", _chunk);
+    });
+}
+
+/// Makes the process write the trace to a file when it exits.
+#[cfg(context = "native")]
+pub(crate) fn write_file_at_exit() {
+    unsafe extern "C" {
+        fn atexit(callback: extern "C" fn()) -> core::ffi::c_int;
+    }
+
+    extern "C" fn write_file() {
+        let path =
+            std::env::var_os("ARIEL_OS_TRACE_FILE").unwrap_or_else(|| "ariel-os-trace.bin".into());
+        let mut data = std::vec::Vec::new();
+        dump(|chunk| data.extend_from_slice(chunk));
+        if std::fs::write(path, data).is_err() {
+            ariel_os_log::error!("failed to write the trace file");
+        }
+    }
+
+    // SAFETY: `atexit()` is provided by the C library, and the callback doesn't unwind.
+    if unsafe { atexit(write_file) } != 0 {
+        ariel_os_log::error!("failed to register writing the trace file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_with(n: u8) -> Record {
+        let mut record = [0; RECORD_SIZE];
+        record[0] = n;
+        record
+    }
+
+    #[test]
+    fn ring_overwrites_oldest() {
+        let mut ring = Ring::new();
+        for n in 0..TRACE_LEN + 2 {
+            ring.push(record_with(n as u8));
+        }
+        assert_eq!(ring.dropped, 2);
+        assert_eq!(ring.pop(), Some(record_with(2)));
+        let mut remaining = 1;
+        while ring.pop().is_some() {
+            remaining += 1;
+        }
+        assert_eq!(remaining, TRACE_LEN);
+    }
+
+    #[test]
+    fn encode_state_change() {
+        let record = Event::StateChange {
+            thread_id: ThreadId::new(3),
+            from: ThreadState::Running,
+            to: ThreadState::JoinBlocked,
+        }
+        .encode(0x0102_0304_0506_0708, 1);
+        assert_eq!(record, [8, 7, 6, 5, 4, 3, 2, 1, 1, 1, 3, 1, 14, 0, 0, 0],);
+    }
+
+    #[test]
+    fn state_codes_are_stable() {
+        assert_eq!(state_code(ThreadState::Invalid), 0);
+        assert_eq!(state_code(ThreadState::JoinBlocked), 14);
+        assert_eq!(state_code(ThreadState::Finished), 15);
+        assert_eq!(state_code(ThreadState::WorkQueueIdle), 16);
+        assert_eq!(
+            state_code(ThreadState::EventGroupBlocked(
+                crate::thread_flags::WaitMode::Any(0b1)
+            )),
+            17
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Deferred work, executed in thread context.
+//!
+//! A [`Work`] item wraps a function that should run in a thread rather than where the need for
+//! it arises, e.g., in an interrupt handler.
+//! Items are submitted to a [`WorkQueue`], either right away or after a delay, from interrupt
+//! handlers, async tasks or threads.
+//! One or more worker threads run [`WorkQueue::run()`] and execute the submitted items in
+//! submission order; the priorities of these threads set the priority of the work.
+//!
+//! Both work items and queues are meant to be `static`s, nothing is allocated:
+//!
+//! ```ignore
+//! static QUEUE: WorkQueue = WorkQueue::new();
+//! static WORK: Work = Work::new(handle_data);
+//!
+//! fn handle_data() {
+//!     // Runs in a worker thread.
+//! }
+//!
+//! #[ariel_os::thread(autostart, priority = 3)]
+//! fn worker() {
+//!     QUEUE.run()
+//! }
+//!
+//! fn irq_handler() {
+//!     QUEUE.submit(&WORK);
+//! }
+//! ```
+//!
+//! An item is either idle, pending (queued, or waiting for its delay to pass) or running.
+//! A pending item is queued at most once: submitting it again has no effect until a worker
+//! thread has started running it, so that bursts of submissions get coalesced.
+//! A running item can be submitted again, including from its own function.
+use core::{
+    cell::UnsafeCell,
+    task::{RawWaker, RawWakerVTable, Waker},
+};
+
+use critical_section::CriticalSection;
+use embassy_time::{Duration, Instant};
+
+use crate::{ThreadState, threadlist::ThreadList, timeout::deadline_from};
+
+/// A work item, which can be submitted to a [`WorkQueue`].
+pub struct Work {
+    handler: fn(),
+    state: UnsafeCell<WorkState>,
+}
+
+struct WorkState {
+    /// The queue the item was last submitted to.
+    queue: Option<&'static WorkQueue>,
+    /// Next item in the queue.
+    next: Option<&'static Work>,
+    /// Whether the item is in the queue.
+    queued: bool,
+    /// Deadline of a delayed submission, in timer ticks.
+    deadline: Option<u64>,
+}
+
+// SAFETY: the state is only accessed in critical sections.
+unsafe impl Sync for Work {}
+
+impl Work {
+    /// Creates a new idle [`Work`] item, which runs `handler` when executed.
+    #[must_use]
+    pub const fn new(handler: fn()) -> Self {
+        Self {
+            handler,
+            state: UnsafeCell::new(WorkState {
+                queue: None,
+                next: None,
+                queued: false,
+                deadline: None,
+            }),
+        }
+    }
+
+    /// Returns the state of the item.
+    ///
+    /// # Safety
+    ///
+    /// The returned reference must not outlive the critical section, and must be the only
+    /// reference to the state of this item.
+    #[expect(clippy::mut_from_ref)]
+    unsafe fn state(&self, _cs: CriticalSection<'_>) -> &mut WorkState {
+        // SAFETY: upheld by the caller.
+        unsafe { &mut *self.state.get() }
+    }
+
+    /// Returns whether the item is pending, i.e., queued or waiting for a delayed submission.
+    pub fn is_pending(&self) -> bool {
+        critical_section::with(|cs| {
+            // SAFETY: the reference is dropped at the end of the critical section.
+            let state = unsafe { self.state(cs) };
+            state.queued || state.deadline.is_some()
+        })
+    }
+
+    /// Cancels the item if it is pending.
+    ///
+    /// Does not wait for the item to finish if it is currently running.
+    ///
+    /// Returns whether the item was pending.
+    pub fn cancel(&'static self) -> bool {
+        critical_section::with(|cs| {
+            let (queued, was_delayed, queue) = {
+                // SAFETY: the reference is dropped before the queue accesses the state of its
+                // items.
+                let state = unsafe { self.state(cs) };
+                (state.queued, state.deadline.take().is_some(), state.queue)
+            };
+            match queue {
+                Some(queue) if queued => {
+                    queue.remove_cs(cs, self);
+                    true
+                }
+                _ => was_delayed,
+            }
+        })
+    }
+}
+
+/// A queue of [`Work`] items, executed by the threads running [`WorkQueue::run()`].
+pub struct WorkQueue {
+    state: UnsafeCell<QueueState>,
+}
+
+struct QueueState {
+    head: Option<&'static Work>,
+    tail: Option<&'static Work>,
+    /// Worker threads waiting for work.
+    workers: ThreadList,
+}
+
+// SAFETY: the state is only accessed in critical sections.
+unsafe impl Sync for WorkQueue {}
+
+impl WorkQueue {
+    /// Creates a new empty [`WorkQueue`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(QueueState {
+                head: None,
+                tail: None,
+                workers: ThreadList::new(),
+            }),
+        }
+    }
+
+    /// Returns the state of the queue.
+    ///
+    /// # Safety
+    ///
+    /// The returned reference must not outlive the critical section, and must be the only
+    /// reference to the state of this queue.
+    #[expect(clippy::mut_from_ref)]
+    unsafe fn state(&self, _cs: CriticalSection<'_>) -> &mut QueueState {
+        // SAFETY: upheld by the caller.
+        unsafe { &mut *self.state.get() }
+    }
+
+    /// Submits a work item to this queue.
+    ///
+    /// If the item was waiting for a delayed submission, it is queued right away instead.
+    ///
+    /// Can be called from interrupt handlers.
+    ///
+    /// Returns `false` if the item was already queued, in which case this has no effect.
+    pub fn submit(&'static self, work: &'static Work) -> bool {
+        critical_section::with(|cs| self.submit_cs(cs, work))
+    }
+
+    /// Submits a work item to this queue once `delay` has passed.
+    ///
+    /// See [`Self::submit_at()`].
+    pub fn submit_after(&'static self, work: &'static Work, delay: Duration) -> bool {
+        self.submit_at(work, deadline_from(delay))
+    }
+
+    /// Submits a work item to this queue once `deadline` is reached.
+    ///
+    /// If the item was already waiting for a delayed submission, the previous deadline is
+    /// replaced.
+    /// The item can be canceled until it has started running, see [`Work::cancel()`].
+    ///
+    /// Can be called from interrupt handlers.
+    ///
+    /// Returns `false` if the item was already queued, in which case this has no effect.
+    pub fn submit_at(&'static self, work: &'static Work, deadline: Instant) -> bool {
+        let deadline = deadline.as_ticks();
+        let scheduled = critical_section::with(|cs| {
+            // SAFETY: the reference is dropped at the end of the critical section.
+            let state = unsafe { work.state(cs) };
+            if state.queued {
+                return false;
+            }
+            state.queue = Some(self);
+            state.deadline = Some(deadline);
+            true
+        });
+        if scheduled {
+            schedule_wake(work, deadline);
+        }
+        scheduled
+    }
+
+    fn submit_cs(&'static self, cs: CriticalSection<'_>, work: &'static Work) -> bool {
+        {
+            // SAFETY: the reference is dropped before accessing the state of other items.
+            let state = unsafe { work.state(cs) };
+            state.deadline = None;
+            if state.queued {
+                return false;
+            }
+            state.queue = Some(self);
+            state.queued = true;
+            state.next = None;
+        }
+
+        // SAFETY: the reference is dropped at the end of the critical section.
+        let queue = unsafe { self.state(cs) };
+        match queue.tail {
+            // SAFETY: `tail` is a different item than `work`, which was not queued.
+            Some(tail) => unsafe { tail.state(cs) }.next = Some(work),
+            None => queue.head = Some(work),
+        }
+        queue.tail = Some(work);
+
+        // Wake up one idle worker, if any.
+        queue.workers.pop(cs);
+        true
+    }
+
+    /// Removes a queued work item from this queue.
+    fn remove_cs(&self, cs: CriticalSection<'_>, work: &'static Work) {
+        // SAFETY: the reference is dropped at the end of the critical section, and only the state
+        // of one item is accessed at a time below.
+        let queue = unsafe { self.state(cs) };
+        // SAFETY: see above.
+        let next = unsafe { work.state(cs) }.next.take();
+        // SAFETY: see above.
+        unsafe { work.state(cs) }.queued = false;
+
+        let mut prev: Option<&'static Work> = None;
+        let mut cur = queue.head;
+        while let Some(item) = cur {
+            if core::ptr::eq(item, work) {
+                match prev {
+                    // SAFETY: see above.
+                    Some(prev) => unsafe { prev.state(cs) }.next = next,
+                    None => queue.head = next,
+                }
+                if queue.tail.is_some_and(|tail| core::ptr::eq(tail, work)) {
+                    queue.tail = prev;
+                }
+                return;
+            }
+            prev = cur;
+            // SAFETY: see above.
+            cur = unsafe { item.state(cs) }.next;
+        }
+    }
+
+    /// Removes the oldest item from this queue.
+    fn pop_cs(&self, cs: CriticalSection<'_>) -> Option<&'static Work> {
+        // SAFETY: the reference is dropped at the end of the critical section.
+        let queue = unsafe { self.state(cs) };
+        let work = queue.head?;
+        // SAFETY: only the state of this item is accessed.
+        let state = unsafe { work.state(cs) };
+        queue.head = state.next.take();
+        if queue.head.is_none() {
+            queue.tail = None;
+        }
+        state.queued = false;
+        Some(work)
+    }
+
+    /// Executes the items submitted to this queue, forever.
+    ///
+    /// This is meant to be the body of a worker thread; several threads may run the same queue
+    /// to execute items concurrently.
+    /// While the queue is empty, the thread blocks with [`ThreadState::WorkQueueIdle`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called outside of a thread context.
+    pub fn run(&'static self) -> ! {
+        loop {
+            let work = critical_section::with(|cs| {
+                let work = self.pop_cs(cs);
+                if work.is_none() {
+                    // SAFETY: the reference is dropped at the end of the critical section.
+                    let queue = unsafe { self.state(cs) };
+                    // The context switch happens as soon as we leave the critical section.
+                    queue.workers.put_current(cs, ThreadState::WorkQueueIdle);
+                }
+                work
+            });
+            if let Some(work) = work {
+                (work.handler)();
+            }
+        }
+    }
+}
+
+impl Default for WorkQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(
+    // clone
+    |ptr| RawWaker::new(ptr, &VTABLE),
+    wake,
+    wake,
+    |_ptr| {},
+);
+
+/// Makes the timer queue call `wake()` for `work` at `deadline`.
+fn schedule_wake(work: &'static Work, deadline: u64) {
+    let raw_waker = RawWaker::new(core::ptr::from_ref(work).cast(), &VTABLE);
+    // SAFETY: the vtable functions are sound for pointers to `'static` work items.
+    let waker = unsafe { Waker::from_raw(raw_waker) };
+    embassy_time_driver::schedule_wake(deadline, &waker);
+}
+
+/// Submits a delayed work item once its deadline is reached.
+fn wake(ptr: *const ()) {
+    // SAFETY: the waker was created from a `&'static Work` in `schedule_wake()`.
+    let work: &'static Work = unsafe { &*ptr.cast() };
+    let not_due = critical_section::with(|cs| {
+        let (deadline, queue) = {
+            // SAFETY: the reference is dropped before submitting the item.
+            let state = unsafe { work.state(cs) };
+            (state.deadline?, state.queue?)
+        };
+        if embassy_time_driver::now() < deadline {
+            // The timer queue keeps only the earliest deadline of a waker, so a wake-up for a
+            // replaced, earlier deadline may arrive first.
+            return Some(deadline);
+        }
+        queue.submit_cs(cs, work);
+        None
+    });
+    if let Some(deadline) = not_due {
+        schedule_wake(work, deadline);
+    }
+}
@@ -0,0 +1,266 @@
+//! Provides a software watchdog that detects stalled threads and async tasks.
+//!
+//! Threads and tasks that must keep making progress register an entry with a timeout, using
+//! [`register()`] or [`register_thread()`], and then periodically [`feed()`](WatchdogEntry::feed)
+//! it.
+//! The [`monitor()`] task regularly checks the registry: once an entry has not been fed within
+//! its timeout, it logs the stalled entry, along with the ID and state of the thread for
+//! entries registered by threads, and applies the [`Policy`] set with [`set_policy()`].
+//!
+//! Where a hardware watchdog is available, [`monitor_with()`] can be used to only feed it as long
+//! as no entry is stalled, so that the system also gets reset if the monitor itself stops
+//! running.
+
+#![no_std]
+#![deny(missing_docs)]
+
+use core::cell::{Cell, RefCell};
+
+use critical_section::Mutex;
+use embassy_time::{Duration, Instant, Ticker};
+
+#[cfg(feature = "threading")]
+use ariel_os_threads::{ThreadId, ThreadState};
+
+/// Maximum number of entries that can be registered at the same time.
+///
+/// Can be configured with the `CONFIG_WATCHDOG_ENTRIES` environment variable, defaults to 8.
+pub const MAX_ENTRIES: usize = ariel_os_utils::usize_from_env_or!(
+    "CONFIG_WATCHDOG_ENTRIES",
+    8,
+    "maximum number of software watchdog entries"
+);
+
+struct Entry {
+    name: &'static str,
+    #[cfg(feature = "threading")]
+    thread_id: Option<ThreadId>,
+    timeout: Duration,
+    last_fed: Instant,
+    /// Whether the current stall has already been reported.
+    reported: bool,
+}
+
+static REGISTRY: Mutex<RefCell<[Option<Entry>; MAX_ENTRIES]>> =
+    Mutex::new(RefCell::new([const { None }; MAX_ENTRIES]));
+
+static POLICY: Mutex<Cell<Policy>> = Mutex::new(Cell::new(Policy::Log));
+
+/// What to do when an entry stalls.
+#[derive(Clone, Copy, Debug)]
+pub enum Policy {
+    /// Only logs the stall.
+    Log,
+    /// Logs the stall and calls the hook.
+    Hook(fn(&Stall)),
+    /// Logs the stall and reboots the system, see [`ariel_os_power::reboot()`].
+    Reboot,
+}
+
+/// Describes an entry that has not been fed within its timeout.
+#[derive(Clone, Copy, Debug)]
+pub struct Stall {
+    /// Name of the entry.
+    pub name: &'static str,
+    /// Thread the entry was registered for, if any.
+    #[cfg(feature = "threading")]
+    pub thread_id: Option<ThreadId>,
+    /// State of that thread when the stall was detected.
+    #[cfg(feature = "threading")]
+    pub thread_state: Option<ThreadState>,
+    /// How long the entry is overdue, past its timeout.
+    pub overdue: Duration,
+}
+
+/// Sets the policy applied when an entry stalls.
+///
+/// Defaults to [`Policy::Log`].
+pub fn set_policy(policy: Policy) {
+    critical_section::with(|cs| POLICY.borrow(cs).set(policy));
+}
+
+/// Registers a watchdog entry, which must be fed at least every `timeout`.
+///
+/// The entry is unregistered when the returned [`WatchdogEntry`] is dropped.
+///
+/// # Panics
+///
+/// Panics if [`MAX_ENTRIES`] entries are already registered.
+pub fn register(name: &'static str, timeout: Duration) -> WatchdogEntry {
+    register_entry(Entry {
+        name,
+        #[cfg(feature = "threading")]
+        thread_id: None,
+        timeout,
+        last_fed: Instant::now(),
+        reported: false,
+    })
+}
+
+/// Registers a watchdog entry for the current thread, which must be fed at least every
+/// `timeout`.
+///
+/// Unlike with [`register()`], stalls of this entry are reported with the [`ThreadId`] and
+/// [`ThreadState`] of the thread.
+///
+/// # Panics
+///
+/// Panics if called outside of a thread context, or if [`MAX_ENTRIES`] entries are already
+/// registered.
+#[cfg(feature = "threading")]
+pub fn register_thread(name: &'static str, timeout: Duration) -> WatchdogEntry {
+    let thread_id = ariel_os_threads::current_tid()
+        .expect("Function should be called inside a thread context.");
+    register_entry(Entry {
+        name,
+        thread_id: Some(thread_id),
+        timeout,
+        last_fed: Instant::now(),
+        reported: false,
+    })
+}
+
+fn register_entry(entry: Entry) -> WatchdogEntry {
+    critical_section::with(|cs| {
+        let mut registry = REGISTRY.borrow_ref_mut(cs);
+        let (index, slot) = registry
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| slot.is_none())
+            .expect("too many watchdog entries, see `CONFIG_WATCHDOG_ENTRIES`");
+        *slot = Some(entry);
+        WatchdogEntry { index }
+    })
+}
+
+/// A registered watchdog entry, see [`register()`].
+///
+/// Dropping it unregisters the entry.
+#[must_use = "dropping a WatchdogEntry unregisters it"]
+pub struct WatchdogEntry {
+    index: usize,
+}
+
+impl WatchdogEntry {
+    /// Feeds the entry, signaling that its thread or task is making progress.
+    pub fn feed(&self) {
+        let now = Instant::now();
+        critical_section::with(|cs| {
+            if let Some(Some(entry)) = REGISTRY.borrow_ref_mut(cs).get_mut(self.index) {
+                entry.last_fed = now;
+                entry.reported = false;
+            }
+        });
+    }
+}
+
+impl Drop for WatchdogEntry {
+    fn drop(&mut self) {
+        critical_section::with(|cs| {
+            if let Some(slot) = REGISTRY.borrow_ref_mut(cs).get_mut(self.index) {
+                *slot = None;
+            }
+        });
+    }
+}
+
+/// Checks all registered entries, and handles the ones that stalled.
+///
+/// Each stall is logged and handled according to the [`Policy`] once; it is handled again only
+/// if the entry is fed and then stalls anew.
+///
+/// Returns `true` if no entry is currently stalled.
+#[expect(
+    clippy::must_use_candidate,
+    reason = "checking is commonly used only for handling the stalls"
+)]
+pub fn check() -> bool {
+    let now = Instant::now();
+    let mut healthy = true;
+
+    for index in 0..MAX_ENTRIES {
+        let stall = critical_section::with(|cs| {
+            let mut registry = REGISTRY.borrow_ref_mut(cs);
+            let entry = registry.get_mut(index)?.as_mut()?;
+            let elapsed = now.checked_duration_since(entry.last_fed)?;
+            if elapsed <= entry.timeout {
+                return None;
+            }
+            healthy = false;
+            if entry.reported {
+                return None;
+            }
+            entry.reported = true;
+            Some(Stall {
+                name: entry.name,
+                #[cfg(feature = "threading")]
+                thread_id: entry.thread_id,
+                #[cfg(feature = "threading")]
+                thread_state: None,
+                overdue: elapsed - entry.timeout,
+            })
+        });
+
+        if let Some(stall) = stall {
+            // Look up the thread state outside of the critical section above.
+            #[cfg(feature = "threading")]
+            let stall = Stall {
+                thread_state: stall.thread_id.and_then(|thread_id| {
+                    ariel_os_threads::threads()
+                        .find(|info| info.tid == thread_id)
+                        .map(|info| info.state)
+                }),
+                ..stall
+            };
+            handle(&stall);
+        }
+    }
+
+    healthy
+}
+
+fn handle(stall: &Stall) {
+    #[cfg(feature = "threading")]
+    ariel_os_log::error!(
+        "watchdog: {} stalled, overdue by {} ms (thread {:?}, state {:?})",
+        stall.name,
+        stall.overdue.as_millis(),
+        stall.thread_id,
+        stall.thread_state
+    );
+    #[cfg(not(feature = "threading"))]
+    ariel_os_log::error!(
+        "watchdog: {} stalled, overdue by {} ms",
+        stall.name,
+        stall.overdue.as_millis()
+    );
+
+    match critical_section::with(|cs| POLICY.borrow(cs).get()) {
+        Policy::Log => {}
+        Policy::Hook(hook) => hook(stall),
+        Policy::Reboot => ariel_os_power::reboot(),
+    }
+}
+
+/// Checks the registry every `period`, see [`check()`].
+///
+/// `period` should be shorter than the smallest timeout of the registered entries.
+pub async fn monitor(period: Duration) -> ! {
+    monitor_with(period, || {}).await
+}
+
+/// Like [`monitor()`], additionally calling `feed_hardware` after every check that found no
+/// stalled entry.
+///
+/// `feed_hardware` is meant to feed a hardware watchdog whose timeout is longer than `period`:
+/// the system then gets reset by the hardware watchdog if an entry stays stalled, or if the
+/// monitor stops running.
+pub async fn monitor_with(period: Duration, mut feed_hardware: impl FnMut()) -> ! {
+    let mut ticker = Ticker::every(period);
+    loop {
+        if check() {
+            feed_hardware();
+        }
+        ticker.next().await;
+    }
+}
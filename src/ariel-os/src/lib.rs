@@ -52,6 +52,9 @@ pub use ariel_os_storage as storage;
 #[cfg(feature = "threading")]
 #[doc(inline)]
 pub use ariel_os_threads as thread;
+#[cfg(feature = "watchdog")]
+#[doc(inline)]
+pub use ariel_os_watchdog as watchdog;
 
 // Attribute macros
 pub use ariel_os_macros::config;
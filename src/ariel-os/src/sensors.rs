@@ -32,6 +32,18 @@
 //! Sensor drivers implement the [`Sensor`] trait, which allows to trigger measurements and obtain
 //! the resulting readings.
 //!
+//! With the `sensors-sampling` feature, the `sampling` module provides a service that samples
+//! sensor driver instances periodically, on behalf of the application.
+//! With the `sensors-senml` feature, the `senml` module allows to encode readings as SenML, in
+//! JSON or CBOR.
+//! With the `sensors-coap` feature, the `coap` module exposes registered sensor driver instances as
+//! CoAP resources.
+//! With the `sensors-calibration` feature, the `calibration` module allows to correct the readings
+//! of sensor driver instances, and to persist their corrections with the `storage` feature.
+//! With the `sensors-derived` feature, the `derived` module provides virtual sensor drivers, which
+//! derive their readings from other sensor driver instances, e.g., the dew point from temperature
+//! and humidity.
+//!
 //! # Obtaining a sensor reading
 //!
 //! After triggering a measurement with [`Sensor::trigger_measurement()`], a reading can be
@@ -63,6 +75,21 @@
 //! [`ReadingChannels`]: ariel_os_sensors::sensor::ReadingChannels
 
 pub use ariel_os_sensors::*;
+#[cfg(feature = "sensors-calibration")]
+#[doc(inline)]
+pub use ariel_os_sensors_calibration as calibration;
+#[cfg(feature = "sensors-coap")]
+#[doc(inline)]
+pub use ariel_os_sensors_coap as coap;
+#[cfg(feature = "sensors-derived")]
+#[doc(inline)]
+pub use ariel_os_sensors_derived as derived;
 #[doc(inline)]
 pub use ariel_os_sensors_registry as registry;
 pub use ariel_os_sensors_registry::{REGISTRY, SENSOR_REFS};
+#[cfg(feature = "sensors-sampling")]
+#[doc(inline)]
+pub use ariel_os_sensors_sampling as sampling;
+#[cfg(feature = "sensors-senml")]
+#[doc(inline)]
+pub use ariel_os_sensors_senml as senml;
@@ -1,20 +1,34 @@
 //! Driver for the sensor used over I2C.
 
+#[cfg(feature = "events")]
+use core::cell::Cell;
+
+#[cfg(feature = "events")]
+use ariel_os_hal::gpio::IntEnabledInput;
+#[cfg(feature = "events")]
+use ariel_os_sensors::sensor::{
+    Event, EventError, EventResult, EventWaiter, SetTriggerError, Trigger,
+};
 use ariel_os_sensors::{
     Category, Label, MeasurementUnit, Sensor,
     sensor::{
         Mode as SensorMode, ReadingChannel, ReadingChannels, ReadingError, ReadingResult,
-        ReadingWaiter, Sample, Samples, SetModeError, State, TriggerMeasurementError,
+        ReadingWaiter, Sample, Samples, SetModeError, Setting, SettingError, SettingInfo, State,
+        TriggerMeasurementError,
     },
     signal::Signal as ReadingSignal,
 };
 use ariel_os_sensors_utils::AtomicState;
+#[cfg(feature = "events")]
+use embassy_futures::select::select;
+#[cfg(feature = "events")]
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex, once_lock::OnceLock, signal::Signal,
 };
 use embassy_time::Timer;
 use embedded_hal_async::i2c::I2c;
-use portable_atomic::{AtomicU8, Ordering};
+use portable_atomic::{AtomicBool, AtomicU8, Ordering};
 
 use crate::{AccelFullScale, PART_NUMBER, Register};
 
@@ -47,9 +61,22 @@ pub struct Lis2du12<I2C> {
     label: Option<&'static str>,
     i2c: OnceLock<Mutex<CriticalSectionRawMutex, I2C>>,
     address: AtomicU8,
-    full_scale: AccelFullScale,
+    /// FS bits of CTRL5, see [`crate::FULL_SCALE_VALUES`].
+    full_scale: AtomicU8,
+    /// Index of the output data rate in `crate::ODR_VALUES`.
+    odr: AtomicU8,
+    /// Whether settings changed since CTRL5 was last written.
+    settings_changed: AtomicBool,
     signaling: Signal<CriticalSectionRawMutex, ()>,
     reading: ReadingSignal<ReadingResult<Samples>>,
+    /// Whether the sensor device is measuring continuously instead of in one-shot mode.
+    continuous: AtomicBool,
+    #[cfg(feature = "events")]
+    trigger: BlockingMutex<CriticalSectionRawMutex, Cell<Option<Trigger>>>,
+    #[cfg(feature = "events")]
+    trigger_changed: Signal<CriticalSectionRawMutex, ()>,
+    #[cfg(feature = "events")]
+    events: ReadingSignal<EventResult>,
 }
 
 impl<I2C: I2c + Send> Lis2du12<I2C> {
@@ -61,9 +88,18 @@ impl<I2C: I2c + Send> Lis2du12<I2C> {
             label,
             i2c: OnceLock::new(),
             address: AtomicU8::new(I2cAddress::Sa0Vdd as u8),
-            full_scale: AccelFullScale::_2g,
+            full_scale: AtomicU8::new(AccelFullScale::_2g as u8),
+            odr: AtomicU8::new(crate::DEFAULT_ODR_INDEX),
+            settings_changed: AtomicBool::new(true),
             signaling: Signal::new(),
             reading: ReadingSignal::new(),
+            continuous: AtomicBool::new(false),
+            #[cfg(feature = "events")]
+            trigger: BlockingMutex::new(Cell::new(None)),
+            #[cfg(feature = "events")]
+            trigger_changed: Signal::new(),
+            #[cfg(feature = "events")]
+            events: ReadingSignal::new(),
         }
     }
 
@@ -149,8 +185,22 @@ impl<I2C: I2c + Send> Lis2du12<I2C> {
         let mut i2c = self.i2c.get().await.lock().await;
         let address = self.address.load(Ordering::Acquire);
 
-        // Trigger acceleration measurement.
-        let ctrl = crate::BDU_BITS | crate::SOC_BITS;
+        // Trigger acceleration measurement, unless the sensor device is already measuring
+        // continuously.
+        let ctrl = if self.continuous.load(Ordering::Acquire) {
+            crate::BDU_BITS
+        } else {
+            // In continuous mode, settings are applied when configuring the trigger.
+            if self.settings_changed.swap(false, Ordering::AcqRel) {
+                let ctrl =
+                    crate::Odr::OneShotInterface as u8 | self.full_scale.load(Ordering::Acquire);
+                i2c.write(address, &[Register::Ctrl5 as u8, ctrl])
+                    .await
+                    .map_err(|_| ReadingError::SensorAccess)?;
+            }
+
+            crate::BDU_BITS | crate::SOC_BITS
+        };
         i2c.write(address, &[Register::Ctrl4 as u8, ctrl])
             .await
             .map_err(|_| ReadingError::SensorAccess)?;
@@ -172,21 +222,26 @@ impl<I2C: I2c + Send> Lis2du12<I2C> {
             Timer::after_millis(10).await;
         }
 
+        self.read_samples(&mut *i2c, address).await
+    }
+
+    /// Reads the acceleration registers.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadingError::SensorAccess` in case of a communication error with the sensor
+    /// device.
+    async fn read_samples(&'static self, i2c: &mut I2C, address: u8) -> ReadingResult<Samples> {
         // Read all acceleration registers.
         let mut buf = [0u8; 3 * 2];
         i2c.write_read(address, &[Register::OutXL as u8], &mut buf)
             .await
             .map_err(|_| ReadingError::SensorAccess)?;
 
-        let accel_x = self
-            .full_scale
-            .to_microg_from_lsb(i16::from_be_bytes([buf[1], buf[0]]));
-        let accel_y = self
-            .full_scale
-            .to_microg_from_lsb(i16::from_be_bytes([buf[3], buf[2]]));
-        let accel_z = self
-            .full_scale
-            .to_microg_from_lsb(i16::from_be_bytes([buf[5], buf[4]]));
+        let full_scale = self.full_scale();
+        let accel_x = full_scale.to_microg_from_lsb(i16::from_be_bytes([buf[1], buf[0]]));
+        let accel_y = full_scale.to_microg_from_lsb(i16::from_be_bytes([buf[3], buf[2]]));
+        let accel_z = full_scale.to_microg_from_lsb(i16::from_be_bytes([buf[5], buf[4]]));
 
         let accel_accuracy = crate::accel_accuracy();
 
@@ -201,6 +256,151 @@ impl<I2C: I2c + Send> Lis2du12<I2C> {
 
         Ok(samples)
     }
+
+    /// Listens for interrupts on the INT1 pin of the sensor device, and notifies the events of
+    /// the trigger configured with [`Sensor::set_trigger()`].
+    /// This must be running for [`Sensor::wait_for_event()`] to return events.
+    ///
+    /// `int1` must be connected to the INT1 pin of the sensor device, which is active high.
+    ///
+    /// # Note
+    ///
+    /// [`Lis2du12::init()`] needs to be called and `await`ed before calling this method.
+    #[cfg(feature = "events")]
+    pub async fn run_events(&'static self, mut int1: IntEnabledInput<'_>) -> ! {
+        loop {
+            let trigger = self.trigger.lock(Cell::get);
+
+            match self.configure(trigger).await {
+                Ok(()) => {
+                    if let Some(trigger) = trigger {
+                        select(
+                            self.trigger_changed.wait(),
+                            self.notify_events(&mut int1, trigger),
+                        )
+                        .await;
+                        continue;
+                    }
+                }
+                Err(()) => self.events.signal(Err(EventError::SensorAccess)),
+            }
+
+            self.trigger_changed.wait().await;
+        }
+    }
+
+    /// Configures the sensor device for `trigger`, switching between one-shot and continuous
+    /// measurements.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(())` in case of a communication error with the sensor device.
+    #[cfg(feature = "events")]
+    async fn configure(&'static self, trigger: Option<Trigger>) -> Result<(), ()> {
+        let (ctrl1, ctrl2, wake_up_ths, interrupt_cfg, md1_cfg, odr) = match trigger {
+            Some(Trigger::DataReady) => (
+                crate::IF_ADD_INC_BITS,
+                crate::INT1_DRDY_BITS,
+                0,
+                0,
+                0,
+                self.odr(),
+            ),
+            Some(Trigger::WakeUp { threshold }) => (
+                crate::IF_ADD_INC_BITS | crate::WU_EN_BITS,
+                0,
+                self.full_scale()
+                    .to_wake_up_threshold_from_microg(threshold),
+                crate::INTERRUPTS_ENABLE_BITS,
+                crate::INT1_WU_BITS,
+                self.odr(),
+            ),
+            // Other triggers are rejected by `set_trigger()`.
+            _ => (
+                crate::IF_ADD_INC_BITS,
+                0,
+                0,
+                0,
+                0,
+                crate::Odr::OneShotInterface,
+            ),
+        };
+
+        let mut i2c = self.i2c.get().await.lock().await;
+        let address = self.address.load(Ordering::Acquire);
+
+        for (register, value) in [
+            (Register::Ctrl1, ctrl1),
+            (Register::Ctrl2, ctrl2),
+            (Register::Ctrl4, crate::BDU_BITS),
+            (Register::WakeUpThs, wake_up_ths),
+            (Register::InterruptCfg, interrupt_cfg),
+            (Register::Md1Cfg, md1_cfg),
+            (
+                Register::Ctrl5,
+                odr as u8 | self.full_scale.load(Ordering::Acquire),
+            ),
+        ] {
+            i2c.write(address, &[register as u8, value])
+                .await
+                .map_err(|_| ())?;
+        }
+
+        self.continuous
+            .store(odr != crate::Odr::OneShotInterface, Ordering::Release);
+        self.settings_changed.store(false, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Notifies an event for every interrupt of `trigger`.
+    #[cfg(feature = "events")]
+    async fn notify_events(&'static self, int1: &mut IntEnabledInput<'_>, trigger: Trigger) -> ! {
+        loop {
+            int1.wait_for_high().await;
+
+            let event = match trigger {
+                Trigger::DataReady => {
+                    // Reading the data clears the interrupt.
+                    let mut i2c = self.i2c.get().await.lock().await;
+                    let address = self.address.load(Ordering::Acquire);
+                    self.read_samples(&mut *i2c, address)
+                        .await
+                        .map(Event::DataReady)
+                        .map_err(|_| EventError::SensorAccess)
+                }
+                // The wake-up interrupt is not latched, and is the only one routed to INT1.
+                _ => Ok(Event::WakeUp),
+            };
+            self.events.signal(event);
+
+            int1.wait_for_low().await;
+        }
+    }
+}
+
+impl<I2C> Lis2du12<I2C> {
+    fn full_scale(&self) -> AccelFullScale {
+        AccelFullScale::from_bits(self.full_scale.load(Ordering::Acquire))
+    }
+
+    #[cfg(feature = "events")]
+    fn odr(&self) -> crate::Odr {
+        let index = usize::from(self.odr.load(Ordering::Acquire));
+        crate::ODRS
+            .get(index)
+            .copied()
+            .unwrap_or(crate::Odr::_25HzNormalMode)
+    }
+
+    /// Returns the index of the current value of `setting`, among the supported values.
+    fn setting_index(&self, setting: Setting) -> &AtomicU8 {
+        match setting {
+            Setting::OutputDataRate => &self.odr,
+            // The full-scale values are indexed by the FS bits.
+            _ => &self.full_scale,
+        }
+    }
 }
 
 impl<I2C: Send> Sensor for Lis2du12<I2C> {
@@ -271,4 +471,168 @@ impl<I2C: Send> Sensor for Lis2du12<I2C> {
     fn version(&self) -> u8 {
         0
     }
+
+    fn settings(&self) -> &'static [SettingInfo] {
+        crate::SETTINGS
+    }
+
+    fn setting(&self, setting: Setting) -> Result<u32, SettingError> {
+        let values = SettingInfo::find(crate::SETTINGS, setting)?.values();
+        let index = usize::from(self.setting_index(setting).load(Ordering::Acquire));
+        values.get(index).copied().ok_or(SettingError::Unsupported)
+    }
+
+    fn set_setting(&self, setting: Setting, value: u32) -> Result<(), SettingError> {
+        let index = SettingInfo::find(crate::SETTINGS, setting)?.position(value)?;
+        let index = u8::try_from(index).map_err(|_| SettingError::InvalidValue)?;
+        self.setting_index(setting).store(index, Ordering::Release);
+        self.settings_changed.store(true, Ordering::Release);
+
+        // Reconfigure the sensor device if it is measuring continuously.
+        #[cfg(feature = "events")]
+        self.trigger_changed.signal(());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "events")]
+    fn set_trigger(&self, trigger: Option<Trigger>) -> Result<(), SetTriggerError> {
+        match trigger {
+            None | Some(Trigger::DataReady | Trigger::WakeUp { .. }) => {}
+            Some(_) => return Err(SetTriggerError::Unsupported),
+        }
+
+        if trigger.is_some() && !matches!(self.state.get(), State::Enabled | State::Measuring) {
+            return Err(SetTriggerError::NonEnabled);
+        }
+
+        self.trigger.lock(|current| current.set(trigger));
+        self.events.clear();
+        self.trigger_changed.signal(());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "events")]
+    fn wait_for_event(&'static self) -> EventWaiter {
+        if self.trigger.lock(Cell::get).is_none() {
+            return EventWaiter::new_err(EventError::NoTrigger);
+        }
+
+        EventWaiter::new(self.events.wait())
+    }
+}
+
+#[cfg(all(test, feature = "events"))]
+mod tests {
+    use ariel_os_sensors::sensor::ThresholdDirection;
+    use embedded_hal_async::i2c::{ErrorKind, Operation};
+
+    use super::*;
+
+    #[derive(Debug)]
+    enum Error {}
+
+    impl embedded_hal_async::i2c::Error for Error {
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    /// Registers of the sensor device, with address auto-increment.
+    struct I2cDeviceMock {
+        registers: [u8; 0x80],
+    }
+
+    impl embedded_hal_async::i2c::ErrorType for I2cDeviceMock {
+        type Error = Error;
+    }
+
+    impl I2c for I2cDeviceMock {
+        async fn transaction(
+            &mut self,
+            _address: embedded_hal_async::i2c::SevenBitAddress,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            match operations {
+                [Operation::Write([register, values @ ..])] => {
+                    let registers = self.registers.iter_mut().skip(usize::from(*register));
+                    for (register, value) in registers.zip(values.iter()) {
+                        *register = *value;
+                    }
+                }
+                [Operation::Write([register]), Operation::Read(rbuf)] => {
+                    let registers = self.registers.iter().skip(usize::from(*register));
+                    for (value, register) in rbuf.iter_mut().zip(registers) {
+                        *value = *register;
+                    }
+                }
+                _ => panic!("unexpected operations"),
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn trigger_configuration() {
+        static LIS2DU12: Lis2du12<I2cDeviceMock> = Lis2du12::<I2cDeviceMock>::new(Some("label"));
+
+        assert!(matches!(
+            LIS2DU12.set_trigger(Some(Trigger::DataReady)),
+            Err(SetTriggerError::NonEnabled)
+        ));
+        assert!(LIS2DU12.set_trigger(None).is_ok());
+
+        init_sensor(&LIS2DU12);
+
+        assert!(matches!(
+            LIS2DU12.set_trigger(Some(Trigger::Threshold {
+                label: Label::AccelerationX,
+                value: 1_000_000,
+                direction: ThresholdDirection::Above,
+            })),
+            Err(SetTriggerError::Unsupported)
+        ));
+
+        embassy_futures::block_on(async {
+            assert!(matches!(
+                LIS2DU12.wait_for_event().await,
+                Err(EventError::NoTrigger)
+            ));
+
+            let trigger = Trigger::WakeUp { threshold: 500_000 };
+            LIS2DU12.set_trigger(Some(trigger)).unwrap();
+            LIS2DU12.configure(Some(trigger)).await.unwrap();
+            assert!(LIS2DU12.continuous.load(Ordering::Acquire));
+
+            let registers = LIS2DU12.i2c.get().await.lock().await.registers;
+            // 500 mg with the default ±2 g full scale.
+            assert_eq!(registers.get(Register::WakeUpThs as usize), Some(&16));
+            assert_eq!(
+                registers.get(Register::Md1Cfg as usize),
+                Some(&crate::INT1_WU_BITS)
+            );
+
+            LIS2DU12.set_trigger(None).unwrap();
+            assert!(matches!(
+                LIS2DU12.wait_for_event().await,
+                Err(EventError::NoTrigger)
+            ));
+            LIS2DU12.configure(None).await.unwrap();
+            assert!(!LIS2DU12.continuous.load(Ordering::Acquire));
+        });
+    }
+
+    fn init_sensor(lis2du12: &'static Lis2du12<I2cDeviceMock>) {
+        embassy_futures::block_on(async {
+            let peripherals = Peripherals {};
+            let i2c_device = I2cDeviceMock {
+                registers: [0; 0x80],
+            };
+            let config = Config::default();
+
+            lis2du12.init(peripherals, i2c_device, config).await;
+        });
+    }
 }
@@ -2,6 +2,9 @@
 //!
 //! Compatible with [`ariel_os_sensors::Sensor`].
 //!
+//! With the `events` Cargo feature, the data-ready and wake-up triggers are supported, see
+//! [`i2c::Lis2du12::run_events()`].
+//!
 //! [LIS2DU12]: https://www.st.com/en/mems-and-sensors/lis2du12.html
 
 #![cfg_attr(not(test), no_std)]
@@ -9,7 +12,7 @@
 
 pub mod i2c;
 
-use ariel_os_sensors::sensor::SampleMetadata;
+use ariel_os_sensors::sensor::{SampleMetadata, Setting, SettingInfo};
 
 const PART_NUMBER: &str = "LIS2DU12";
 
@@ -17,8 +20,12 @@ const PART_NUMBER: &str = "LIS2DU12";
 #[derive(Copy, Clone, PartialEq, Eq)]
 enum Register {
     Ctrl1 = 0x10,
+    Ctrl2 = 0x11,
     Ctrl4 = 0x13,
     Ctrl5 = 0x14,
+    InterruptCfg = 0x17,
+    WakeUpThs = 0x1c,
+    Md1Cfg = 0x1f,
     Status = 0x25,
     OutXL = 0x28,
     WhoAmI = 0x43,
@@ -35,6 +42,15 @@ enum AccelFullScale {
 }
 
 impl AccelFullScale {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0x0 => Self::_2g,
+            0x1 => Self::_4g,
+            0x2 => Self::_8g,
+            _ => Self::_16g,
+        }
+    }
+
     fn to_microg_from_lsb(self, lsb: i16) -> i32 {
         // Table 2 of the datasheet.
         let sensitivity = match self {
@@ -46,6 +62,20 @@ impl AccelFullScale {
 
         i32::from(lsb >> 4) * sensitivity
     }
+
+    #[cfg(feature = "events")]
+    fn to_wake_up_threshold_from_microg(self, microg: u32) -> u8 {
+        // The LSB of the WAKE_UP_THS register is 1/64 of the full scale.
+        let lsb = match self {
+            Self::_2g => 2_000_000 / 64,
+            Self::_4g => 4_000_000 / 64,
+            Self::_8g => 8_000_000 / 64,
+            Self::_16g => 16_000_000 / 64,
+        };
+
+        // The threshold is a 6-bit value, and zero would trigger continuously.
+        u8::try_from((microg / lsb).clamp(1, 63)).unwrap_or(63)
+    }
 }
 
 // Table 34 of the datasheet, includes bit shift for CTRL5.
@@ -69,14 +99,61 @@ enum Odr {
     OneShotInterface = 0xf << 4,
 }
 
+/// Full-scale ranges, in µg, indexed by the FS bits of CTRL5.
+const FULL_SCALE_VALUES: [u32; 4] = [2_000_000, 4_000_000, 8_000_000, 16_000_000];
+
+/// Output data rates of the continuous mode, in millihertz, and their ODR bits.
+#[cfg(feature = "events")]
+const ODR_VALUES: [u32; 10] = [
+    1_600, 3_000, 6_000, 12_500, 25_000, 50_000, 100_000, 200_000, 400_000, 800_000,
+];
+#[cfg(feature = "events")]
+const ODRS: [Odr; 10] = [
+    Odr::_1_6HzUltraLpMode,
+    Odr::_3HzUltraLpMode,
+    Odr::_6HzNormalMode,
+    Odr::_12_5HzNormalMode,
+    Odr::_25HzNormalMode,
+    Odr::_50HzNormalMode,
+    Odr::_100HzNormalMode,
+    Odr::_200HzNormalMode,
+    Odr::_400HzNormalMode,
+    Odr::_800HzNormalMode,
+];
+/// Index of 25 Hz in `ODR_VALUES`.
+const DEFAULT_ODR_INDEX: u8 = 4;
+
+// The output data rate only applies to the continuous mode, used for events.
+#[cfg(feature = "events")]
+const SETTINGS: &[SettingInfo] = &[
+    SettingInfo::new(Setting::OutputDataRate, &ODR_VALUES),
+    SettingInfo::new(Setting::FullScale, &FULL_SCALE_VALUES),
+];
+#[cfg(not(feature = "events"))]
+const SETTINGS: &[SettingInfo] = &[SettingInfo::new(Setting::FullScale, &FULL_SCALE_VALUES)];
+
 // CTRL1 register bits.
+#[cfg(feature = "events")]
+const WU_EN_BITS: u8 = 0b111;
 const IF_ADD_INC_BITS: u8 = 1 << 4;
 const SW_RESET: u8 = 1 << 5;
 
+// CTRL2 register bits.
+#[cfg(feature = "events")]
+const INT1_DRDY_BITS: u8 = 1 << 3;
+
 // CTRL4 register bits
 const SOC_BITS: u8 = 1 << 1;
 const BDU_BITS: u8 = 1 << 5;
 
+// INTERRUPT_CFG register bits.
+#[cfg(feature = "events")]
+const INTERRUPTS_ENABLE_BITS: u8 = 1 << 0;
+
+// MD1_CFG register bits.
+#[cfg(feature = "events")]
+const INT1_WU_BITS: u8 = 1 << 5;
+
 // STATUS register bits.
 const DRDY_BITS: u8 = 1 << 0;
 
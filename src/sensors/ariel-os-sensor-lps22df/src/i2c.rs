@@ -1,20 +1,34 @@
 //! Driver for the sensor used over I2C.
 
+#[cfg(feature = "events")]
+use core::cell::Cell;
+
+#[cfg(feature = "events")]
+use ariel_os_hal::gpio::IntEnabledInput;
+#[cfg(feature = "events")]
+use ariel_os_sensors::sensor::{
+    Event, EventError, EventResult, EventWaiter, SetTriggerError, ThresholdDirection, Trigger,
+};
 use ariel_os_sensors::{
     Category, Label, MeasurementUnit, Sensor,
     sensor::{
         Mode as SensorMode, ReadingChannel, ReadingChannels, ReadingError, ReadingResult,
-        ReadingWaiter, Sample, Samples, SetModeError, State, TriggerMeasurementError,
+        ReadingWaiter, Sample, Samples, SetModeError, Setting, SettingError, SettingInfo, State,
+        TriggerMeasurementError,
     },
     signal::Signal as ReadingSignal,
 };
 use ariel_os_sensors_utils::AtomicState;
+#[cfg(feature = "events")]
+use embassy_futures::select::select;
+#[cfg(feature = "events")]
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex, once_lock::OnceLock, signal::Signal,
 };
 use embassy_time::Timer;
 use embedded_hal_async::i2c::I2c;
-use portable_atomic::{AtomicI16, AtomicU8, Ordering};
+use portable_atomic::{AtomicBool, AtomicI16, AtomicU8, Ordering};
 
 use crate::{PART_NUMBER, Register, i32_from_i24_be_bytes};
 
@@ -49,8 +63,24 @@ pub struct Lps22df<I2C> {
     i2c: OnceLock<Mutex<CriticalSectionRawMutex, I2C>>,
     address: AtomicU8,
     pressure_offset: AtomicI16,
+    /// `FS_MODE` bit of `CTRL_REG2`, see [`crate::FULL_SCALE_VALUES`].
+    full_scale: AtomicU8,
+    /// Index of the number of averaged measurements in [`crate::AVG_VALUES`].
+    avg: AtomicU8,
+    /// Index of the output data rate in `crate::ODR_VALUES`.
+    odr: AtomicU8,
+    /// Whether settings changed since `CTRL_REG1` was last written.
+    settings_changed: AtomicBool,
     signaling: Signal<CriticalSectionRawMutex, ()>,
     reading: ReadingSignal<ReadingResult<Samples>>,
+    /// Whether the sensor device is measuring continuously instead of in one-shot mode.
+    continuous: AtomicBool,
+    #[cfg(feature = "events")]
+    trigger: BlockingMutex<CriticalSectionRawMutex, Cell<Option<Trigger>>>,
+    #[cfg(feature = "events")]
+    trigger_changed: Signal<CriticalSectionRawMutex, ()>,
+    #[cfg(feature = "events")]
+    events: ReadingSignal<EventResult>,
 }
 
 impl<I2C: I2c + Send> Lps22df<I2C> {
@@ -63,8 +93,19 @@ impl<I2C: I2c + Send> Lps22df<I2C> {
             i2c: OnceLock::new(),
             address: AtomicU8::new(I2cAddress::Sa0Vdd as u8),
             pressure_offset: AtomicI16::new(0),
+            full_scale: AtomicU8::new(0),
+            avg: AtomicU8::new(0),
+            odr: AtomicU8::new(0),
+            settings_changed: AtomicBool::new(false),
             signaling: Signal::new(),
             reading: ReadingSignal::new(),
+            continuous: AtomicBool::new(false),
+            #[cfg(feature = "events")]
+            trigger: BlockingMutex::new(Cell::new(None)),
+            #[cfg(feature = "events")]
+            trigger_changed: Signal::new(),
+            #[cfg(feature = "events")]
+            events: ReadingSignal::new(),
         }
     }
 
@@ -145,8 +186,21 @@ impl<I2C: I2c + Send> Lps22df<I2C> {
         let mut i2c = self.i2c.get().await.lock().await;
         let address = self.address.load(Ordering::Acquire);
 
-        // Trigger a one-shot measurement.
-        let ctrl = crate::BDU_BITS | crate::ONESHOT_BITS;
+        // Trigger a one-shot measurement, unless the sensor device is already measuring
+        // continuously.
+        let ctrl = if self.continuous.load(Ordering::Acquire) {
+            crate::BDU_BITS
+        } else {
+            // In continuous mode, settings are applied when configuring the trigger.
+            if self.settings_changed.swap(false, Ordering::AcqRel) {
+                i2c.write(address, &[Register::CtrlReg1 as u8, self.avg_bits()])
+                    .await
+                    .map_err(|_| ReadingError::SensorAccess)?;
+            }
+
+            crate::BDU_BITS | crate::ONESHOT_BITS
+        };
+        let ctrl = ctrl | self.full_scale.load(Ordering::Acquire) << crate::FS_MODE_SHIFT;
         i2c.write(address, &[Register::CtrlReg2 as u8, ctrl])
             .await
             .map_err(|_| ReadingError::SensorAccess)?;
@@ -168,14 +222,25 @@ impl<I2C: I2c + Send> Lps22df<I2C> {
             }
         }
 
+        self.read_samples(&mut *i2c, address).await
+    }
+
+    /// Reads the pressure and temperature registers.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReadingError::SensorAccess` in case of a communication error with the sensor
+    /// device.
+    async fn read_samples(&'static self, i2c: &mut I2C, address: u8) -> ReadingResult<Samples> {
         // Requires `IF_ADD_INC` to be set (which is the default).
         let mut buf = [0u8; 5];
         i2c.write_read(address, &[Register::PressOutXl as u8], &mut buf)
             .await
             .map_err(|_| ReadingError::SensorAccess)?;
 
+        let sensitivity = crate::pressure_sensitivity(self.full_scale.load(Ordering::Acquire));
         let pressure = i32::from(self.pressure_offset.load(Ordering::Acquire))
-            + i32_from_i24_be_bytes([buf[2], buf[1], buf[0]]) / crate::PRESSURE_SENSITIVITY;
+            + i32_from_i24_be_bytes([buf[2], buf[1], buf[0]]) / sensitivity;
         let temperature = i32::from(i16::from_be_bytes([buf[4], buf[3]]));
 
         let pressure_accuracy = crate::pressure_accuracy(pressure);
@@ -193,6 +258,167 @@ impl<I2C: I2c + Send> Lps22df<I2C> {
 
         Ok(samples)
     }
+
+    /// Listens for interrupts on the INT pin of the sensor device, and notifies the events of
+    /// the trigger configured with [`Sensor::set_trigger()`].
+    /// This must be running for [`Sensor::wait_for_event()`] to return events.
+    ///
+    /// `int` must be connected to the INT pin of the sensor device, which is active high.
+    ///
+    /// # Note
+    ///
+    /// [`Lps22df::init()`] needs to be called and `await`ed before calling this method.
+    #[cfg(feature = "events")]
+    pub async fn run_events(&'static self, mut int: IntEnabledInput<'_>) -> ! {
+        loop {
+            let trigger = self.trigger.lock(Cell::get);
+
+            match self.configure(trigger).await {
+                Ok(()) => {
+                    if let Some(trigger) = trigger {
+                        select(
+                            self.trigger_changed.wait(),
+                            self.notify_events(&mut int, trigger),
+                        )
+                        .await;
+                        continue;
+                    }
+                }
+                Err(()) => self.events.signal(Err(EventError::SensorAccess)),
+            }
+
+            self.trigger_changed.wait().await;
+        }
+    }
+
+    /// Configures the sensor device for `trigger`, switching between one-shot and continuous
+    /// measurements.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(())` in case of a communication error with the sensor device.
+    #[cfg(feature = "events")]
+    async fn configure(&'static self, trigger: Option<Trigger>) -> Result<(), ()> {
+        let (ctrl_reg1, ctrl_reg4, interrupt_cfg, ths_p) = match trigger {
+            Some(Trigger::DataReady) => {
+                (self.odr_bits(), crate::INT_EN_BITS | crate::DRDY_BITS, 0, 0)
+            }
+            Some(Trigger::Threshold { value, .. }) => {
+                // The interrupt compares the raw pressure output, which does not include the
+                // offset, with the threshold expressed in 1/16 hPa (1/8 hPa with the larger full
+                // scale).
+                let offset = i32::from(self.pressure_offset.load(Ordering::Acquire));
+                let sensitivity =
+                    crate::pressure_sensitivity(self.full_scale.load(Ordering::Acquire));
+                let ths_p = value
+                    .saturating_sub(offset)
+                    .saturating_mul(sensitivity / 256);
+                let ths_p = u16::try_from(ths_p.clamp(1, 0x7fff)).unwrap_or(0x7fff);
+                (
+                    self.odr_bits(),
+                    crate::INT_EN_BITS,
+                    crate::PHE_BITS | crate::LIR_BITS,
+                    ths_p,
+                )
+            }
+            // Other triggers are rejected by `set_trigger()`.
+            _ => (0, 0, 0, 0),
+        };
+
+        let mut i2c = self.i2c.get().await.lock().await;
+        let address = self.address.load(Ordering::Acquire);
+
+        let [ths_p_l, ths_p_h] = ths_p.to_le_bytes();
+        // Writes `INTERRUPT_CFG`, `THS_P_L` and `THS_P_H`; requires `IF_ADD_INC` to be set (which
+        // is the default).
+        i2c.write(
+            address,
+            &[
+                Register::InterruptCfg as u8,
+                interrupt_cfg,
+                ths_p_l,
+                ths_p_h,
+            ],
+        )
+        .await
+        .map_err(|_| ())?;
+        i2c.write(address, &[Register::CtrlReg4 as u8, ctrl_reg4])
+            .await
+            .map_err(|_| ())?;
+        let ctrl_reg2 =
+            crate::BDU_BITS | self.full_scale.load(Ordering::Acquire) << crate::FS_MODE_SHIFT;
+        i2c.write(address, &[Register::CtrlReg2 as u8, ctrl_reg2])
+            .await
+            .map_err(|_| ())?;
+        i2c.write(
+            address,
+            &[Register::CtrlReg1 as u8, ctrl_reg1 | self.avg_bits()],
+        )
+        .await
+        .map_err(|_| ())?;
+
+        self.continuous.store(ctrl_reg1 != 0, Ordering::Release);
+        self.settings_changed.store(false, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Notifies an event for every interrupt of `trigger`.
+    #[cfg(feature = "events")]
+    async fn notify_events(&'static self, int: &mut IntEnabledInput<'_>, trigger: Trigger) -> ! {
+        loop {
+            int.wait_for_high().await;
+
+            let event = {
+                let mut i2c = self.i2c.get().await.lock().await;
+                let address = self.address.load(Ordering::Acquire);
+
+                if trigger == Trigger::DataReady {
+                    // Reading the data clears the interrupt.
+                    self.read_samples(&mut *i2c, address)
+                        .await
+                        .map(|samples| Some(Event::DataReady(samples)))
+                        .map_err(|_| EventError::SensorAccess)
+                } else {
+                    // Reading the source clears the latched interrupt.
+                    let mut buf = [0u8];
+                    i2c.write_read(address, &[Register::IntSource as u8], &mut buf)
+                        .await
+                        .map(|()| (buf[0] & crate::PH_BITS != 0).then_some(Event::Threshold))
+                        .map_err(|_| EventError::SensorAccess)
+                }
+            };
+            match event {
+                Ok(Some(event)) => self.events.signal(Ok(event)),
+                Ok(None) => {}
+                Err(err) => self.events.signal(Err(err)),
+            }
+
+            int.wait_for_low().await;
+        }
+    }
+}
+
+impl<I2C> Lps22df<I2C> {
+    fn avg_bits(&self) -> u8 {
+        let index = usize::from(self.avg.load(Ordering::Acquire));
+        crate::AVG_BITS.get(index).copied().unwrap_or_default()
+    }
+
+    #[cfg(feature = "events")]
+    fn odr_bits(&self) -> u8 {
+        (self.odr.load(Ordering::Acquire) + 1) << crate::ODR_SHIFT
+    }
+
+    /// Returns the index of the current value of `setting`, among the supported values.
+    fn setting_index(&self, setting: Setting) -> &AtomicU8 {
+        match setting {
+            Setting::OutputDataRate => &self.odr,
+            Setting::Oversampling => &self.avg,
+            // The full-scale values are indexed by the `FS_MODE` bit.
+            _ => &self.full_scale,
+        }
+    }
 }
 
 impl<I2C: Send> Sensor for Lps22df<I2C> {
@@ -272,4 +498,186 @@ impl<I2C: Send> Sensor for Lps22df<I2C> {
     fn version(&self) -> u8 {
         0
     }
+
+    fn settings(&self) -> &'static [SettingInfo] {
+        crate::SETTINGS
+    }
+
+    fn setting(&self, setting: Setting) -> Result<u32, SettingError> {
+        let values = SettingInfo::find(crate::SETTINGS, setting)?.values();
+        let index = usize::from(self.setting_index(setting).load(Ordering::Acquire));
+        values.get(index).copied().ok_or(SettingError::Unsupported)
+    }
+
+    fn set_setting(&self, setting: Setting, value: u32) -> Result<(), SettingError> {
+        let index = SettingInfo::find(crate::SETTINGS, setting)?.position(value)?;
+        let index = u8::try_from(index).map_err(|_| SettingError::InvalidValue)?;
+        self.setting_index(setting).store(index, Ordering::Release);
+        self.settings_changed.store(true, Ordering::Release);
+
+        // Reconfigure the sensor device if it is measuring continuously.
+        #[cfg(feature = "events")]
+        self.trigger_changed.signal(());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "events")]
+    fn set_trigger(&self, trigger: Option<Trigger>) -> Result<(), SetTriggerError> {
+        match trigger {
+            None
+            | Some(
+                Trigger::DataReady
+                | Trigger::Threshold {
+                    label: Label::Pressure,
+                    direction: ThresholdDirection::Above,
+                    ..
+                },
+            ) => {}
+            // Detecting falling pressure would require a reference pressure.
+            Some(_) => return Err(SetTriggerError::Unsupported),
+        }
+
+        if trigger.is_some() && !matches!(self.state.get(), State::Enabled | State::Measuring) {
+            return Err(SetTriggerError::NonEnabled);
+        }
+
+        self.trigger.lock(|current| current.set(trigger));
+        self.events.clear();
+        self.trigger_changed.signal(());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "events")]
+    fn wait_for_event(&'static self) -> EventWaiter {
+        if self.trigger.lock(Cell::get).is_none() {
+            return EventWaiter::new_err(EventError::NoTrigger);
+        }
+
+        EventWaiter::new(self.events.wait())
+    }
+}
+
+#[cfg(all(test, feature = "events"))]
+mod tests {
+    use embedded_hal_async::i2c::{ErrorKind, Operation};
+
+    use super::*;
+
+    #[derive(Debug)]
+    enum Error {}
+
+    impl embedded_hal_async::i2c::Error for Error {
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    /// Registers of the sensor device, with address auto-increment.
+    struct I2cDeviceMock {
+        registers: [u8; 0x80],
+    }
+
+    impl embedded_hal_async::i2c::ErrorType for I2cDeviceMock {
+        type Error = Error;
+    }
+
+    impl I2c for I2cDeviceMock {
+        async fn transaction(
+            &mut self,
+            _address: embedded_hal_async::i2c::SevenBitAddress,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            match operations {
+                [Operation::Write([register, values @ ..])] => {
+                    let registers = self.registers.iter_mut().skip(usize::from(*register));
+                    for (register, value) in registers.zip(values.iter()) {
+                        *register = *value;
+                    }
+                }
+                [Operation::Write([register]), Operation::Read(rbuf)] => {
+                    let registers = self.registers.iter().skip(usize::from(*register));
+                    for (value, register) in rbuf.iter_mut().zip(registers) {
+                        *value = *register;
+                    }
+                }
+                _ => panic!("unexpected operations"),
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn trigger_configuration() {
+        static LPS22DF: Lps22df<I2cDeviceMock> = Lps22df::<I2cDeviceMock>::new(Some("label"));
+
+        assert!(matches!(
+            LPS22DF.set_trigger(Some(Trigger::DataReady)),
+            Err(SetTriggerError::NonEnabled)
+        ));
+        assert!(LPS22DF.set_trigger(None).is_ok());
+
+        init_sensor(&LPS22DF);
+
+        // Only rising pressure can be detected.
+        for (label, direction) in [
+            (Label::Pressure, ThresholdDirection::Below),
+            (Label::Temperature, ThresholdDirection::Above),
+        ] {
+            assert!(matches!(
+                LPS22DF.set_trigger(Some(Trigger::Threshold {
+                    label,
+                    value: 1013,
+                    direction,
+                })),
+                Err(SetTriggerError::Unsupported)
+            ));
+        }
+
+        embassy_futures::block_on(async {
+            assert!(matches!(
+                LPS22DF.wait_for_event().await,
+                Err(EventError::NoTrigger)
+            ));
+
+            let trigger = Trigger::Threshold {
+                label: Label::Pressure,
+                value: 1013,
+                direction: ThresholdDirection::Above,
+            };
+            LPS22DF.set_trigger(Some(trigger)).unwrap();
+            LPS22DF.configure(Some(trigger)).await.unwrap();
+            assert!(LPS22DF.continuous.load(Ordering::Acquire));
+
+            let registers = LPS22DF.i2c.get().await.lock().await.registers;
+            let interrupt_cfg = Register::InterruptCfg as usize;
+            // `INTERRUPT_CFG`, then the threshold in 1/16 hPa with the default full scale.
+            assert_eq!(
+                registers.get(interrupt_cfg..interrupt_cfg + 3),
+                Some([crate::PHE_BITS | crate::LIR_BITS, 0x50, 0x3f].as_slice())
+            );
+
+            LPS22DF.set_trigger(None).unwrap();
+            assert!(matches!(
+                LPS22DF.wait_for_event().await,
+                Err(EventError::NoTrigger)
+            ));
+            LPS22DF.configure(None).await.unwrap();
+            assert!(!LPS22DF.continuous.load(Ordering::Acquire));
+        });
+    }
+
+    fn init_sensor(lps22df: &'static Lps22df<I2cDeviceMock>) {
+        embassy_futures::block_on(async {
+            let peripherals = Peripherals {};
+            let i2c_device = I2cDeviceMock {
+                registers: [0; 0x80],
+            };
+            let config = Config::default();
+
+            lps22df.init(peripherals, i2c_device, config).await;
+        });
+    }
 }
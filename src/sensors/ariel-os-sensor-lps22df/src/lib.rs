@@ -2,6 +2,9 @@
 //!
 //! Compatible with [`ariel_os_sensors::Sensor`].
 //!
+//! With the `events` Cargo feature, the data-ready trigger and the threshold trigger for rising
+//! pressure are supported, see [`i2c::Lps22df::run_events()`].
+//!
 //! [LPS22DF]: https://www.st.com/en/mems-and-sensors/lps22df.html
 
 #![cfg_attr(not(test), no_std)]
@@ -9,24 +12,74 @@
 
 pub mod i2c;
 
-use ariel_os_sensors::sensor::SampleMetadata;
+use ariel_os_sensors::sensor::{SampleMetadata, Setting, SettingInfo};
 
 const PART_NUMBER: &str = "LPS22DF";
 
 #[expect(dead_code)]
 #[derive(Copy, Clone, PartialEq, Eq)]
 enum Register {
+    InterruptCfg = 0x0b,
     WhoAmI = 0x0f,
+    CtrlReg1 = 0x10,
     CtrlReg2 = 0x11,
+    CtrlReg4 = 0x13,
     RpdsL = 0x1a,
+    IntSource = 0x24,
     Status = 0x27,
     PressOutXl = 0x28,
 }
 
+// `INTERRUPT_CFG` register bits.
+#[cfg(feature = "events")]
+const PHE_BITS: u8 = 1 << 0;
+#[cfg(feature = "events")]
+const LIR_BITS: u8 = 1 << 2;
+
+// `CTRL_REG1` register bits.
+#[cfg(feature = "events")]
+const ODR_SHIFT: u8 = 3;
+
 // `CTRL_REG2` register bits.
 const ONESHOT_BITS: u8 = 1 << 0;
 const SWRESET_BITS: u8 = 1 << 2;
 const BDU_BITS: u8 = 1 << 3;
+const FS_MODE_SHIFT: u8 = 6;
+
+/// Output data rates of the continuous mode, in millihertz; the ODR bits of `CTRL_REG1` are the
+/// index plus one.
+#[cfg(feature = "events")]
+const ODR_VALUES: [u32; 8] = [
+    1_000, 4_000, 10_000, 25_000, 50_000, 75_000, 100_000, 200_000,
+];
+/// Numbers of averaged measurements, and their AVG bits of `CTRL_REG1`.
+const AVG_VALUES: [u32; 7] = [4, 8, 16, 32, 64, 128, 512];
+const AVG_BITS: [u8; 7] = [0b000, 0b001, 0b010, 0b011, 0b100, 0b101, 0b111];
+/// Full-scale ranges, in hPa, indexed by the `FS_MODE` bit of `CTRL_REG2`.
+const FULL_SCALE_VALUES: [u32; 2] = [1260, 4060];
+
+// The output data rate only applies to the continuous mode, used for events.
+#[cfg(feature = "events")]
+const SETTINGS: &[SettingInfo] = &[
+    SettingInfo::new(Setting::OutputDataRate, &ODR_VALUES),
+    SettingInfo::new(Setting::FullScale, &FULL_SCALE_VALUES),
+    SettingInfo::new(Setting::Oversampling, &AVG_VALUES),
+];
+#[cfg(not(feature = "events"))]
+const SETTINGS: &[SettingInfo] = &[
+    SettingInfo::new(Setting::FullScale, &FULL_SCALE_VALUES),
+    SettingInfo::new(Setting::Oversampling, &AVG_VALUES),
+];
+
+// `CTRL_REG4` register bits.
+#[cfg(feature = "events")]
+const INT_EN_BITS: u8 = 1 << 4;
+#[cfg(feature = "events")]
+const DRDY_BITS: u8 = 1 << 5;
+
+// `INT_SOURCE` register bits.
+#[cfg(feature = "events")]
+const PH_BITS: u8 = 1 << 0;
 
 // `STATUS` register bits.
 const P_DA_BITS: u8 = 1 << 0;
@@ -36,9 +89,14 @@ const T_DA_BITS: u8 = 1 << 1;
 const DEVICE_ID: u8 = 0xb4;
 
 // Table 2 of the datasheet.
-const PRESSURE_SENSITIVITY: i32 = 4096;
 const TEMP_SENSITIVITY: i32 = 100;
 
+/// Returns the pressure sensitivity in LSB/hPa for the `FS_MODE` bit, see Table 2 of the
+/// datasheet.
+fn pressure_sensitivity(fs_mode: u8) -> i32 {
+    if fs_mode == 0 { 4096 } else { 2048 }
+}
+
 fn pressure_accuracy(_pressure: i32) -> SampleMetadata {
     // Takes into account `PAccT` + `P_drift` and a rough upper bound of the temperature offset
     // from Table 2 of the datasheet.
@@ -0,0 +1,436 @@
+//! Simulated sensor driver, for the native target.
+//!
+//! Compatible with [`ariel_os_sensors::Sensor`].
+//!
+//! [`SimulatedSensor`] exposes arbitrary [`Category`]s and [`ReadingChannel`]s, and obtains its
+//! sample values from a [`Source`]: [`Waveform`]s generated over time, or a [`Script`] of
+//! timestamped values, e.g., loaded from a CSV file.
+//! This allows to exercise application logic consuming sensor readings without sensor devices,
+//! e.g., in CI.
+//!
+//! Sensor driver instances are registered like other sensor drivers:
+//!
+//! ```ignore
+//! use ariel_os::sensors::{Category, Label, MeasurementUnit, sensor::ReadingChannel};
+//! use ariel_os_sensor_simulated::{Config, SimulatedSensor, Source, Waveform};
+//!
+//! pub static THERMOMETER: SimulatedSensor = const {
+//!     SimulatedSensor::new(
+//!         Some("simulated"),
+//!         &[Category::Temperature],
+//!         &[ReadingChannel::new(Label::Temperature, -2, MeasurementUnit::Celsius)],
+//!     )
+//! };
+//! #[ariel_os::reexports::linkme::distributed_slice(ariel_os::sensors::SENSOR_REFS)]
+//! #[linkme(crate = ariel_os::reexports::linkme)]
+//! static THERMOMETER_REF: &'static dyn ariel_os::sensors::Sensor = &THERMOMETER;
+//!
+//! #[ariel_os::task(autostart)]
+//! async fn thermometer_runner() {
+//!     let mut config = Config::default();
+//!     config.source = Source::Waveforms(vec![Waveform::Sine {
+//!         offset: 20.0,
+//!         amplitude: 5.0,
+//!         period: Duration::from_secs(60),
+//!     }]);
+//!     THERMOMETER.init(config).await;
+//!
+//!     THERMOMETER.run().await
+//! }
+//! ```
+#![deny(missing_docs)]
+
+mod source;
+
+use std::sync::{Mutex, OnceLock};
+
+use ariel_os_sensors::{
+    Category, Sensor,
+    sensor::{
+        Mode as SensorMode, ReadingChannel, ReadingChannels, ReadingError, ReadingResult,
+        ReadingWaiter, Sample, SampleMetadata, Samples, SetModeError, State,
+        TriggerMeasurementError,
+    },
+    signal::Signal as ReadingSignal,
+};
+use ariel_os_sensors_utils::AtomicState;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Instant, Timer};
+
+pub use source::{Script, ScriptError, Source, Waveform};
+
+use source::Generator;
+
+/// Maximum number of reading channels of a [`SimulatedSensor`].
+pub const MAX_CHANNELS: usize = 12;
+
+/// Configuration of the sensor driver.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Config {
+    /// Source of the sample values.
+    ///
+    /// By default, all reading channels are reported as temporarily unavailable.
+    pub source: Source,
+    /// Metadata of the samples, [`SampleMetadata::UnknownAccuracy`] by default.
+    ///
+    /// Samples of temporarily unavailable reading channels have
+    /// [`SampleMetadata::ChannelTemporarilyUnavailable`] instead.
+    pub metadata: SampleMetadata,
+    /// Simulated duration of a measurement, zero by default.
+    pub measurement_time: Duration,
+    /// Seed of the pseudo-random number generator used by [`Waveform::Noise`].
+    ///
+    /// Sensor driver instances configured with the same seed and source return the same
+    /// sequence of values.
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            source: Source::default(),
+            metadata: SampleMetadata::UnknownAccuracy,
+            measurement_time: Duration::from_ticks(0),
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+/// Simulated sensor driver.
+pub struct SimulatedSensor {
+    state: AtomicState,
+    label: Option<&'static str>,
+    categories: &'static [Category],
+    channels: &'static [ReadingChannel],
+    simulation: OnceLock<Simulation>,
+    signaling: Signal<CriticalSectionRawMutex, ()>,
+    reading: ReadingSignal<ReadingResult<Samples>>,
+}
+
+/// State of the simulation, set up on initialization.
+struct Simulation {
+    generator: Mutex<Generator>,
+    metadata: SampleMetadata,
+    measurement_time: Duration,
+    start: Instant,
+}
+
+impl SimulatedSensor {
+    /// Creates an uninitialized driver, with the given categories and reading channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no reading channels, or more than [`MAX_CHANNELS`].
+    #[must_use]
+    pub const fn new(
+        label: Option<&'static str>,
+        categories: &'static [Category],
+        channels: &'static [ReadingChannel],
+    ) -> Self {
+        assert!(
+            !channels.is_empty() && channels.len() <= MAX_CHANNELS,
+            "unsupported number of reading channels"
+        );
+
+        Self {
+            state: AtomicState::new(State::Uninitialized),
+            label,
+            categories,
+            channels,
+            simulation: OnceLock::new(),
+            signaling: Signal::new(),
+            reading: ReadingSignal::new(),
+        }
+    }
+
+    /// Initializes the driver.
+    ///
+    /// Time, as used by the [`Source`], is measured from the first initialization.
+    #[expect(clippy::unused_async, reason = "consistency with other sensor drivers")]
+    pub async fn init(&'static self, config: Config) {
+        if self.simulation.get().is_some() {
+            return;
+        }
+
+        let _ = self.simulation.set(Simulation {
+            generator: Mutex::new(Generator::new(config.source, config.seed)),
+            metadata: config.metadata,
+            measurement_time: config.measurement_time,
+            start: Instant::now(),
+        });
+
+        self.state.set(State::Enabled);
+    }
+
+    /// Listens for measurement requests generated by
+    /// [`SimulatedSensor::trigger_measurement()`], and responds to them.
+    /// This should be called before [`SimulatedSensor::wait_for_reading()`], as that method will
+    /// otherwise not be able to respond to measurement requests from
+    /// [`SimulatedSensor::trigger_measurement()`].
+    ///
+    /// # Note
+    ///
+    /// [`SimulatedSensor::init()`] needs to be called and `await`ed before calling this method.
+    pub async fn run(&'static self) -> ! {
+        loop {
+            self.signaling.wait().await;
+
+            self.reading.signal(self.measure().await);
+        }
+    }
+
+    /// Simulates a measurement.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReadingError::NonEnabled`] if the driver is not initialized.
+    async fn measure(&'static self) -> ReadingResult<Samples> {
+        let Some(simulation) = self.simulation.get() else {
+            return Err(ReadingError::NonEnabled);
+        };
+
+        if simulation.measurement_time > Duration::from_ticks(0) {
+            Timer::after(simulation.measurement_time).await;
+        }
+
+        let elapsed = Instant::now().saturating_duration_since(simulation.start);
+        // The generator remains consistent even if a panic occurred while it was locked.
+        let mut generator = simulation
+            .generator
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        let samples = self
+            .channels
+            .iter()
+            .enumerate()
+            .map(|(index, channel)| match generator.value(index, elapsed) {
+                Some(value) => Sample::new(scale(value, *channel), simulation.metadata),
+                None => Sample::new(0, SampleMetadata::ChannelTemporarilyUnavailable),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(samples_from_slice(self, &samples))
+    }
+}
+
+/// Converts `value`, expressed in the unit of `channel`, to a sample value, saturating if it
+/// does not fit.
+fn scale(value: f64, channel: ReadingChannel) -> i32 {
+    let scaled = value * 10f64.powi(-i32::from(channel.scaling()));
+
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "float to integer casts saturate"
+    )]
+    let scaled = scaled.round() as i32;
+    scaled
+}
+
+/// Returns the [`Samples`] of `sensor`, with one [`Sample`] per reading channel.
+fn samples_from_slice(sensor: &'static SimulatedSensor, samples: &[Sample]) -> Samples {
+    macro_rules! from_slice {
+        ($($len:literal => $from:ident),+ $(,)?) => {
+            match samples.len() {
+                $($len => Samples::$from(
+                    sensor,
+                    samples.try_into().expect("the length was just checked"),
+                ),)+
+                _ => unreachable!("the number of reading channels is checked in `new()`"),
+            }
+        };
+    }
+
+    from_slice!(
+        1 => from_1,
+        2 => from_2,
+        3 => from_3,
+        4 => from_4,
+        5 => from_5,
+        6 => from_6,
+        7 => from_7,
+        8 => from_8,
+        9 => from_9,
+        10 => from_10,
+        11 => from_11,
+        12 => from_12,
+    )
+}
+
+/// Returns the [`ReadingChannels`] made of `channels`.
+fn reading_channels_from_slice(channels: &[ReadingChannel]) -> ReadingChannels {
+    macro_rules! from_slice {
+        ($($len:literal),+ $(,)?) => {
+            match channels.len() {
+                $($len => ReadingChannels::from(
+                    <[ReadingChannel; $len]>::try_from(channels)
+                        .expect("the length was just checked"),
+                ),)+
+                _ => unreachable!("the number of reading channels is checked in `new()`"),
+            }
+        };
+    }
+
+    from_slice!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12)
+}
+
+impl Sensor for SimulatedSensor {
+    fn trigger_measurement(&self) -> Result<(), TriggerMeasurementError> {
+        self.reading.clear();
+
+        match self.state.get() {
+            State::Measuring => {}
+            State::Enabled => {
+                self.state.set(State::Measuring);
+            }
+            State::Uninitialized | State::Disabled | State::Sleeping => {
+                return Err(TriggerMeasurementError::NonEnabled);
+            }
+        }
+
+        self.signaling.signal(());
+
+        Ok(())
+    }
+
+    fn wait_for_reading(&'static self) -> ReadingWaiter {
+        match self.state.get() {
+            State::Measuring => {
+                self.state.set(State::Enabled);
+
+                ReadingWaiter::new(self.reading.wait())
+            }
+            State::Enabled => ReadingWaiter::new_err(ReadingError::NotMeasuring),
+            State::Uninitialized | State::Disabled | State::Sleeping => {
+                ReadingWaiter::new_err(ReadingError::NonEnabled)
+            }
+        }
+    }
+
+    fn set_mode(&self, mode: SensorMode) -> Result<State, SetModeError> {
+        self.state.set_mode(mode)
+    }
+
+    fn state(&self) -> State {
+        self.state.get()
+    }
+
+    fn categories(&self) -> &'static [Category] {
+        self.categories
+    }
+
+    fn reading_channels(&self) -> ReadingChannels {
+        reading_channels_from_slice(self.channels)
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    fn display_name(&self) -> Option<&'static str> {
+        Some("simulated sensor")
+    }
+
+    fn part_number(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn version(&self) -> u8 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ariel_os_sensors::{Label, MeasurementUnit, Reading as _};
+
+    use super::*;
+
+    const CHANNELS: &[ReadingChannel] = &[
+        ReadingChannel::new(
+            Label::RelativeHumidity,
+            0,
+            MeasurementUnit::PercentageRelativeHumidity,
+        ),
+        ReadingChannel::new(Label::Temperature, -2, MeasurementUnit::Celsius),
+    ];
+
+    #[test]
+    fn scaling() {
+        let channel = ReadingChannel::new(Label::Temperature, -2, MeasurementUnit::Celsius);
+        assert_eq!(scale(21.456, channel), 2146);
+        assert_eq!(scale(-3.0, channel), -300);
+        assert_eq!(scale(1e12, channel), i32::MAX);
+
+        let channel = ReadingChannel::new(Label::Pressure, 2, MeasurementUnit::Pascal);
+        assert_eq!(scale(101_325.0, channel), 1013);
+    }
+
+    #[test]
+    fn fetch_reading() {
+        static SENSOR: SimulatedSensor = SimulatedSensor::new(
+            Some("label"),
+            &[Category::RelativeHumidityTemperature],
+            CHANNELS,
+        );
+
+        assert_eq!(SENSOR.state(), State::Uninitialized);
+        assert!(SENSOR.trigger_measurement().is_err());
+
+        let config = Config {
+            source: Source::Script(Script::from_csv("0,45,21.5").unwrap()),
+            ..Config::default()
+        };
+
+        embassy_futures::block_on(async {
+            SENSOR.init(config).await;
+            assert_eq!(SENSOR.state(), State::Enabled);
+
+            embassy_futures::select::select(SENSOR.run(), async {
+                SENSOR.trigger_measurement().unwrap();
+                let reading = SENSOR.wait_for_reading().await.unwrap();
+
+                let samples = reading.samples().collect::<Vec<_>>();
+                let [(rh_channel, rh_sample), (t_channel, t_sample)] = samples.as_slice() else {
+                    unreachable!()
+                };
+
+                assert_eq!(rh_channel.label(), Label::RelativeHumidity);
+                assert_eq!(rh_sample.value(), Ok(45));
+                assert_eq!(rh_sample.metadata(), SampleMetadata::UnknownAccuracy);
+
+                assert_eq!(t_channel.label(), Label::Temperature);
+                assert_eq!(t_sample.value(), Ok(2150));
+            })
+            .await;
+        });
+    }
+
+    #[test]
+    fn missing_values_are_unavailable() {
+        static SENSOR: SimulatedSensor =
+            SimulatedSensor::new(None, &[Category::RelativeHumidityTemperature], CHANNELS);
+
+        let config = Config {
+            source: Source::Waveforms(vec![Waveform::Constant(50.)]),
+            ..Config::default()
+        };
+
+        embassy_futures::block_on(async {
+            SENSOR.init(config).await;
+
+            embassy_futures::select::select(SENSOR.run(), async {
+                SENSOR.trigger_measurement().unwrap();
+                let reading = SENSOR.wait_for_reading().await.unwrap();
+
+                let samples = reading.samples().map(|(_, sample)| sample.metadata());
+                assert!(samples.eq([
+                    SampleMetadata::UnknownAccuracy,
+                    SampleMetadata::ChannelTemporarilyUnavailable,
+                ]));
+            })
+            .await;
+        });
+    }
+}
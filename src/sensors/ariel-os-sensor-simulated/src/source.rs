@@ -0,0 +1,372 @@
+//! Sources of simulated sample values.
+
+use std::path::Path;
+
+use embassy_time::Duration;
+
+/// Source of the values returned by a [`SimulatedSensor`](crate::SimulatedSensor).
+///
+/// Values are expressed in the [unit](ariel_os_sensors::sensor::ReadingChannel::unit()) of
+/// their reading channel, and are converted according to its
+/// [scaling](ariel_os_sensors::sensor::ReadingChannel::scaling()).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// Values generated by one waveform per reading channel, in the same order.
+    ///
+    /// Reading channels without a waveform are reported as temporarily unavailable.
+    Waveforms(Vec<Waveform>),
+    /// Values replayed from a [`Script`].
+    Script(Script),
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self::Waveforms(Vec::new())
+    }
+}
+
+/// Waveform generating the values of a reading channel over time.
+///
+/// Time is measured from the initialization of the sensor driver.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Waveform {
+    /// Constant value.
+    Constant(f64),
+    /// Sine wave oscillating around `offset`, starting from it.
+    Sine {
+        /// Value around which the wave oscillates.
+        offset: f64,
+        /// Amplitude of the wave.
+        amplitude: f64,
+        /// Period of the wave.
+        period: Duration,
+    },
+    /// Linear ramp starting from `start`.
+    Ramp {
+        /// Value at initialization.
+        start: f64,
+        /// Change of the value per second.
+        slope: f64,
+    },
+    /// Pseudo-random values uniformly distributed within `mean ± amplitude`.
+    ///
+    /// The sequence of values is reproducible, see
+    /// [`Config::seed`](crate::Config::seed).
+    Noise {
+        /// Mean value.
+        mean: f64,
+        /// Maximum deviation from the mean.
+        amplitude: f64,
+    },
+}
+
+impl Waveform {
+    /// Returns the value of the waveform `elapsed` after initialization.
+    fn value(&self, elapsed: Duration, rng: &mut Rng) -> f64 {
+        #[expect(clippy::cast_precision_loss, reason = "durations are not that long")]
+        let secs = elapsed.as_micros() as f64 / 1_000_000.;
+
+        match *self {
+            Self::Constant(value) => value,
+            Self::Sine {
+                offset,
+                amplitude,
+                period,
+            } => {
+                #[expect(clippy::cast_precision_loss, reason = "durations are not that long")]
+                let period = period.as_micros() as f64 / 1_000_000.;
+                offset + amplitude * (core::f64::consts::TAU * secs / period).sin()
+            }
+            Self::Ramp { start, slope } => start + slope * secs,
+            Self::Noise { mean, amplitude } => mean + amplitude * (2. * rng.next_unit() - 1.),
+        }
+    }
+}
+
+/// Timestamped values to be replayed, one row per point in time.
+///
+/// The values of a row are returned from its timestamp until the timestamp of the next row; the
+/// last row is returned indefinitely.
+/// Before the first row, reading channels are reported as temporarily unavailable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+    rows: Vec<Row>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    /// Time since the initialization of the sensor driver.
+    at: Duration,
+    /// One value per reading channel, `None` if temporarily unavailable.
+    values: Vec<Option<f64>>,
+}
+
+impl Script {
+    /// Parses a script from CSV.
+    ///
+    /// Each row starts with a timestamp, in milliseconds since the initialization of the sensor
+    /// driver, followed by one value per reading channel, in the same order.
+    /// An empty value marks its reading channel as temporarily unavailable.
+    /// Timestamps must be non-decreasing.
+    ///
+    /// Empty lines and lines starting with `#` are ignored, and so is the first line if it is a
+    /// header, i.e., if its first field is not a timestamp:
+    ///
+    /// ```csv
+    /// time_ms,temperature,relative_humidity
+    /// 0,21.5,40
+    /// 1000,21.7,
+    /// 2000,22.0,42
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScriptError`] if a row is not valid.
+    pub fn from_csv(csv: &str) -> Result<Self, ScriptError> {
+        let mut rows: Vec<Row> = Vec::new();
+
+        let lines = csv
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        for (index, (line, content)) in lines.enumerate() {
+            let mut fields = content.split(',').map(str::trim);
+
+            let Ok(at) = fields.next().unwrap_or_default().parse::<u64>() else {
+                if index == 0 {
+                    // Header.
+                    continue;
+                }
+                return Err(ScriptError::InvalidTimestamp { line });
+            };
+            let at = Duration::from_millis(at);
+
+            if rows.last().is_some_and(|previous| previous.at > at) {
+                return Err(ScriptError::DecreasingTimestamp { line });
+            }
+
+            let values = fields
+                .map(|field| {
+                    if field.is_empty() {
+                        return Ok(None);
+                    }
+                    field
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| ScriptError::InvalidValue { line })
+                })
+                .collect::<Result<_, _>>()?;
+
+            rows.push(Row { at, values });
+        }
+
+        Ok(Self { rows })
+    }
+
+    /// Reads and parses a CSV script from the file at `path`, see [`Script::from_csv()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScriptError::Io`] if the file cannot be read, or another [`ScriptError`] if a
+    /// row is not valid.
+    pub fn from_csv_file(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let csv = std::fs::read_to_string(path).map_err(|err| ScriptError::Io(err.kind()))?;
+        Self::from_csv(&csv)
+    }
+
+    /// Returns the values of the row current `elapsed` after initialization.
+    fn values(&self, elapsed: Duration) -> &[Option<f64>] {
+        // Rows are sorted by timestamp.
+        let current = self.rows.partition_point(|row| row.at <= elapsed);
+        current
+            .checked_sub(1)
+            .and_then(|index| self.rows.get(index))
+            .map_or(&[], |row| &row.values)
+    }
+}
+
+/// Errors returned when parsing a [`Script`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    /// The script file could not be read.
+    Io(std::io::ErrorKind),
+    /// The timestamp of the row at this line is not a non-negative integer.
+    InvalidTimestamp {
+        /// Line number, starting from 1.
+        line: usize,
+    },
+    /// The timestamp of the row at this line is lower than the timestamp of the previous row.
+    DecreasingTimestamp {
+        /// Line number, starting from 1.
+        line: usize,
+    },
+    /// A value of the row at this line is not a number.
+    InvalidValue {
+        /// Line number, starting from 1.
+        line: usize,
+    },
+}
+
+impl core::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(kind) => write!(f, "script file could not be read: {kind}"),
+            Self::InvalidTimestamp { line } => write!(f, "invalid timestamp at line {line}"),
+            Self::DecreasingTimestamp { line } => write!(f, "decreasing timestamp at line {line}"),
+            Self::InvalidValue { line } => write!(f, "invalid value at line {line}"),
+        }
+    }
+}
+
+impl core::error::Error for ScriptError {}
+
+/// Generates the values of a [`Source`].
+#[derive(Debug)]
+pub(crate) struct Generator {
+    source: Source,
+    rng: Rng,
+}
+
+impl Generator {
+    pub(crate) fn new(source: Source, seed: u64) -> Self {
+        Self {
+            source,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Returns the value of the reading channel at `index`, `elapsed` after initialization, or
+    /// `None` if temporarily unavailable.
+    pub(crate) fn value(&mut self, index: usize, elapsed: Duration) -> Option<f64> {
+        match &self.source {
+            Source::Waveforms(waveforms) => waveforms
+                .get(index)
+                .map(|waveform| waveform.value(elapsed, &mut self.rng)),
+            Source::Script(script) => script.values(elapsed).get(index).copied().flatten(),
+        }
+    }
+}
+
+/// Xorshift pseudo-random number generator, good enough for noise.
+#[derive(Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        Self(seed.max(1))
+    }
+
+    /// Returns a pseudo-random value in `[0, 1)`.
+    fn next_unit(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        // Keep the 53 most significant bits, the precision of an `f64`.
+        #[expect(clippy::cast_precision_loss, reason = "the values fit in 53 bits")]
+        let value = (self.0 >> 11) as f64 / (1u64 << 53) as f64;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_is_parsed() {
+        let script = Script::from_csv(
+            "time_ms, temperature, humidity\n\
+             # Warming up.\n\
+             \n\
+             0,21.5,40\n\
+             1000,21.7,\n\
+             1000,-3,41\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.rows,
+            [
+                Row {
+                    at: Duration::from_millis(0),
+                    values: vec![Some(21.5), Some(40.)],
+                },
+                Row {
+                    at: Duration::from_secs(1),
+                    values: vec![Some(21.7), None],
+                },
+                Row {
+                    at: Duration::from_secs(1),
+                    values: vec![Some(-3.), Some(41.)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_scripts() {
+        assert_eq!(
+            Script::from_csv("0,1\nnow,2"),
+            Err(ScriptError::InvalidTimestamp { line: 2 })
+        );
+        assert_eq!(
+            Script::from_csv("1000,1\n\n500,2"),
+            Err(ScriptError::DecreasingTimestamp { line: 3 })
+        );
+        assert_eq!(
+            Script::from_csv("0,one"),
+            Err(ScriptError::InvalidValue { line: 1 })
+        );
+    }
+
+    #[test]
+    fn script_is_replayed() {
+        let script = Script::from_csv("500,1\n1000,2\n1000,3\n2000,4").unwrap();
+        let mut generator = Generator::new(Source::Script(script), 0);
+
+        let mut value_at = |millis| generator.value(0, Duration::from_millis(millis));
+        assert_eq!(value_at(0), None);
+        assert_eq!(value_at(500), Some(1.));
+        assert_eq!(value_at(999), Some(1.));
+        // The last row with a given timestamp wins.
+        assert_eq!(value_at(1000), Some(3.));
+        assert_eq!(value_at(60_000), Some(4.));
+    }
+
+    #[test]
+    fn waveforms() {
+        let mut generator = Generator::new(
+            Source::Waveforms(vec![
+                Waveform::Constant(7.),
+                Waveform::Sine {
+                    offset: 20.,
+                    amplitude: 5.,
+                    period: Duration::from_secs(4),
+                },
+                Waveform::Ramp {
+                    start: 10.,
+                    slope: -0.5,
+                },
+                Waveform::Noise {
+                    mean: 50.,
+                    amplitude: 2.,
+                },
+            ]),
+            42,
+        );
+
+        let at = Duration::from_secs(1);
+        assert_eq!(generator.value(0, at), Some(7.));
+        assert!((generator.value(1, at).unwrap() - 25.).abs() < 1e-9);
+        assert_eq!(generator.value(2, at), Some(9.5));
+        for _ in 0..100 {
+            let noise = generator.value(3, at).unwrap();
+            assert!((48. ..=52.).contains(&noise));
+        }
+        assert_eq!(generator.value(4, at), None);
+    }
+}
@@ -0,0 +1,111 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    sensors::{
+        Category, Label, MeasurementUnit, REGISTRY, Reading as _, Sensor,
+        sensor::{ReadingChannel, Sample, SampleError},
+    },
+    time::{Duration, Timer},
+};
+use ariel_os_sensor_simulated::{Config, Script, SimulatedSensor, Source, Waveform};
+
+static THERMOMETER: SimulatedSensor = const {
+    SimulatedSensor::new(
+        Some("waveform"),
+        &[Category::Temperature],
+        &[ReadingChannel::new(
+            Label::Temperature,
+            -2,
+            MeasurementUnit::Celsius,
+        )],
+    )
+};
+#[ariel_os::reexports::linkme::distributed_slice(ariel_os::sensors::SENSOR_REFS)]
+#[linkme(crate = ariel_os::reexports::linkme)]
+static THERMOMETER_REF: &'static dyn Sensor = &THERMOMETER;
+
+static HYGROMETER: SimulatedSensor = const {
+    SimulatedSensor::new(
+        Some("script"),
+        &[Category::RelativeHumidityTemperature],
+        &[
+            ReadingChannel::new(
+                Label::RelativeHumidity,
+                0,
+                MeasurementUnit::PercentageRelativeHumidity,
+            ),
+            ReadingChannel::new(Label::Temperature, -1, MeasurementUnit::Celsius),
+        ],
+    )
+};
+#[ariel_os::reexports::linkme::distributed_slice(ariel_os::sensors::SENSOR_REFS)]
+#[linkme(crate = ariel_os::reexports::linkme)]
+static HYGROMETER_REF: &'static dyn Sensor = &HYGROMETER;
+
+const SCRIPT: &str = "\
+time_ms,relative_humidity,temperature
+0,40,21.5
+200,,21.7
+";
+
+#[ariel_os::task(autostart)]
+async fn thermometer_runner() {
+    THERMOMETER.run().await
+}
+
+#[ariel_os::task(autostart)]
+async fn hygrometer_runner() {
+    HYGROMETER.run().await
+}
+
+#[ariel_os::task(autostart)]
+async fn main() {
+    let mut config = Config::default();
+    config.source = Source::Waveforms(vec![Waveform::Noise {
+        mean: 20.0,
+        amplitude: 0.5,
+    }]);
+    THERMOMETER.init(config).await;
+
+    let mut config = Config::default();
+    config.source = Source::Script(Script::from_csv(SCRIPT).unwrap());
+    HYGROMETER.init(config).await;
+
+    // Simulated sensor driver instances are accessed through the registry like any other.
+    assert_eq!(REGISTRY.sensors().count(), 2);
+
+    let [temperature] = read(&THERMOMETER).await.as_slice() else {
+        panic!("unexpected number of samples");
+    };
+    assert!((1950..=2050).contains(&temperature.value().unwrap()));
+
+    let [humidity, temperature] = read(&HYGROMETER).await.as_slice() else {
+        panic!("unexpected number of samples");
+    };
+    assert_eq!(humidity.value(), Ok(40));
+    assert_eq!(temperature.value(), Ok(215));
+
+    // The script moves on to its next row.
+    Timer::after(Duration::from_millis(200)).await;
+
+    let [humidity, temperature] = read(&HYGROMETER).await.as_slice() else {
+        panic!("unexpected number of samples");
+    };
+    assert_eq!(humidity.value(), Err(SampleError::TemporarilyUnavailable));
+    assert_eq!(temperature.value(), Ok(217));
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+async fn read(sensor: &'static dyn Sensor) -> Vec<Sample> {
+    sensor.trigger_measurement().unwrap();
+    let reading = sensor.wait_for_reading().await.unwrap();
+    reading.samples().map(|(_, sample)| sample).collect()
+}
@@ -0,0 +1,73 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{
+        self, ThreadId,
+        sync::{Channel, Event, Mutex},
+        thread_flags,
+    },
+    time::{Duration, with_timeout},
+};
+
+static EVENT: Event = Event::new();
+static VALUES: Channel<u32> = Channel::new();
+static THREAD_IDS: Channel<ThreadId> = Channel::new();
+static MUTEX: Mutex<u32> = Mutex::new(0);
+
+#[ariel_os::task(autostart)]
+async fn main() {
+    EVENT.wait_async().await;
+
+    assert_eq!(VALUES.recv_async().await, 42);
+
+    // This task runs in the executor thread, so the flags are those of that thread.
+    let executor_thread = thread::current_tid().unwrap();
+    THREAD_IDS.send_async(&executor_thread).await;
+    assert_eq!(thread_flags::wait_any_async(0b100).await, 0b100);
+
+    // Nothing sets this flag yet, so waiting for it times out.
+    assert!(
+        with_timeout(
+            Duration::from_millis(1),
+            thread_flags::wait_any_async(0b1000)
+        )
+        .await
+        .is_err()
+    );
+
+    // The thread still holds the mutex.
+    let mut value = MUTEX.lock_async().await;
+    assert_eq!(*value, 1);
+    *value += 1;
+    drop(value);
+
+    // The thread sets this flag once it has released the mutex, well before the timeout.
+    assert_eq!(
+        with_timeout(Duration::from_secs(1), thread_flags::wait_any_async(0b1000))
+            .await
+            .ok(),
+        Some(0b1000)
+    );
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+#[ariel_os::thread(autostart)]
+fn thread0() {
+    let mut value = MUTEX.lock();
+
+    EVENT.set();
+    VALUES.send(&42);
+
+    let executor_thread = THREAD_IDS.recv();
+    thread_flags::set(executor_thread, 0b100);
+
+    thread::sleep(Duration::from_millis(10));
+    *value += 1;
+    drop(value);
+
+    thread_flags::set(executor_thread, 0b1000);
+}
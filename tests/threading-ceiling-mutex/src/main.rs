@@ -0,0 +1,56 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{self, RunqueueId, ThreadId, sync::CeilingMutex, thread_flags},
+};
+use portable_atomic::{AtomicUsize, Ordering};
+
+const CEILING: RunqueueId = RunqueueId::new(3);
+const HIGHER_CEILING: RunqueueId = RunqueueId::new(4);
+
+static MUTEX: CeilingMutex<()> = CeilingMutex::new(CEILING, ());
+static HIGHER_MUTEX: CeilingMutex<()> = CeilingMutex::new(HIGHER_CEILING, ());
+static RUN_ORDER: AtomicUsize = AtomicUsize::new(0);
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread0() {
+    let tid = thread::current_tid().unwrap();
+
+    {
+        let _guard = MUTEX.lock();
+        // The priority is raised to the ceiling right away.
+        assert_eq!(thread::get_priority(tid), Some(CEILING));
+
+        // Thread 1 has a higher priority than this thread, but not higher than the ceiling, so
+        // it must not preempt this thread while it holds the mutex.
+        thread_flags::set(ThreadId::new(1), 0b1);
+        assert_eq!(RUN_ORDER.fetch_add(1, Ordering::AcqRel), 0);
+    }
+
+    // Thread 1 ran as soon as the mutex was released.
+    assert_eq!(RUN_ORDER.load(Ordering::Acquire), 2);
+    assert_eq!(thread::get_priority(tid), Some(RunqueueId::new(1)));
+
+    {
+        let _higher_guard = HIGHER_MUTEX.lock();
+        assert_eq!(thread::get_priority(tid), Some(HIGHER_CEILING));
+        {
+            // Locking a mutex with a lower ceiling keeps the higher priority.
+            let _guard = MUTEX.lock();
+            assert_eq!(thread::get_priority(tid), Some(HIGHER_CEILING));
+        }
+        assert_eq!(thread::get_priority(tid), Some(HIGHER_CEILING));
+    }
+    assert_eq!(thread::get_priority(tid), Some(RunqueueId::new(1)));
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread1() {
+    thread_flags::wait_any(0b1);
+    assert_eq!(RUN_ORDER.fetch_add(1, Ordering::AcqRel), 1);
+}
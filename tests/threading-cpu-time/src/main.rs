@@ -0,0 +1,41 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{self, CoreId, cpu_time},
+    time::{Duration, Instant},
+};
+
+const INTERVAL: Duration = Duration::from_millis(50);
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    let tid = thread::current_tid().unwrap();
+    cpu_time::reset();
+
+    // Busy-wait for half of the interval, and sleep for the other half.
+    let start = Instant::now();
+    while start.elapsed() < INTERVAL / 2 {}
+    thread::sleep(INTERVAL / 2);
+
+    let run_time = cpu_time::thread_time(tid).unwrap().run_time;
+    let idle_time = cpu_time::idle_time(CoreId::new(0));
+    // Read last so that it covers the other values.
+    let elapsed = cpu_time::elapsed();
+    ariel_os::log::info!(
+        "elapsed: {} us, run time: {} us, idle time: {} us",
+        elapsed.as_micros(),
+        run_time.as_micros(),
+        idle_time.as_micros()
+    );
+
+    assert!(elapsed >= INTERVAL);
+    assert!(run_time >= INTERVAL / 2);
+    assert!(run_time + idle_time <= elapsed);
+    // Switched back to after sleeping.
+    assert!(cpu_time::thread_time(tid).unwrap().switches >= 1);
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
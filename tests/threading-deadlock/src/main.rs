@@ -0,0 +1,45 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{
+        ThreadId,
+        sync::{self, Mutex},
+        thread_flags,
+    },
+};
+
+static A: Mutex<()> = Mutex::new(());
+static B: Mutex<()> = Mutex::new(());
+
+fn on_deadlock(cycle: &[ThreadId]) {
+    // Thread 1 is about to block on `A`, owned by thread 0, which is blocked on `B`, owned by
+    // thread 1.
+    assert_eq!(cycle, [ThreadId::new(1), ThreadId::new(0)]);
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    sync::set_deadlock_handler(on_deadlock);
+
+    let _a = A.lock();
+    // Let thread 1 lock `B`.
+    thread_flags::wait_any(0b1);
+    let _b = B.lock();
+
+    unreachable!("the deadlock should have been detected");
+}
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread1() {
+    let _b = B.lock();
+    // Thread 0 preempts this thread and blocks on `B`.
+    thread_flags::set(ThreadId::new(0), 0b1);
+    let _a = A.lock();
+
+    unreachable!("the deadlock should have been detected");
+}
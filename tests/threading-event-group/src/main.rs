@@ -0,0 +1,77 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{TimeoutError, sync::EventGroup, thread_flags::WaitMode},
+    time::Duration,
+};
+use portable_atomic::{AtomicUsize, Ordering};
+
+const NETWORK_UP: u16 = 0b001;
+const TIME_SYNCED: u16 = 0b010;
+const REQUEST: u16 = 0b100;
+
+static EVENTS: EventGroup = EventGroup::new();
+
+static ANY_DONE: AtomicUsize = AtomicUsize::new(0);
+static ALL_DONE: AtomicUsize = AtomicUsize::new(0);
+static CONSUMED: AtomicUsize = AtomicUsize::new(0);
+
+#[ariel_os::thread(autostart, priority = 3)]
+fn wait_any() {
+    let bits = EVENTS.wait(WaitMode::Any(NETWORK_UP | TIME_SYNCED), false);
+    assert_eq!(bits, NETWORK_UP);
+    ANY_DONE.fetch_add(1, Ordering::AcqRel);
+}
+
+#[ariel_os::thread(autostart, priority = 3)]
+fn wait_all() {
+    let bits = EVENTS.wait(WaitMode::All(NETWORK_UP | TIME_SYNCED), false);
+    assert_eq!(bits, NETWORK_UP | TIME_SYNCED);
+    ALL_DONE.fetch_add(1, Ordering::AcqRel);
+}
+
+#[ariel_os::thread(autostart, priority = 3)]
+fn consumer0() {
+    assert_eq!(EVENTS.wait(WaitMode::Any(REQUEST), true), REQUEST);
+    CONSUMED.fetch_add(1, Ordering::AcqRel);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn consumer1() {
+    assert_eq!(EVENTS.wait(WaitMode::Any(REQUEST), true), REQUEST);
+    CONSUMED.fetch_add(1, Ordering::AcqRel);
+}
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread0() {
+    // All other threads have a higher priority, so they are waiting by now.
+    EVENTS.set(NETWORK_UP);
+    assert_eq!(ANY_DONE.load(Ordering::Acquire), 1);
+    assert_eq!(ALL_DONE.load(Ordering::Acquire), 0);
+
+    EVENTS.set(TIME_SYNCED);
+    assert_eq!(ALL_DONE.load(Ordering::Acquire), 1);
+    // Bits are not cleared by waiters that don't ask for it.
+    assert_eq!(EVENTS.get(), NETWORK_UP | TIME_SYNCED);
+
+    // Both consumers get woken up, but the first one to run consumes the bit.
+    EVENTS.set(REQUEST);
+    assert_eq!(CONSUMED.load(Ordering::Acquire), 1);
+    assert_eq!(EVENTS.get() & REQUEST, 0);
+
+    EVENTS.set(REQUEST);
+    assert_eq!(CONSUMED.load(Ordering::Acquire), 2);
+    assert_eq!(EVENTS.get() & REQUEST, 0);
+
+    assert_eq!(
+        EVENTS.wait_timeout(WaitMode::All(REQUEST), true, Duration::from_millis(10)),
+        Err(TimeoutError)
+    );
+    assert_eq!(EVENTS.clear(NETWORK_UP | REQUEST), NETWORK_UP);
+    assert_eq!(EVENTS.get(), TIME_SYNCED);
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
@@ -0,0 +1,37 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{self, ThreadState},
+};
+
+#[ariel_os::thread(autostart, stacksize = 4096, name = "main")]
+fn thread0() {
+    let tid = thread::current_tid().unwrap();
+
+    let info = thread::threads().find(|info| info.tid == tid).unwrap();
+    assert_eq!(info.name, Some("main"));
+    assert_eq!(info.state, ThreadState::Running);
+    assert_eq!(thread::get_priority(tid), Some(info.prio));
+    assert!(info.stack_size <= 4096);
+    assert!(info.stack_peak_usage > 0);
+    assert!(info.stack_peak_usage <= info.stack_size);
+
+    // Unnamed threads are named after their function.
+    let info = thread::threads()
+        .find(|info| info.name == Some("thread1"))
+        .unwrap();
+    assert_eq!(info.state, ThreadState::Parked);
+
+    thread::log_threads();
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+// Higher priority so that it parks itself before `thread0` runs.
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread1() {
+    thread::park();
+}
@@ -0,0 +1,50 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    reexports::static_cell::ConstStaticCell,
+    thread::{self, thread_flags},
+    time::Duration,
+};
+
+const STACK_SIZE: usize = 2048;
+
+static STACK0: ConstStaticCell<[u8; STACK_SIZE]> = ConstStaticCell::new([0u8; STACK_SIZE]);
+static STACK1: ConstStaticCell<[u8; STACK_SIZE]> = ConstStaticCell::new([0u8; STACK_SIZE]);
+
+fn square(x: usize) -> usize {
+    x * x
+}
+
+fn wait_for_flag() {
+    thread_flags::wait_one(0b1);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    // The worker has a lower priority, so it only runs once we block on it.
+    let handle = thread::create_returning(square, 7, STACK0.take(), 1, None);
+    assert!(!handle.is_finished());
+    assert_eq!(handle.join(), Some(49));
+
+    let handle = thread::create_noarg(wait_for_flag, STACK1.take(), 1, None);
+    let worker = handle.thread_id();
+    // The worker is blocked on its flags, so joining needs to time out.
+    let handle = handle
+        .join_timeout(Duration::from_millis(10))
+        .expect_err("worker should not have finished");
+    assert!(!handle.is_finished());
+
+    thread_flags::set(worker, 0b1);
+    assert_eq!(
+        handle.join_timeout(Duration::from_millis(10)).unwrap(),
+        None
+    );
+
+    // The slot of the joined thread has been released.
+    assert!(!thread::is_valid_tid(worker));
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
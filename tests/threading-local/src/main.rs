@@ -0,0 +1,53 @@
+#![no_main]
+#![no_std]
+
+use core::cell::Cell;
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread,
+};
+use portable_atomic::{AtomicUsize, Ordering};
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        DROPS.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+thread::thread_local! {
+    static COUNTER: Cell<u32> = Cell::new(0);
+    static GUARD: Guard = Guard;
+}
+
+fn increment() -> u32 {
+    COUNTER.with(|counter| {
+        counter.set(counter.get() + 1);
+        counter.get()
+    })
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    // Runs to completion before thread1 starts, which has a lower priority.
+    assert_eq!(increment(), 1);
+    assert_eq!(increment(), 2);
+    GUARD.with(|_| {});
+    assert_eq!(DROPS.load(Ordering::Acquire), 0);
+}
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread1() {
+    // The values of thread0 have been dropped when it finished.
+    assert_eq!(DROPS.load(Ordering::Acquire), 1);
+
+    // This thread has its own counter.
+    assert_eq!(increment(), 1);
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
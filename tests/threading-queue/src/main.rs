@@ -0,0 +1,56 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{ThreadId, sync::Queue, thread_flags},
+};
+
+/// Deliberately not `Copy`.
+#[derive(Debug, PartialEq)]
+struct Msg(usize);
+
+static QUEUE: Queue<Msg, 4> = Queue::new();
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread0() {
+    // Fill the queue without blocking.
+    for i in 0..4 {
+        assert!(QUEUE.try_send(Msg(i)).is_ok());
+    }
+    assert!(QUEUE.is_full());
+    // The value is handed back if the queue is full.
+    assert_eq!(QUEUE.try_send(Msg(4)), Err(Msg(4)));
+
+    // Let the higher priority thread drain the queue.
+    // It blocks on the empty queue afterwards.
+    thread_flags::set(ThreadId::new(1), 0b1);
+    assert!(QUEUE.is_empty());
+
+    // Wakes up the blocked receiver, which starts sending from then on.
+    QUEUE.send(Msg(4));
+
+    // The higher priority thread is blocked on the full queue, each receive lets it send the
+    // next message.
+    for i in 5..13 {
+        assert_eq!(QUEUE.recv(), Msg(i));
+    }
+    assert!(QUEUE.is_empty());
+    assert_eq!(QUEUE.try_recv(), None);
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread1() {
+    thread_flags::wait_one(0b1);
+
+    for i in 0..5 {
+        assert_eq!(QUEUE.recv(), Msg(i));
+    }
+
+    for i in 5..13 {
+        QUEUE.send(Msg(i));
+    }
+}
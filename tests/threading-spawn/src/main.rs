@@ -0,0 +1,65 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread,
+};
+
+static DETACHED_RAN: AtomicBool = AtomicBool::new(false);
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    // The handle is dropped right away, so the slot and stack get reclaimed by a later `spawn()`.
+    drop(thread::spawn(
+        || DETACHED_RAN.store(true, Ordering::Release),
+        1024,
+        3,
+        None,
+    ));
+
+    // Spawn more threads than there are thread slots, to check that slots and stacks are
+    // released after joining.
+    for i in 0..thread::THREAD_COUNT * 2 {
+        let values: Vec<usize> = (0..=i).collect();
+        let stack_size = 1024 + i * 64;
+        let handle = thread::spawn(
+            move || {
+                let sum: usize = values.iter().sum();
+                assert_eq!(sum, i * (i + 1) / 2);
+            },
+            stack_size,
+            1,
+            None,
+        );
+        assert_eq!(handle.join(), None);
+    }
+
+    assert!(DETACHED_RAN.load(Ordering::Acquire));
+
+    // Fill all free slots with detached threads, and let them finish.
+    let free = thread::THREAD_COUNT - thread::threads().count();
+    for _ in 0..free {
+        drop(thread::spawn(|| {}, 1024, 2, None));
+    }
+    while thread::threads()
+        .filter(|info| info.state == thread::ThreadState::Finished)
+        .count()
+        < free
+    {
+        thread::yield_same();
+    }
+
+    // Creating a thread with a static stack reclaims the slots as well.
+    let stack = Box::leak(vec![0u8; 1024].into_boxed_slice());
+    let handle = thread::create_noarg(|| {}, stack, 2, None);
+    assert_eq!(handle.join(), None);
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
@@ -0,0 +1,57 @@
+#![no_main]
+#![no_std]
+
+use core::hint::black_box;
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{self, ThreadId, ThreadState, thread_flags},
+};
+
+fn on_stack_overflow(thread_id: ThreadId) {
+    assert_eq!(thread_id, ThreadId::new(0));
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+/// Recurses until the stack grows more than `stack_size` bytes below `first_frame`.
+#[inline(never)]
+fn overflow(first_frame: usize, stack_size: usize) {
+    let frame = [0u8; 32];
+    // Make sure the frame is actually written to the stack.
+    black_box(&frame);
+    if first_frame - &raw const frame as usize <= stack_size {
+        overflow(first_frame, stack_size);
+    }
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    thread::set_stack_overflow_handler(on_stack_overflow);
+
+    // On native, threads run on host thread stacks, so only the stack size is meaningful.
+    let (stack_lowest, stack_highest) = thread::current_stack_limits().unwrap();
+    let first_frame = black_box(0u8);
+    overflow(
+        &raw const first_frame as usize,
+        stack_highest - stack_lowest,
+    );
+
+    // The stack canary is checked when switching away from this thread, on MPU-less devices.
+    thread_flags::wait_any(0b1);
+
+    unreachable!("the stack overflow should have been detected");
+}
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread1() {
+    // On native and multi-core, threads run concurrently, so wait until `thread0` has blocked.
+    while !thread::threads().any(|info| {
+        info.tid == ThreadId::new(0) && matches!(info.state, ThreadState::FlagBlocked(_))
+    }) {
+        core::hint::spin_loop();
+    }
+
+    unreachable!("the stack overflow should have been detected");
+}
@@ -0,0 +1,120 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{
+        TimeoutError,
+        sync::{Condvar, Event, Mutex, RwLock, Semaphore},
+    },
+    time::Duration,
+};
+use portable_atomic::{AtomicUsize, Ordering};
+
+static SEMAPHORE: Semaphore = Semaphore::new(2);
+static ACQUIRED: AtomicUsize = AtomicUsize::new(0);
+
+static RWLOCK: RwLock<u32> = RwLock::new(0);
+static READERS: AtomicUsize = AtomicUsize::new(0);
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+static RELEASE_READERS: Event = Event::new();
+static START_WRITER: Event = Event::new();
+
+static MUTEX: Mutex<()> = Mutex::new(());
+static CONDVAR: Condvar = Condvar::new();
+static WOKEN: AtomicUsize = AtomicUsize::new(0);
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn semaphore_user() {
+    // Only two permits are available, so the third acquisition blocks.
+    for _ in 0..3 {
+        SEMAPHORE.acquire();
+        ACQUIRED.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+fn read() {
+    let _value = RWLOCK.read();
+    READERS.fetch_add(1, Ordering::AcqRel);
+    // Keep holding the read lock.
+    RELEASE_READERS.wait();
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn reader0() {
+    read();
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn reader1() {
+    read();
+}
+
+#[ariel_os::thread(autostart, priority = 3)]
+fn writer() {
+    START_WRITER.wait();
+    *RWLOCK.write() += 1;
+    WRITES.fetch_add(1, Ordering::AcqRel);
+}
+
+fn wait_for_notification() {
+    let guard = MUTEX.lock();
+    drop(CONDVAR.wait(guard));
+    WOKEN.fetch_add(1, Ordering::AcqRel);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn waiter0() {
+    wait_for_notification();
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn waiter1() {
+    wait_for_notification();
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn waiter2() {
+    wait_for_notification();
+}
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread0() {
+    // All other threads have a higher priority, so they are blocked by now.
+
+    // Semaphore permits are counted.
+    assert_eq!(ACQUIRED.load(Ordering::Acquire), 2);
+    assert_eq!(SEMAPHORE.available_permits(), 0);
+    SEMAPHORE.release();
+    assert_eq!(ACQUIRED.load(Ordering::Acquire), 3);
+    assert_eq!(SEMAPHORE.available_permits(), 0);
+    SEMAPHORE.release();
+    assert_eq!(SEMAPHORE.available_permits(), 1);
+    assert!(SEMAPHORE.try_acquire());
+    assert!(!SEMAPHORE.try_acquire());
+    assert_eq!(
+        SEMAPHORE.acquire_timeout(Duration::from_millis(10)),
+        Err(TimeoutError)
+    );
+
+    // Readers share the lock, and exclude writers.
+    assert_eq!(READERS.load(Ordering::Acquire), 2);
+    assert!(RWLOCK.try_read().is_some());
+    assert!(RWLOCK.try_write().is_none());
+    START_WRITER.set();
+    assert_eq!(WRITES.load(Ordering::Acquire), 0);
+    // The writer gets the lock once the last reader released it.
+    RELEASE_READERS.set();
+    assert_eq!(WRITES.load(Ordering::Acquire), 1);
+    assert_eq!(*RWLOCK.read(), 1);
+
+    // `notify_one()` wakes a single waiter, `notify_all()` wakes all of them.
+    assert_eq!(WOKEN.load(Ordering::Acquire), 0);
+    CONDVAR.notify_one();
+    assert_eq!(WOKEN.load(Ordering::Acquire), 1);
+    CONDVAR.notify_all();
+    assert_eq!(WOKEN.load(Ordering::Acquire), 3);
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
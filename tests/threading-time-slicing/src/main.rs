@@ -0,0 +1,45 @@
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread,
+    time::Instant,
+};
+
+static STARTED: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+static FINISHED: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+
+/// Marks thread `n` as started, then busy-waits until the other thread has started as well.
+///
+/// Both threads have the same priority and never yield, so this only terminates if they get
+/// time-sliced.
+fn spin(n: usize) {
+    STARTED[n].store(true, Ordering::Release);
+
+    let start = Instant::now();
+    while !STARTED[1 - n].load(Ordering::Acquire) {
+        assert!(
+            start.elapsed() < thread::TIME_SLICE * 10,
+            "the other thread did not get a time slice"
+        );
+    }
+
+    FINISHED[n].store(true, Ordering::SeqCst);
+    if FINISHED[1 - n].load(Ordering::SeqCst) {
+        ariel_os::log::info!("Test passed!");
+        exit(ExitCode::Success);
+    }
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    spin(0);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread1() {
+    spin(1);
+}
@@ -0,0 +1,99 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{
+        ThreadId, TimeoutError, sleep,
+        sync::{Channel, Lock, Mutex, Queue, Semaphore, SendTimeoutError},
+        thread_flags,
+    },
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_millis(10);
+
+static MUTEX: Mutex<usize> = Mutex::new(0);
+static LOCK: Lock = Lock::new();
+static CHANNEL: Channel<u8> = Channel::new();
+static SEMAPHORE: Semaphore = Semaphore::new(0);
+static QUEUE: Queue<u8, 1> = Queue::new();
+static DEADLINE: Channel<Instant> = Channel::new();
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread0() {
+    // Nothing will ever wake us up, so all of these need to time out.
+    assert_eq!(
+        thread_flags::wait_any_timeout(0b1, TIMEOUT),
+        Err(TimeoutError)
+    );
+    // Timeouts are not reported through thread flags, so all flags can be waited for.
+    assert_eq!(
+        thread_flags::wait_any_timeout(0b11, TIMEOUT),
+        Err(TimeoutError)
+    );
+    assert_eq!(
+        thread_flags::wait_all_timeout(0b10, TIMEOUT),
+        Err(TimeoutError)
+    );
+    assert_eq!(thread_flags::get(), 0);
+    assert_eq!(CHANNEL.recv_timeout(TIMEOUT), Err(TimeoutError));
+    assert_eq!(SEMAPHORE.acquire_timeout(TIMEOUT), Err(TimeoutError));
+    assert_eq!(QUEUE.recv_timeout(TIMEOUT), Err(TimeoutError));
+
+    QUEUE.send(0);
+    assert_eq!(QUEUE.send_timeout(1, TIMEOUT), Err(SendTimeoutError(1)));
+
+    LOCK.acquire();
+    assert_eq!(LOCK.acquire_timeout(TIMEOUT), Err(TimeoutError));
+    LOCK.release();
+
+    // Deadlines in the past do not block.
+    assert_eq!(SEMAPHORE.acquire_until(Instant::now()), Err(TimeoutError));
+
+    // Primitives that are available do not time out.
+    SEMAPHORE.release();
+    assert_eq!(SEMAPHORE.acquire_timeout(TIMEOUT), Ok(()));
+    assert_eq!(QUEUE.recv_timeout(TIMEOUT), Ok(0));
+    assert_eq!(LOCK.acquire_timeout(TIMEOUT), Ok(()));
+    LOCK.release();
+
+    // Let the higher priority thread grab the mutex.
+    thread_flags::set(ThreadId::new(1), 0b1);
+    assert!(MUTEX.lock_timeout(TIMEOUT).is_err());
+
+    // The other thread releases the mutex and sends on the channel once unblocked.
+    thread_flags::set(ThreadId::new(1), 0b1);
+    assert_eq!(*MUTEX.lock_timeout(TIMEOUT).unwrap(), 1);
+    assert_eq!(CHANNEL.recv_timeout(TIMEOUT), Ok(42));
+
+    // The other thread only tries to send once the deadline has been reached, while this thread
+    // did not get to run yet.
+    let deadline = DEADLINE.recv();
+    assert_eq!(CHANNEL.recv_until(deadline), Err(TimeoutError));
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread1() {
+    thread_flags::wait_one(0b1);
+    let mut guard = MUTEX.lock();
+    *guard += 1;
+
+    thread_flags::wait_one(0b1);
+    drop(guard);
+
+    // Blocks until the lower priority thread receives.
+    assert_eq!(CHANNEL.send_timeout(&42, TIMEOUT), Ok(()));
+
+    let deadline = Instant::now() + TIMEOUT;
+    DEADLINE.send(&deadline);
+    // Let the lower priority thread block until the deadline.
+    sleep(TIMEOUT / 2);
+    // Busy-wait past the deadline, so that the lower priority thread cannot run in between.
+    while Instant::now() <= deadline + Duration::from_millis(1) {}
+    // The timed out receiver must not receive anything.
+    assert!(!CHANNEL.try_send(&43));
+}
@@ -0,0 +1,54 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{self, ThreadId, thread_flags, trace},
+};
+
+const FLAG: thread_flags::ThreadFlags = 0b100;
+
+/// Trace encoding of `ThreadState::FlagBlocked`.
+const FLAG_BLOCKED: u8 = 4;
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    let tid = usize::from(thread::current_tid().unwrap());
+    // Drop the events recorded during startup.
+    trace::dump(|_| {});
+
+    thread_flags::wait_all(FLAG);
+
+    let mut chunks = 0;
+    let mut blocked = false;
+    let mut flags_set = false;
+    trace::dump(|chunk| {
+        if chunks == 0 {
+            assert_eq!(&chunk[..4], b"ATRC");
+        } else {
+            let thread = usize::from(chunk[10]);
+            match chunk[8] {
+                1 if thread == tid && chunk[12] == FLAG_BLOCKED => blocked = true,
+                2 if thread == tid && u16::from_le_bytes([chunk[12], chunk[13]]) == FLAG => {
+                    flags_set = true;
+                }
+                _ => {}
+            }
+        }
+        chunks += 1;
+    });
+
+    assert!(
+        blocked,
+        "the thread should have been traced as blocked on flags"
+    );
+    assert!(flags_set, "setting the flag should have been traced");
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn thread1() {
+    thread_flags::set(ThreadId::new(0), FLAG);
+}
@@ -0,0 +1,64 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{
+        self,
+        work_queue::{Work, WorkQueue},
+    },
+    time::Duration,
+};
+use portable_atomic::{AtomicUsize, Ordering};
+
+static QUEUE: WorkQueue = WorkQueue::new();
+
+static IMMEDIATE: Work = Work::new(|| {
+    IMMEDIATE_RUNS.fetch_add(1, Ordering::AcqRel);
+});
+static IMMEDIATE_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+static DELAYED: Work = Work::new(|| {
+    DELAYED_RUNS.fetch_add(1, Ordering::AcqRel);
+});
+static DELAYED_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+static CANCELED: Work = Work::new(|| {
+    panic!("canceled work item was run");
+});
+
+#[ariel_os::thread(autostart, priority = 1)]
+fn worker() {
+    QUEUE.run()
+}
+
+#[ariel_os::thread(autostart, priority = 2)]
+fn thread0() {
+    // The worker has a lower priority, so nothing runs until this thread sleeps.
+    assert!(QUEUE.submit(&IMMEDIATE));
+    // Submitting a queued item again is coalesced.
+    assert!(!QUEUE.submit(&IMMEDIATE));
+    assert!(IMMEDIATE.is_pending());
+
+    assert!(QUEUE.submit_after(&DELAYED, Duration::from_millis(20)));
+    assert!(QUEUE.submit_after(&CANCELED, Duration::from_millis(10)));
+    assert!(CANCELED.cancel());
+    assert!(!CANCELED.cancel());
+
+    thread::sleep(Duration::from_millis(5));
+    assert_eq!(IMMEDIATE_RUNS.load(Ordering::Acquire), 1);
+    assert!(!IMMEDIATE.is_pending());
+    assert_eq!(DELAYED_RUNS.load(Ordering::Acquire), 0);
+    assert!(DELAYED.is_pending());
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(DELAYED_RUNS.load(Ordering::Acquire), 1);
+
+    // A queued item can be canceled as well.
+    assert!(QUEUE.submit(&CANCELED));
+    assert!(CANCELED.cancel());
+    thread::sleep(Duration::from_millis(5));
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
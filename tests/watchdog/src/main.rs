@@ -0,0 +1,50 @@
+#![no_main]
+#![no_std]
+
+use ariel_os::{
+    debug::{ExitCode, exit},
+    thread::{ThreadState, thread_flags},
+    time::{Duration, Timer},
+    watchdog::{self, Policy, Stall},
+};
+
+fn on_stall(stall: &Stall) {
+    // Only the thread stops feeding its entry.
+    assert_eq!(stall.name, "worker");
+    assert!(stall.thread_id.is_some());
+    assert!(matches!(
+        stall.thread_state,
+        Some(ThreadState::FlagBlocked(_))
+    ));
+
+    ariel_os::log::info!("Test passed!");
+    exit(ExitCode::Success);
+}
+
+#[ariel_os::task(autostart)]
+async fn monitor() {
+    watchdog::set_policy(Policy::Hook(on_stall));
+    watchdog::monitor(Duration::from_millis(10)).await;
+}
+
+#[ariel_os::task(autostart)]
+async fn healthy_task() {
+    let entry = watchdog::register("healthy task", Duration::from_millis(50));
+    loop {
+        entry.feed();
+        Timer::after_millis(5).await;
+    }
+}
+
+#[ariel_os::thread(autostart)]
+fn worker() {
+    let entry = watchdog::register_thread("worker", Duration::from_millis(50));
+    for _ in 0..5 {
+        entry.feed();
+        ariel_os::thread::sleep(Duration::from_millis(5));
+    }
+
+    // Stop feeding the entry, the monitor should notice.
+    thread_flags::wait_any(0b1);
+    unreachable!("the stall should have been detected");
+}